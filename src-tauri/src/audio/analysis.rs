@@ -0,0 +1,235 @@
+//! 可视化分析模块
+//!
+//! 音频线程只把处理后的采样复制到无锁的采样缓冲区（不阻塞，满时丢弃），
+//! 分析线程按目标刷新率读取最新采样，完成加窗、FFT、平滑以及频谱和播放位置事件的发送。
+
+use super::playback::{emit_playback_position, emit_spectrum_update};
+use super::ring::{sample_ring, RingConsumer, RingProducer};
+use spectrum_analyzer::scaling::divide_by_N_sqrt;
+use spectrum_analyzer::windows::hann_window;
+use spectrum_analyzer::{samples_fft_to_spectrum, FrequencyLimit};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+/// 采样缓冲区时长（毫秒），需覆盖分析线程两次读取之间的采样
+const TAP_BUFFER_MS: usize = 200;
+/// 播放位置事件间隔
+const POSITION_INTERVAL: Duration = Duration::from_millis(100);
+/// 频谱柱数
+const NUM_BINS: usize = 128;
+
+/// 根据采样率计算最佳FFT缓冲区大小
+/// 目标是保持约~43ms的分析窗口（2048@48kHz）
+#[must_use]
+const fn calculate_fft_size(sample_rate: u32) -> usize {
+    // 基准：48kHz使用2048样本 ≈ 42.7ms
+    // 公式：fft_size = sample_rate * 0.0427
+    // FFT大小必须是2的幂次
+    match sample_rate {
+        0..=32000 => 1024,      // ≤32kHz: 1024 样本
+        32001..=64000 => 2048,  // 44.1k/48k: 2048 样本
+        64001..=128000 => 4096, // 88.2k/96k: 4096 样本
+        _ => 8192,              // 176.4k/192k/384k: 8192 样本
+    }
+}
+
+/// 分析线程的参数
+pub struct AnalysisConfig {
+    pub app_handle: Option<AppHandle>,
+    pub spectrum_data: Arc<Mutex<Vec<f32>>>,
+    pub target_fps: Arc<AtomicU64>,
+    pub enable_vertical_sync: Arc<AtomicBool>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// 音频线程一侧的采样接入点，丢弃时分析线程随之结束
+pub struct AnalysisTap {
+    producer: RingProducer,
+    /// 当前音轨已播放的交错采样数
+    position: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+}
+
+impl AnalysisTap {
+    /// 启动分析线程
+    #[must_use]
+    pub fn spawn(config: AnalysisConfig) -> Self {
+        let fft_size = calculate_fft_size(config.sample_rate);
+        let capacity_frames = (config.sample_rate as usize * TAP_BUFFER_MS / 1000).max(fft_size);
+        let (producer, consumer) = sample_ring(capacity_frames, config.channels);
+        let position = Arc::new(AtomicU64::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker = AnalysisWorker {
+            consumer,
+            scratch: vec![0.0; capacity_frames * config.channels.max(1) as usize],
+            window: Vec::with_capacity(fft_size * 2),
+            fft_buffer: vec![0.0; fft_size],
+            spectrum_buffer: vec![0.0; NUM_BINS],
+            prev_spectrum: vec![0.0; NUM_BINS],
+            fft_size,
+            position: Arc::clone(&position),
+            stop: Arc::clone(&stop),
+            config,
+        };
+        thread::spawn(move || worker.run());
+        Self { producer, position, stop }
+    }
+
+    /// 复制采样到缓冲区（音频线程调用，不阻塞）
+    #[inline]
+    pub fn write(&mut self, samples: &[f32]) {
+        let _ = self.producer.try_push(samples);
+    }
+
+    /// 更新播放位置（交错采样数）
+    #[inline]
+    pub fn set_position(&self, samples: u64) {
+        self.position.store(samples, Ordering::Relaxed);
+    }
+}
+
+impl Drop for AnalysisTap {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+struct AnalysisWorker {
+    consumer: RingConsumer,
+    config: AnalysisConfig,
+    scratch: Vec<f32>,
+    /// 最近的采样（最多 fft_size 个）
+    window: Vec<f32>,
+    fft_buffer: Vec<f32>,
+    spectrum_buffer: Vec<f32>,
+    prev_spectrum: Vec<f32>,
+    fft_size: usize,
+    position: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+}
+
+impl AnalysisWorker {
+    fn run(mut self) {
+        let mut last_fft: Option<Instant> = None;
+        let mut last_position: Option<Instant> = None;
+        while !self.stop.load(Ordering::Relaxed) {
+            let fft_interval = self.fft_interval();
+            thread::sleep(fft_interval.min(POSITION_INTERVAL));
+            // 没有新采样（暂停或尚未开始播放）时不发送事件
+            if self.drain() == 0 {
+                if self.consumer.is_drained() { break; }
+                continue;
+            }
+            let now = Instant::now();
+
+            // 发送播放位置（每100ms一次）
+            if last_position.is_none_or(|t| now.duration_since(t) >= POSITION_INTERVAL) {
+                last_position = Some(now);
+                if let Some(ref app) = self.config.app_handle {
+                    let samples = self.position.load(Ordering::Relaxed);
+                    let position = samples as f32 / (self.config.sample_rate as f32 * self.config.channels as f32);
+                    let _ = emit_playback_position(app, position);
+                }
+            }
+
+            // 限制FFT计算和发送频率
+            if self.window.len() >= self.fft_size && last_fft.is_none_or(|t| now.duration_since(t) >= fft_interval) {
+                last_fft = Some(now);
+                self.compute_spectrum();
+            }
+        }
+    }
+
+    /// FFT 间隔
+    fn fft_interval(&self) -> Duration {
+        // 根据垂直同步设置决定FFT频率
+        let enable_vsync = self.config.enable_vertical_sync.load(Ordering::Relaxed);
+        let target_fps = self.config.target_fps.load(Ordering::Relaxed).max(1);
+        let fft_interval_ms = if enable_vsync {
+            // 垂直同步开启：使用屏幕刷新率同步
+            1000 / target_fps
+        } else {
+            // 垂直同步关闭：使用目标帧率
+            1000 / target_fps
+        };
+        Duration::from_millis(fft_interval_ms)
+    }
+
+    /// 读出缓冲区中的全部采样，保留最近的 fft_size 个，返回读出的数量
+    fn drain(&mut self) -> usize {
+        let mut total = 0;
+        loop {
+            let count = self.consumer.pop(&mut self.scratch);
+            if count == 0 { break; }
+            total += count;
+            let keep = count.min(self.fft_size);
+            self.window.extend_from_slice(&self.scratch[count - keep..count]);
+        }
+        if self.window.len() > self.fft_size {
+            let excess = self.window.len() - self.fft_size;
+            self.window.drain(..excess);
+        }
+        total
+    }
+
+    /// 计算频谱数据
+    fn compute_spectrum(&mut self) {
+        if let Ok(mut spec) = self.config.spectrum_data.try_lock() {
+            // 复用预分配的缓冲区
+            self.fft_buffer.copy_from_slice(&self.window[..self.fft_size]);
+            let hann = hann_window(&self.fft_buffer);
+
+            if let Ok(spectrum) = samples_fft_to_spectrum(
+                &hann,
+                self.config.sample_rate,
+                FrequencyLimit::Range(20.0, 20000.0),
+                Some(&divide_by_N_sqrt),
+            ) {
+                // 重置频谱缓冲区
+                self.spectrum_buffer.fill(0.0);
+
+                // AE风格：线性频率分布
+                const FREQ_MIN: f32 = 20.0;
+                const FREQ_MAX: f32 = 16000.0;
+                const FREQ_STEP: f32 = (FREQ_MAX - FREQ_MIN) / NUM_BINS as f32;
+
+                for (freq, value) in spectrum.data() {
+                    let f = freq.val();
+                    if !(FREQ_MIN..=FREQ_MAX).contains(&f) { continue; }
+
+                    let bin = ((f - FREQ_MIN) / FREQ_STEP).floor() as usize;
+                    let bin = bin.min(NUM_BINS - 1);
+
+                    let v = value.val();
+                    if v > self.spectrum_buffer[bin] {
+                        self.spectrum_buffer[bin] = v;
+                    }
+                }
+
+                // AE风格的平滑：快速上升，缓慢下降
+                for i in 0..NUM_BINS {
+                    let target = self.spectrum_buffer[i];
+                    let current = self.prev_spectrum[i];
+
+                    self.prev_spectrum[i] = if target > current {
+                        current * 0.3 + target * 0.7 // 快速上升
+                    } else {
+                        current * 0.85 + target * 0.15 // 缓慢下降
+                    };
+                }
+
+                spec.clear();
+                spec.extend_from_slice(&self.prev_spectrum);
+            }
+        }
+
+        // 发送事件 - 与FFT计算同步
+        if let Some(ref app) = self.config.app_handle {
+            let _ = emit_spectrum_update(app, &self.prev_spectrum);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! 音轨时钟模块
+//!
+//! 记录当前音轨的播放进度（以音轨时间计的交错采样数）和路径。
+//! 音源链中发生音轨切换（如交叉淡化）时由切换方重置，位置事件从这里读取。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+struct TrackClockInner {
+    samples: AtomicU64,
+    path: Mutex<Option<String>>,
+}
+
+/// 当前音轨的播放时钟（可在线程间共享）
+#[derive(Clone)]
+pub struct TrackClock {
+    inner: Arc<TrackClockInner>,
+}
+
+impl TrackClock {
+    #[must_use]
+    pub fn new(path: &str, start_samples: u64) -> Self {
+        Self {
+            inner: Arc::new(TrackClockInner {
+                samples: AtomicU64::new(start_samples),
+                path: Mutex::new(Some(path.to_string())),
+            }),
+        }
+    }
+
+    /// 当前音轨已播放的交错采样数
+    #[inline]
+    #[must_use]
+    pub fn samples(&self) -> u64 {
+        self.inner.samples.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn advance(&self, samples: u64) {
+        self.inner.samples.fetch_add(samples, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn set_samples(&self, samples: u64) {
+        self.inner.samples.store(samples, Ordering::Relaxed);
+    }
+
+    /// 切换到新的音轨
+    pub fn reset(&self, path: &str, samples: u64) {
+        *self.inner.path.lock().unwrap() = Some(path.to_string());
+        self.inner.samples.store(samples, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn path(&self) -> Option<String> {
+        self.inner.path.lock().unwrap().clone()
+    }
+}
@@ -3,14 +3,24 @@
 //! 包含播放控制、设备管理等命令。
 
 use super::device::{get_all_audio_devices, AudioDeviceInfo};
+use super::gapless;
 use super::playback::{
-    check_track_finished, get_status, play_track_exclusive, play_track_shared, seek_track_shared,
-    PlaybackStatus,
+    check_track_finished, get_status, queue_next_track, seek_track_exclusive, seek_track_shared,
+    start_track, PlaybackStatus,
 };
 
 #[cfg(windows)]
 use super::wasapi::WasapiExclusivePlayback;
 
+use super::decoder::{decode_buffer_stats, DecodeBufferStats, SymphoniaDecoder};
+use super::looping::{AbLoop, LoopRegion};
+use super::player_state::{OutputFormat, PlayerStateEvent, PlayerStatus, TransitionReason};
+use super::sleep_timer::{SleepTimerMode, SleepTimerStatus, SLEEP_TIMER_DEFAULT_FADE_SECS};
+use crate::config::{
+    CrossfadeConfig, LoopConfig, PitchShift, PitchShiftConfig, ReplayGainConfig, CROSSFADE_MAX_SECS, CROSSFADE_MIN_SECS, LOOP_FADE_OUT_MAX_SECS, REPLAY_GAIN_PREAMP_MAX_DB,
+    REPLAY_GAIN_PREAMP_MIN_DB,
+};
+use crate::error::{AppError, AppResult, ErrorCode};
 use crate::AppState;
 use cpal::traits::{DeviceTrait, HostTrait};
 use rodio::{OutputStreamBuilder, Sink};
@@ -21,7 +31,7 @@ use tauri::{command, AppHandle, State};
 // ============================================================================
 
 #[command]
-pub fn get_waveform_data(state: State<AppState>) -> Result<Vec<f32>, String> {
+pub fn get_waveform_data(state: State<AppState>) -> AppResult<Vec<f32>> {
     // 使用 try_lock 避免阻塞主线程
     match state.player.waveform_data.try_lock() {
         Ok(data) => Ok(data.clone()),
@@ -30,7 +40,7 @@ pub fn get_waveform_data(state: State<AppState>) -> Result<Vec<f32>, String> {
 }
 
 #[command]
-pub fn get_spectrum_data(state: State<AppState>) -> Result<Vec<f32>, String> {
+pub fn get_spectrum_data(state: State<AppState>) -> AppResult<Vec<f32>> {
     // 使用 try_lock 避免阻塞主线程
     match state.player.spectrum_data.try_lock() {
         Ok(data) => Ok(data.clone()),
@@ -39,16 +49,12 @@ pub fn get_spectrum_data(state: State<AppState>) -> Result<Vec<f32>, String> {
 }
 
 #[command]
-pub fn play_track(app: AppHandle, state: State<AppState>, path: String, position: Option<f32>) -> Result<(), String> {
-    if *state.player.exclusive_mode.lock().unwrap() {
-        play_track_exclusive(&app, &state, &path, position)
-    } else {
-        play_track_shared(&app, &state, &path, position)
-    }
+pub fn play_track(app: AppHandle, state: State<AppState>, path: String, position: Option<f32>) -> AppResult<()> {
+    start_track(&app, &state, &path, position)
 }
 
 #[command]
-pub fn pause_track(state: State<AppState>) -> Result<(), String> {
+pub fn pause_track(app: AppHandle, state: State<AppState>) -> AppResult<()> {
     // 使用 try_lock 避免阻塞
     let exclusive_mode = state.player.exclusive_mode.try_lock()
         .map(|g| *g)
@@ -59,7 +65,7 @@ pub fn pause_track(state: State<AppState>) -> Result<(), String> {
         {
             if let Ok(guard) = state.player.wasapi_player.try_lock() {
                 if let Some(ref wasapi) = *guard {
-                    wasapi.pause()?;
+                    wasapi.pause().map_err(AppError::device_unavailable)?;
                 }
             }
         }
@@ -68,11 +74,12 @@ pub fn pause_track(state: State<AppState>) -> Result<(), String> {
             sink.pause();
         }
     }
+    state.player.player_state.transition(&app, PlayerStatus::Paused, TransitionReason::Pause);
     Ok(())
 }
 
 #[command]
-pub fn resume_track(state: State<AppState>) -> Result<(), String> {
+pub fn resume_track(app: AppHandle, state: State<AppState>) -> AppResult<()> {
     // 使用 try_lock 避免阻塞
     let exclusive_mode = state.player.exclusive_mode.try_lock()
         .map(|g| *g)
@@ -83,7 +90,7 @@ pub fn resume_track(state: State<AppState>) -> Result<(), String> {
         {
             if let Ok(guard) = state.player.wasapi_player.try_lock() {
                 if let Some(ref wasapi) = *guard {
-                    wasapi.resume()?;
+                    wasapi.resume().map_err(AppError::device_unavailable)?;
                 }
             }
         }
@@ -92,13 +99,14 @@ pub fn resume_track(state: State<AppState>) -> Result<(), String> {
             sink.play();
         }
     }
+    state.player.player_state.transition(&app, PlayerStatus::Playing, TransitionReason::Resume);
     Ok(())
 }
 
 #[command]
-pub fn set_volume(state: State<AppState>, volume: f32) -> Result<(), String> {
+pub fn set_volume(state: State<AppState>, volume: f32) -> AppResult<()> {
     if !(0.0..=1.0).contains(&volume) {
-        return Err("Volume must be between 0.0 and 1.0".to_string());
+        return Err(AppError::invalid_argument("Volume must be between 0.0 and 1.0"));
     }
     
     // 使用 try_lock 避免阻塞
@@ -115,7 +123,7 @@ pub fn set_volume(state: State<AppState>, volume: f32) -> Result<(), String> {
         {
             if let Ok(guard) = state.player.wasapi_player.try_lock() {
                 if let Some(ref wasapi) = *guard {
-                    wasapi.set_volume(volume)?;
+                    wasapi.set_volume(volume).map_err(AppError::device_unavailable)?;
                 }
             }
         }
@@ -128,23 +136,257 @@ pub fn set_volume(state: State<AppState>, volume: f32) -> Result<(), String> {
 }
 
 #[command]
-pub fn get_playback_status(state: State<AppState>) -> Result<PlaybackStatus, String> {
+pub fn get_playback_status(state: State<AppState>) -> AppResult<PlaybackStatus> {
     get_status(&state)
 }
 
+/// 获取播放器状态机的当前快照（与最近一次 `player-state` 事件一致）
+#[command]
+pub fn get_player_state(state: State<AppState>) -> AppResult<PlayerStateEvent> {
+    Ok(state.player.player_state.snapshot())
+}
+
+/// 解码缓冲区统计（欠载次数、填充水平），用于评估缓冲效果
 #[command]
-pub fn is_track_finished(state: State<AppState>) -> Result<bool, String> {
+pub fn get_decode_buffer_stats() -> AppResult<Vec<DecodeBufferStats>> {
+    Ok(decode_buffer_stats())
+}
+
+#[command]
+pub fn is_track_finished(state: State<AppState>) -> AppResult<bool> {
     check_track_finished(&state)
 }
 
 #[command]
-pub fn seek_track(app: AppHandle, state: State<AppState>, time: f32) -> Result<(), String> {
-    let path = state.player.current_path.lock().unwrap().clone().ok_or("No track currently loaded")?;
+pub fn seek_track(app: AppHandle, state: State<AppState>, time: f32) -> AppResult<()> {
+    let path = state.player.current_path.lock().unwrap().clone().ok_or_else(AppError::no_track_loaded)?;
     if *state.player.exclusive_mode.lock().unwrap() {
-        play_track_exclusive(&app, &state, &path, Some(time))
+        seek_track_exclusive(&app, &state, &path, time)?;
     } else {
-        seek_track_shared(&app, &state, &path, time)
+        seek_track_shared(&app, &state, &path, time)?;
+    }
+    state.player.player_state.seeked(&app, time);
+    Ok(())
+}
+
+/// 排队下一首音轨，当前音轨结束后无缝衔接
+#[command]
+pub fn set_next_track(app: AppHandle, state: State<AppState>, path: String) -> AppResult<()> {
+    queue_next_track(&app, &state, &path)
+}
+
+/// 取消已排队的下一首音轨
+#[command]
+pub fn clear_next_track(state: State<AppState>) -> AppResult<()> {
+    gapless::clear_next_track(&state.player.next_track);
+    Ok(())
+}
+
+/// 获取已排队的下一首音轨路径
+#[command]
+pub fn get_next_track(state: State<AppState>) -> AppResult<Option<String>> {
+    Ok(gapless::peek_next_path(&state.player.next_track))
+}
+
+/// 获取交叉淡化设置
+#[command]
+pub fn get_crossfade_settings(state: State<AppState>) -> AppResult<CrossfadeConfig> {
+    Ok(state.player.crossfade.read().unwrap().clone())
+}
+
+/// 更新交叉淡化设置（立即生效并持久化）
+#[command]
+pub fn set_crossfade_settings(state: State<AppState>, mut settings: CrossfadeConfig) -> AppResult<()> {
+    if !settings.duration.is_finite() {
+        return Err(AppError::invalid_argument("Invalid crossfade duration"));
+    }
+    settings.duration = settings.duration.clamp(CROSSFADE_MIN_SECS, CROSSFADE_MAX_SECS);
+    println!("Crossfade settings: {settings:?}");
+
+    state.player.crossfade.write().unwrap().clone_from(&settings);
+
+    let mut config = state.config_manager.load_config()?;
+    config.audio.crossfade = settings;
+    state.config_manager.save_config(&config)
+}
+
+/// 获取回放增益设置
+#[command]
+pub fn get_replay_gain_settings(state: State<AppState>) -> AppResult<ReplayGainConfig> {
+    Ok(state.player.replay_gain.settings())
+}
+
+/// 更新回放增益设置（立即生效并持久化）
+#[command]
+pub fn set_replay_gain_settings(state: State<AppState>, mut settings: ReplayGainConfig) -> AppResult<()> {
+    if !settings.preamp.is_finite() || !settings.fallback_gain.is_finite() {
+        return Err(AppError::invalid_argument("Invalid replay gain value"));
+    }
+    settings.preamp = settings.preamp.clamp(REPLAY_GAIN_PREAMP_MIN_DB, REPLAY_GAIN_PREAMP_MAX_DB);
+    settings.fallback_gain = settings.fallback_gain.clamp(REPLAY_GAIN_PREAMP_MIN_DB, REPLAY_GAIN_PREAMP_MAX_DB);
+    println!("ReplayGain settings: {settings:?}");
+
+    state.player.replay_gain.set_settings(settings.clone());
+
+    let mut config = state.config_manager.load_config()?;
+    config.audio.replay_gain = settings;
+    state.config_manager.save_config(&config)
+}
+
+/// 获取播放倍速
+#[command]
+pub fn get_playback_rate(state: State<AppState>) -> AppResult<f32> {
+    Ok(state.player.stretch.rate())
+}
+
+/// 设置播放倍速（0.5~2.0，保持音调不变），返回实际生效的倍速
+#[command]
+pub fn set_playback_rate(state: State<AppState>, rate: f32) -> AppResult<f32> {
+    if !rate.is_finite() {
+        return Err(AppError::invalid_argument("Invalid playback rate"));
     }
+    let rate = state.player.stretch.set_rate(rate);
+    println!("Playback rate: {rate}");
+    Ok(rate)
+}
+
+/// 保存变调设置
+fn save_pitch_settings(state: &State<AppState>) -> AppResult<()> {
+    let mut config = state.config_manager.load_config()?;
+    config.audio.pitch_shift = state.player.stretch.pitch_settings();
+    state.config_manager.save_config(&config)
+}
+
+/// 获取变调设置（全局及已记住的单曲设置）
+#[command]
+pub fn get_pitch_shift_settings(state: State<AppState>) -> AppResult<PitchShiftConfig> {
+    Ok(state.player.stretch.pitch_settings())
+}
+
+/// 设置全局变调（±12 半音、±100 音分，不改变速度），返回实际生效的值
+#[command]
+pub fn set_pitch_shift(state: State<AppState>, shift: PitchShift) -> AppResult<PitchShift> {
+    let shift = state.player.stretch.set_global_pitch(shift);
+    println!("Pitch shift: {shift:?}");
+    save_pitch_settings(&state)?;
+    Ok(shift)
+}
+
+/// 记住单曲变调（优先于全局设置），`shift` 为空时清除
+#[command]
+pub fn set_track_pitch_shift(state: State<AppState>, path: String, shift: Option<PitchShift>) -> AppResult<Option<PitchShift>> {
+    let shift = state.player.stretch.set_track_pitch(&path, shift);
+    println!("Track pitch shift: {path} -> {shift:?}");
+    save_pitch_settings(&state)?;
+    Ok(shift)
+}
+
+/// 设置当前音轨的 A–B 循环区间（秒），由解码器无缝循环
+#[command]
+pub fn set_loop_region(state: State<AppState>, start: f64, end: f64) -> AppResult<AbLoop> {
+    if !start.is_finite() || !end.is_finite() || start < 0.0 || end <= start {
+        return Err(AppError::invalid_argument(format!("Invalid loop region: {start} - {end}")));
+    }
+    let path = state.player.current_path.lock().unwrap().clone().ok_or_else(AppError::no_track_loaded)?;
+    // 按音轨的采样率和时长检查：区间至少一帧，且不超出音轨结尾。
+    // 优先使用播放状态中已知的格式，未知时才打开解码器读取
+    let snapshot = state.player.player_state.snapshot();
+    let (sample_rate, duration) = match snapshot.sample_rate {
+        Some(sample_rate) if snapshot.path.as_deref() == Some(path.as_str()) => (sample_rate, snapshot.duration),
+        _ => {
+            let decoder = SymphoniaDecoder::new(&path)?;
+            (decoder.sample_rate(), decoder.total_duration().map(|d| d.as_secs_f64()))
+        }
+    };
+    if LoopRegion::from_seconds(start, end, sample_rate).is_none() {
+        return Err(AppError::invalid_argument(format!("Loop region is shorter than one frame: {start} - {end}"))
+            .with_context("sampleRate", sample_rate));
+    }
+    if let Some(total) = duration
+        && end > total
+    {
+        return Err(AppError::invalid_argument(format!("Loop region ends beyond the track: {start} - {end}"))
+            .with_context("duration", total));
+    }
+    let ab = AbLoop { path, start, end };
+    println!("Loop region: {ab:?}");
+    state.player.looping.set_ab_loop(Some(ab.clone()));
+    Ok(ab)
+}
+
+/// 清除 A–B 循环区间
+#[command]
+pub fn clear_loop_region(state: State<AppState>) -> AppResult<()> {
+    state.player.looping.set_ab_loop(None);
+    Ok(())
+}
+
+#[command]
+pub fn get_loop_region(state: State<AppState>) -> AppResult<Option<AbLoop>> {
+    Ok(state.player.looping.ab_loop())
+}
+
+/// 获取标签循环设置
+#[command]
+pub fn get_loop_settings(state: State<AppState>) -> AppResult<LoopConfig> {
+    Ok(state.player.looping.settings())
+}
+
+/// 更新标签循环设置（之后打开的音轨生效）并持久化
+#[command]
+pub fn set_loop_settings(state: State<AppState>, mut settings: LoopConfig) -> AppResult<()> {
+    if !settings.fade_out.is_finite() {
+        return Err(AppError::invalid_argument("Invalid fade out duration"));
+    }
+    settings.fade_out = settings.fade_out.clamp(0.0, LOOP_FADE_OUT_MAX_SECS);
+    println!("Loop settings: {settings:?}");
+
+    state.player.looping.set_settings(settings.clone());
+
+    let mut config = state.config_manager.load_config()?;
+    config.audio.looping = settings;
+    state.config_manager.save_config(&config)
+}
+
+/// 设置睡眠定时器（替换已有的定时器），`fade_out` 为最后淡出的秒数
+#[command]
+pub fn set_sleep_timer(app: AppHandle, state: State<AppState>, mode: SleepTimerMode, fade_out: Option<f32>) -> AppResult<SleepTimerStatus> {
+    state.player.sleep_timer.start(&app, mode, fade_out.unwrap_or(SLEEP_TIMER_DEFAULT_FADE_SECS))
+}
+
+/// 延长睡眠定时器（按时间计时时增加分钟数，按音轨计时时增加音轨数）
+#[command]
+pub fn extend_sleep_timer(app: AppHandle, state: State<AppState>, minutes: Option<f64>, tracks: Option<u32>) -> AppResult<SleepTimerStatus> {
+    state.player.sleep_timer.extend(&app, minutes, tracks)
+}
+
+/// 取消睡眠定时器
+#[command]
+pub fn cancel_sleep_timer(app: AppHandle, state: State<AppState>) -> AppResult<bool> {
+    Ok(state.player.sleep_timer.cancel(&app))
+}
+
+#[command]
+pub fn get_sleep_timer(state: State<AppState>) -> AppResult<Option<SleepTimerStatus>> {
+    Ok(state.player.sleep_timer.status())
+}
+
+/// 后台扫描文件响度（EBU R128），返回扫描 ID
+///
+/// 进度通过 `loudness-scan-progress` 事件发送，结果通过 `loudness-scan-complete` 事件发送。
+#[command]
+pub fn scan_loudness(app: AppHandle, state: State<AppState>, paths: Vec<String>, write_tags: Option<bool>) -> AppResult<u64> {
+    if paths.is_empty() {
+        return Err(AppError::invalid_argument("No files to scan"));
+    }
+    println!("Starting loudness scan of {} files", paths.len());
+    state.loudness_scanner.start(app, paths, write_tags.unwrap_or(false))
+}
+
+/// 取消正在运行的响度扫描
+#[command]
+pub fn cancel_loudness_scan(state: State<AppState>) -> AppResult<bool> {
+    Ok(state.loudness_scanner.cancel())
 }
 
 // ============================================================================
@@ -152,7 +394,7 @@ pub fn seek_track(app: AppHandle, state: State<AppState>, time: f32) -> Result<(
 // ============================================================================
 
 #[command]
-pub fn get_audio_devices() -> Result<Vec<AudioDeviceInfo>, String> {
+pub fn get_audio_devices() -> AppResult<Vec<AudioDeviceInfo>> {
     get_all_audio_devices()
 }
 
@@ -162,7 +404,7 @@ pub fn set_audio_device(
     state: State<AppState>,
     device_name: String,
     current_time: Option<f32>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     println!("Attempting to switch to audio device: {device_name}");
 
     let exclusive_mode = *state.player.exclusive_mode.lock().unwrap();
@@ -187,7 +429,7 @@ fn switch_to_wasapi_exclusive(
     state: &State<AppState>,
     device_name: &str,
     _current_time: Option<f32>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     println!("Switching to WASAPI exclusive mode for device: {device_name}");
 
     {
@@ -211,6 +453,7 @@ fn switch_to_wasapi_exclusive(
 
             *state.player.wasapi_player.lock().unwrap() = Some(wasapi_playback);
             *state.player.current_device_name.lock().unwrap() = device_name.to_string();
+            state.player.player_state.set_output_format(OutputFormat { sample_rate, channels, exclusive: true });
 
             println!("Successfully switched to WASAPI exclusive mode");
             Ok(())
@@ -218,7 +461,8 @@ fn switch_to_wasapi_exclusive(
         Err(e) => {
             eprintln!("Failed to initialize WASAPI exclusive mode: {e}");
             *state.player.exclusive_mode.lock().unwrap() = false;
-            Err(format!("Failed to initialize WASAPI exclusive mode: {e}. The device may be in use by another application."))
+            Err(AppError::device_unavailable(format!("Failed to initialize WASAPI exclusive mode: {e}. The device may be in use by another application."))
+                .with_context("device", device_name))
         }
     }
 }
@@ -229,8 +473,8 @@ fn switch_to_wasapi_exclusive(
     _state: &State<AppState>,
     _device_name: &str,
     _current_time: Option<f32>,
-) -> Result<(), String> {
-    Err("Exclusive mode is only supported on Windows".to_string())
+) -> AppResult<()> {
+    Err(AppError::new(ErrorCode::Unsupported, "Exclusive mode is only supported on Windows"))
 }
 
 fn switch_to_shared_mode(
@@ -238,23 +482,25 @@ fn switch_to_shared_mode(
     state: &State<AppState>,
     device_name: &str,
     current_time: Option<f32>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     println!("Switching to shared mode for device: {device_name}");
 
     let host = cpal::default_host();
     let device = host
         .output_devices()
-        .map_err(|e| format!("Failed to get output devices: {e}"))?
+        .map_err(|e| AppError::device_unavailable(format!("Failed to get output devices: {e}")).with_source(e))?
         .find(|d| d.name().is_ok_and(|name| name == device_name))
-        .ok_or(format!("Audio device not found: {device_name}"))?;
+        .ok_or_else(|| AppError::device_unavailable(format!("Audio device not found: {device_name}")).with_context("device", device_name))?;
 
     let stream = OutputStreamBuilder::from_device(device)
-        .map_err(|e| format!("Failed to create output stream builder: {e}"))?
+        .map_err(|e| AppError::device_unavailable(format!("Failed to create output stream builder: {e}")).with_context("device", device_name))?
         .open_stream()
-        .map_err(|e| format!("Failed to open output stream: {e}"))?;
+        .map_err(|e| AppError::device_unavailable(format!("Failed to open output stream: {e}")).with_context("device", device_name))?;
 
     let new_sink = Sink::connect_new(stream.mixer());
-    
+    let config = stream.config();
+    state.player.player_state.set_output_format(OutputFormat { sample_rate: config.sample_rate(), channels: config.channel_count(), exclusive: false });
+
     Box::leak(Box::new(stream));
 
     let (is_playing, volume, current_path) = {
@@ -294,7 +540,7 @@ pub fn toggle_exclusive_mode(
     state: State<AppState>,
     enabled: bool,
     _current_time: Option<f32>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     println!("Toggling exclusive mode: {enabled} (requires restart)");
 
     let prev_exclusive = *state.player.exclusive_mode.lock().unwrap();
@@ -308,16 +554,16 @@ pub fn toggle_exclusive_mode(
         state.config_manager.save_config(&config)?;
     }
 
-    Err("RESTART_REQUIRED".to_string())
+    Err(AppError::new(ErrorCode::RestartRequired, "RESTART_REQUIRED"))
 }
 
 #[command]
-pub fn get_exclusive_mode(state: State<AppState>) -> Result<bool, String> {
+pub fn get_exclusive_mode(state: State<AppState>) -> AppResult<bool> {
     Ok(*state.player.exclusive_mode.lock().unwrap())
 }
 
 #[command]
-pub fn get_current_audio_device(state: State<AppState>) -> Result<AudioDeviceInfo, String> {
+pub fn get_current_audio_device(state: State<AppState>) -> AppResult<AudioDeviceInfo> {
     let current_device_name = state.player.current_device_name.lock().unwrap().clone();
 
     let host = cpal::default_host();
@@ -366,9 +612,9 @@ pub fn get_current_audio_device(state: State<AppState>) -> Result<AudioDeviceInf
 }
 
 #[command]
-pub fn set_target_fps(state: State<AppState>, fps: u32) -> Result<(), String> {
+pub fn set_target_fps(state: State<AppState>, fps: u32) -> AppResult<()> {
     if fps == 0 {
-        return Err("FPS cannot be zero".to_string());
+        return Err(AppError::invalid_argument("FPS cannot be zero"));
     }
     // 限制最大刷新率为 240fps，防止过高频率
     let clamped_fps = fps.min(240);
@@ -378,7 +624,7 @@ pub fn set_target_fps(state: State<AppState>, fps: u32) -> Result<(), String> {
 }
 
 #[command]
-pub fn set_vertical_sync(state: State<AppState>, enabled: bool) -> Result<(), String> {
+pub fn set_vertical_sync(state: State<AppState>, enabled: bool) -> AppResult<()> {
     state.player.enable_vertical_sync.store(enabled, std::sync::atomic::Ordering::Relaxed);
     println!("Vertical sync {}", if enabled { "enabled" } else { "disabled" });
     Ok(())
@@ -0,0 +1,319 @@
+//! 交叉淡化模块
+//!
+//! 在 `LockFreeSymphoniaSource` 之上实现混音音源：当前音轨进入尾部时，
+//! 从下一首槽位取出预先构建的音源，将两首音轨按所选曲线重叠混合。
+//! 无法淡化时（时长未知、格式不一致、未排队）保持原样，由无缝播放机制接管；
+//! 共享模式下同格式的下一首在当前音轨结束时直接接上。
+//! 每首音轨在混音前先经过变速处理，淡化时长按播放时间计算。
+
+use super::clock::TrackClock;
+use super::decoder::LockFreeSymphoniaSource;
+use super::events::{PlaybackEvent, PlaybackEvents};
+use super::gapless::{NextTrack, NextTrackSlot};
+use super::stretch::{StretchControl, StretchSource};
+use crate::config::{CrossfadeConfig, CrossfadeCurve, CROSSFADE_MAX_SECS, CROSSFADE_MIN_SECS};
+use rodio::source::SeekError;
+use rodio::Source;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::Duration;
+
+/// 每隔多少采样检查一次是否进入淡化区
+const POLL_INTERVAL: u32 = 1024;
+
+/// 交叉淡化切换记录
+#[derive(Debug, Clone)]
+pub struct CrossfadeTransition {
+    /// 切换发生时本音源已输出的采样数
+    pub at_sample: u64,
+    pub from: String,
+    pub to: String,
+    /// 下一首的时长
+    pub duration: Option<Duration>,
+}
+
+/// 音轨切换的通知方式
+pub enum TransitionNotifier {
+    /// 交给事件线程立即发送事件并更新当前路径（共享模式，输出延迟很小）
+    Emit(PlaybackEvents),
+    /// 记录切换点，由消费方在对应采样真正播放时处理（独占模式，缓冲区较深）
+    Deferred(Arc<Mutex<VecDeque<CrossfadeTransition>>>),
+}
+
+/// 单首音轨的音源（解码 -> 变速）
+type TrackSource = StretchSource<LockFreeSymphoniaSource>;
+
+/// 正在淡出的上一首音轨
+struct Fade {
+    outgoing: TrackSource,
+    curve: CrossfadeCurve,
+    /// 已淡化的帧数
+    pos: u64,
+    /// 淡化总帧数
+    len: u64,
+}
+
+/// 交叉淡化混音音源
+pub struct CrossfadeSource {
+    current: TrackSource,
+    path: String,
+    fade: Option<Fade>,
+    /// 当前音轨总帧数（未知时无法提前开始淡化）
+    total_frames: Option<u64>,
+    output_samples: u64,
+    channels: u16,
+    sample_rate: u32,
+    settings: Arc<RwLock<CrossfadeConfig>>,
+    next_track: NextTrackSlot,
+    notifier: TransitionNotifier,
+    clock: TrackClock,
+    stretch: StretchControl,
+    poll_counter: u32,
+    /// 槽位锁被占用时补齐当前静音帧还需输出的采样数
+    silence: u16,
+}
+
+fn total_frames_of(source: &TrackSource) -> Option<u64> {
+    source.total_duration().map(|d| (d.as_secs_f64() * source.sample_rate() as f64) as u64)
+}
+
+impl CrossfadeSource {
+    pub fn new(
+        current: LockFreeSymphoniaSource,
+        path: &str,
+        start_position: f32,
+        settings: Arc<RwLock<CrossfadeConfig>>,
+        next_track: NextTrackSlot,
+        notifier: TransitionNotifier,
+        stretch: StretchControl,
+    ) -> Self {
+        // 起始位置与循环跳转由解码器换算，变速级从 0 开始计数
+        let current = StretchSource::new(current, stretch.clone(), path, 0.0);
+        let (channels, sample_rate) = (current.channels(), current.sample_rate());
+        let samples_played = (start_position.max(0.0) as f64 * sample_rate as f64) as u64 * channels as u64;
+        Self {
+            total_frames: total_frames_of(&current),
+            current,
+            path: path.to_string(),
+            fade: None,
+            output_samples: 0,
+            channels,
+            sample_rate,
+            settings,
+            next_track,
+            notifier,
+            clock: TrackClock::new(path, samples_played),
+            stretch,
+            poll_counter: 0,
+            silence: 0,
+        }
+    }
+
+    /// 当前输出位置对应的音轨采样数（锁被占用时返回 None）
+    fn track_samples(&self) -> Option<u64> {
+        let frame = self.current.input().track_frame(self.current.input_frames())?;
+        Some(frame * self.channels as u64)
+    }
+
+    /// 当前音轨时钟（位置事件使用）
+    #[must_use]
+    pub fn clock(&self) -> TrackClock {
+        self.clock.clone()
+    }
+
+    /// 检查是否进入淡化区，并在可以时开始淡化
+    #[inline(never)]
+    fn poll_crossfade(&mut self) {
+        if self.fade.is_some() { return; }
+        let Some(total_frames) = self.total_frames else { return };
+        // 仍在循环的音轨结尾不可预知，由无缝播放衔接
+        if self.current.input().is_looping() { return; }
+        let Ok(settings) = self.settings.try_read() else { return };
+        if !settings.enabled { return; }
+        let duration = settings.duration.clamp(CROSSFADE_MIN_SECS, CROSSFADE_MAX_SECS);
+        let curve = settings.curve;
+        drop(settings);
+
+        // 剩余时长按音轨时间计算，淡化时长按播放时间计算
+        let rate = self.stretch.rate() as f64;
+        let fade_frames = (duration as f64 * self.sample_rate as f64 * rate) as u64;
+        let Some(played) = self.track_samples() else { return };
+        let played_frames = played / self.channels as u64;
+        let remaining = total_frames.saturating_sub(played_frames);
+        if remaining > fade_frames || remaining == 0 { return; }
+
+        // 音频线程上不阻塞等待槽位锁
+        let Ok(slot) = self.next_track.try_lock() else { return };
+        let Some((next, input)) = self.take_next(slot) else { return };
+        let (outgoing, from) = self.switch_to(&next, input);
+        let len = ((remaining as f64 / rate) as u64).max(1);
+        self.fade = Some(Fade { outgoing, curve, pos: 0, len });
+        self.notify_transition(from);
+    }
+
+    /// 从槽位取出同格式、音源仍在槽位中的下一首（格式不同或已追加到 sink 的由无缝播放衔接）
+    fn take_next(&self, mut slot: MutexGuard<'_, Option<NextTrack>>) -> Option<(NextTrack, LockFreeSymphoniaSource)> {
+        let input = slot
+            .as_mut()
+            .filter(|n| n.sample_rate() == self.sample_rate && n.channels() == self.channels)
+            .and_then(NextTrack::take_source)?;
+        let next = slot.take()?;
+        drop(slot);
+        next.cancel();
+        Some((next, input))
+    }
+
+    /// 切换到下一首，返回上一首音源和路径
+    fn switch_to(&mut self, next: &NextTrack, input: LockFreeSymphoniaSource) -> (TrackSource, String) {
+        let incoming = StretchSource::new(input, self.stretch.clone(), &next.path, 0.0);
+        let outgoing = std::mem::replace(&mut self.current, incoming);
+        let from = std::mem::replace(&mut self.path, next.path.clone());
+        self.total_frames = total_frames_of(&self.current);
+        self.clock.reset(&self.path, 0);
+        (outgoing, from)
+    }
+
+    /// 当前音轨结束时直接接上槽位中的下一首（共享模式；独占模式由推送线程在 EOF 时接管）
+    ///
+    /// 音频线程上不等待槽位锁：锁被占用时先输出一帧静音，下一帧再尝试。
+    #[inline(never)]
+    fn take_over_next(&mut self) -> Option<f32> {
+        if !matches!(self.notifier, TransitionNotifier::Emit(_)) { return None; }
+        let Ok(slot) = self.next_track.try_lock() else {
+            self.silence = self.channels - 1;
+            return Some(0.0);
+        };
+        let (next, input) = self.take_next(slot)?;
+        let (_, from) = self.switch_to(&next, input);
+        self.notify_transition(from);
+        self.current.next()
+    }
+
+    fn notify_transition(&self, from: String) {
+        match &self.notifier {
+            TransitionNotifier::Emit(events) => {
+                events.send(PlaybackEvent::Switched {
+                    from,
+                    to: self.path.clone(),
+                    duration: self.current.total_duration(),
+                    sample_rate: self.sample_rate,
+                });
+            }
+            TransitionNotifier::Deferred(queue) => {
+                queue.lock().unwrap().push_back(CrossfadeTransition {
+                    at_sample: self.output_samples,
+                    from,
+                    to: self.path.clone(),
+                    duration: self.current.total_duration(),
+                });
+            }
+        }
+    }
+}
+
+impl Iterator for CrossfadeSource {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        self.poll_counter += 1;
+        // 只在帧边界切换，避免声道错位
+        if self.poll_counter >= POLL_INTERVAL && self.output_samples.is_multiple_of(self.channels as u64) {
+            self.poll_counter = 0;
+            if let Some(samples) = self.track_samples() {
+                self.clock.set_samples(samples);
+            }
+            self.poll_crossfade();
+        }
+
+        let sample = if let Some(fade) = self.fade.as_mut() {
+            let t = fade.pos as f32 / fade.len as f32;
+            let (gain_out, gain_in) = fade.curve.gains(t);
+            let outgoing = fade.outgoing.next();
+            let incoming = self.current.next();
+            // 每帧最后一个声道处理完后推进淡化进度
+            if (self.output_samples + 1).is_multiple_of(self.channels as u64) {
+                fade.pos += 1;
+            }
+            if fade.pos >= fade.len {
+                self.fade = None;
+            }
+            match (outgoing, incoming) {
+                (Some(o), Some(i)) => Some(o * gain_out + i * gain_in),
+                (None, Some(i)) => Some(i * gain_in),
+                (Some(o), None) => Some(o * gain_out),
+                (None, None) => None,
+            }
+        } else if self.silence > 0 {
+            self.silence -= 1;
+            Some(0.0)
+        } else {
+            match self.current.next() {
+                Some(sample) => Some(sample),
+                None => self.take_over_next(),
+            }
+        }?;
+
+        self.output_samples += 1;
+        Some(sample)
+    }
+}
+
+impl Source for CrossfadeSource {
+    fn current_span_len(&self) -> Option<usize> { None }
+    fn channels(&self) -> u16 { self.channels }
+    fn sample_rate(&self) -> u32 { self.sample_rate }
+    fn total_duration(&self) -> Option<Duration> { self.current.total_duration() }
+
+    /// 在当前音轨内定位；淡化进行中时直接结束淡出的上一首
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.current.try_seek(pos)?;
+        self.fade = None;
+        let frame = (pos.as_secs_f64() * self.sample_rate as f64) as u64;
+        self.clock.set_samples(frame * self.channels as u64);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [CrossfadeCurve; 3] = [CrossfadeCurve::Linear, CrossfadeCurve::EqualPower, CrossfadeCurve::SCurve];
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-6, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn curves_start_and_end_at_full_gain() {
+        for curve in CURVES {
+            let (out, inc) = curve.gains(0.0);
+            assert_near(out, 1.0);
+            assert_near(inc, 0.0);
+            let (out, inc) = curve.gains(1.0);
+            assert_near(out, 0.0);
+            assert_near(inc, 1.0);
+        }
+    }
+
+    #[test]
+    fn curves_are_monotonic() {
+        for curve in CURVES {
+            let mut last = curve.gains(0.0);
+            for i in 1..=100 {
+                let gains = curve.gains(i as f32 / 100.0);
+                assert!(gains.0 <= last.0 && gains.1 >= last.1, "{curve:?} at {i}%");
+                last = gains;
+            }
+        }
+    }
+
+    #[test]
+    fn equal_power_keeps_constant_power() {
+        for i in 0..=100 {
+            let (out, inc) = CrossfadeCurve::EqualPower.gains(i as f32 / 100.0);
+            assert_near(out.mul_add(out, inc * inc), 1.0);
+        }
+    }
+}
@@ -2,21 +2,26 @@
 //!
 //! 使用 Symphonia 库实现高性能音频解码，支持多种格式。
 
-use crossbeam_channel::{unbounded, Receiver};
+use super::looping::{DecoderLoop, LoopControl, LoopPositions, LoopRegion};
+use super::ring::{sample_ring, RingConsumer, RingMonitor, RingStats};
+use crate::error::{AppError, AppResult, ErrorCode};
+use rodio::source::SeekError;
 use rodio::Source;
+use serde::Serialize;
 use std::fs::File;
 use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
-use symphonia::core::audio::AudioBufferRef;
+use symphonia::core::audio::{AudioBufferRef, Channels};
 use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
 use symphonia::core::errors::Error;
 use symphonia::core::formats::FormatOptions;
 use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
-use symphonia::core::probe::Hint;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision};
+use symphonia::core::probe::{Hint, ProbeResult};
+use symphonia::core::units::TimeBase;
 
 #[derive(Debug, PartialEq, Eq)]
 enum DecoderState {
@@ -49,6 +54,13 @@ impl AudioBuffer {
         if self.position < self.samples.len() { let s = self.samples[self.position]; self.position += 1; Some(s) } else { None }
     }
     fn clear(&mut self) { self.samples.clear(); self.position = 0; }
+    /// 批量读出采样，返回读出的数量
+    fn read_into(&mut self, out: &mut [f32]) -> usize {
+        let n = self.remaining().min(out.len());
+        out[..n].copy_from_slice(&self.samples[self.position..self.position + n]);
+        self.position += n;
+        n
+    }
     fn append(&mut self, samples: &[f32]) { self.samples.extend_from_slice(samples); }
     #[inline] fn remaining(&self) -> usize { self.samples.len() - self.position }
     fn needs_refill(&self) -> bool {
@@ -57,37 +69,217 @@ impl AudioBuffer {
     fn set_refill_threshold(&mut self, threshold_ms: u32) { self.refill_threshold_ms = threshold_ms; }
 }
 
+/// 解码线程领先音频线程的最大时长（环形缓冲区容量），使循环区间等修改能及时生效
+const RING_BUFFER_MS: u32 = 250;
+/// 解码线程与音频线程每次批量读写的采样数
+const RING_CHUNK_SAMPLES: usize = 4096;
+/// 解码结束后检查定位请求的间隔
+const EOF_POLL_INTERVAL: Duration = Duration::from_millis(5);
+/// 定位时新旧位置交叉淡化的时长（毫秒）
+const DECLICK_MS: usize = 5;
+
+/// 音频线程发给解码线程的定位请求
+#[derive(Default)]
+struct SeekRequest {
+    /// 目标帧
+    target: AtomicU64,
+    /// 最新请求的序号
+    requested: AtomicU64,
+    /// 已完成的请求序号
+    completed: AtomicU64,
+}
+
+impl SeekRequest {
+    fn request(&self, frame: u64) -> u64 {
+        self.target.store(frame, Ordering::Relaxed);
+        self.requested.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    /// 尚未处理的请求：(序号, 目标帧)
+    fn pending(&self) -> Option<(u64, u64)> {
+        let generation = self.requested.load(Ordering::Acquire);
+        (generation != self.completed.load(Ordering::Acquire)).then(|| (generation, self.target.load(Ordering::Relaxed)))
+    }
+
+    fn complete(&self, generation: u64) {
+        self.completed.store(generation, Ordering::Release);
+    }
+
+    fn is_completed(&self, generation: u64) -> bool {
+        self.completed.load(Ordering::Acquire) >= generation
+    }
+}
+
+/// 运行中的解码缓冲区（音轨路径与统计句柄）
+static DECODE_BUFFERS: Mutex<Vec<(String, RingMonitor)>> = Mutex::new(Vec::new());
+
+/// 解码缓冲区统计
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodeBufferStats {
+    pub path: String,
+    #[serde(flatten)]
+    pub stats: RingStats,
+}
+
+/// 正在播放和已预加载的解码缓冲区的统计（欠载次数、填充水平），用于评估缓冲效果
+#[must_use]
+pub fn decode_buffer_stats() -> Vec<DecodeBufferStats> {
+    let mut stats = Vec::new();
+    DECODE_BUFFERS.lock().unwrap().retain(|(path, monitor)| match monitor.stats() {
+        Some(ring) => {
+            stats.push(DecodeBufferStats { path: path.clone(), stats: ring });
+            true
+        }
+        None => false,
+    });
+    stats
+}
+
+/// 登记新的解码缓冲区，同时移除已释放的
+fn register_decode_buffer(path: &str, monitor: RingMonitor) {
+    let mut buffers = DECODE_BUFFERS.lock().unwrap();
+    buffers.retain(|(_, existing)| existing.stats().is_some());
+    buffers.push((path.to_string(), monitor));
+}
+
 pub struct LockFreeSymphoniaSource {
-    receiver: Receiver<f32>,
+    consumer: RingConsumer,
     _decoder_thread: thread::JoinHandle<()>,
     stop_flag: Arc<AtomicBool>,
+    seek: Arc<SeekRequest>,
     cached_channels: u16,
     cached_sample_rate: u32,
     cached_total_duration: Option<Duration>,
     chunk_buffer: Vec<f32>,
+    chunk_len: usize,
     chunk_pos: usize,
+    /// 定位前位置的尾部采样（与定位后的采样交叉淡化）
+    declick: Vec<f32>,
+    declick_pos: usize,
+    /// 解码线程尚未完成的定位请求序号
+    pending_seek: Option<u64>,
+    /// 定位后等待解码线程或欠载时输出的静音采样数（不对应音轨位置）
+    padding: u64,
+    /// 正在输出欠载静音（每次欠载只计数一次）
+    underrun: bool,
+    positions: Arc<LoopPositions>,
 }
 
 impl LockFreeSymphoniaSource {
     pub fn new(mut decoder: SymphoniaDecoder) -> Self {
         let (channels, sample_rate, total_duration) = (decoder.target_channels(), decoder.sample_rate(), decoder.total_duration());
-        let (sender, receiver) = unbounded();
+        let positions = decoder.loop_positions();
+        let capacity_frames = calculate_buffer_size(sample_rate, channels, RING_BUFFER_MS) / (channels as usize).max(1);
+        let (mut producer, consumer) = sample_ring(capacity_frames.max(RING_CHUNK_SAMPLES), channels);
         let stop_flag = Arc::new(AtomicBool::new(false));
         let stop_flag_clone = Arc::clone(&stop_flag);
+        let seek = Arc::new(SeekRequest::default());
+        let seek_clone = Arc::clone(&seek);
         let _ = decoder.prefill_buffer();
+        register_decode_buffer(&decoder.path, consumer.monitor());
 
         let decoder_thread = thread::spawn(move || {
-            let mut batch = Vec::with_capacity(16384);
-            loop {
-                if stop_flag_clone.load(Ordering::Relaxed) { break; }
-                batch.clear();
-                for _ in 0..16384 { if let Some(s) = decoder.next() { batch.push(s); } else { break; } }
-                if batch.is_empty() { break; }
-                for s in &batch { if sender.send(*s).is_err() { break; } }
+            let (stop, seek) = (stop_flag_clone, seek_clone);
+            let stopped = || stop.load(Ordering::Relaxed);
+            let mut batch = vec![0.0; RING_CHUNK_SAMPLES];
+            while !stopped() && !producer.is_closed() {
+                if let Some((generation, frame)) = seek.pending() {
+                    // 作废缓冲区中的旧数据，从目标位置重新解码
+                    if let Err(e) = decoder.restart_at(frame) { eprintln!("Seek failed: {e}"); }
+                    producer.flush();
+                    producer.set_finished(false);
+                    let count = decoder.read_samples(&mut batch);
+                    // 写入第一批数据后再通知音频线程，避免定位后立即欠载
+                    producer.push(&batch[..count], stopped);
+                    seek.complete(generation);
+                    continue;
+                }
+                let count = decoder.read_samples(&mut batch);
+                if count == 0 {
+                    // 解码结束，音源仍可能被定位，等待请求直到音源被丢弃
+                    producer.set_finished(true);
+                    while seek.pending().is_none() && !stopped() && !producer.is_closed() { thread::sleep(EOF_POLL_INTERVAL); }
+                    continue;
+                }
+                // 缓冲区已满时在这里阻塞，直到音频线程读出数据；有定位请求时放弃这批数据
+                producer.push(&batch[..count], || stopped() || seek.pending().is_some());
             }
         });
 
-        Self { receiver, _decoder_thread: decoder_thread, stop_flag, cached_channels: channels, cached_sample_rate: sample_rate, cached_total_duration: total_duration, chunk_buffer: Vec::with_capacity(16384), chunk_pos: 0 }
+        Self { consumer, _decoder_thread: decoder_thread, stop_flag, seek, cached_channels: channels, cached_sample_rate: sample_rate, cached_total_duration: total_duration, chunk_buffer: vec![0.0; RING_CHUNK_SAMPLES], chunk_len: 0, chunk_pos: 0, declick: Vec::new(), declick_pos: 0, pending_seek: None, padding: 0, underrun: false, positions }
+    }
+
+    /// 已输出的帧数对应的音轨帧（考虑循环跳转与起始位置）
+    #[must_use]
+    pub fn track_frame(&self, output_frame: u64) -> Option<u64> {
+        let padding = self.padding / u64::from(self.cached_channels.max(1));
+        self.positions.track_frame(output_frame.saturating_sub(padding))
+    }
+
+    /// 解码器是否仍会循环
+    #[must_use]
+    pub fn is_looping(&self) -> bool {
+        self.positions.is_looping()
+    }
+
+    /// 缓冲区为空且解码未结束时立即输出一帧静音（不等待解码线程），避免音源提前结束
+    ///
+    /// 静音不对应音轨位置，计入 `padding`。
+    fn underrun_frame(&mut self) -> Option<usize> {
+        if self.consumer.is_drained() { return None; }
+        if !self.underrun {
+            self.underrun = true;
+            self.consumer.record_underrun();
+        }
+        let frame = (self.cached_channels as usize).max(1);
+        self.chunk_buffer[..frame].fill(0.0);
+        self.padding += frame as u64;
+        Some(frame)
+    }
+
+    /// 保存即将播放的旧位置采样，作为定位后交叉淡化的淡出部分（不足时补零）
+    fn capture_declick(&mut self) {
+        let len = (self.cached_sample_rate as usize * DECLICK_MS / 1000).max(1) * (self.cached_channels as usize).max(1);
+        self.declick.clear();
+        let pending = (self.chunk_len - self.chunk_pos).min(len);
+        self.declick.extend_from_slice(&self.chunk_buffer[self.chunk_pos..self.chunk_pos + pending]);
+        self.chunk_len = 0;
+        self.chunk_pos = 0;
+        let start = self.declick.len();
+        self.declick.resize(len, 0.0);
+        let count = self.consumer.pop(&mut self.declick[start..]);
+        self.declick[start + count..].fill(0.0);
+        self.declick_pos = 0;
+    }
+
+    /// 定位请求尚未完成时的输出：作废旧数据，旧位置淡出后输出静音（完成后返回 None）
+    #[inline(never)]
+    fn pad_seek(&mut self, generation: u64) -> Option<f32> {
+        if self.seek.is_completed(generation) {
+            self.pending_seek = None;
+            // 淡出已经结束：新位置从静音淡入
+            if self.padding > 0 && self.declick_pos >= self.declick.len() {
+                self.declick.fill(0.0);
+                self.declick_pos = 0;
+            }
+            return None;
+        }
+        self.consumer.skip_flushed();
+        self.padding += 1;
+        if self.declick_pos < self.declick.len() { return Some(self.declick_sample(0.0)); }
+        Some(0.0)
+    }
+
+    /// 定位后的交叉淡化：旧位置淡出，新位置淡入
+    #[inline(never)]
+    fn declick_sample(&mut self, sample: f32) -> f32 {
+        let channels = (self.cached_channels as usize).max(1);
+        let frames = self.declick.len() / channels;
+        let gain = ((self.declick_pos / channels) as f32 + 0.5) / frames as f32;
+        let old = self.declick[self.declick_pos];
+        self.declick_pos += 1;
+        sample * gain + old * (1.0 - gain)
     }
 }
 
@@ -95,20 +287,24 @@ impl Iterator for LockFreeSymphoniaSource {
     type Item = f32;
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        if self.chunk_pos < self.chunk_buffer.len() { let s = self.chunk_buffer[self.chunk_pos]; self.chunk_pos += 1; return Some(s); }
-        self.chunk_buffer.clear(); self.chunk_pos = 0;
-        let mut count = 0;
-        loop {
-            match self.receiver.try_recv() {
-                Ok(s) => { self.chunk_buffer.push(s); count += 1; if count >= 16384 { break; } }
-                Err(crossbeam_channel::TryRecvError::Empty) => {
-                    if count > 0 { break; }
-                    match self.receiver.recv_timeout(Duration::from_micros(100)) { Ok(s) => { self.chunk_buffer.push(s); count += 1; } Err(_) => break }
-                }
-                Err(crossbeam_channel::TryRecvError::Disconnected) => break,
+        if self.chunk_pos >= self.chunk_len {
+            if let Some(generation) = self.pending_seek
+                && let Some(s) = self.pad_seek(generation)
+            {
+                return Some(s);
+            }
+            self.chunk_pos = 0;
+            self.chunk_len = self.consumer.pop(&mut self.chunk_buffer);
+            if self.chunk_len == 0 {
+                self.chunk_len = self.underrun_frame()?;
+            } else {
+                self.underrun = false;
             }
         }
-        if self.chunk_pos < self.chunk_buffer.len() { let s = self.chunk_buffer[self.chunk_pos]; self.chunk_pos += 1; Some(s) } else { None }
+        let s = self.chunk_buffer[self.chunk_pos];
+        self.chunk_pos += 1;
+        if self.declick_pos < self.declick.len() { return Some(self.declick_sample(s)); }
+        Some(s)
     }
 }
 
@@ -117,10 +313,35 @@ impl Source for LockFreeSymphoniaSource {
     fn channels(&self) -> u16 { self.cached_channels }
     fn sample_rate(&self) -> u32 { self.cached_sample_rate }
     fn total_duration(&self) -> Option<Duration> { self.cached_total_duration }
+
+    /// 在运行中的解码线程内定位：清空缓冲区，输出帧从 0 重新计数
+    ///
+    /// 只发出请求、不等待（可能在音频线程调用），解码线程完成前输出淡出和静音。
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let mut frame = (pos.as_secs_f64() * self.cached_sample_rate as f64) as u64;
+        if let Some(total) = self.cached_total_duration {
+            frame = frame.min((total.as_secs_f64() * self.cached_sample_rate as f64) as u64);
+        }
+        // 上一次定位仍未完成时旧位置已经淡出，不再重新截取
+        if self.pending_seek.is_none() {
+            self.capture_declick();
+        }
+        self.pending_seek = Some(self.seek.request(frame));
+        self.padding = 0;
+        self.underrun = false;
+        self.consumer.wake_producer();
+        Ok(())
+    }
 }
 
 impl Drop for LockFreeSymphoniaSource {
-    fn drop(&mut self) { self.stop_flag.store(true, Ordering::Relaxed); }
+    fn drop(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+        let stats = self.consumer.stats();
+        if stats.underruns > 0 {
+            println!("Decode buffer: {} underruns, min fill {}/{} samples", stats.underruns, stats.min_fill, stats.capacity);
+        }
+    }
 }
 
 pub struct SymphoniaSource {
@@ -171,31 +392,123 @@ pub struct SymphoniaDecoder {
     current_sample: u64,
     target_channels: u16,
     source_channels: u16,
+    /// 源声道布局（未知时为 None）
+    channel_layout: Option<Channels>,
     channel_map: Option<Vec<usize>>,
+    time_base: Option<TimeBase>,
+    /// 已写入缓冲区的帧数（即解码器输出的帧序号）
+    appended: u64,
+    /// 定位后需要丢弃的帧（包的起点早于目标位置）
+    skip_until: Option<u64>,
+    /// LOOPSTART / LOOPLENGTH / LOOPEND 标签
+    tag_loop: Option<LoopRegion>,
+    looping: Option<DecoderLoop>,
+    positions: Arc<LoopPositions>,
+}
+
+/// 从元数据中读取循环标签
+fn read_loop_tags(probed: &mut ProbeResult, total_frames: Option<u64>) -> Option<LoopRegion> {
+    let (mut start, mut length, mut end) = (None, None, None);
+    let mut scan = |revision: &MetadataRevision| {
+        for tag in revision.tags() {
+            // ID3v2 的 TXXX 帧键名可能带有前缀
+            let key = tag.key.rsplit(':').next().unwrap_or_default().to_ascii_uppercase();
+            let value = tag.value.to_string().trim().parse::<u64>().ok();
+            match key.as_str() {
+                "LOOPSTART" => start = value.or(start),
+                "LOOPLENGTH" => length = value.or(length),
+                "LOOPEND" => end = value.or(end),
+                _ => {}
+            }
+        }
+    };
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+        scan(revision);
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        scan(revision);
+    }
+    LoopRegion::from_tags(start, length, end, total_frames)
+}
+
+/// 包时间戳换算为帧
+#[inline]
+fn ts_to_frame(ts: u64, time_base: Option<TimeBase>, sample_rate: u32) -> u64 {
+    match time_base {
+        Some(tb) if tb.denom != sample_rate || tb.numer != 1 => {
+            (ts as u128 * tb.numer as u128 * sample_rate as u128 / tb.denom.max(1) as u128) as u64
+        }
+        _ => ts,
+    }
 }
 
 impl SymphoniaDecoder {
-    pub fn new(path: &str) -> Result<Self, String> { Self::new_with_buffer_duration(path, None) }
+    pub fn new(path: &str) -> AppResult<Self> { Self::new_with_buffer_duration(path, None) }
 
-    pub fn new_with_buffer_duration(path: &str, buffer_duration_ms: Option<u32>) -> Result<Self, String> {
-        let file = File::open(path).map_err(|e| e.to_string())?;
-        let mss = MediaSourceStream::new(Box::new(file.try_clone().map_err(|e| e.to_string())?), Default::default());
+    pub fn new_with_buffer_duration(path: &str, buffer_duration_ms: Option<u32>) -> AppResult<Self> {
+        let file = File::open(path).map_err(|e| AppError::from(e).with_path(path))?;
+        let mss = MediaSourceStream::new(Box::new(file.try_clone()?), Default::default());
         let mut hint = Hint::new();
         if let Some(ext) = Path::new(path).extension().and_then(|s| s.to_str()) { hint.with_extension(ext); }
         let mut fmt_opts: FormatOptions = Default::default();
         fmt_opts.enable_gapless = true;
-        let probed = symphonia::default::get_probe().format(&hint, mss, &fmt_opts, &MetadataOptions::default()).map_err(|e| format!("Failed to probe format: {e}"))?;
-        let format = probed.format;
-        let track = format.tracks().iter().find(|t| t.codec_params.codec != CODEC_TYPE_NULL).ok_or("No audio track found")?;
+        let mut probed = symphonia::default::get_probe()
+            .format(&hint, mss, &fmt_opts, &MetadataOptions::default())
+            .map_err(|e| AppError::from(e).with_code(ErrorCode::UnsupportedFormat).context_message("Failed to probe format").with_path(path))?;
+        let track = probed
+            .format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| AppError::new(ErrorCode::UnsupportedFormat, "No audio track found").with_path(path))?;
         let sample_rate = track.codec_params.sample_rate.unwrap_or(44100);
-        let source_channels = track.codec_params.channels.map(|c| c.count()).unwrap_or(2) as u16;
+        let channel_layout = track.codec_params.channels;
+        let source_channels = channel_layout.map(|c| c.count()).unwrap_or(2) as u16;
         let total_duration = track.codec_params.n_frames.and_then(|n| track.codec_params.sample_rate.map(|sr| Duration::from_secs_f64(n as f64 / sr as f64)));
+        let (n_frames, time_base) = (track.codec_params.n_frames, track.codec_params.time_base);
+        let tag_loop = read_loop_tags(&mut probed, n_frames);
         let buffer_duration_ms = buffer_duration_ms.unwrap_or(if sample_rate <= 48000 { 500 } else { 400 });
         let target_channels = 2u16;
         let buffer_size = calculate_buffer_size(sample_rate, target_channels, buffer_duration_ms);
         let channel_map = Self::create_channel_mapping(source_channels);
 
-        Ok(Self { path: path.to_string(), sample_rate, source_channels, total_duration, state: DecoderState::Uninitialized, buffer: AudioBuffer::new(buffer_size, sample_rate, target_channels), scratch_buffer: Vec::with_capacity(4096), decoder: None, format: None, track_id: None, current_sample: 0, target_channels, channel_map })
+        Ok(Self { path: path.to_string(), sample_rate, source_channels, channel_layout, total_duration, state: DecoderState::Uninitialized, buffer: AudioBuffer::new(buffer_size, sample_rate, target_channels), scratch_buffer: Vec::with_capacity(4096), decoder: None, format: None, track_id: None, current_sample: 0, target_channels, channel_map, time_base, appended: 0, skip_until: None, tag_loop, looping: None, positions: Arc::new(LoopPositions::default()) })
+    }
+
+    /// 启用循环（A–B 区间与循环标签），需在预填充之前调用
+    #[must_use]
+    pub fn with_looping(mut self, control: &Arc<LoopControl>) -> Self {
+        self.looping = Some(DecoderLoop::new(Arc::clone(control), &self.path, self.sample_rate, self.tag_loop, Arc::clone(&self.positions)));
+        self
+    }
+
+    /// 保留源声道，不下混为立体声（响度分析需要按声道加权）
+    #[must_use]
+    pub fn with_source_channels(mut self) -> Self {
+        let channels = self.source_channels.max(1);
+        let capacity = self.buffer.capacity / self.target_channels as usize * channels as usize;
+        self.channel_map = None;
+        self.target_channels = channels;
+        self.buffer = AudioBuffer::new(capacity, self.sample_rate, channels);
+        self
+    }
+
+    /// 源声道布局
+    #[must_use]
+    pub const fn channel_layout(&self) -> Option<Channels> { self.channel_layout }
+
+    /// 循环标签给出的区间（帧）
+    #[must_use]
+    pub const fn loop_tags(&self) -> Option<LoopRegion> { self.tag_loop }
+
+    /// 输出帧与音轨帧的对应关系
+    #[must_use]
+    pub fn loop_positions(&self) -> Arc<LoopPositions> { Arc::clone(&self.positions) }
+
+    /// 每帧输出的采样数（多声道下混为立体声，保留源声道时除外）
+    #[inline]
+    const fn output_channels(&self) -> usize {
+        if self.channel_map.is_some() && self.source_channels > 2 { 2 } else { self.source_channels as usize }
     }
 
     fn create_channel_mapping(channels: u16) -> Option<Vec<usize>> {
@@ -287,7 +600,7 @@ impl SymphoniaDecoder {
     #[must_use] pub const fn sample_rate(&self) -> u32 { self.sample_rate }
     #[must_use] pub const fn total_duration(&self) -> Option<Duration> { self.total_duration }
 
-    pub fn prefill_buffer(&mut self) -> Result<(), String> {
+    pub fn prefill_buffer(&mut self) -> AppResult<()> {
         if self.state == DecoderState::Uninitialized { self.initialize_decoder()?; }
         let target_size = (self.buffer.capacity * 95) / 100;
         let mut attempts = 0;
@@ -295,56 +608,138 @@ impl SymphoniaDecoder {
             if let Err(e) = self.fill_buffer() {
                 if self.state == DecoderState::EndOfStream { break; }
                 if attempts > 10 && self.buffer.remaining() < (self.buffer.capacity * 50) / 100 {
-                    return Err(format!("Buffer prefill failed after {attempts} attempts: {e}"));
+                    return Err(e.context_message(&format!("Buffer prefill failed after {attempts} attempts")));
                 }
             }
             attempts += 1;
         }
         if self.buffer.remaining() < (self.buffer.capacity * 50) / 100 {
-            return Err(format!("Buffer prefill incomplete: only {}% filled", (self.buffer.remaining() * 100) / self.buffer.capacity));
+            let filled = (self.buffer.remaining() * 100) / self.buffer.capacity;
+            return Err(AppError::new(ErrorCode::DecodeFailed, format!("Buffer prefill incomplete: only {filled}% filled")).with_path(&self.path));
         }
         Ok(())
     }
 
-    pub fn seek(&mut self, time: Duration) -> Result<(), String> {
-        let target_ts = (time.as_secs_f64() * self.sample_rate as f64) as u64;
+    /// 批量读取采样（解码线程使用），返回读取的数量，0 表示已结束
+    pub fn read_samples(&mut self, out: &mut [f32]) -> usize {
+        let mut written = 0;
+        while written < out.len() {
+            if self.buffer.is_empty() || self.buffer.needs_refill() {
+                if let Err(e) = self.fill_buffer() { eprintln!("Buffer fill error: {e}"); if self.buffer.is_empty() { break; } }
+            }
+            let count = self.buffer.read_into(&mut out[written..]);
+            if count == 0 { break; }
+            written += count;
+        }
+        self.current_sample += written as u64;
+        written
+    }
+
+    pub fn seek(&mut self, time: Duration) -> AppResult<()> {
+        self.seek_frame((time.as_secs_f64() * self.sample_rate as f64) as u64)
+    }
+
+    /// 定位到指定帧，并从 0 重新计数输出帧（运行中的解码线程定位时使用，此前的输出已作废）
+    pub fn restart_at(&mut self, frame: u64) -> AppResult<()> {
+        self.buffer.clear();
+        self.appended = 0;
+        self.seek_frame(frame)
+    }
+
+    fn seek_frame(&mut self, target_ts: u64) -> AppResult<()> {
         self.current_sample = target_ts;
+        // 缓冲区中尚未输出的帧被丢弃
+        self.appended = self.appended.saturating_sub((self.buffer.remaining() / self.output_channels().max(1)) as u64);
         self.buffer.clear();
-        if let (Some(format), Some(decoder)) = (&mut self.format, &mut self.decoder) {
-            let seek_to = symphonia::core::formats::SeekTo::TimeStamp { ts: target_ts, track_id: self.track_id.unwrap() };
-            match format.seek(symphonia::core::formats::SeekMode::Accurate, seek_to) {
-                Ok(_) => { decoder.reset(); self.state = DecoderState::Ready; Ok(()) }
-                Err(e) => { self.current_sample = 0; self.state = DecoderState::Uninitialized; Err(format!("Seek failed: {e:?}")) }
+        if self.format.is_some() && self.decoder.is_some() {
+            match self.seek_stream(target_ts) {
+                Ok(()) => { self.state = DecoderState::Ready; Ok(()) }
+                Err(e) => { self.current_sample = 0; self.state = DecoderState::Uninitialized; Err(e) }
+            }
+        } else {
+            self.positions.mark(self.appended, target_ts);
+            self.state = DecoderState::Uninitialized;
+            Ok(())
+        }
+    }
+
+    /// 定位到指定帧（不清空缓冲区），之后输出的第一帧即为该帧
+    fn seek_stream(&mut self, frame: u64) -> AppResult<()> {
+        let (Some(format), Some(decoder), Some(track_id)) = (&mut self.format, &mut self.decoder, self.track_id) else {
+            return Err(AppError::new(ErrorCode::Internal, "Decoder not initialized"));
+        };
+        let ts = match self.time_base {
+            Some(tb) if tb.denom != self.sample_rate || tb.numer != 1 => {
+                (frame as u128 * tb.denom as u128 / (tb.numer.max(1) as u128 * self.sample_rate as u128)) as u64
             }
-        } else { self.state = DecoderState::Uninitialized; Ok(()) }
+            _ => frame,
+        };
+        let seek_to = symphonia::core::formats::SeekTo::TimeStamp { ts, track_id };
+        format.seek(symphonia::core::formats::SeekMode::Accurate, seek_to).map_err(|e| AppError::from(e).context_message("Seek failed").with_path(&self.path))?;
+        decoder.reset();
+        self.skip_until = Some(frame);
+        self.positions.mark(self.appended, frame);
+        Ok(())
     }
 
-    fn initialize_decoder(&mut self) -> Result<(), String> {
-        let file = File::open(&self.path).map_err(|e| e.to_string())?;
-        let mss = MediaSourceStream::new(Box::new(file.try_clone().map_err(|e| e.to_string())?), Default::default());
+    fn initialize_decoder(&mut self) -> AppResult<()> {
+        let file = File::open(&self.path).map_err(|e| AppError::from(e).with_path(&self.path))?;
+        let mss = MediaSourceStream::new(Box::new(file.try_clone()?), Default::default());
         let mut hint = Hint::new();
         if let Some(ext) = Path::new(&self.path).extension().and_then(|s| s.to_str()) { hint.with_extension(ext); }
         let mut fmt_opts: FormatOptions = Default::default();
         fmt_opts.enable_gapless = true;
-        let probed = symphonia::default::get_probe().format(&hint, mss, &fmt_opts, &MetadataOptions::default()).map_err(|e| format!("Failed to probe format: {e}"))?;
-        let mut format = probed.format;
-        let track = format.tracks().iter().find(|t| t.codec_params.codec != CODEC_TYPE_NULL).ok_or("No audio track found")?;
+        let probed = symphonia::default::get_probe()
+            .format(&hint, mss, &fmt_opts, &MetadataOptions::default())
+            .map_err(|e| AppError::from(e).with_code(ErrorCode::UnsupportedFormat).context_message("Failed to probe format").with_path(&self.path))?;
+        let format = probed.format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| AppError::new(ErrorCode::UnsupportedFormat, "No audio track found").with_path(&self.path))?;
         let track_id = track.id;
-        let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default()).map_err(|e| format!("Failed to create decoder: {e}"))?;
-        if self.current_sample > 0 {
-            let seek_to = symphonia::core::formats::SeekTo::TimeStamp { ts: self.current_sample, track_id };
-            if format.seek(symphonia::core::formats::SeekMode::Accurate, seek_to).is_ok() { decoder.reset(); } else { self.current_sample = 0; }
-        }
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| AppError::from(e).context_message("Failed to create decoder").with_path(&self.path))?;
         self.format = Some(format); self.decoder = Some(decoder); self.track_id = Some(track_id); self.state = DecoderState::Ready;
+        if self.current_sample > 0 && self.seek_stream(self.current_sample).is_err() {
+            self.current_sample = 0;
+            self.positions.mark(self.appended, 0);
+        }
         Ok(())
     }
 
-    fn fill_buffer(&mut self) -> Result<(), String> {
+    fn fill_buffer(&mut self) -> AppResult<()> {
         if self.state == DecoderState::Uninitialized { self.initialize_decoder()?; }
-        if matches!(self.state, DecoderState::Error(_) | DecoderState::EndOfStream) { return Ok(()); }
+        let mut jumped = false;
+        loop {
+            // 到达循环终点时跳回起点，继续填充
+            let appended = self.appended;
+            let Some(start) = self.fill_packets()? else { return Ok(()) };
+            if jumped && self.appended == appended {
+                // 跳回起点后没有输出任何帧（区间为空或起点超出文件结尾），继续跳转会死循环
+                eprintln!("Loop from frame {start} produced no audio, stopping");
+                self.state = DecoderState::EndOfStream;
+                return Ok(());
+            }
+            jumped = true;
+            if let Err(e) = self.seek_stream(start) {
+                eprintln!("Loop seek failed: {e}");
+                self.state = DecoderState::EndOfStream;
+                return Ok(());
+            }
+        }
+    }
+
+    /// 解码数据包填充缓冲区，到达需要跳转的循环终点时返回区间起点
+    fn fill_packets(&mut self) -> AppResult<Option<u64>> {
+        if matches!(self.state, DecoderState::Error(_) | DecoderState::EndOfStream) { return Ok(None); }
+        let region = self.looping.as_mut().and_then(DecoderLoop::region);
         let format = self.format.as_mut().unwrap();
         let decoder = self.decoder.as_mut().unwrap();
         let track_id = self.track_id.unwrap();
+        let ch = if self.channel_map.is_some() && self.source_channels > 2 { 2 } else { (self.source_channels as usize).max(1) };
         let mut decoded_packets = 0;
         let target_fill = (self.buffer.capacity * 80) / 100;
 
@@ -352,23 +747,61 @@ impl SymphoniaDecoder {
             let packet = match format.next_packet() {
                 Ok(p) => p,
                 Err(Error::ResetRequired) => { decoder.reset(); continue; }
-                Err(Error::IoError(ref e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => { self.state = DecoderState::EndOfStream; break; }
-                Err(e) => { self.state = DecoderState::Error(format!("Read packet error: {e}")); return Err(format!("Read packet error: {e}")); }
+                Err(Error::IoError(ref e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    // 循环终点超出文件长度时在结尾处跳转
+                    if let (Some(r), Some(l)) = (region, self.looping.as_mut()) && l.on_loop_end() { return Ok(Some(r.start)); }
+                    self.state = DecoderState::EndOfStream;
+                    break;
+                }
+                Err(e) => {
+                    let err = AppError::from(e).context_message("Read packet error").with_path(&self.path);
+                    self.state = DecoderState::Error(err.to_string());
+                    return Err(err);
+                }
             };
             if packet.track_id() != track_id { continue; }
+            let packet_frame = ts_to_frame(packet.ts(), self.time_base, self.sample_rate);
             match decoder.decode(&packet) {
                 Ok(decoded) => { 
                     self.scratch_buffer.clear(); 
                     Self::convert_audio_buffer(decoded, &mut self.scratch_buffer, &self.channel_map, self.source_channels as usize); 
-                    self.buffer.append(&self.scratch_buffer); 
                     decoded_packets += 1; 
+                    let frames = self.scratch_buffer.len() / ch;
+                    let mut from = 0;
+                    if let Some(skip) = self.skip_until {
+                        from = (skip.saturating_sub(packet_frame) as usize).min(frames);
+                        if from < frames { self.skip_until = None; }
+                    }
+                    let mut to = frames;
+                    let jump = if let (Some(r), Some(l)) = (region, self.looping.as_mut())
+                        && packet_frame + frames as u64 > r.end
+                        && l.on_loop_end()
+                    {
+                        to = (r.end.saturating_sub(packet_frame) as usize).clamp(from, frames);
+                        Some(r.start)
+                    } else {
+                        None
+                    };
+                    let kept = &mut self.scratch_buffer[from * ch..to * ch];
+                    let keep = self.looping.as_mut().map_or(kept.len(), |l| l.apply_fade(kept, ch));
+                    self.buffer.append(&kept[..keep]);
+                    self.appended += (keep / ch) as u64;
+                    if self.looping.as_ref().is_some_and(DecoderLoop::faded_out) {
+                        self.state = DecoderState::EndOfStream;
+                        break;
+                    }
+                    if jump.is_some() { return Ok(jump); }
                 }
                 Err(Error::IoError(ref e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => { self.state = DecoderState::EndOfStream; break; }
                 Err(Error::DecodeError(_)) => continue,
-                Err(e) => { self.state = DecoderState::Error(format!("Decode error: {e}")); return Err(format!("Decode error: {e}")); }
+                Err(e) => {
+                    let err = AppError::from(e).context_message("Decode error").with_path(&self.path);
+                    self.state = DecoderState::Error(err.to_string());
+                    return Err(err);
+                }
             }
         }
-        Ok(())
+        Ok(None)
     }
 
     fn convert_audio_buffer(audio_buf: AudioBufferRef, samples: &mut Vec<f32>, channel_map: &Option<Vec<usize>>, src_channels: usize) {
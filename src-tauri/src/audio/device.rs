@@ -2,6 +2,7 @@
 //!
 //! 提供音频设备的检测、切换和管理功能。
 
+use crate::error::{AppError, AppResult};
 use cpal::traits::{DeviceTrait, HostTrait};
 use cpal::StreamConfig;
 use serde::Serialize;
@@ -18,11 +19,11 @@ pub struct AudioDeviceInfo {
 }
 
 /// 获取所有可用的音频输出设备
-pub fn get_all_audio_devices() -> Result<Vec<AudioDeviceInfo>, String> {
+pub fn get_all_audio_devices() -> AppResult<Vec<AudioDeviceInfo>> {
     let host = cpal::default_host();
     let default_device_name = host.default_output_device().and_then(|d| d.name().ok());
 
-    let devices = host.output_devices().map_err(|e| e.to_string())?;
+    let devices = host.output_devices().map_err(|e| AppError::device_unavailable(format!("Failed to get output devices: {e}")).with_source(e))?;
     let mut device_infos: Vec<AudioDeviceInfo> = Vec::new();
 
     for device in devices {
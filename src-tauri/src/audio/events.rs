@@ -0,0 +1,87 @@
+//! 播放事件线程
+//!
+//! rodio 的输出回调（音频线程）上不能等待锁或发送 Tauri 事件。音源链在音轨结束或切换时
+//! 只把事件放入有界通道（不阻塞，满时丢弃），由事件线程更新当前路径、播放状态、
+//! 睡眠定时器、播放队列和历史，并发送 `track-ended` / `track-transition` 事件。
+
+use super::gapless::{self, NextTrackSlot};
+use super::playback::emit_track_ended;
+use crate::AppState;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// 通道容量（每首音轨只产生少量事件）
+const EVENT_CAPACITY: usize = 64;
+
+/// 音频线程发出的播放事件
+pub enum PlaybackEvent {
+    /// 音源链播放到结尾，已衔接的下一首由事件线程从槽位读取
+    Ended { path: Option<String>, next_track: Option<NextTrackSlot> },
+    /// 音源链内切换到下一首（交叉淡化或同格式直接接上）
+    Switched { from: String, to: String, duration: Option<Duration>, sample_rate: u32 },
+    /// 追加到 sink 的下一首开始播放
+    GaplessStarted { path: String, cancelled: Arc<AtomicBool>, format: (u32, u16), duration: Option<Duration> },
+}
+
+/// 播放事件通道（由播放器状态持有，音源链持有克隆）
+#[derive(Clone)]
+pub struct PlaybackEvents {
+    sender: SyncSender<PlaybackEvent>,
+    receiver: Arc<Mutex<Option<Receiver<PlaybackEvent>>>>,
+}
+
+impl Default for PlaybackEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlaybackEvents {
+    #[must_use]
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::sync_channel(EVENT_CAPACITY);
+        Self { sender, receiver: Arc::new(Mutex::new(Some(receiver))) }
+    }
+
+    /// 启动事件线程（只有第一次调用生效）
+    pub fn start(&self, app: AppHandle) {
+        let Some(receiver) = self.receiver.lock().unwrap().take() else { return };
+        let spawned = thread::Builder::new().name("playback-events".to_string()).spawn(move || {
+            for event in receiver {
+                handle(&app, event);
+            }
+        });
+        if let Err(e) = spawned {
+            eprintln!("Failed to start playback event thread: {e}");
+        }
+    }
+
+    /// 发送事件（音频线程调用，不阻塞）
+    #[inline]
+    pub fn send(&self, event: PlaybackEvent) {
+        let _ = self.sender.try_send(event);
+    }
+}
+
+fn handle(app: &AppHandle, event: PlaybackEvent) {
+    match event {
+        PlaybackEvent::Ended { path, next_track } => {
+            let next_path = next_track.as_ref().and_then(gapless::peek_next_path);
+            let _ = emit_track_ended(app, path, next_path);
+        }
+        PlaybackEvent::Switched { from, to, duration, sample_rate } => {
+            if let Some(state) = app.try_state::<AppState>() {
+                *state.player.current_path.lock().unwrap() = Some(to.clone());
+            }
+            let _ = emit_track_ended(app, Some(from.clone()), Some(to.clone()));
+            let _ = gapless::emit_track_transition(app, Some(from), &to, duration, sample_rate);
+        }
+        PlaybackEvent::GaplessStarted { path, cancelled, format, duration } => {
+            gapless::on_entry_started(app, &path, &cancelled, format, duration);
+        }
+    }
+}
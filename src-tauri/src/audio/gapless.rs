@@ -0,0 +1,211 @@
+//! 无缝播放模块
+//!
+//! 提前打开下一首音轨并构建音源，使其在当前音轨最后一个采样之后立即开始播放。
+//! 共享模式下同格式的下一首由当前音源链在结尾直接接管，格式不同时追加到同一个 `Sink`；
+//! 独占模式下由 WASAPI 推送线程在 EOF 时接管。
+
+use super::decoder::{LockFreeSymphoniaSource, SymphoniaDecoder};
+use super::events::{PlaybackEvent, PlaybackEvents};
+use super::looping::LoopControl;
+use crate::error::AppResult;
+use crate::AppState;
+use rodio::Source;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// 下一首音轨槽位（由播放器状态持有）
+pub type NextTrackSlot = Arc<Mutex<Option<NextTrack>>>;
+
+/// 音轨切换事件（无缝衔接到下一首时发送）
+#[derive(Debug, serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackTransitionEvent {
+    pub from: Option<String>,
+    pub to: String,
+}
+
+pub fn emit_track_transition(app: &AppHandle, from: Option<String>, to: &str, duration: Option<Duration>, sample_rate: u32) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(state) = app.try_state::<AppState>() {
+        state.player.player_state.track_changed(app, to, duration, sample_rate);
+    }
+    app.emit("track-transition", TrackTransitionEvent { from, to: to.to_string() })?;
+    Ok(())
+}
+
+/// 共享模式下正在播放的 Symphonia 音源链的采样率与声道数（rodio 回退解码时为 None）
+///
+/// 该音源链会在结尾直接接管同格式的下一首，格式不同的下一首才追加到 sink。
+pub type ChainFormat = Arc<Mutex<Option<(u32, u16)>>>;
+
+/// 已排队的下一首音轨
+pub struct NextTrack {
+    pub path: String,
+    /// 预先构建的音源（解码线程已启动并填充缓冲区），交叉淡化或衔接时直接取走
+    source: Option<LockFreeSymphoniaSource>,
+    sample_rate: u32,
+    channels: u16,
+    /// 共享模式下已追加到 sink 的音源的取消标志
+    cancelled: Arc<AtomicBool>,
+    looping: Arc<LoopControl>,
+}
+
+/// 打开并预填充解码器，构建无锁音源
+fn build_source(path: &str, looping: &Arc<LoopControl>) -> AppResult<LockFreeSymphoniaSource> {
+    let mut decoder = SymphoniaDecoder::new(path).map_err(|e| e.context_message("Failed to create decoder"))?.with_looping(looping);
+    decoder.prefill_buffer().map_err(|e| e.with_path(path))?;
+    Ok(LockFreeSymphoniaSource::new(decoder))
+}
+
+impl NextTrack {
+    /// 打开下一首音轨并提前构建音源
+    pub fn prepare(path: &str, looping: &Arc<LoopControl>) -> AppResult<Self> {
+        let source = build_source(path, looping)?;
+        Ok(Self {
+            path: path.to_string(),
+            sample_rate: source.sample_rate(),
+            channels: source.channels(),
+            source: Some(source),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            looping: Arc::clone(looping),
+        })
+    }
+
+    /// 取消已追加到 sink 的音源（音源开始播放时会直接跳过）
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// 为共享模式创建新的取消标志，并丢弃旧的音源
+    pub fn rearm(&mut self) -> Arc<AtomicBool> {
+        self.cancel();
+        self.cancelled = Arc::new(AtomicBool::new(false));
+        Arc::clone(&self.cancelled)
+    }
+
+    /// 准备时记录的采样率（音源被取走后仍然有效）
+    #[must_use]
+    pub const fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// 准备时记录的声道数
+    #[must_use]
+    pub const fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// 取出预先构建的音源（不读写文件，可在音频线程调用），已被取走时返回 None
+    pub const fn take_source(&mut self) -> Option<LockFreeSymphoniaSource> {
+        self.source.take()
+    }
+
+    /// 确保预先构建的音源存在，已被取走时重新打开（会读写文件，不能在音频线程调用）
+    pub fn ensure_source(&mut self) -> AppResult<()> {
+        if self.source.is_none() {
+            self.source = Some(build_source(&self.path, &self.looping)?);
+        }
+        Ok(())
+    }
+
+    /// 取出音源，已被取走时重新打开（会读写文件，不能在音频线程调用）
+    pub fn open_source(&mut self) -> AppResult<LockFreeSymphoniaSource> {
+        match self.source.take() {
+            Some(source) => Ok(source),
+            None => build_source(&self.path, &self.looping),
+        }
+    }
+}
+
+/// 获取已排队的下一首音轨路径
+#[must_use]
+pub fn peek_next_path(slot: &NextTrackSlot) -> Option<String> {
+    slot.lock().unwrap().as_ref().map(|n| n.path.clone())
+}
+
+/// 清除已排队的下一首音轨
+pub fn clear_next_track(slot: &NextTrackSlot) {
+    let pending = slot.lock().unwrap().take();
+    if let Some(next) = pending {
+        next.cancel();
+    }
+}
+
+/// 替换已排队的下一首音轨（旧条目会被取消）
+pub fn replace_next_track(slot: &NextTrackSlot, next: NextTrack) {
+    let old = slot.lock().unwrap().replace(next);
+    if let Some(old) = old {
+        old.cancel();
+    }
+}
+
+/// 追加到 sink 的下一首开始播放（事件线程调用）：移出槽位、更新当前路径并发送 `track-transition` 事件
+pub(crate) fn on_entry_started(app: &AppHandle, path: &str, cancelled: &Arc<AtomicBool>, format: (u32, u16), duration: Option<Duration>) {
+    let Some(state) = app.try_state::<AppState>() else { return };
+    {
+        let mut slot = state.player.next_track.lock().unwrap();
+        if slot.as_ref().is_some_and(|n| Arc::ptr_eq(&n.cancelled, cancelled)) {
+            *slot = None;
+        }
+    }
+    *state.player.chain_format.lock().unwrap() = Some(format);
+    let from = state.player.current_path.lock().unwrap().replace(path.to_string());
+    let _ = emit_track_transition(app, from, path, duration, format.0);
+}
+
+/// 共享模式下追加到 sink 的下一首音源
+///
+/// 第一次被拉取采样时视为衔接点，由事件线程更新当前路径、移出槽位并发送 `track-transition` 事件。
+/// 若已被取消，则不输出任何采样，rodio 会直接跳到队列中的下一个音源。
+pub struct GaplessEntry<I: Source<Item = f32> + Send> {
+    input: I,
+    path: String,
+    cancelled: Arc<AtomicBool>,
+    events: PlaybackEvents,
+    started: bool,
+    skipped: bool,
+}
+
+impl<I: Source<Item = f32> + Send> GaplessEntry<I> {
+    pub fn new(input: I, path: String, cancelled: Arc<AtomicBool>, events: PlaybackEvents) -> Self {
+        Self { input, path, cancelled, events, started: false, skipped: false }
+    }
+
+    #[inline(never)]
+    fn begin(&mut self) {
+        self.started = true;
+        if self.cancelled.load(Ordering::SeqCst) {
+            self.skipped = true;
+            return;
+        }
+        self.events.send(PlaybackEvent::GaplessStarted {
+            path: self.path.clone(),
+            cancelled: Arc::clone(&self.cancelled),
+            format: (self.input.sample_rate(), self.input.channels()),
+            duration: self.input.total_duration(),
+        });
+    }
+}
+
+impl<I: Source<Item = f32> + Send> Iterator for GaplessEntry<I> {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if !self.started {
+            self.begin();
+        }
+        if self.skipped {
+            return None;
+        }
+        self.input.next()
+    }
+}
+
+impl<I: Source<Item = f32> + Send> Source for GaplessEntry<I> {
+    fn current_span_len(&self) -> Option<usize> { if self.skipped { Some(0) } else { self.input.current_span_len() } }
+    fn channels(&self) -> u16 { self.input.channels() }
+    fn sample_rate(&self) -> u32 { self.input.sample_rate() }
+    fn total_duration(&self) -> Option<Duration> { self.input.total_duration() }
+}
@@ -0,0 +1,275 @@
+//! 循环播放模块
+//!
+//! 支持两种循环：命令设置的 A–B 区间（无限循环，直到清除），以及游戏音乐常用的
+//! LOOPSTART / LOOPLENGTH / LOOPEND 标签（循环指定次数后淡出）。
+//! 循环在解码器中完成：解码到区间终点时截断，并在缓冲区播放到终点之前跳转回起点，输出采样连续。
+//! 解码器同时记录跳转点，音源链据此把已输出的帧数换算为音轨位置。
+
+use crate::config::LoopConfig;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// 循环区间（帧，终点不含）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopRegion {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl LoopRegion {
+    /// 由标签值构造：终点优先取 LOOPEND，其次 LOOPSTART + LOOPLENGTH，都没有时循环到文件结尾；
+    /// 已知总帧数时终点不超过文件结尾
+    #[must_use]
+    pub fn from_tags(start: Option<u64>, length: Option<u64>, end: Option<u64>, total_frames: Option<u64>) -> Option<Self> {
+        let start = start?;
+        let end = match (end, length) {
+            (Some(end), _) => Some(end),
+            // 损坏的 LOOPLENGTH 可能溢出
+            (None, Some(length)) => Some(start.checked_add(length)?),
+            (None, None) => None,
+        };
+        let end = match (end, total_frames) {
+            (Some(end), Some(total)) => end.min(total),
+            (end, total) => end.or(total).unwrap_or(u64::MAX),
+        };
+        (end > start).then_some(Self { start, end })
+    }
+
+    /// 由秒构造（A–B 区间），不足一帧时返回 None
+    #[must_use]
+    pub fn from_seconds(start: f64, end: f64, sample_rate: u32) -> Option<Self> {
+        let to_frame = |secs: f64| (secs * f64::from(sample_rate)) as u64;
+        let (start, end) = (to_frame(start), to_frame(end));
+        (end > start).then_some(Self { start, end })
+    }
+}
+
+/// A–B 循环区间（秒）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AbLoop {
+    pub path: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// 循环控制（由播放器状态持有，解码线程读取）
+pub struct LoopControl {
+    ab: Mutex<Option<AbLoop>>,
+    settings: RwLock<LoopConfig>,
+    /// A–B 区间每次修改时递增，解码器据此判断是否需要重新读取
+    generation: AtomicU64,
+}
+
+impl LoopControl {
+    #[must_use]
+    pub fn new(settings: LoopConfig) -> Self {
+        Self { ab: Mutex::new(None), settings: RwLock::new(settings), generation: AtomicU64::new(0) }
+    }
+
+    #[must_use]
+    pub fn settings(&self) -> LoopConfig {
+        self.settings.read().unwrap().clone()
+    }
+
+    /// 更新标签循环设置（之后打开的音轨生效）
+    pub fn set_settings(&self, settings: LoopConfig) {
+        *self.settings.write().unwrap() = settings;
+    }
+
+    #[must_use]
+    pub fn ab_loop(&self) -> Option<AbLoop> {
+        self.ab.lock().unwrap().clone()
+    }
+
+    pub fn set_ab_loop(&self, ab: Option<AbLoop>) {
+        *self.ab.lock().unwrap() = ab;
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// 切换到其他音轨时清除 A–B 区间
+    pub fn retain_ab_for(&self, path: &str) {
+        let stale = self.ab.lock().unwrap().as_ref().is_some_and(|ab| ab.path != path);
+        if stale {
+            self.set_ab_loop(None);
+        }
+    }
+}
+
+/// 解码器输出帧与音轨帧的对应关系
+#[derive(Default)]
+pub struct LoopPositions {
+    /// 跳转点：(输出帧, 对应的音轨帧)
+    jumps: Mutex<VecDeque<(u64, u64)>>,
+    /// 解码器是否仍会循环（此时音轨结尾不可预知）
+    looping: AtomicBool,
+}
+
+impl LoopPositions {
+    pub(crate) fn mark(&self, output_frame: u64, track_frame: u64) {
+        let mut jumps = self.jumps.lock().unwrap();
+        // 之后的跳转点作废（重新定位后解码器不会再输出它们）
+        while jumps.back().is_some_and(|(at, _)| *at >= output_frame) {
+            jumps.pop_back();
+        }
+        jumps.push_back((output_frame, track_frame));
+    }
+
+    /// 输出帧对应的音轨帧（音频线程调用，锁被占用时返回 None）
+    #[must_use]
+    pub fn track_frame(&self, output_frame: u64) -> Option<u64> {
+        let mut jumps = self.jumps.try_lock().ok()?;
+        while jumps.len() > 1 && jumps[1].0 <= output_frame {
+            jumps.pop_front();
+        }
+        Some(match jumps.front() {
+            Some(&(at, track)) if at <= output_frame => track + (output_frame - at),
+            _ => output_frame,
+        })
+    }
+
+    #[must_use]
+    pub fn is_looping(&self) -> bool {
+        self.looping.load(Ordering::Relaxed)
+    }
+}
+
+/// 解码器内的循环状态
+pub struct DecoderLoop {
+    control: Arc<LoopControl>,
+    path: String,
+    sample_rate: u32,
+    generation: Option<u64>,
+    ab: Option<LoopRegion>,
+    tag: Option<LoopRegion>,
+    /// 标签循环剩余次数
+    remaining: u32,
+    /// 淡出总长（帧）
+    fade_len: u64,
+    /// 淡出进度（帧），None 表示尚未开始淡出
+    fade_pos: Option<u64>,
+    positions: Arc<LoopPositions>,
+}
+
+impl DecoderLoop {
+    #[must_use]
+    pub fn new(control: Arc<LoopControl>, path: &str, sample_rate: u32, tag: Option<LoopRegion>, positions: Arc<LoopPositions>) -> Self {
+        let settings = control.settings();
+        let tag = tag.filter(|_| settings.honor_tags);
+        if let Some(region) = tag {
+            println!("Loop tags: {path} [{}, {}) x{}", region.start, region.end, settings.loop_count);
+        }
+        let mut this = Self {
+            control,
+            path: path.to_string(),
+            sample_rate,
+            generation: None,
+            ab: None,
+            tag,
+            remaining: settings.loop_count,
+            fade_len: (settings.fade_out.max(0.0) as f64 * sample_rate as f64) as u64,
+            fade_pos: None,
+            positions,
+        };
+        this.refresh();
+        this
+    }
+
+    /// A–B 区间有变化时重新读取（不阻塞，取不到锁时下次再试）
+    fn refresh(&mut self) {
+        let generation = self.control.generation.load(Ordering::Acquire);
+        if self.generation == Some(generation) {
+            return;
+        }
+        let Ok(ab) = self.control.ab.try_lock() else { return };
+        self.ab = ab
+            .as_ref()
+            .filter(|ab| ab.path == self.path)
+            .and_then(|ab| LoopRegion::from_seconds(ab.start, ab.end, self.sample_rate));
+        drop(ab);
+        self.generation = Some(generation);
+        self.update_flag();
+    }
+
+    fn update_flag(&self) {
+        self.positions.looping.store(self.ab.is_some() || self.tag.is_some(), Ordering::Relaxed);
+    }
+
+    /// 当前生效的区间（A–B 优先）
+    pub fn region(&mut self) -> Option<LoopRegion> {
+        self.refresh();
+        self.ab.or(self.tag)
+    }
+
+    /// 解码到区间终点，返回是否跳回起点
+    pub fn on_loop_end(&mut self) -> bool {
+        if self.ab.is_some() || self.fade_pos.is_some() {
+            return true;
+        }
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            return true;
+        }
+        if self.fade_len > 0 {
+            // 最后一遍边播放边淡出
+            self.fade_pos = Some(0);
+            return true;
+        }
+        // 循环结束，继续播放到文件结尾
+        self.tag = None;
+        self.update_flag();
+        false
+    }
+
+    /// 对即将输出的采样应用淡出，返回保留的采样数（淡出结束后为 0）
+    pub fn apply_fade(&mut self, samples: &mut [f32], channels: usize) -> usize {
+        let Some(pos) = self.fade_pos else { return samples.len() };
+        // A–B 循环期间暂停淡出
+        if self.ab.is_some() {
+            return samples.len();
+        }
+        let frames = samples.len() / channels.max(1);
+        let keep = (self.fade_len.saturating_sub(pos) as usize).min(frames);
+        for (i, frame) in samples.chunks_exact_mut(channels.max(1)).take(keep).enumerate() {
+            let gain = 1.0 - (pos + i as u64) as f32 / self.fade_len as f32;
+            for s in frame {
+                *s *= gain * gain;
+            }
+        }
+        self.fade_pos = Some(pos + keep as u64);
+        keep * channels
+    }
+
+    /// 淡出是否已完成
+    #[must_use]
+    pub fn faded_out(&self) -> bool {
+        self.ab.is_none() && self.fade_pos.is_some_and(|pos| pos >= self.fade_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_end_prefers_loopend_then_length() {
+        assert_eq!(LoopRegion::from_tags(Some(10), Some(5), Some(30), None), Some(LoopRegion { start: 10, end: 30 }));
+        assert_eq!(LoopRegion::from_tags(Some(10), Some(5), None, None), Some(LoopRegion { start: 10, end: 15 }));
+        assert_eq!(LoopRegion::from_tags(Some(10), None, None, Some(100)), Some(LoopRegion { start: 10, end: 100 }));
+        assert_eq!(LoopRegion::from_tags(None, Some(5), Some(30), Some(100)), None);
+    }
+
+    #[test]
+    fn overflowing_length_is_rejected() {
+        assert_eq!(LoopRegion::from_tags(Some(10), Some(u64::MAX), None, Some(100)), None);
+    }
+
+    #[test]
+    fn tag_end_is_clamped_to_track() {
+        assert_eq!(LoopRegion::from_tags(Some(10), None, Some(500), Some(100)), Some(LoopRegion { start: 10, end: 100 }));
+        assert_eq!(LoopRegion::from_tags(Some(10), Some(500), None, Some(100)), Some(LoopRegion { start: 10, end: 100 }));
+        assert_eq!(LoopRegion::from_tags(Some(200), None, Some(500), Some(100)), None);
+    }
+}
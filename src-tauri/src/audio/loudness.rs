@@ -0,0 +1,560 @@
+//! 响度分析模块（EBU R128 / ITU-R BS.1770-4）
+//!
+//! 计算综合响度、响度范围（LRA）与真峰值，并按文件夹汇总专辑响度，
+//! 换算为以 -18 LUFS 为参考的回放增益，可选写回 REPLAYGAIN_* 标签。
+
+use super::decoder::SymphoniaDecoder;
+use crate::error::{AppError, AppResult};
+use crate::media::metadata::write_replay_gain_tags;
+use crate::media::ReplayGainInfo;
+use rayon::prelude::*;
+use rodio::Source;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use symphonia::core::audio::Channels;
+use tauri::{AppHandle, Emitter};
+
+/// 回放增益参考响度（ReplayGain 2.0）
+pub const REFERENCE_LUFS: f64 = -18.0;
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+const LRA_RELATIVE_GATE_LU: f64 = -20.0;
+/// 400ms 测量块 = 4 个 100ms 步长
+const MOMENTARY_STEPS: usize = 4;
+/// 3s 短期窗口 = 30 个 100ms 步长
+const SHORT_TERM_STEPS: usize = 30;
+/// 真峰值插值滤波器每相位的抽头数
+const TRUE_PEAK_TAPS: usize = 12;
+/// 环绕声道的权重（BS.1770 中方位角 60°–120° 的声道，+1.5 dB）
+const SURROUND_WEIGHT: f64 = 1.41;
+
+#[inline]
+fn energy_to_lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+#[inline]
+fn lufs_to_energy(lufs: f64) -> f64 {
+    10f64.powf((lufs + 0.691) / 10.0)
+}
+
+/// 双二阶滤波器（直接 II 型转置）
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    const fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, z: [0.0; 2] }
+    }
+
+    #[inline]
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// K 加权滤波器（高架 + 高通），系数按任意采样率推导
+fn k_weighting(sample_rate: u32) -> (Biquad, Biquad) {
+    let rate = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1_681.974_450_955_533, 3.999_843_853_973_347, 0.707_175_236_955_419_6);
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let (f0, q) = (38.135_470_876_024_44, 0.500_327_037_323_877_3);
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad::new([1.0, -2.0, 1.0], [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0]);
+
+    (shelf, highpass)
+}
+
+/// 多相插值真峰值检测
+struct TruePeakDetector {
+    factor: usize,
+    /// 按相位排列的滤波器系数：phases[p][j]
+    phases: Vec<[f64; TRUE_PEAK_TAPS]>,
+    /// 每声道最近的输入采样（环形）
+    history: Vec<[f64; TRUE_PEAK_TAPS]>,
+    pos: usize,
+    peak: f64,
+}
+
+impl TruePeakDetector {
+    fn new(sample_rate: u32, channels: usize) -> Self {
+        // 高采样率下降低过采样倍数
+        let factor = match sample_rate {
+            0..=95_999 => 4,
+            96_000..=191_999 => 2,
+            _ => 1,
+        };
+        let len = factor * TRUE_PEAK_TAPS;
+        let center = (len - 1) as f64 / 2.0;
+        let mut phases = vec![[0.0; TRUE_PEAK_TAPS]; factor];
+        for n in 0..len {
+            let x = (n as f64 - center) / factor as f64;
+            let sinc = if x.abs() < 1e-12 { 1.0 } else { (PI * x).sin() / (PI * x) };
+            // Blackman 窗
+            let w = 0.42 - 0.5 * (2.0 * PI * n as f64 / (len - 1) as f64).cos() + 0.08 * (4.0 * PI * n as f64 / (len - 1) as f64).cos();
+            phases[n % factor][n / factor] = sinc * w;
+        }
+        Self { factor, phases, history: vec![[0.0; TRUE_PEAK_TAPS]; channels], pos: 0, peak: 0.0 }
+    }
+
+    #[inline]
+    fn process_frame(&mut self, frame: &[f32]) {
+        for (ch, &s) in frame.iter().enumerate() {
+            let s = s as f64;
+            self.peak = self.peak.max(s.abs());
+            if self.factor == 1 {
+                continue;
+            }
+            let hist = &mut self.history[ch];
+            hist[self.pos] = s;
+            for phase in &self.phases {
+                let mut acc = 0.0;
+                for (j, coeff) in phase.iter().enumerate() {
+                    acc += coeff * hist[(self.pos + TRUE_PEAK_TAPS - j) % TRUE_PEAK_TAPS];
+                }
+                self.peak = self.peak.max(acc.abs());
+            }
+        }
+        self.pos = (self.pos + 1) % TRUE_PEAK_TAPS;
+    }
+}
+
+/// 声道按 BS.1770 计入响度的权重：LFE 不计入，环绕声道 1.41，其余 1.0
+fn channel_weight(channel: Channels) -> f64 {
+    if channel.intersects(Channels::LFE1 | Channels::LFE2) {
+        0.0
+    } else if channel.intersects(Channels::REAR_LEFT | Channels::REAR_RIGHT | Channels::SIDE_LEFT | Channels::SIDE_RIGHT) {
+        SURROUND_WEIGHT
+    } else {
+        1.0
+    }
+}
+
+/// 没有声道布局时按常见的声道顺序（FL FR FC LFE …）推断
+fn default_layout(channels: usize) -> Option<Channels> {
+    let front = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+    let rear = Channels::REAR_LEFT | Channels::REAR_RIGHT;
+    let five_one = front | Channels::FRONT_CENTRE | Channels::LFE1 | rear;
+    match channels {
+        4 => Some(front | rear),
+        5 => Some(front | Channels::FRONT_CENTRE | rear),
+        6 => Some(five_one),
+        8 => Some(five_one | Channels::SIDE_LEFT | Channels::SIDE_RIGHT),
+        _ => None,
+    }
+}
+
+/// BS.1770 响度计
+pub struct LoudnessMeter {
+    channels: usize,
+    /// 各声道的权重
+    weights: Vec<f64>,
+    filters: Vec<(Biquad, Biquad)>,
+    /// 每个 100ms 步长的帧数
+    step_frames: usize,
+    step_sum: f64,
+    step_count: usize,
+    /// 每个 100ms 步长的均方能量（各声道加权之和）
+    steps: Vec<f64>,
+    true_peak: TruePeakDetector,
+}
+
+impl LoudnessMeter {
+    /// 声道布局未知时按声道数推断（采样按 FL FR FC LFE … 的顺序交错）
+    #[must_use]
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let channels = channels.max(1) as usize;
+        let weights = default_layout(channels).map_or_else(|| vec![1.0; channels], |layout| layout.iter().map(channel_weight).collect());
+        Self::with_weights(sample_rate, weights)
+    }
+
+    /// 按声道布局加权（采样按布局中声道的顺序交错）
+    #[must_use]
+    pub fn with_layout(sample_rate: u32, layout: Channels) -> Self {
+        if layout.count() == 0 {
+            return Self::new(sample_rate, 1);
+        }
+        Self::with_weights(sample_rate, layout.iter().map(channel_weight).collect())
+    }
+
+    fn with_weights(sample_rate: u32, weights: Vec<f64>) -> Self {
+        let channels = weights.len();
+        Self {
+            channels,
+            weights,
+            filters: vec![k_weighting(sample_rate); channels],
+            step_frames: (sample_rate as usize / 10).max(1),
+            step_sum: 0.0,
+            step_count: 0,
+            steps: Vec::new(),
+            true_peak: TruePeakDetector::new(sample_rate, channels),
+        }
+    }
+
+    /// 输入交错采样（长度应为声道数的整数倍）
+    pub fn process(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            let mut energy = 0.0;
+            for ((filter, weight), &s) in self.filters.iter_mut().zip(&self.weights).zip(frame) {
+                let y = filter.1.process(filter.0.process(s as f64));
+                energy += weight * y * y;
+            }
+            self.step_sum += energy;
+            self.step_count += 1;
+            if self.step_count == self.step_frames {
+                self.steps.push(self.step_sum / self.step_frames as f64);
+                self.step_sum = 0.0;
+                self.step_count = 0;
+            }
+            self.true_peak.process_frame(frame);
+        }
+    }
+
+    /// 400ms 测量块能量（75% 重叠）
+    fn momentary_blocks(&self) -> Vec<f64> {
+        sliding_means(&self.steps, MOMENTARY_STEPS)
+    }
+
+    #[must_use]
+    pub fn finish(self) -> LoudnessMeasurement {
+        let blocks = self.momentary_blocks();
+        let integrated = integrated_loudness(&blocks);
+        let loudness_range = loudness_range(&sliding_means(&self.steps, SHORT_TERM_STEPS));
+        LoudnessMeasurement { integrated, loudness_range, true_peak: self.true_peak.peak, blocks }
+    }
+}
+
+fn sliding_means(steps: &[f64], window: usize) -> Vec<f64> {
+    if steps.len() < window {
+        return Vec::new();
+    }
+    steps.windows(window).map(|w| w.iter().sum::<f64>() / window as f64).collect()
+}
+
+/// 带门限的综合响度（LUFS），全部低于绝对门限时返回 None
+fn integrated_loudness(blocks: &[f64]) -> Option<f64> {
+    let abs_threshold = lufs_to_energy(ABSOLUTE_GATE_LUFS);
+    let gated: Vec<f64> = blocks.iter().copied().filter(|e| *e > abs_threshold).collect();
+    if gated.is_empty() {
+        return None;
+    }
+    let relative = energy_to_lufs(gated.iter().sum::<f64>() / gated.len() as f64) + INTEGRATED_RELATIVE_GATE_LU;
+    let rel_threshold = lufs_to_energy(relative);
+    let (sum, count) = gated.iter().filter(|e| **e > rel_threshold).fold((0.0, 0usize), |(s, c), e| (s + e, c + 1));
+    (count > 0).then(|| energy_to_lufs(sum / count as f64))
+}
+
+/// 响度范围（LU，EBU Tech 3342）
+fn loudness_range(short_term: &[f64]) -> f64 {
+    let abs_threshold = lufs_to_energy(ABSOLUTE_GATE_LUFS);
+    let gated: Vec<f64> = short_term.iter().copied().filter(|e| *e > abs_threshold).collect();
+    if gated.is_empty() {
+        return 0.0;
+    }
+    let rel_threshold = lufs_to_energy(energy_to_lufs(gated.iter().sum::<f64>() / gated.len() as f64) + LRA_RELATIVE_GATE_LU);
+    let mut values: Vec<f64> = gated.into_iter().filter(|e| *e > rel_threshold).map(energy_to_lufs).collect();
+    if values.len() < 2 {
+        return 0.0;
+    }
+    values.sort_by(f64::total_cmp);
+    let percentile = |p: f64| values[((values.len() - 1) as f64 * p).round() as usize];
+    percentile(0.95) - percentile(0.10)
+}
+
+/// 单个音频流的测量结果
+pub struct LoudnessMeasurement {
+    pub integrated: Option<f64>,
+    pub loudness_range: f64,
+    /// 真峰值（线性）
+    pub true_peak: f64,
+    /// 测量块能量（用于汇总专辑响度）
+    blocks: Vec<f64>,
+}
+
+/// 音轨响度分析结果
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackLoudness {
+    pub path: String,
+    /// 综合响度（LUFS，静音时为 None）
+    pub integrated_lufs: Option<f64>,
+    /// 响度范围（LU）
+    pub loudness_range: f64,
+    /// 真峰值（线性）
+    pub true_peak: f64,
+    /// 真峰值（dBTP）
+    pub true_peak_dbtp: f64,
+    /// 音轨增益（dB）
+    pub track_gain: Option<f64>,
+    /// 所属文件夹（专辑）
+    pub album: String,
+    /// 专辑增益（dB）
+    pub album_gain: Option<f64>,
+    /// 专辑峰值（线性）
+    pub album_peak: Option<f64>,
+    /// 标签写入失败原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub write_error: Option<String>,
+}
+
+/// 文件夹（专辑）响度汇总
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumLoudness {
+    pub folder: String,
+    pub track_count: usize,
+    pub integrated_lufs: Option<f64>,
+    pub true_peak: f64,
+    pub album_gain: Option<f64>,
+}
+
+#[inline]
+fn amplitude_to_db(amplitude: f64) -> f64 {
+    if amplitude > 0.0 { 20.0 * amplitude.log10() } else { f64::NEG_INFINITY }
+}
+
+/// 解码并分析单个文件
+pub fn analyze_file(path: &str, cancel: &AtomicBool) -> Result<LoudnessMeasurement, String> {
+    // 不下混：环绕声道和 LFE 需要按声道加权
+    let decoder = SymphoniaDecoder::new(path).map_err(|e| format!("Failed to create decoder: {e}"))?.with_source_channels();
+    let channels = decoder.channels();
+    let mut meter = match decoder.channel_layout() {
+        Some(layout) if layout.count() == channels as usize => LoudnessMeter::with_layout(decoder.sample_rate(), layout),
+        _ => LoudnessMeter::new(decoder.sample_rate(), channels),
+    };
+
+    let chunk_len = 4096 * channels as usize;
+    let mut chunk = Vec::with_capacity(chunk_len);
+    for sample in decoder {
+        chunk.push(sample);
+        if chunk.len() == chunk_len {
+            if cancel.load(Ordering::Relaxed) {
+                return Err("Cancelled".to_string());
+            }
+            meter.process(&chunk);
+            chunk.clear();
+        }
+    }
+    meter.process(&chunk);
+    Ok(meter.finish())
+}
+
+/// 响度扫描进度事件
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessScanProgress {
+    pub scan_id: u64,
+    pub completed: usize,
+    pub total: usize,
+    pub path: String,
+    pub error: Option<String>,
+}
+
+/// 响度扫描完成事件
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessScanComplete {
+    pub scan_id: u64,
+    pub cancelled: bool,
+    pub tracks: Vec<TrackLoudness>,
+    pub albums: Vec<AlbumLoudness>,
+    pub failed: Vec<String>,
+}
+
+/// 响度扫描任务管理（同一时间只运行一个扫描）
+#[derive(Default)]
+pub struct LoudnessScanner {
+    next_id: AtomicU64,
+    active: Mutex<Option<(u64, Arc<AtomicBool>)>>,
+}
+
+impl LoudnessScanner {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在后台启动扫描，返回扫描 ID
+    pub fn start(self: &Arc<Self>, app: AppHandle, paths: Vec<String>, write_tags: bool) -> AppResult<u64> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let scan_id = {
+            let mut active = self.active.lock().unwrap();
+            if active.is_some() {
+                return Err(AppError::invalid_argument("A loudness scan is already running"));
+            }
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+            *active = Some((id, Arc::clone(&cancel)));
+            id
+        };
+
+        let scanner = Arc::clone(self);
+        std::thread::spawn(move || {
+            let complete = run_scan(&app, scan_id, &paths, write_tags, &cancel);
+            *scanner.active.lock().unwrap() = None;
+            println!(
+                "Loudness scan {scan_id} finished: {} tracks, {} failed, cancelled: {}",
+                complete.tracks.len(),
+                complete.failed.len(),
+                complete.cancelled
+            );
+            let _ = app.emit("loudness-scan-complete", complete);
+        });
+        Ok(scan_id)
+    }
+
+    /// 取消正在运行的扫描
+    pub fn cancel(&self) -> bool {
+        let active = self.active.lock().unwrap();
+        active.as_ref().is_some_and(|(_, cancel)| {
+            cancel.store(true, Ordering::SeqCst);
+            true
+        })
+    }
+}
+
+fn run_scan(app: &AppHandle, scan_id: u64, paths: &[String], write_tags: bool, cancel: &AtomicBool) -> LoudnessScanComplete {
+    let total = paths.len();
+    let completed = AtomicUsize::new(0);
+
+    let results: Vec<(String, Result<LoudnessMeasurement, String>)> = paths
+        .par_iter()
+        .map(|path| {
+            let result = if cancel.load(Ordering::Relaxed) { Err("Cancelled".to_string()) } else { analyze_file(path, cancel) };
+            let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+            let _ = app.emit(
+                "loudness-scan-progress",
+                LoudnessScanProgress { scan_id, completed: done, total, path: path.clone(), error: result.as_ref().err().cloned() },
+            );
+            (path.clone(), result)
+        })
+        .collect();
+
+    let cancelled = cancel.load(Ordering::Relaxed);
+    let mut failed = Vec::new();
+    // 按文件夹分组汇总专辑响度
+    let mut folders: BTreeMap<String, Vec<(String, LoudnessMeasurement)>> = BTreeMap::new();
+    for (path, result) in results {
+        match result {
+            Ok(m) => {
+                let folder = Path::new(&path).parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+                folders.entry(folder).or_default().push((path, m));
+            }
+            Err(e) => {
+                if !cancelled {
+                    eprintln!("Loudness scan failed for {path}: {e}");
+                }
+                failed.push(path);
+            }
+        }
+    }
+
+    let mut tracks = Vec::new();
+    let mut albums = Vec::new();
+    for (folder, measurements) in folders {
+        let all_blocks: Vec<f64> = measurements.iter().flat_map(|(_, m)| m.blocks.iter().copied()).collect();
+        let album_lufs = integrated_loudness(&all_blocks);
+        let album_peak = measurements.iter().map(|(_, m)| m.true_peak).fold(0.0, f64::max);
+        let album_gain = album_lufs.map(|l| REFERENCE_LUFS - l);
+        albums.push(AlbumLoudness {
+            folder: folder.clone(),
+            track_count: measurements.len(),
+            integrated_lufs: album_lufs,
+            true_peak: album_peak,
+            album_gain,
+        });
+
+        for (path, m) in measurements {
+            let mut track = TrackLoudness {
+                track_gain: m.integrated.map(|l| REFERENCE_LUFS - l),
+                integrated_lufs: m.integrated,
+                loudness_range: m.loudness_range,
+                true_peak: m.true_peak,
+                true_peak_dbtp: amplitude_to_db(m.true_peak),
+                album: folder.clone(),
+                album_gain,
+                album_peak: album_gain.map(|_| album_peak),
+                write_error: None,
+                path,
+            };
+            if write_tags && !cancelled {
+                let info = ReplayGainInfo {
+                    track_gain: track.track_gain.map(|g| g as f32),
+                    track_peak: track.track_gain.map(|_| track.true_peak as f32),
+                    album_gain: track.album_gain.map(|g| g as f32),
+                    album_peak: track.album_peak.map(|p| p as f32),
+                };
+                if let Err(e) = write_replay_gain_tags(&track.path, &info) {
+                    eprintln!("Failed to write ReplayGain tags to {}: {e}", track.path);
+                    track.write_error = Some(e.to_string());
+                }
+            }
+            tracks.push(track);
+        }
+    }
+
+    LoudnessScanComplete { scan_id, cancelled, tracks, albums, failed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    /// 1 kHz 正弦波（指定声道，其余静音），`dbfs` 为峰值电平
+    fn sine(channels: usize, active: &[usize], dbfs: f64, secs: usize) -> Vec<f32> {
+        let amplitude = 10f64.powf(dbfs / 20.0);
+        let frames = RATE as usize * secs;
+        let mut samples = vec![0.0; frames * channels];
+        for (i, frame) in samples.chunks_exact_mut(channels).enumerate() {
+            let s = (amplitude * (2.0 * PI * 1000.0 * i as f64 / RATE as f64).sin()) as f32;
+            for &ch in active {
+                frame[ch] = s;
+            }
+        }
+        samples
+    }
+
+    fn integrated(mut meter: LoudnessMeter, samples: &[f32]) -> Option<f64> {
+        meter.process(samples);
+        meter.finish().integrated
+    }
+
+    #[test]
+    fn stereo_sine_reads_reference_loudness() {
+        // EBU Tech 3341 用例 1：双声道 -23 dBFS 1 kHz 正弦波为 -23.0 LUFS
+        let lufs = integrated(LoudnessMeter::new(RATE, 2), &sine(2, &[0, 1], -23.0, 5)).unwrap();
+        assert!((lufs - -23.0).abs() < 0.1, "{lufs}");
+    }
+
+    #[test]
+    fn surround_channels_are_weighted_and_lfe_is_ignored() {
+        let front = integrated(LoudnessMeter::new(RATE, 6), &sine(6, &[0], -23.0, 3)).unwrap();
+        assert!((front - -26.0).abs() < 0.1, "{front}");
+        let surround = integrated(LoudnessMeter::new(RATE, 6), &sine(6, &[4], -23.0, 3)).unwrap();
+        assert!((surround - front - 10.0 * SURROUND_WEIGHT.log10()).abs() < 0.01, "{surround}");
+        assert_eq!(integrated(LoudnessMeter::new(RATE, 6), &sine(6, &[3], -23.0, 3)), None);
+    }
+}
@@ -2,20 +2,43 @@
 //!
 //! 提供音频播放、解码、设备管理等功能。
 
+pub mod clock;
+pub mod analysis;
 pub mod commands;
+pub mod crossfade;
 pub mod decoder;
 pub mod device;
 pub mod device_monitor;
+pub mod events;
+pub mod gapless;
+pub mod looping;
+pub mod loudness;
 pub mod playback;
+pub mod player_state;
+pub mod replaygain;
+pub mod ring;
+pub mod sleep_timer;
+pub mod stretch;
 
 #[cfg(windows)]
 pub mod wasapi;
 
 // 重新导出常用类型
-pub use decoder::{LockFreeSymphoniaSource, SymphoniaDecoder, SymphoniaSource};
+pub use clock::TrackClock;
+pub use crossfade::CrossfadeSource;
+pub use decoder::{DecodeBufferStats, LockFreeSymphoniaSource, SymphoniaDecoder, SymphoniaSource};
 pub use device::AudioDeviceInfo;
 pub use device_monitor::{DeviceChangeEvent, DeviceMonitor};
-pub use playback::{PlaybackStatus, VisualizationSource};
+pub use events::{PlaybackEvent, PlaybackEvents};
+pub use gapless::{ChainFormat, NextTrack, NextTrackSlot};
+pub use looping::{AbLoop, LoopControl, LoopRegion};
+pub use loudness::LoudnessScanner;
+pub use playback::{ExclusiveSeek, PlaybackStatus, VisualizationSource};
+pub use player_state::{OutputFormat, PlayerStateEvent, PlayerStateMachine, PlayerStatus, TransitionReason};
+pub use replaygain::{GainStage, ReplayGain};
+pub use ring::RingStats;
+pub use sleep_timer::{SleepTimer, SleepTimerMode, SleepTimerStatus};
+pub use stretch::{StretchControl, StretchSource};
 
 #[cfg(windows)]
 pub use wasapi::{PlaybackState, WasapiExclusivePlayback};
@@ -6,22 +6,35 @@
 //! 预计算查找表避免热路径上的数学运算
 //! 无锁设计减少线程竞争
 
+use super::analysis::{AnalysisConfig, AnalysisTap};
+use super::clock::TrackClock;
+use super::crossfade::{CrossfadeSource, TransitionNotifier};
 use super::decoder::{LockFreeSymphoniaSource, SymphoniaDecoder};
+use super::events::{PlaybackEvent, PlaybackEvents};
+use super::gapless::{self, GaplessEntry, NextTrack, NextTrackSlot};
+use super::player_state::{PlayerStatus, TransitionReason};
+#[cfg(windows)]
+use super::player_state::OutputFormat;
+use super::replaygain::{GainStage, ReplayGain};
+use super::stretch::StretchSource;
+#[cfg(windows)]
+use super::stretch::StretchControl;
 
 #[cfg(windows)]
 use super::wasapi::PlaybackState;
 use crate::equalizer::{EqSettings, EQ_BAND_COUNT};
+use crate::error::{AppError, AppResult, ErrorCode};
 use crate::AppState;
+use rodio::source::SeekError;
 use rodio::Source;
-use spectrum_analyzer::scaling::divide_by_N_sqrt;
-use spectrum_analyzer::windows::hann_window;
-use spectrum_analyzer::{samples_fft_to_spectrum, FrequencyLimit};
 use std::fs::File;
 use std::io::BufReader;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64};
+#[cfg(windows)]
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, Manager, State};
 
 // ============================================================================
 // 预计算查找表
@@ -114,18 +127,48 @@ pub struct SpectrumUpdateEvent {
 
 /// 音轨结束事件
 #[derive(Debug, serde::Serialize, Clone)]
-pub struct TrackEndedEvent {}
+#[serde(rename_all = "camelCase")]
+pub struct TrackEndedEvent {
+    /// 结束的音轨路径
+    pub path: Option<String>,
+    /// 已无缝衔接的下一首音轨路径（为空表示播放停止）
+    pub next_path: Option<String>,
+    /// 睡眠定时器到时停止播放，前端不应继续播放下一首
+    ///
+    /// 若下一首已经衔接（`next_path` 不为空），它会被暂停在开头，队列也已指向它。
+    pub stopped: bool,
+    /// 播放队列前进后由后端加载播放的下一首（非无缝衔接），为空且 `next_path` 为空表示播放结束
+    pub queued_path: Option<String>,
+}
 
 #[inline]
-fn emit_spectrum_update(app: &AppHandle, data: &[f32]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+pub(crate) fn emit_spectrum_update(app: &AppHandle, data: &[f32]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // 直接发送数据数组，减少JSON包装开销
     app.emit("spectrum-update", SpectrumUpdateEvent { data: data.to_vec() })?;
     Ok(())
 }
 
-#[inline]
-fn emit_track_ended(app: &AppHandle) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    app.emit("track-ended", TrackEndedEvent {})?;
+/// 音轨结束：更新播放状态、睡眠定时器、历史和队列并发送 `track-ended` 事件
+///
+/// 会等待锁，只能在播放事件线程或独占模式推送线程中调用，不能在 rodio 输出回调中调用。
+pub(crate) fn emit_track_ended(app: &AppHandle, path: Option<String>, next_path: Option<String>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // 睡眠定时器到时则不再前进，否则后端播放队列自动前进
+    let state = app.try_state::<AppState>();
+    let stopped = state.as_ref().is_some_and(|state| state.player.sleep_timer.on_track_ended(app));
+    if next_path.is_none()
+        && let Some(state) = state
+    {
+        let reason = if stopped { TransitionReason::SleepTimer } else { TransitionReason::TrackEnded };
+        state.player.player_state.transition(app, PlayerStatus::Ended, reason);
+    }
+    crate::history::on_track_ended(app, path.as_deref(), next_path.as_deref());
+    // 定时器到时不再前进；下一首已经衔接时队列仍需指向实际加载的音轨（它由定时器暂停）
+    let queued_path = if !stopped || next_path.is_some() {
+        crate::queue::on_track_ended(app, path.as_deref(), next_path.as_deref())
+    } else {
+        None
+    };
+    app.emit("track-ended", TrackEndedEvent { path, next_path, stopped, queued_path })?;
     Ok(())
 }
 
@@ -135,7 +178,12 @@ pub struct PlaybackPositionEvent {
     pub position: f32, // 秒
 }
 
-fn emit_playback_position(app: &AppHandle, position: f32) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+pub(crate) fn emit_playback_position(app: &AppHandle, position: f32) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(state) = app.try_state::<AppState>() {
+        state.player.sleep_timer.set_position(position);
+        state.player.player_state.set_position(position);
+        state.history.on_position(position);
+    }
     app.emit("playback-position", PlaybackPositionEvent { position })?;
     Ok(())
 }
@@ -216,76 +264,60 @@ pub struct VisualizationSource<I: Source<Item = f32> + Send> {
     input: I,
     #[allow(dead_code)]
     waveform_data: Arc<Mutex<Vec<f32>>>,
-    spectrum_data: Arc<Mutex<Vec<f32>>>,
-    buffer: Vec<f32>,
-    prev_spectrum: Vec<f32>,
-    app_handle: Option<AppHandle>,
-    last_fft_time: AtomicU64,
-    last_position_emit_time: AtomicU64,
     eq_settings: Arc<RwLock<EqSettings>>,
     eq_processor: BatchEqProcessor,
     eq_update_counter: u32,
-    fft_buffer: Vec<f32>,
-    spectrum_buffer: Vec<f32>,
     samples_played: u64,
     sample_rate: u32,
     channels: u16,
-    fft_size: usize,
     // 批量处理缓冲区
     pending_samples: Vec<f32>,
-    pending_processed: Vec<f32>,
     pending_index: usize,
     // EOF标志 - 用于发送track-ended事件
     eof_sent: bool,
-    /// 目标刷新率（用于FFT计算频率）
-    target_fps: Arc<AtomicU64>,
-    /// 是否启用垂直同步（启用后FFT与屏幕刷新率同步）
-    enable_vertical_sync: Arc<AtomicBool>,
-}
-
-/// 根据采样率计算最佳FFT缓冲区大小
-/// 目标是保持约~43ms的分析窗口（2048@48kHz）
-#[must_use]
-const fn calculate_fft_size(sample_rate: u32) -> usize {
-    // 基准：48kHz使用2048样本 ≈ 42.7ms
-    // 公式：fft_size = sample_rate * 0.0427
-    // FFT大小必须是2的幂次
-    match sample_rate {
-        0..=32000 => 1024,      // ≤32kHz: 1024 样本
-        32001..=64000 => 2048,  // 44.1k/48k: 2048 样本
-        64001..=128000 => 4096, // 88.2k/96k: 4096 样本
-        _ => 8192,              // 176.4k/192k/384k: 8192 样本
-    }
+    /// 当前音轨路径（用于track-ended事件）
+    track_path: Option<String>,
+    /// 下一首音轨槽位（用于判断是否无缝衔接）
+    next_track: Option<NextTrackSlot>,
+    /// 播放事件通道（track-ended 由事件线程发送）
+    events: Option<PlaybackEvents>,
+    /// 音轨时钟（音源链内部会切换音轨时，位置以它为准）
+    clock: Option<TrackClock>,
+    /// 回放增益
+    gain_stage: Option<GainStage>,
+    /// 可视化分析（在独立线程中进行 FFT 与事件发送）
+    analysis: AnalysisTap,
 }
 
 impl<I: Source<Item = f32> + Send> VisualizationSource<I> {
     pub fn new(input: I, waveform_data: Arc<Mutex<Vec<f32>>>, spectrum_data: Arc<Mutex<Vec<f32>>>, app_handle: Option<AppHandle>, target_fps: Arc<AtomicU64>, enable_vertical_sync: Arc<AtomicBool>) -> Self {
         let (sr, ch) = (input.sample_rate(), input.channels());
-        let fft_size = calculate_fft_size(sr);
+        let analysis = AnalysisTap::spawn(AnalysisConfig {
+            app_handle,
+            spectrum_data,
+            target_fps,
+            enable_vertical_sync,
+            sample_rate: sr,
+            channels: ch,
+        });
         Self {
             input,
             waveform_data,
-            spectrum_data,
-            buffer: Vec::with_capacity(fft_size),
-            prev_spectrum: vec![0.0; 128],
-            app_handle,
-            last_fft_time: AtomicU64::new(0),
-            last_position_emit_time: AtomicU64::new(0),
             eq_settings: Arc::new(RwLock::new(EqSettings::default())),
             eq_processor: BatchEqProcessor::new(sr, ch),
             eq_update_counter: 0,
-            fft_buffer: vec![0.0; fft_size],
-            spectrum_buffer: vec![0.0; 128],
             samples_played: 0,
             sample_rate: sr,
             channels: ch,
-            fft_size,
             pending_samples: Vec::with_capacity(BATCH_SIZE),
-            pending_processed: Vec::with_capacity(BATCH_SIZE),
             pending_index: 0,
             eof_sent: false,
-            target_fps,
-            enable_vertical_sync,
+            track_path: None,
+            next_track: None,
+            events: None,
+            clock: None,
+            gain_stage: None,
+            analysis,
         }
     }
     
@@ -296,6 +328,35 @@ impl<I: Source<Item = f32> + Send> VisualizationSource<I> {
         self
     }
 
+    /// 设置音轨路径、下一首槽位和事件通道（用于track-ended事件）
+    #[must_use]
+    pub fn with_track(mut self, path: &str, next_track: NextTrackSlot, events: PlaybackEvents) -> Self {
+        self.track_path = Some(path.to_string());
+        self.next_track = Some(next_track);
+        self.events = Some(events);
+        self
+    }
+
+    /// 使用音源链提供的音轨时钟计算播放位置
+    #[must_use]
+    pub fn with_track_clock(mut self, clock: TrackClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// 启用回放增益（需在设置音轨路径之后调用）
+    #[must_use]
+    pub fn with_replay_gain(mut self, replay_gain: Arc<ReplayGain>) -> Self {
+        let path = self.current_track_path().unwrap_or_default();
+        self.gain_stage = Some(GainStage::new(replay_gain, &path));
+        self
+    }
+
+    /// 当前音轨路径（优先使用音轨时钟）
+    fn current_track_path(&self) -> Option<String> {
+        self.clock.as_ref().and_then(TrackClock::path).or_else(|| self.track_path.clone())
+    }
+
     #[must_use]
     pub fn with_eq_settings(mut self, eq_settings: Arc<RwLock<EqSettings>>) -> Self {
         self.eq_settings = eq_settings;
@@ -331,11 +392,23 @@ impl<I: Source<Item = f32> + Send> VisualizationSource<I> {
             if let Ok(s) = self.eq_settings.try_read() {
                 self.eq_processor.update_settings(&s);
             }
+            if self.gain_stage.is_some()
+                && let Some(path) = self.current_track_path()
+                && let Some(stage) = self.gain_stage.as_mut()
+            {
+                stage.refresh(&path);
+            }
         }
         
-        // 批量EQ处理
-        self.pending_processed = self.pending_samples.clone();
-        self.eq_processor.process_batch(&mut self.pending_processed);
+        // 批量增益 + EQ处理（原地处理）
+        if let Some(stage) = self.gain_stage.as_mut() {
+            stage.apply(&mut self.pending_samples);
+        }
+        self.eq_processor.process_batch(&mut self.pending_samples);
+
+        // 交给分析线程（只复制，不做计算）
+        self.analysis.write(&self.pending_samples);
+        self.analysis.set_position(self.clock.as_ref().map_or(self.samples_played, TrackClock::samples));
         
         true
     }
@@ -346,147 +419,77 @@ impl<I: Source<Item = f32> + Send> Iterator for VisualizationSource<I> {
 
     fn next(&mut self) -> Option<Self::Item> {
         // 从批量处理缓冲区获取采样
-        if self.pending_index >= self.pending_processed.len() {
+        if self.pending_index >= self.pending_samples.len() {
             if !self.refill_batch() {
-                // EOF - 发送 track-ended 事件（只发送一次）
+                // EOF - 交给事件线程发送 track-ended 事件（只发送一次）
                 if !self.eof_sent {
                     self.eof_sent = true;
-                    if let Some(ref app) = self.app_handle {
-                        let _ = emit_track_ended(app);
+                    if let Some(ref events) = self.events {
+                        events.send(PlaybackEvent::Ended { path: self.current_track_path(), next_track: self.next_track.clone() });
                     }
                 }
                 return None;
             }
         }
         
-        let processed = self.pending_processed[self.pending_index];
+        let processed = self.pending_samples[self.pending_index];
         self.pending_index += 1;
         self.samples_played += 1;
         
-        // 添加到可视化缓冲区
-        self.buffer.push(processed);
-        
-        // FFT和事件发送逻辑（仅在缓冲区满时执行）
-        if self.buffer.len() >= self.fft_size {
-            self.process_visualization();
-        }
-        
         Some(processed)
     }
 }
 
-impl<I: Source<Item = f32> + Send> VisualizationSource<I> {
-    /// 处理可视化数据（FFT和事件发送）
-    #[inline(never)] // 避免内联到热路径
-    fn process_visualization(&mut self) {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis() as u64;
-        
-        // 发送播放位置（每100ms一次）
-        let last_pos_emit = self.last_position_emit_time.load(Ordering::Relaxed);
-        if now - last_pos_emit >= 100 {
-            self.last_position_emit_time.store(now, Ordering::Relaxed);
-            if let Some(ref app) = self.app_handle {
-                let position = self.samples_played as f32 / (self.sample_rate as f32 * self.channels as f32);
-                let _ = emit_playback_position(app, position);
-            }
-        }
-        
-        let last_fft = self.last_fft_time.load(Ordering::Relaxed);
-        
-        // 根据垂直同步设置决定FFT频率
-        let enable_vsync = self.enable_vertical_sync.load(Ordering::Relaxed);
-        let target_fps = self.target_fps.load(Ordering::Relaxed).max(1);
-        let fft_interval_ms = if enable_vsync {
-            // 垂直同步开启：使用屏幕刷新率同步
-            1000 / target_fps
-        } else {
-            // 垂直同步关闭：使用目标帧率
-            1000 / target_fps
-        };
-        
-        // 限制FFT计算和发送频率
-        if now - last_fft >= fft_interval_ms {
-            self.last_fft_time.store(now, Ordering::Relaxed);
-            self.compute_spectrum();
-        }
-        
-        // 保留后半部分数据用于重叠分析
-        let half = self.buffer.len() / 2;
-        self.buffer.drain(..half);
-    }
-    
-    /// 计算频谱数据
-    #[inline(never)]
-    fn compute_spectrum(&mut self) {
-        if let Ok(mut spec) = self.spectrum_data.try_lock() {
-            // 复用预分配的缓冲区
-            self.fft_buffer[..self.fft_size].copy_from_slice(&self.buffer[..self.fft_size]);
-            let hann = hann_window(&self.fft_buffer);
-            
-            if let Ok(spectrum) = samples_fft_to_spectrum(
-                &hann,
-                self.sample_rate,
-                FrequencyLimit::Range(20.0, 20000.0),
-                Some(&divide_by_N_sqrt),
-            ) {
-                // 重置频谱缓冲区
-                self.spectrum_buffer.fill(0.0);
-                
-                // AE风格：线性频率分布
-                const NUM_BINS: usize = 128;
-                const FREQ_MIN: f32 = 20.0;
-                const FREQ_MAX: f32 = 16000.0;
-                const FREQ_STEP: f32 = (FREQ_MAX - FREQ_MIN) / NUM_BINS as f32;
-                
-                for (freq, value) in spectrum.data() {
-                    let f = freq.val();
-                    if !(FREQ_MIN..=FREQ_MAX).contains(&f) { continue; }
-                    
-                    let bin = ((f - FREQ_MIN) / FREQ_STEP).floor() as usize;
-                    let bin = bin.min(NUM_BINS - 1);
-                    
-                    let v = value.val();
-                    if v > self.spectrum_buffer[bin] {
-                        self.spectrum_buffer[bin] = v;
-                    }
-                }
-                
-                // AE风格的平滑：快速上升，缓慢下降
-                for i in 0..128 {
-                    let target = self.spectrum_buffer[i];
-                    let current = self.prev_spectrum[i];
-                    
-                    self.prev_spectrum[i] = if target > current {
-                        current * 0.3 + target * 0.7 // 快速上升
-                    } else {
-                        current * 0.85 + target * 0.15 // 缓慢下降
-                    };
-                }
-                
-                spec.clear();
-                spec.extend_from_slice(&self.prev_spectrum);
-            }
-        }
-        
-        // 发送事件 - 与FFT计算同步，不再单独节流
-        if let Some(ref app) = self.app_handle {
-            let _ = emit_spectrum_update(app, &self.prev_spectrum);
-        }
-    }
-}
-
 impl<I: Source<Item = f32> + Send> Source for VisualizationSource<I> {
     fn current_span_len(&self) -> Option<usize> { self.input.current_span_len() }
     fn channels(&self) -> u16 { self.input.channels() }
     fn sample_rate(&self) -> u32 { self.input.sample_rate() }
     fn total_duration(&self) -> Option<Duration> { self.input.total_duration() }
+
+    /// 定位输入音源，EQ 滤波器状态保持不变
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.samples_played = (pos.as_secs_f64() * self.sample_rate as f64) as u64 * self.channels as u64;
+        self.analysis.set_position(self.clock.as_ref().map_or(self.samples_played, TrackClock::samples));
+        Ok(())
+    }
+}
+
+/// 为输入音源包装 EQ 与可视化处理
+fn wrap_visualization<I: Source<Item = f32> + Send>(app: &AppHandle, state: &State<AppState>, input: I, path: &str, start_pos: f32) -> VisualizationSource<I> {
+    let player = &state.player;
+    VisualizationSource::new(
+        input,
+        Arc::clone(&player.waveform_data),
+        Arc::clone(&player.spectrum_data),
+        Some(app.clone()),
+        Arc::clone(&player.target_fps),
+        Arc::clone(&player.enable_vertical_sync),
+    )
+    .with_start_position(start_pos)
+    .with_eq_settings(state.equalizer.get_settings_handle())
+    .with_track(path, Arc::clone(&player.next_track), player.events.clone())
+    .with_replay_gain(Arc::clone(&player.replay_gain))
+}
+
+/// 构建 Symphonia 解码的共享模式音源链（解码 -> 交叉淡化 -> EQ/可视化）
+fn build_symphonia_chain(app: &AppHandle, state: &State<AppState>, input: LockFreeSymphoniaSource, path: &str, start_pos: f32) -> VisualizationSource<CrossfadeSource> {
+    let player = &state.player;
+    let source = CrossfadeSource::new(
+        input,
+        path,
+        start_pos,
+        Arc::clone(&player.crossfade),
+        Arc::clone(&player.next_track),
+        TransitionNotifier::Emit(player.events.clone()),
+        player.stretch.clone(),
+    );
+    let clock = source.clock();
+    wrap_visualization(app, state, source, path, start_pos).with_track_clock(clock)
 }
 
 /// 播放音轨（共享模式）
-pub fn play_track_shared(app: &AppHandle, state: &State<AppState>, path: &str, position: Option<f32>) -> Result<(), String> {
+pub fn play_track_shared(app: &AppHandle, state: &State<AppState>, path: &str, position: Option<f32>) -> AppResult<()> {
     let player = &state.player;
     {
         let sink = player.sink.lock().unwrap();
@@ -497,47 +500,202 @@ pub fn play_track_shared(app: &AppHandle, state: &State<AppState>, path: &str, p
     }
     *player.current_path.lock().unwrap() = Some(path.to_string());
     *player.current_source.lock().unwrap() = None;
-    let (waveform, spectrum, eq_settings, target_fps, enable_vertical_sync) = (
-        Arc::clone(&player.waveform_data),
-        Arc::clone(&player.spectrum_data),
-        state.equalizer.get_settings_handle(),
-        Arc::clone(&player.target_fps),
-        Arc::clone(&player.enable_vertical_sync),
-    );
 
     let source: Box<dyn Source<Item = f32> + Send> = match SymphoniaDecoder::new(path) {
-        Ok(mut dec) => {
+        Ok(dec) => {
+            let mut dec = dec.with_looping(&player.looping);
             let start_pos = position.unwrap_or(0.0);
             if let Some(t) = position { let _ = dec.seek(Duration::from_secs_f32(t)); }
             let _ = dec.prefill_buffer();
+            player.player_state.buffering(app, dec.total_duration(), dec.sample_rate(), None);
             println!("Symphonia decoder: {path}");
+            *player.chain_format.lock().unwrap() = Some((dec.sample_rate(), dec.target_channels()));
             Box::new(
-                VisualizationSource::new(LockFreeSymphoniaSource::new(dec), waveform, spectrum, Some(app.clone()), target_fps, enable_vertical_sync)
-                    .with_start_position(start_pos)
-                    .with_eq_settings(eq_settings)
+                build_symphonia_chain(app, state, LockFreeSymphoniaSource::new(dec), path, start_pos)
                     .fade_in(Duration::from_millis(80)) // 稍长的淡入来补偿没有淡出
             )
         }
         Err(e) => {
             println!("Symphonia decoder failed, fallback to rodio: {e}");
-            let file = File::open(path).map_err(|e| e.to_string())?;
+            *player.chain_format.lock().unwrap() = None;
+            let file = File::open(path).map_err(|e| AppError::from(e).with_path(path))?;
+            let decoder = rodio::Decoder::new(BufReader::new(file)).map_err(|e| {
+                let code = if matches!(e, rodio::decoder::DecoderError::UnrecognizedFormat) { ErrorCode::UnsupportedFormat } else { ErrorCode::DecodeFailed };
+                AppError::new(code, e.to_string()).with_path(path).with_source(e)
+            })?;
+            player.player_state.buffering(app, decoder.total_duration(), decoder.sample_rate(), None);
+            let start_pos = position.unwrap_or(0.0);
+            let stretched = StretchSource::new(decoder, player.stretch.clone(), path, start_pos);
+            let clock = TrackClock::new(path, stretched.track_samples());
+            let stretched = stretched.with_clock(clock.clone());
             Box::new(
-                VisualizationSource::new(rodio::Decoder::new(BufReader::new(file)).map_err(|e| e.to_string())?, waveform, spectrum, Some(app.clone()), target_fps, enable_vertical_sync)
-                    .with_start_position(position.unwrap_or(0.0))
-                    .with_eq_settings(eq_settings)
+                wrap_visualization(app, state, stretched, path, start_pos)
+                    .with_track_clock(clock)
                     .fade_in(Duration::from_millis(80))
             )
         }
     };
-    let sink = player.sink.lock().unwrap();
-    sink.append(source);
-    sink.play();
+    {
+        let sink = player.sink.lock().unwrap();
+        sink.append(source);
+        sink.play();
+    }
+    // sink.stop() 会清空已追加的下一首，需要重新追加
+    requeue_next_track_shared(app, state)
+}
+
+/// 排队下一首音轨（共享模式）
+///
+/// 与当前音源链格式相同时只放入槽位，由当前音源链在结尾接管（可交叉淡化）；否则追加到 sink。
+fn append_next_track_shared(app: &AppHandle, state: &State<AppState>, mut next: NextTrack) -> AppResult<()> {
+    let player = &state.player;
+    let cancelled = next.rearm();
+    if *player.chain_format.lock().unwrap() == Some((next.sample_rate(), next.channels())) {
+        next.ensure_source()?;
+        gapless::replace_next_track(&player.next_track, next);
+        return Ok(());
+    }
+    let input = next.open_source()?;
+    let path = next.path.clone();
+    // 无缝衔接不使用 fade_in，保证采样连续
+    let source = GaplessEntry::new(build_symphonia_chain(app, state, input, &path, 0.0), path, cancelled, player.events.clone());
+    gapless::replace_next_track(&player.next_track, next);
+    player.sink.lock().unwrap().append(source);
     Ok(())
 }
 
+/// 重新追加已排队的下一首音轨（sink 被重建或清空后调用）
+fn requeue_next_track_shared(app: &AppHandle, state: &State<AppState>) -> AppResult<()> {
+    let pending = state.player.next_track.lock().unwrap().take();
+    match pending {
+        Some(next) => append_next_track_shared(app, state, next),
+        None => Ok(()),
+    }
+}
+
+/// 排队下一首音轨：提前打开并预填充解码器，当前音轨结束后无缝衔接
+pub fn queue_next_track(app: &AppHandle, state: &State<AppState>, path: &str) -> AppResult<()> {
+    if state.player.current_path.lock().unwrap().is_none() {
+        return Err(AppError::no_track_loaded());
+    }
+    if state.player.sleep_timer.is_last_track() {
+        println!("Sleep timer stops after the current track, not queueing {path}");
+        return Ok(());
+    }
+    let next = NextTrack::prepare(path, &state.player.looping)?;
+    state.player.replay_gain.register(path);
+    println!("Queued next track: {path}");
+    if *state.player.exclusive_mode.lock().unwrap() {
+        // 独占模式：解码线程在当前音轨 EOF 时接管预填充的解码器
+        gapless::replace_next_track(&state.player.next_track, next);
+        Ok(())
+    } else {
+        append_next_track_shared(app, state, next)
+    }
+}
+
+/// 开始播放音轨（根据当前模式选择共享或独占输出）
+pub fn start_track(app: &AppHandle, state: &State<AppState>, path: &str, position: Option<f32>) -> AppResult<()> {
+    // 手动切换音轨时丢弃已排队的下一首
+    if position.is_none() {
+        gapless::clear_next_track(&state.player.next_track);
+        state.player.looping.retain_ab_for(path);
+    }
+    state.player.replay_gain.register(path);
+    let player_state = &state.player.player_state;
+    player_state.load(app, path, position.unwrap_or(0.0));
+    let result = if *state.player.exclusive_mode.lock().unwrap() {
+        play_track_exclusive(app, state, path, position)
+    } else {
+        play_track_shared(app, state, path, position)
+    };
+    match &result {
+        Ok(()) => {
+            player_state.transition(app, PlayerStatus::Playing, TransitionReason::Ready);
+            crate::history::on_track_started(app, path, position);
+        }
+        Err(e) => player_state.fail(app, e.message()),
+    }
+    result
+}
+
+/// 独占模式的定位请求，由正在推送的线程在当前音源内处理（不重建解码和处理管线）
+#[derive(Debug, Default)]
+pub struct ExclusiveSeek {
+    /// 正在推送的线程 ID（0 表示没有线程）
+    thread_id: u64,
+    /// 正在推送的音轨（无缝衔接后缓冲区中可能仍是上一首）
+    path: String,
+    /// 待处理的定位位置（秒）
+    position: Option<f32>,
+}
+
+impl ExclusiveSeek {
+    /// 交给正在推送 `path` 的线程处理，没有这样的线程时返回 false
+    pub fn post(&mut self, thread_id: u64, path: &str, position: f32) -> bool {
+        if self.thread_id == 0 || self.thread_id != thread_id || self.path != path {
+            return false;
+        }
+        self.position = Some(position);
+        true
+    }
+
+    #[cfg(windows)]
+    fn attach(&mut self, thread_id: u64, path: &str) {
+        self.thread_id = thread_id;
+        path.clone_into(&mut self.path);
+        self.position = None;
+    }
+
+    #[cfg(windows)]
+    fn set_path(&mut self, thread_id: u64, path: &str) {
+        if self.thread_id == thread_id {
+            path.clone_into(&mut self.path);
+        }
+    }
+
+    #[cfg(windows)]
+    fn take(&mut self, thread_id: u64) -> Option<f32> {
+        if self.thread_id == thread_id { self.position.take() } else { None }
+    }
+
+    #[cfg(windows)]
+    fn is_pending(&self, thread_id: u64) -> bool {
+        self.thread_id == thread_id && self.position.is_some()
+    }
+
+    /// 播放结束时解除关联；有未处理的定位请求时返回 false（线程继续处理）
+    #[cfg(windows)]
+    fn detach(&mut self, thread_id: u64) -> bool {
+        if self.is_pending(thread_id) {
+            return false;
+        }
+        self.release(thread_id);
+        true
+    }
+
+    #[cfg(windows)]
+    fn release(&mut self, thread_id: u64) {
+        if self.thread_id == thread_id {
+            self.thread_id = 0;
+            self.position = None;
+        }
+    }
+}
+
+/// 定位（独占模式）：交给正在推送的线程处理，线程已结束（如播放完毕）时重新开始播放
+#[cfg(windows)]
+pub fn seek_track_exclusive(app: &AppHandle, state: &State<AppState>, path: &str, time: f32) -> AppResult<()> {
+    let thread_id = state.player.decode_thread_id.load(Ordering::SeqCst);
+    if state.player.exclusive_seek.lock().unwrap().post(thread_id, path, time.max(0.0)) {
+        return Ok(());
+    }
+    play_track_exclusive(app, state, path, Some(time))
+}
+
 /// 播放音轨（独占模式）
 #[cfg(windows)]
-pub fn play_track_exclusive(app: &AppHandle, state: &State<AppState>, path: &str, position: Option<f32>) -> Result<(), String> {
+pub fn play_track_exclusive(app: &AppHandle, state: &State<AppState>, path: &str, position: Option<f32>) -> AppResult<()> {
     let player = &state.player;
     player.decode_thread_stop.store(true, Ordering::SeqCst);
     let new_thread_id = player.decode_thread_id.fetch_add(1, Ordering::SeqCst) + 1;
@@ -554,7 +712,7 @@ pub fn play_track_exclusive(app: &AppHandle, state: &State<AppState>, path: &str
 
     let (target_sr, target_ch) = {
         let g = player.wasapi_player.lock().unwrap();
-        let wasapi = g.as_ref().ok_or("WASAPI player not initialized")?;
+        let wasapi = g.as_ref().ok_or_else(|| AppError::device_unavailable("WASAPI player not initialized"))?;
         (wasapi.get_sample_rate(), wasapi.get_channels())
     };
     if position.is_none() {
@@ -562,14 +720,25 @@ pub fn play_track_exclusive(app: &AppHandle, state: &State<AppState>, path: &str
     }
     println!("WASAPI Exclusive: {path} @ {target_sr}Hz, {target_ch} ch");
 
-    let mut decoder = SymphoniaDecoder::new(path).map_err(|e| format!("Failed to create decoder: {e}"))?;
+    let mut decoder = SymphoniaDecoder::new(path).map_err(|e| e.context_message("Failed to create decoder"))?.with_looping(&player.looping);
     if let Some(t) = position { let _ = decoder.seek(Duration::from_secs_f32(t)); }
     let _ = decoder.prefill_buffer();
+    let output_format = OutputFormat { sample_rate: target_sr, channels: target_ch, exclusive: true };
+    player.player_state.buffering(app, decoder.total_duration(), decoder.sample_rate(), Some(output_format));
     let (src_sr, src_ch) = (decoder.sample_rate(), decoder.channels());
     println!("Source: {src_sr}Hz, {src_ch} ch -> Target: {target_sr}Hz, {target_ch} ch");
 
-    let source = LockFreeSymphoniaSource::new(decoder);
     let start_pos = position.unwrap_or(0.0);
+    let transitions = Arc::new(Mutex::new(std::collections::VecDeque::new()));
+    let source = CrossfadeSource::new(
+        LockFreeSymphoniaSource::new(decoder),
+        path,
+        start_pos,
+        Arc::clone(&player.crossfade),
+        Arc::clone(&player.next_track),
+        TransitionNotifier::Deferred(Arc::clone(&transitions)),
+        player.stretch.clone(),
+    );
     let (wasapi_clone, waveform, spectrum, stop_flag, thread_id, eq_settings) = (
         Arc::clone(&player.wasapi_player),
         Arc::clone(&player.waveform_data),
@@ -578,6 +747,16 @@ pub fn play_track_exclusive(app: &AppHandle, state: &State<AppState>, path: &str
         Arc::clone(&player.decode_thread_id),
         state.equalizer.get_settings_handle(),
     );
+    let (next_track, current_path, track_path, crossfade) = (
+        Arc::clone(&player.next_track),
+        Arc::clone(&player.current_path),
+        path.to_string(),
+        Arc::clone(&player.crossfade),
+    );
+    let exclusive_seek = Arc::clone(&player.exclusive_seek);
+    exclusive_seek.lock().unwrap().attach(new_thread_id, path);
+    let gain_stage = GainStage::new(Arc::clone(&player.replay_gain), path);
+    let stretch = player.stretch.clone();
     let app_clone = app.clone();
     let thread_started = Arc::new(AtomicBool::new(false));
     let thread_started_clone = Arc::clone(&thread_started);
@@ -585,7 +764,7 @@ pub fn play_track_exclusive(app: &AppHandle, state: &State<AppState>, path: &str
     std::thread::spawn(move || {
         thread_started_clone.store(true, Ordering::SeqCst);
         let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            decode_and_push_to_wasapi(source, wasapi_clone, waveform, spectrum, app_clone, stop_flag, thread_id, new_thread_id, src_sr, src_ch, target_sr, target_ch, eq_settings, start_pos)
+            decode_and_push_to_wasapi(source, wasapi_clone, waveform, spectrum, app_clone, stop_flag, thread_id, new_thread_id, src_sr, src_ch, target_sr, target_ch, eq_settings, start_pos, track_path, next_track, current_path, crossfade, transitions, gain_stage, stretch, exclusive_seek)
         }));
     });
 
@@ -608,7 +787,7 @@ pub fn play_track_exclusive(app: &AppHandle, state: &State<AppState>, path: &str
             }
             // 额外等待一小段时间确保数据稳定
             std::thread::sleep(Duration::from_millis(20));
-            wasapi.start().map_err(|e| format!("Failed to start WASAPI: {e:?}"))?;
+            wasapi.start().map_err(|e| AppError::device_unavailable(format!("Failed to start WASAPI: {e:?}")))?;
         }
     }
     Ok(())
@@ -616,8 +795,14 @@ pub fn play_track_exclusive(app: &AppHandle, state: &State<AppState>, path: &str
 
 /// 播放音轨（独占模式）
 #[cfg(not(windows))]
-pub fn play_track_exclusive(_app: &AppHandle, _state: &State<AppState>, _path: &str, _position: Option<f32>) -> Result<(), String> {
-    Err("Exclusive mode is only supported on Windows".to_string())
+pub fn play_track_exclusive(_app: &AppHandle, _state: &State<AppState>, _path: &str, _position: Option<f32>) -> AppResult<()> {
+    Err(AppError::new(ErrorCode::Unsupported, "Exclusive mode is only supported on Windows"))
+}
+
+/// 定位（独占模式）
+#[cfg(not(windows))]
+pub fn seek_track_exclusive(_app: &AppHandle, _state: &State<AppState>, _path: &str, _time: f32) -> AppResult<()> {
+    Err(AppError::device_unavailable("Exclusive mode is only supported on Windows"))
 }
 
 /// 根据采样率计算解码chunk 大小
@@ -688,9 +873,156 @@ impl EqProcessor {
     }
 }
 
+/// 独占模式的分块处理管线（EQ + 重采样），源采样率变化时需要重建
+#[cfg(windows)]
+struct ChunkPipeline {
+    chunk_size: usize,
+    resample_ratio: f64,
+    resampler: Option<rubato::SincFixedIn<f32>>,
+    eq_proc: EqProcessor,
+    input_frames: Vec<Vec<f32>>,
+}
+
+#[cfg(windows)]
+impl ChunkPipeline {
+    fn new(src_sr: u32, src_ch: u16, target_sr: u32, eq_settings: &RwLock<EqSettings>) -> Self {
+        use rubato::{SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction};
+        let mut eq_proc = EqProcessor::new(src_sr, src_ch);
+        if let Ok(settings) = eq_settings.read() {
+            eq_proc.update_settings(&settings);
+        }
+        let chunk_size = calculate_decode_chunk_size(src_sr);
+        let resample_ratio = target_sr as f64 / src_sr as f64;
+        let resampler = if src_sr != target_sr {
+            SincFixedIn::<f32>::new(
+                resample_ratio,
+                2.0,
+                SincInterpolationParameters {
+                    sinc_len: 128,
+                    f_cutoff: 0.925,
+                    interpolation: SincInterpolationType::Linear,
+                    oversampling_factor: 128,
+                    window: WindowFunction::BlackmanHarris2,
+                },
+                chunk_size,
+                src_ch as usize,
+            ).ok()
+        } else { None };
+        Self { chunk_size, resample_ratio, resampler, eq_proc, input_frames: vec![Vec::with_capacity(chunk_size); src_ch as usize] }
+    }
+
+    /// 源采样数换算为输出采样数
+    fn output_samples_for(&self, src_frames: usize, target_ch: u16) -> u64 {
+        (src_frames as f64 * self.resample_ratio) as u64 * target_ch as u64
+    }
+}
+
+/// 独占模式播放位置追踪（处理无缝衔接时缓冲区中尚未播放的上一首）
+///
+/// 每次推送后记录检查点（推送位置, 音轨位置），按硬件已播放的位置在检查点之间插值，
+/// 因此变速播放时上报的仍是音轨时间。
+#[cfg(windows)]
+struct WasapiPositionTracker {
+    samples_per_sec: f32,
+    checkpoints: std::collections::VecDeque<(u64, f32)>,
+    /// 已推送到 WASAPI 缓冲区的采样数
+    pushed: u64,
+    /// 尚未到达硬件的音轨衔接点：(推送采样位置, 上一首, 下一首, 下一首时长, 下一首采样率)
+    pending: std::collections::VecDeque<(u64, String, String, Option<Duration>, u32)>,
+    last_emit_time: u64,
+}
+
+#[cfg(windows)]
+impl WasapiPositionTracker {
+    fn new(target_sr: u32, target_ch: u16, start_position: f32) -> Self {
+        Self {
+            samples_per_sec: target_sr as f32 * target_ch as f32,
+            checkpoints: std::collections::VecDeque::from([(0, start_position)]),
+            pushed: 0,
+            pending: std::collections::VecDeque::new(),
+            last_emit_time: 0,
+        }
+    }
+
+    fn mark_transition(&mut self, offset: u64, from: String, to: String, duration: Option<Duration>, sample_rate: u32) {
+        self.pending.push_back((self.pushed + offset, from, to, duration, sample_rate));
+    }
+
+    /// 记录一次推送及推送末尾对应的音轨位置
+    fn record_push(&mut self, samples: u64, track_position: f32) {
+        self.pushed += samples;
+        self.checkpoints.push_back((self.pushed, track_position));
+    }
+
+    /// 硬件已播放到 `written` 时的音轨位置
+    fn position_at(&mut self, written: u64) -> f32 {
+        while self.checkpoints.len() > 1 && self.checkpoints[1].0 <= written {
+            self.checkpoints.pop_front();
+        }
+        let (prev_at, prev_pos) = self.checkpoints[0];
+        let Some(&(next_at, next_pos)) = self.checkpoints.get(1) else { return prev_pos };
+        if next_pos >= prev_pos {
+            let t = written.saturating_sub(prev_at) as f32 / next_at.saturating_sub(prev_at).max(1) as f32;
+            prev_pos + (next_pos - prev_pos) * t
+        } else {
+            // 两个检查点之间发生了音轨切换
+            (next_pos - next_at.saturating_sub(written) as f32 / self.samples_per_sec).max(0.0)
+        }
+    }
+
+    /// 检查衔接点并发送播放位置（每100ms一次）
+    fn poll(&mut self, app: &AppHandle, wasapi: &Mutex<Option<super::wasapi::WasapiExclusivePlayback>>, current_path: &Mutex<Option<String>>) {
+        let written = wasapi.lock().unwrap().as_ref().map_or(0, |p| p.get_samples_written());
+        while self.pending.front().is_some_and(|(at, ..)| written >= *at) {
+            let Some((_, from, to, duration, sample_rate)) = self.pending.pop_front() else { break };
+            *current_path.lock().unwrap() = Some(to.clone());
+            let _ = emit_track_ended(app, Some(from.clone()), Some(to.clone()));
+            let _ = gapless::emit_track_transition(app, Some(from), &to, duration, sample_rate);
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        if now - self.last_emit_time >= 100 {
+            self.last_emit_time = now;
+            let position = self.position_at(written);
+            let _ = emit_playback_position(app, position);
+        }
+    }
+}
+
+/// 从槽位取出下一首音轨并创建音源
+#[cfg(windows)]
+fn take_next_source(
+    next_track: &NextTrackSlot,
+    crossfade: &Arc<RwLock<crate::config::CrossfadeConfig>>,
+    transitions: &Arc<Mutex<std::collections::VecDeque<super::crossfade::CrossfadeTransition>>>,
+    stretch: &StretchControl,
+) -> Option<(CrossfadeSource, String)> {
+    let mut next = next_track.lock().unwrap().take()?;
+    match next.open_source() {
+        Ok(input) => {
+            let source = CrossfadeSource::new(
+                input,
+                &next.path,
+                0.0,
+                Arc::clone(crossfade),
+                Arc::clone(next_track),
+                TransitionNotifier::Deferred(Arc::clone(transitions)),
+                stretch.clone(),
+            );
+            Some((source, next.path.clone()))
+        }
+        Err(e) => {
+            eprintln!("Failed to open next track {}: {e}", next.path);
+            None
+        }
+    }
+}
+
 #[cfg(windows)]
 fn decode_and_push_to_wasapi(
-    mut source: LockFreeSymphoniaSource,
+    mut source: CrossfadeSource,
     wasapi: Arc<Mutex<Option<super::wasapi::WasapiExclusivePlayback>>>,
     _waveform: Arc<Mutex<Vec<f32>>>,
     _spectrum: Arc<Mutex<Vec<f32>>>,
@@ -698,106 +1030,133 @@ fn decode_and_push_to_wasapi(
     stop_flag: Arc<AtomicBool>,
     thread_id_ref: Arc<AtomicU64>,
     my_id: u64,
-    src_sr: u32,
+    mut src_sr: u32,
     src_ch: u16,
     target_sr: u32,
     target_ch: u16,
     eq_settings: Arc<RwLock<EqSettings>>,
     start_position: f32,
+    mut track_path: String,
+    next_track: NextTrackSlot,
+    current_path: Arc<Mutex<Option<String>>>,
+    crossfade: Arc<RwLock<crate::config::CrossfadeConfig>>,
+    transitions: Arc<Mutex<std::collections::VecDeque<super::crossfade::CrossfadeTransition>>>,
+    mut gain_stage: GainStage,
+    stretch: StretchControl,
+    exclusive_seek: Arc<Mutex<ExclusiveSeek>>,
 ) {
-    use rubato::{Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction};
-    if stop_flag.load(Ordering::SeqCst) || thread_id_ref.load(Ordering::SeqCst) != my_id { return; }
+    use rubato::Resampler;
+    let is_active = || !stop_flag.load(Ordering::SeqCst) && thread_id_ref.load(Ordering::SeqCst) == my_id;
+    if !is_active() { return; }
 
-    let mut eq_proc = EqProcessor::new(src_sr, src_ch);
-    if let Ok(settings) = eq_settings.read() {
-        eq_proc.update_settings(&settings);
-    }
-    let need_resample = src_sr != target_sr;
-    let chunk_size = calculate_decode_chunk_size(src_sr);
-    let resample_ratio = target_sr as f64 / src_sr as f64;
+    let mut pipeline = ChunkPipeline::new(src_sr, src_ch, target_sr, &eq_settings);
     let mut eq_update_counter: u32 = 0;
-    let mut resampler: Option<SincFixedIn<f32>> = if need_resample {
-        SincFixedIn::<f32>::new(
-            resample_ratio,
-            2.0,
-            SincInterpolationParameters {
-                sinc_len: 128,
-                f_cutoff: 0.925,
-                interpolation: SincInterpolationType::Linear,
-                oversampling_factor: 128,
-                window: WindowFunction::BlackmanHarris2,
-            },
-            chunk_size,
-            src_ch as usize,
-        ).ok()
-    } else { None };
-
-    let mut input_frames: Vec<Vec<f32>> = vec![Vec::with_capacity(chunk_size); src_ch as usize];
-    // 精确计算最大输出缓冲区大小
-    let max_output_frames = ((chunk_size as f64 * resample_ratio).ceil() as usize).max(chunk_size);
-    let mut output_buffer: Vec<f32> = Vec::with_capacity(max_output_frames * target_ch as usize);
-    
+    let mut output_buffer: Vec<f32> = Vec::new();
+
     // 播放位置追踪
-    let mut last_position_emit_time: u64 = 0;
-    
-    // 发送播放位置的闭包
-    let emit_position = |last_time: &mut u64| {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis() as u64;
-        if now - *last_time >= 100 {
-            *last_time = now;
-            let samples_played = wasapi.lock().unwrap()
-                .as_ref()
-                .map_or(0, |p| p.get_samples_written());
-            let position = start_position + samples_played as f32 / (target_sr as f32 * target_ch as f32);
-            let _ = emit_playback_position(&app, position);
+    let mut tracker = WasapiPositionTracker::new(target_sr, target_ch, start_position);
+    // 已从当前音源读取的采样数（用于定位交叉淡化切换点）
+    let mut source_read: u64 = 0;
+    let set_pushing = |path: &str| exclusive_seek.lock().unwrap().set_path(my_id, path);
+
+    'decode: loop {
+        if !is_active() || wasapi.lock().unwrap().is_none() { break; }
+
+        // 在当前音源内定位：丢弃缓冲区中旧位置的数据，从新位置重新追踪播放位置
+        let seek_to = exclusive_seek.lock().unwrap().take(my_id);
+        if let Some(position) = seek_to {
+            if let Err(e) = source.try_seek(Duration::from_secs_f32(position)) {
+                eprintln!("Exclusive seek to {position}s failed: {e}");
+            }
+            if let Some(ref p) = *wasapi.lock().unwrap() { let _ = p.clear_buffer(); }
+            transitions.lock().unwrap().clear();
+            tracker = WasapiPositionTracker::new(target_sr, target_ch, position);
+            pipeline = ChunkPipeline::new(src_sr, src_ch, target_sr, &eq_settings);
+            source_read = 0;
         }
-    };
-
-    loop {
-        if stop_flag.load(Ordering::SeqCst) || thread_id_ref.load(Ordering::SeqCst) != my_id || wasapi.lock().unwrap().is_none() { break; }
-        for ch in &mut input_frames { ch.clear(); }
+        for ch in &mut pipeline.input_frames { ch.clear(); }
 
+        let chunk_size = pipeline.chunk_size;
         let samples_needed = chunk_size * src_ch as usize;
         let mut interleaved = Vec::with_capacity(samples_needed);
         let mut eof = false;
-        for _ in 0..samples_needed {
-            if let Some(s) = source.next() { interleaved.push(s); }
-            else { eof = true; break; }
+        // 采样率不同的下一首需要在当前块处理完后重建管线
+        let mut switch_to: Option<(CrossfadeSource, String)> = None;
+        let chunk_start_read = source_read;
+        while interleaved.len() < samples_needed {
+            if let Some(s) = source.next() {
+                interleaved.push(s);
+                source_read += 1;
+                continue;
+            }
+            match take_next_source(&next_track, &crossfade, &transitions, &stretch) {
+                Some((next, next_path)) if next.sample_rate() == src_sr && next.channels() == src_ch => {
+                    // 同一块内直接衔接：下一首的第一个采样紧跟上一首的最后一个采样
+                    let offset = pipeline.output_samples_for(interleaved.len() / src_ch as usize, target_ch);
+                    set_pushing(&next_path);
+                    let from = std::mem::replace(&mut track_path, next_path.clone());
+                    tracker.mark_transition(offset, from, next_path, next.total_duration(), next.sample_rate());
+                    source = next;
+                    source_read = 0;
+                }
+                Some(next) => {
+                    switch_to = Some(next);
+                    eof = true;
+                    break;
+                }
+                None => {
+                    eof = true;
+                    break;
+                }
+            }
         }
-        if interleaved.is_empty() { break; }
-        
+
+        // 交叉淡化切换点：在对应采样真正播放时再发送事件
+        let crossfaded: Vec<_> = transitions.lock().unwrap().drain(..).collect();
+        for t in crossfaded {
+            let frames = t.at_sample.saturating_sub(chunk_start_read) as usize / src_ch as usize;
+            let offset = pipeline.output_samples_for(frames, target_ch);
+            track_path.clone_from(&t.to);
+            set_pushing(&t.to);
+            tracker.mark_transition(offset, t.from, t.to, t.duration, src_sr);
+        }
+
         // 发送播放位置
-        emit_position(&mut last_position_emit_time);
+        tracker.poll(&app, &wasapi, &current_path);
+
+        // 回放增益（在 EQ 之前）
+        gain_stage.refresh(&track_path);
+        gain_stage.apply(&mut interleaved);
 
         for (i, s) in interleaved.iter().enumerate() {
-            input_frames[i % src_ch as usize].push(*s);
+            pipeline.input_frames[i % src_ch as usize].push(*s);
         }
 
         eq_update_counter += 1;
         if eq_update_counter >= 4 {
             eq_update_counter = 0;
             if let Ok(settings) = eq_settings.try_read() {
-                eq_proc.update_settings(&settings);
+                pipeline.eq_proc.update_settings(&settings);
             }
         }
 
-        if eq_proc.is_enabled() {
+        if pipeline.eq_proc.is_enabled() {
             for ch in 0..src_ch as usize {
-                for s in &mut input_frames[ch] {
-                    *s = eq_proc.process_sample_cached(*s, ch);
+                for s in &mut pipeline.input_frames[ch] {
+                    *s = pipeline.eq_proc.process_sample_cached(*s, ch);
                 }
             }
         }
 
         // 处理重采样
-        let output_frames: Vec<Vec<f32>> = if let Some(ref mut r) = resampler {
+        let output_frames: Vec<Vec<f32>> = if interleaved.is_empty() {
+            Vec::new()
+        } else if let Some(ref mut r) = pipeline.resampler {
+            let input_frames = &mut pipeline.input_frames;
             let actual = input_frames[0].len();
             if actual < chunk_size && !eof {
                 // 非EOF情况下填充到chunk_size
-                for ch in &mut input_frames {
+                for ch in input_frames.iter_mut() {
                     let last_sample = ch.last().copied().unwrap_or(0.0);
                     let samples_to_add = chunk_size - ch.len();
                     ch.extend((0..samples_to_add).map(|i| {
@@ -805,14 +1164,14 @@ fn decode_and_push_to_wasapi(
                         last_sample * fade
                     }));
                 }
-                r.process(&input_frames, None).unwrap_or_else(|_| input_frames.clone())
+                r.process(input_frames, None).unwrap_or_else(|_| input_frames.clone())
             } else if eof && actual < chunk_size {
                 // EOF情况下 - 直接复制剩余数据
                 input_frames.clone()
             } else {
-                r.process(&input_frames, None).unwrap_or_else(|_| input_frames.clone())
+                r.process(input_frames, None).unwrap_or_else(|_| input_frames.clone())
             }
-        } else { input_frames.clone() };
+        } else { pipeline.input_frames.clone() };
 
         // 交错输出帧
         output_buffer.clear();
@@ -834,37 +1193,64 @@ fn decode_and_push_to_wasapi(
         if !final_out.is_empty() {
             // 等待缓冲区有空间（防止解码过快导致内存无限增长）
             loop {
-                if stop_flag.load(Ordering::SeqCst) || thread_id_ref.load(Ordering::SeqCst) != my_id { break; }
+                if !is_active() { break; }
                 let buf_size = wasapi.lock().unwrap().as_ref().map_or(0, |p| p.get_buffer_size());
                 // 缓冲区容量约为 target_sr * target_ch * 4秒，保持在2秒以下
                 let max_buffer = target_sr as usize * target_ch as usize * 2;
                 if buf_size < max_buffer { break; }
                 // 等待时继续发送播放位置
-                emit_position(&mut last_position_emit_time);
+                tracker.poll(&app, &wasapi, &current_path);
                 std::thread::sleep(Duration::from_millis(10));
             }
-            if stop_flag.load(Ordering::SeqCst) || thread_id_ref.load(Ordering::SeqCst) != my_id { break; }
-            
+            if !is_active() { break; }
+
+            let track_position = source.clock().samples() as f32 / (src_sr as f32 * src_ch as f32);
+            tracker.record_push(final_out.len() as u64, track_position);
             if let Some(ref p) = *wasapi.lock().unwrap() {
                 if p.push_samples(final_out).is_err() { break; }
             }
         }
 
-        if eof && interleaved.len() < samples_needed {
+        if eof && switch_to.is_none() {
+            // 等待缓冲区播放完毕，期间仍可接管新排队的下一首
             loop {
-                if stop_flag.load(Ordering::SeqCst) || thread_id_ref.load(Ordering::SeqCst) != my_id { break; }
+                if !is_active() { break; }
+                if let Some(next) = take_next_source(&next_track, &crossfade, &transitions, &stretch) {
+                    switch_to = Some(next);
+                    break;
+                }
+                if exclusive_seek.lock().unwrap().is_pending(my_id) { continue 'decode; }
+                tracker.poll(&app, &wasapi, &current_path);
                 let buf_size = wasapi.lock().unwrap().as_ref().map_or(0, |p| p.get_buffer_size());
                 if buf_size == 0 { break; }
                 std::thread::sleep(Duration::from_millis(50));
             }
-            if !stop_flag.load(Ordering::SeqCst) && thread_id_ref.load(Ordering::SeqCst) == my_id {
+        }
+
+        if let Some((next, next_path)) = switch_to {
+            // 下一首采样率不同：在当前推送位置衔接并重建处理管线
+            set_pushing(&next_path);
+            let from = std::mem::replace(&mut track_path, next_path.clone());
+            tracker.mark_transition(0, from, next_path, next.total_duration(), next.sample_rate());
+            src_sr = next.sample_rate();
+            pipeline = ChunkPipeline::new(src_sr, src_ch, target_sr, &eq_settings);
+            source = next;
+            source_read = 0;
+            continue 'decode;
+        }
+
+        if eof {
+            // 播放完毕前收到的定位请求仍需处理
+            if !exclusive_seek.lock().unwrap().detach(my_id) { continue 'decode; }
+            if is_active() {
                 if let Some(ref p) = *wasapi.lock().unwrap() { let _ = p.stop(); }
-                let _ = emit_track_ended(&app);
+                let _ = emit_track_ended(&app, Some(track_path), None);
             }
             break;
         }
         std::thread::sleep(Duration::from_micros(500));
     }
+    exclusive_seek.lock().unwrap().release(my_id);
 }
 
 /// 5.1/7.1环绕声到立体声的专业混音
@@ -951,24 +1337,24 @@ fn convert_channels(samples: &[f32], src_ch: u16, target_ch: u16) -> Vec<f32> {
 }
 
 /// Seek共享模式
-pub fn seek_track_shared(app: &AppHandle, state: &State<AppState>, path: &str, time: f32) -> Result<(), String> {
+pub fn seek_track_shared(app: &AppHandle, state: &State<AppState>, path: &str, time: f32) -> AppResult<()> {
     let player = &state.player;
-    let eq_settings = state.equalizer.get_settings_handle();
-    let mut decoder = SymphoniaDecoder::new(path).map_err(|e| format!("Failed to create decoder: {e}"))?;
-    decoder.seek(Duration::from_secs_f32(time))?;
+    // 优先在正在播放的音源链内定位，保留解码线程、EQ 状态和淡入进度
+    let seeked = {
+        let sink = player.sink.lock().unwrap();
+        if sink.empty() { Err("Sink is empty".to_string()) } else { sink.try_seek(Duration::from_secs_f32(time.max(0.0))).map_err(|e| e.to_string()) }
+    };
+    match seeked {
+        Ok(()) => return Ok(()),
+        Err(e) => println!("In-place seek unavailable, rebuilding playback: {e}"),
+    }
+    let mut decoder = SymphoniaDecoder::new(path).map_err(|e| e.context_message("Failed to create decoder"))?.with_looping(&player.looping);
+    decoder.seek(Duration::from_secs_f32(time)).map_err(|e| e.with_path(path))?;
     let _ = decoder.prefill_buffer();
+    *player.chain_format.lock().unwrap() = Some((decoder.sample_rate(), decoder.target_channels()));
     let source: Box<dyn Source<Item = f32> + Send> = Box::new(
-        VisualizationSource::new(
-            LockFreeSymphoniaSource::new(decoder),
-            Arc::clone(&player.waveform_data),
-            Arc::clone(&player.spectrum_data),
-            Some(app.clone()),
-            Arc::clone(&player.target_fps),
-            Arc::clone(&player.enable_vertical_sync),
-        )
-        .with_start_position(time)
-        .with_eq_settings(eq_settings)
-        .fade_in(Duration::from_millis(50)) // seek时使用较短的淡入
+        build_symphonia_chain(app, state, LockFreeSymphoniaSource::new(decoder), path, time)
+            .fade_in(Duration::from_millis(50)) // seek时使用较短的淡入
     );
     {
         let sink = player.sink.lock().unwrap();
@@ -976,14 +1362,16 @@ pub fn seek_track_shared(app: &AppHandle, state: &State<AppState>, path: &str, t
         sink.stop();
         sink.set_volume(*player.target_volume.lock().unwrap());
     }
-    let sink = player.sink.lock().unwrap();
-    sink.append(source);
-    sink.play();
-    Ok(())
+    {
+        let sink = player.sink.lock().unwrap();
+        sink.append(source);
+        sink.play();
+    }
+    requeue_next_track_shared(app, state)
 }
 
 /// 获取播放状态
-pub fn get_status(state: &State<AppState>) -> Result<PlaybackStatus, String> {
+pub fn get_status(state: &State<AppState>) -> AppResult<PlaybackStatus> {
     // 使用try_lock避免阻塞主线程
     let volume = state.player.target_volume.try_lock()
         .map(|g| *g)
@@ -1015,7 +1403,7 @@ pub fn get_status(state: &State<AppState>) -> Result<PlaybackStatus, String> {
 }
 
 /// 检查音轨是否播放完毕
-pub fn check_track_finished(state: &State<AppState>) -> Result<bool, String> {
+pub fn check_track_finished(state: &State<AppState>) -> AppResult<bool> {
     // 使用try_lock避免阻塞主线程
     let exclusive_mode = state.player.exclusive_mode.try_lock()
         .map(|g| *g)
@@ -0,0 +1,235 @@
+//! 播放器状态机模块
+//!
+//! 后端统一维护播放状态（停止、加载、缓冲、播放、暂停、结束、错误）以及当前音轨、时长、
+//! 位置和输出格式。每次状态转换都发送一个带版本号的 `player-state` 事件，
+//! 前端、插件和 Windows 任务栏都以它为准，不再各自推断播放状态。
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// `player-state` 事件的结构版本，字段不兼容变更时递增
+pub const PLAYER_STATE_VERSION: u32 = 1;
+
+/// 播放状态
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlayerStatus {
+    #[default]
+    Stopped,
+    /// 正在打开音轨
+    Loading,
+    /// 解码器已打开，正在预填充缓冲区
+    Buffering,
+    Playing,
+    Paused,
+    /// 播放到结尾且没有下一首
+    Ended,
+    Error,
+}
+
+impl PlayerStatus {
+    /// 是否允许转换到 `next`
+    #[must_use]
+    pub const fn can_transition_to(self, next: Self) -> bool {
+        match (self, next) {
+            // 任何状态都可以开始加载、停止或出错
+            (_, Self::Loading | Self::Stopped | Self::Error) => true,
+            (Self::Loading, Self::Buffering | Self::Playing | Self::Paused)
+            | (Self::Buffering, Self::Playing | Self::Paused)
+            // 播放或暂停中自动切换音轨、定位时保持原状态
+            | (Self::Playing, Self::Playing | Self::Paused | Self::Ended)
+            | (Self::Paused, Self::Paused | Self::Playing | Self::Ended) => true,
+            _ => false,
+        }
+    }
+}
+
+/// 状态转换原因
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransitionReason {
+    #[default]
+    Init,
+    /// 开始播放音轨
+    Play,
+    /// 解码器已就绪
+    Ready,
+    Pause,
+    Resume,
+    Seek,
+    /// 无缝衔接或交叉淡化到下一首
+    TrackChanged,
+    /// 播放到结尾
+    TrackEnded,
+    SleepTimer,
+    Error,
+}
+
+/// 输出格式
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// 是否为 WASAPI 独占模式
+    pub exclusive: bool,
+}
+
+/// 播放器状态快照（`player-state` 事件负载）
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerStateEvent {
+    pub version: u32,
+    /// 单调递增的转换序号，用于丢弃乱序到达的事件
+    pub seq: u64,
+    pub status: PlayerStatus,
+    pub path: Option<String>,
+    /// 音轨时长（秒）
+    pub duration: Option<f64>,
+    /// 音轨采样率（解码输出，未知时为空）
+    pub sample_rate: Option<u32>,
+    /// 转换时的播放位置（秒）
+    pub position: f64,
+    pub output_format: Option<OutputFormat>,
+    pub reason: TransitionReason,
+    /// 错误信息（仅 Error 状态）
+    pub error: Option<String>,
+}
+
+/// 播放器状态机（由播放器状态持有）
+pub struct PlayerStateMachine {
+    state: Mutex<PlayerStateEvent>,
+}
+
+impl Default for PlayerStateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlayerStateMachine {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { state: Mutex::new(PlayerStateEvent { version: PLAYER_STATE_VERSION, ..PlayerStateEvent::default() }) }
+    }
+
+    /// 当前状态快照
+    #[must_use]
+    pub fn snapshot(&self) -> PlayerStateEvent {
+        self.state.lock().unwrap().clone()
+    }
+
+    #[must_use]
+    pub fn status(&self) -> PlayerStatus {
+        self.state.lock().unwrap().status
+    }
+
+    /// 转换到 `status` 并发送事件，非法转换被忽略并返回 false
+    pub fn transition(&self, app: &AppHandle, status: PlayerStatus, reason: TransitionReason) -> bool {
+        self.update(app, status, reason, |_| {})
+    }
+
+    /// 转换到 `status`，同时通过 `apply` 修改音轨信息
+    pub fn update(&self, app: &AppHandle, status: PlayerStatus, reason: TransitionReason, apply: impl FnOnce(&mut PlayerStateEvent)) -> bool {
+        let event = {
+            let mut state = self.state.lock().unwrap();
+            if !state.status.can_transition_to(status) {
+                eprintln!("Ignored player state transition {:?} -> {status:?} ({reason:?})", state.status);
+                return false;
+            }
+            state.seq += 1;
+            state.status = status;
+            state.reason = reason;
+            if status != PlayerStatus::Error {
+                state.error = None;
+            }
+            apply(&mut state);
+            state.clone()
+        };
+        notify(app, &event);
+        true
+    }
+
+    /// 开始加载音轨
+    pub fn load(&self, app: &AppHandle, path: &str, position: f32) {
+        self.update(app, PlayerStatus::Loading, TransitionReason::Play, |s| {
+            s.path = Some(path.to_string());
+            s.duration = None;
+            s.sample_rate = None;
+            s.position = f64::from(position);
+        });
+    }
+
+    /// 解码器已打开，记录时长、采样率与输出格式（定位时重建音源不经过加载状态，此时忽略）
+    pub fn buffering(&self, app: &AppHandle, duration: Option<Duration>, sample_rate: u32, output_format: Option<OutputFormat>) {
+        if self.status() != PlayerStatus::Loading {
+            return;
+        }
+        self.update(app, PlayerStatus::Buffering, TransitionReason::Ready, |s| {
+            s.duration = duration.map(|d| d.as_secs_f64());
+            s.sample_rate = Some(sample_rate);
+            if output_format.is_some() {
+                s.output_format = output_format;
+            }
+        });
+    }
+
+    /// 切换到下一首（保持播放或暂停状态）
+    pub fn track_changed(&self, app: &AppHandle, path: &str, duration: Option<Duration>, sample_rate: u32) {
+        let status = self.status();
+        if matches!(status, PlayerStatus::Playing | PlayerStatus::Paused) {
+            self.update(app, status, TransitionReason::TrackChanged, |s| {
+                s.path = Some(path.to_string());
+                s.duration = duration.map(|d| d.as_secs_f64());
+                s.sample_rate = Some(sample_rate);
+                s.position = 0.0;
+            });
+        }
+    }
+
+    /// 定位完成（保持播放或暂停状态）
+    pub fn seeked(&self, app: &AppHandle, position: f32) {
+        let status = self.status();
+        if matches!(status, PlayerStatus::Playing | PlayerStatus::Paused) {
+            self.update(app, status, TransitionReason::Seek, |s| s.position = f64::from(position));
+        }
+    }
+
+    /// 进入错误状态
+    pub fn fail(&self, app: &AppHandle, error: &str) {
+        self.update(app, PlayerStatus::Error, TransitionReason::Error, |s| s.error = Some(error.to_string()));
+    }
+
+    /// 记录播放位置（不发送事件）
+    pub fn set_position(&self, position: f32) {
+        if let Ok(mut state) = self.state.try_lock() {
+            state.position = f64::from(position);
+        }
+    }
+
+    /// 记录输出格式（输出设备变化时调用，不发送事件）
+    pub fn set_output_format(&self, output_format: OutputFormat) {
+        self.state.lock().unwrap().output_format = Some(output_format);
+    }
+}
+
+/// 发送状态事件并同步到任务栏
+fn notify(app: &AppHandle, event: &PlayerStateEvent) {
+    #[cfg(windows)]
+    {
+        use crate::taskbar::PlaybackState;
+        let taskbar_state = match event.status {
+            PlayerStatus::Playing | PlayerStatus::Buffering => Some(PlaybackState::Playing),
+            PlayerStatus::Paused => Some(PlaybackState::Paused),
+            PlayerStatus::Stopped | PlayerStatus::Ended | PlayerStatus::Error => Some(PlaybackState::Stopped),
+            PlayerStatus::Loading => None,
+        };
+        if let Some(taskbar_state) = taskbar_state {
+            // 任务栏尚未初始化时忽略
+            let _ = crate::taskbar::update_playback_state(taskbar_state);
+        }
+    }
+    let _ = app.emit("player-state", event);
+}
@@ -0,0 +1,170 @@
+//! 回放增益模块
+//!
+//! 在控制线程（播放、排队命令）中预先读取 REPLAYGAIN_* 标签并缓存，
+//! 音频线程按当前音轨路径查询线性增益，只使用 `try_lock`，取不到锁时沿用上一次的增益。
+
+use crate::config::{ReplayGainConfig, ReplayGainMode};
+use crate::media::metadata::read_replay_gain;
+use crate::media::ReplayGainInfo;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// 最多缓存的音轨数（上一首、当前、下一首）
+const MAX_TRACKS: usize = 3;
+
+/// 增益平滑系数（每个采样向目标靠近的比例，约 10ms 过渡）
+const GAIN_SMOOTHING: f32 = 0.002;
+
+struct TrackEntry {
+    path: String,
+    info: ReplayGainInfo,
+    /// 同一专辑的判定依据：所在目录 + 专辑增益
+    album_key: Option<(String, u32)>,
+    /// 自动模式下首次查询时确定是否使用专辑增益，之后不再变化
+    use_album: Option<bool>,
+}
+
+/// 回放增益状态（由播放器状态持有）
+pub struct ReplayGain {
+    settings: RwLock<ReplayGainConfig>,
+    tracks: Mutex<VecDeque<TrackEntry>>,
+}
+
+#[inline]
+fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+impl ReplayGain {
+    #[must_use]
+    pub fn new(settings: ReplayGainConfig) -> Self {
+        Self { settings: RwLock::new(settings), tracks: Mutex::new(VecDeque::with_capacity(MAX_TRACKS)) }
+    }
+
+    #[must_use]
+    pub fn settings(&self) -> ReplayGainConfig {
+        self.settings.read().unwrap().clone()
+    }
+
+    pub fn set_settings(&self, settings: ReplayGainConfig) {
+        *self.settings.write().unwrap() = settings;
+        // 模式可能已改变，重新判定
+        for entry in self.tracks.lock().unwrap().iter_mut() {
+            entry.use_album = None;
+        }
+    }
+
+    /// 读取并缓存音轨的增益标签
+    pub fn register(&self, path: &str) {
+        let info = match read_replay_gain(path) {
+            Ok(info) => info,
+            Err(e) => {
+                eprintln!("ReplayGain: failed to read tags of {path}: {e}");
+                ReplayGainInfo::default()
+            }
+        };
+        let album_key = info.album_gain.map(|gain| {
+            let dir = Path::new(path).parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+            (dir, gain.to_bits())
+        });
+
+        let mut tracks = self.tracks.lock().unwrap();
+        tracks.retain(|t| t.path != path);
+        if tracks.len() >= MAX_TRACKS {
+            tracks.pop_front();
+        }
+        tracks.push_back(TrackEntry { path: path.to_string(), info, album_key, use_album: None });
+    }
+
+    /// 查询音轨的线性增益（音频线程调用，锁被占用时返回 None）
+    #[must_use]
+    pub fn gain_for(&self, path: &str) -> Option<f32> {
+        let settings = self.settings.try_read().ok()?.clone();
+        if settings.mode == ReplayGainMode::Off {
+            return Some(1.0);
+        }
+        let mut tracks = self.tracks.try_lock().ok()?;
+        let Some(index) = tracks.iter().position(|t| t.path == path) else {
+            // 未读取过标签的音轨按无标签处理
+            return Some(db_to_linear(settings.fallback_gain));
+        };
+
+        let use_album = match settings.mode {
+            ReplayGainMode::Off | ReplayGainMode::Track => false,
+            ReplayGainMode::Album => true,
+            ReplayGainMode::AutoByQueue => {
+                if let Some(decided) = tracks[index].use_album {
+                    decided
+                } else {
+                    let key = tracks[index].album_key.clone();
+                    let decided = key.is_some()
+                        && tracks.iter().enumerate().any(|(i, t)| i != index && t.album_key == key);
+                    tracks[index].use_album = Some(decided);
+                    decided
+                }
+            }
+        };
+
+        Some(compute_gain(&tracks[index].info, &settings, use_album))
+    }
+}
+
+/// 根据标签和设置计算线性增益
+#[must_use]
+pub fn compute_gain(info: &ReplayGainInfo, settings: &ReplayGainConfig, use_album: bool) -> f32 {
+    let track = info.track_gain.map(|g| (g, info.track_peak));
+    let album = info.album_gain.map(|g| (g, info.album_peak.or(info.track_peak)));
+    let selected = if use_album { album.or(track) } else { track.or(album) };
+
+    let Some((gain_db, peak)) = selected else {
+        return db_to_linear(settings.fallback_gain);
+    };
+
+    let mut gain = db_to_linear(gain_db + settings.preamp);
+    if settings.prevent_clipping
+        && let Some(peak) = peak.filter(|p| *p > 0.0)
+    {
+        gain = gain.min(1.0 / peak);
+    }
+    gain
+}
+
+/// 增益处理级（切换音轨或修改设置时平滑过渡）
+pub struct GainStage {
+    handle: Arc<ReplayGain>,
+    current: f32,
+    target: f32,
+}
+
+impl GainStage {
+    #[must_use]
+    pub fn new(handle: Arc<ReplayGain>, path: &str) -> Self {
+        let gain = handle.gain_for(path).unwrap_or(1.0);
+        Self { handle, current: gain, target: gain }
+    }
+
+    /// 按当前音轨重新计算目标增益
+    pub fn refresh(&mut self, path: &str) {
+        if let Some(gain) = self.handle.gain_for(path) {
+            self.target = gain;
+        }
+    }
+
+    #[inline]
+    pub fn apply(&mut self, samples: &mut [f32]) {
+        if (self.target - self.current).abs() < 1e-4 {
+            self.current = self.target;
+            if (self.current - 1.0).abs() > f32::EPSILON {
+                for s in samples {
+                    *s *= self.current;
+                }
+            }
+            return;
+        }
+        for s in samples {
+            self.current += (self.target - self.current) * GAIN_SMOOTHING;
+            *s *= self.current;
+        }
+    }
+}
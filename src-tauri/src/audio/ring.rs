@@ -0,0 +1,457 @@
+//! 采样环形缓冲区模块
+//!
+//! 解码线程（生产者）与音频线程（消费者）之间的单生产者单消费者无锁环形缓冲区。
+//! 读写以整帧为单位批量拷贝，每批只更新一次读写计数；缓冲区满时生产者阻塞等待，
+//! 消费者读出数据后唤醒生产者。生产者可以作废已写入的数据（定位时清空缓冲区），
+//! 消费者下次读取时跳过它们。同时统计欠载次数与填充水平，便于评估缓冲效果。
+
+#![allow(unsafe_code)] // 环形缓冲区的读写区域由读写计数保证互不重叠
+
+use serde::Serialize;
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock, Weak};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+/// 生产者等待空间时的最长休眠（防止错过唤醒）
+const PRODUCER_PARK_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// 缓冲区统计
+#[derive(Debug, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RingStats {
+    /// 容量（采样）
+    pub capacity: usize,
+    /// 当前填充量（采样）
+    pub fill: usize,
+    /// 消费时的最低填充量（采样）
+    pub min_fill: usize,
+    /// 欠载次数（消费者需要数据而缓冲区为空）
+    pub underruns: u64,
+    /// 生产者因缓冲区已满而等待的次数
+    pub producer_waits: u64,
+    /// 累计写入的采样数
+    pub written: u64,
+}
+
+impl RingStats {
+    /// 填充比例（0.0 - 1.0）
+    #[must_use]
+    pub fn fill_ratio(&self) -> f32 {
+        if self.capacity == 0 { 0.0 } else { self.fill as f32 / self.capacity as f32 }
+    }
+}
+
+struct Shared {
+    buffer: Box<[UnsafeCell<f32>]>,
+    channels: usize,
+    /// 累计写入的采样数（只由生产者修改）
+    head: AtomicUsize,
+    /// 累计读出的采样数（只由消费者修改）
+    tail: AtomicUsize,
+    /// 此前写入的数据已作废（只由生产者修改）
+    flush_at: AtomicUsize,
+    /// 生产者已结束（正常结束或被丢弃）
+    finished: AtomicBool,
+    /// 消费者已丢弃
+    closed: AtomicBool,
+    /// 生产者正在等待空间
+    waiting: AtomicBool,
+    producer: OnceLock<Thread>,
+    underruns: AtomicU64,
+    producer_waits: AtomicU64,
+    min_fill: AtomicUsize,
+}
+
+// 读写区域由 head / tail 隔开，同一时刻每个槽位只被一方访问
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    #[inline]
+    fn fill(&self) -> usize {
+        self.head.load(Ordering::Acquire).wrapping_sub(self.tail.load(Ordering::Acquire))
+    }
+
+    fn stats(&self) -> RingStats {
+        let min_fill = self.min_fill.load(Ordering::Relaxed);
+        RingStats {
+            capacity: self.capacity(),
+            fill: self.fill(),
+            min_fill: if min_fill == usize::MAX { 0 } else { min_fill },
+            underruns: self.underruns.load(Ordering::Relaxed),
+            producer_waits: self.producer_waits.load(Ordering::Relaxed),
+            written: self.head.load(Ordering::Relaxed) as u64,
+        }
+    }
+
+    /// 把采样写入从 `start` 开始的槽位（可跨越缓冲区结尾）
+    ///
+    /// # Safety
+    /// 调用方需保证这些槽位当前不会被另一端访问
+    unsafe fn write_at(&self, start: usize, src: &[f32]) {
+        let base = UnsafeCell::raw_get(self.buffer.as_ptr());
+        let first = src.len().min(self.capacity() - start);
+        unsafe {
+            std::ptr::copy_nonoverlapping(src.as_ptr(), base.add(start), first);
+            std::ptr::copy_nonoverlapping(src.as_ptr().add(first), base, src.len() - first);
+        }
+    }
+
+    /// 从 `start` 开始的槽位读出采样（可跨越缓冲区结尾）
+    ///
+    /// # Safety
+    /// 调用方需保证这些槽位已写入且当前不会被另一端修改
+    unsafe fn read_at(&self, start: usize, dst: &mut [f32]) {
+        let base = UnsafeCell::raw_get(self.buffer.as_ptr()).cast_const();
+        let first = dst.len().min(self.capacity() - start);
+        unsafe {
+            std::ptr::copy_nonoverlapping(base.add(start), dst.as_mut_ptr(), first);
+            std::ptr::copy_nonoverlapping(base, dst.as_mut_ptr().add(first), dst.len() - first);
+        }
+    }
+
+    fn wake_producer(&self) {
+        if self.waiting.swap(false, Ordering::SeqCst)
+            && let Some(thread) = self.producer.get()
+        {
+            thread.unpark();
+        }
+    }
+}
+
+/// 创建容量为 `capacity_frames` 帧的环形缓冲区
+#[must_use]
+pub fn sample_ring(capacity_frames: usize, channels: u16) -> (RingProducer, RingConsumer) {
+    let channels = (channels as usize).max(1);
+    let capacity = capacity_frames.max(1) * channels;
+    let shared = Arc::new(Shared {
+        buffer: (0..capacity).map(|_| UnsafeCell::new(0.0)).collect(),
+        channels,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        flush_at: AtomicUsize::new(0),
+        finished: AtomicBool::new(false),
+        closed: AtomicBool::new(false),
+        waiting: AtomicBool::new(false),
+        producer: OnceLock::new(),
+        underruns: AtomicU64::new(0),
+        producer_waits: AtomicU64::new(0),
+        min_fill: AtomicUsize::new(usize::MAX),
+    });
+    (RingProducer { shared: Arc::clone(&shared) }, RingConsumer { shared })
+}
+
+/// 生产者端（解码线程）
+pub struct RingProducer {
+    shared: Arc<Shared>,
+}
+
+impl RingProducer {
+    /// 写入尽量多的整帧采样，返回写入的采样数（不阻塞，空间不足的部分被丢弃）
+    pub fn try_push(&mut self, samples: &[f32]) -> usize {
+        let shared = &*self.shared;
+        let capacity = shared.capacity();
+        let head = shared.head.load(Ordering::Relaxed);
+        let free = capacity - head.wrapping_sub(shared.tail.load(Ordering::Acquire));
+        // 按整帧写入（不足一帧的结尾除外）
+        let mut count = free.min(samples.len());
+        if count < samples.len() {
+            count -= count % shared.channels;
+        }
+        if count > 0 {
+            // SAFETY: [head, head + count) 是空闲区域，消费者不会读取
+            unsafe { shared.write_at(head % capacity, &samples[..count]) };
+            shared.head.store(head.wrapping_add(count), Ordering::Release);
+        }
+        count
+    }
+
+    /// 写入全部采样，空间不足时阻塞等待；消费者已丢弃或 `interrupted` 返回 true 时放弃写入并返回 false
+    pub fn push(&mut self, mut samples: &[f32], interrupted: impl Fn() -> bool) -> bool {
+        while !samples.is_empty() {
+            if self.is_closed() || interrupted() {
+                return false;
+            }
+            let count = self.try_push(samples);
+            if count == 0 {
+                self.wait_for_space(samples.len());
+                continue;
+            }
+            samples = &samples[count..];
+        }
+        true
+    }
+
+    /// 作废已写入但尚未读出的数据
+    pub fn flush(&mut self) {
+        let head = self.shared.head.load(Ordering::Relaxed);
+        self.shared.flush_at.store(head, Ordering::Release);
+    }
+
+    /// 标记数据是否已全部写入（之后仍可重新开始写入）
+    pub fn set_finished(&mut self, finished: bool) {
+        self.shared.finished.store(finished, Ordering::Release);
+    }
+
+    /// 消费者是否已丢弃
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::Acquire)
+    }
+
+    /// 等待消费者读出数据
+    fn wait_for_space(&self, pending: usize) {
+        let shared = &*self.shared;
+        shared.producer.get_or_init(thread::current);
+        shared.waiting.store(true, Ordering::SeqCst);
+        // 登记等待后再检查一次，避免消费者在登记前读出数据导致错过唤醒
+        let head = shared.head.load(Ordering::Relaxed);
+        let free = shared.capacity() - head.wrapping_sub(shared.tail.load(Ordering::SeqCst));
+        if free < shared.channels.min(pending) {
+            shared.producer_waits.fetch_add(1, Ordering::Relaxed);
+            thread::park_timeout(PRODUCER_PARK_TIMEOUT);
+        }
+        shared.waiting.store(false, Ordering::Release);
+    }
+
+    /// 当前统计
+    #[must_use]
+    pub fn stats(&self) -> RingStats {
+        self.shared.stats()
+    }
+}
+
+impl Drop for RingProducer {
+    fn drop(&mut self) {
+        self.shared.finished.store(true, Ordering::Release);
+    }
+}
+
+/// 消费者端（音频线程）
+pub struct RingConsumer {
+    shared: Arc<Shared>,
+}
+
+impl RingConsumer {
+    /// 读出尽量多的整帧采样，返回读出的采样数（不阻塞）
+    pub fn pop(&mut self, out: &mut [f32]) -> usize {
+        self.skip_flushed();
+        let shared = &*self.shared;
+        let capacity = shared.capacity();
+        let tail = shared.tail.load(Ordering::Relaxed);
+        let available = shared.head.load(Ordering::Acquire).wrapping_sub(tail);
+        shared.min_fill.fetch_min(available, Ordering::Relaxed);
+        let mut count = available.min(out.len());
+        // 生产者结束后允许读出不足一帧的结尾
+        if count < available || !shared.finished.load(Ordering::Acquire) {
+            count -= count % shared.channels;
+        }
+        if count == 0 {
+            return 0;
+        }
+        // SAFETY: [tail, tail + count) 已由生产者写入，读出前不会被覆盖
+        unsafe { shared.read_at(tail % capacity, &mut out[..count]) };
+        shared.tail.store(tail.wrapping_add(count), Ordering::SeqCst);
+        shared.wake_producer();
+        count
+    }
+
+    /// 跳过生产者已作废的数据
+    pub fn skip_flushed(&mut self) {
+        let shared = &*self.shared;
+        let tail = shared.tail.load(Ordering::Relaxed);
+        let flush_at = shared.flush_at.load(Ordering::Acquire);
+        // 计数会回绕，按差值判断先后
+        if flush_at != tail && flush_at.wrapping_sub(tail) <= shared.capacity() {
+            shared.tail.store(flush_at, Ordering::SeqCst);
+            shared.wake_producer();
+        }
+    }
+
+    /// 生产者已结束且数据已读完
+    #[must_use]
+    pub fn is_drained(&self) -> bool {
+        let shared = &*self.shared;
+        let head = shared.head.load(Ordering::Acquire);
+        // 写入的数据都已读出或已作废
+        shared.finished.load(Ordering::Acquire)
+            && (head == shared.tail.load(Ordering::Relaxed) || head == shared.flush_at.load(Ordering::Acquire))
+    }
+
+    /// 唤醒等待空间的生产者（例如通知它处理新的请求）
+    pub fn wake_producer(&self) {
+        self.shared.wake_producer();
+    }
+
+    /// 记录一次欠载
+    pub fn record_underrun(&self) {
+        self.shared.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 当前统计
+    #[must_use]
+    pub fn stats(&self) -> RingStats {
+        self.shared.stats()
+    }
+
+    /// 可在其他线程查询统计的句柄
+    #[must_use]
+    pub fn monitor(&self) -> RingMonitor {
+        RingMonitor { shared: Arc::downgrade(&self.shared) }
+    }
+}
+
+impl Drop for RingConsumer {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.wake_producer();
+    }
+}
+
+/// 只读的统计句柄（不延长缓冲区的生命周期）
+#[derive(Clone)]
+pub struct RingMonitor {
+    shared: Weak<Shared>,
+}
+
+impl RingMonitor {
+    /// 当前统计，消费者已丢弃时返回 None
+    #[must_use]
+    pub fn stats(&self) -> Option<RingStats> {
+        let shared = self.shared.upgrade()?;
+        (!shared.closed.load(Ordering::Acquire)).then(|| shared.stats())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(range: std::ops::Range<usize>) -> Vec<f32> {
+        range.map(|i| i as f32).collect()
+    }
+
+    #[test]
+    fn wraps_around_the_buffer_end() {
+        let (mut producer, mut consumer) = sample_ring(4, 2);
+        let mut out = [0.0; 8];
+        for round in 0..5 {
+            let chunk = samples(round * 6..round * 6 + 6);
+            assert_eq!(producer.try_push(&chunk), 6);
+            assert_eq!(consumer.pop(&mut out), 6);
+            assert_eq!(out[..6], chunk[..]);
+        }
+        assert_eq!(consumer.stats().written, 30);
+    }
+
+    #[test]
+    fn reads_and_writes_whole_frames() {
+        let (mut producer, mut consumer) = sample_ring(4, 2);
+        // 空间足够时不足一帧的结尾也会写入，但只有生产者结束后才能读出
+        assert_eq!(producer.try_push(&samples(0..5)), 5);
+        let mut out = [0.0; 8];
+        assert_eq!(consumer.pop(&mut out), 4);
+        assert_eq!(consumer.pop(&mut out), 0);
+        // 空间不足时只写入整帧
+        assert_eq!(producer.try_push(&samples(5..13)), 6);
+        assert_eq!(consumer.pop(&mut out[..3]), 2);
+        assert_eq!(out[..2], [4.0, 5.0]);
+        producer.set_finished(true);
+        assert_eq!(consumer.pop(&mut out), 5);
+        assert_eq!(out[..5], [6.0, 7.0, 8.0, 9.0, 10.0]);
+        assert!(consumer.is_drained());
+    }
+
+    #[test]
+    fn producer_blocks_until_consumer_reads() {
+        let (mut producer, mut consumer) = sample_ring(4, 2);
+        assert_eq!(producer.try_push(&samples(0..8)), 8);
+        let done = Arc::new(AtomicBool::new(false));
+        let done_clone = Arc::clone(&done);
+        let handle = thread::spawn(move || {
+            let pushed = producer.push(&samples(8..12), || false);
+            done_clone.store(true, Ordering::SeqCst);
+            pushed
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert!(!done.load(Ordering::SeqCst));
+        assert!(consumer.stats().producer_waits > 0);
+
+        let mut out = [0.0; 8];
+        assert_eq!(consumer.pop(&mut out), 8);
+        assert!(handle.join().unwrap());
+        assert_eq!(consumer.pop(&mut out), 4);
+        assert_eq!(out[..4], [8.0, 9.0, 10.0, 11.0]);
+    }
+
+    #[test]
+    fn blocked_producer_gives_up_when_consumer_is_dropped() {
+        let (mut producer, consumer) = sample_ring(2, 2);
+        assert_eq!(producer.try_push(&samples(0..4)), 4);
+        let handle = thread::spawn(move || producer.push(&samples(4..8), || false));
+        thread::sleep(Duration::from_millis(20));
+        drop(consumer);
+        assert!(!handle.join().unwrap());
+    }
+
+    #[test]
+    fn flushed_data_is_skipped() {
+        let (mut producer, mut consumer) = sample_ring(8, 2);
+        assert_eq!(producer.try_push(&samples(0..8)), 8);
+        let mut out = [0.0; 16];
+        assert_eq!(consumer.pop(&mut out[..2]), 2);
+        // 定位：作废旧数据后从新位置写入
+        producer.flush();
+        assert_eq!(producer.try_push(&samples(100..104)), 4);
+        assert_eq!(consumer.pop(&mut out), 4);
+        assert_eq!(out[..4], [100.0, 101.0, 102.0, 103.0]);
+
+        // 作废后结束：没有可读的数据
+        assert_eq!(producer.try_push(&samples(0..4)), 4);
+        producer.flush();
+        producer.set_finished(true);
+        assert!(consumer.is_drained());
+        assert_eq!(consumer.pop(&mut out), 0);
+    }
+
+    #[test]
+    fn two_thread_stress_preserves_order() {
+        const TOTAL: usize = 200_000;
+        let (mut producer, mut consumer) = sample_ring(64, 2);
+        let handle = thread::spawn(move || {
+            let mut start = 0;
+            let mut size = 2;
+            while start < TOTAL {
+                let end = (start + size).min(TOTAL);
+                assert!(producer.push(&samples(start..end), || false));
+                start = end;
+                size = size % 300 + 2;
+            }
+            producer.set_finished(true);
+        });
+
+        let mut out = vec![0.0; 96];
+        let mut expected = 0;
+        while !consumer.is_drained() {
+            // 每次读取的长度不是整帧
+            let len = (expected % 7) * 2 + 3;
+            let count = consumer.pop(&mut out[..len]);
+            for &sample in &out[..count] {
+                assert_eq!(sample as usize, expected);
+                expected += 1;
+            }
+            if count == 0 {
+                thread::yield_now();
+            }
+        }
+        handle.join().unwrap();
+        assert_eq!(expected, TOTAL);
+        assert_eq!(consumer.stats().written, TOTAL as u64);
+    }
+}
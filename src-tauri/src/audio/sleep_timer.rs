@@ -0,0 +1,323 @@
+//! 睡眠定时器模块
+//!
+//! 支持三种方式：N 分钟后停止、当前音轨结束后停止、播放 N 首后停止。
+//! 最后若干秒通过音量平滑淡出。计时在后端线程中进行，窗口最小化或 WebView 被节流时照常工作。
+//! 倒计时通过 `sleep-timer` 事件发送，到时停止后发送 `sleep-timer-expired` 事件。
+
+use super::gapless;
+use super::player_state::{PlayerStatus, TransitionReason};
+use super::SymphoniaDecoder;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// 默认淡出时长（秒）
+pub const SLEEP_TIMER_DEFAULT_FADE_SECS: f32 = 10.0;
+/// 淡出时长上限（秒）
+pub const SLEEP_TIMER_MAX_FADE_SECS: f32 = 120.0;
+
+/// 检查间隔
+const TICK: Duration = Duration::from_millis(50);
+
+/// 定时方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum SleepTimerMode {
+    /// N 分钟后停止
+    Duration { minutes: f64 },
+    /// 当前音轨结束后停止
+    EndOfTrack,
+    /// 播放 N 首（含当前音轨）后停止
+    Tracks { count: u32 },
+}
+
+/// 定时器状态（命令返回值和 `sleep-timer` 事件）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SleepTimerStatus {
+    pub mode: SleepTimerMode,
+    /// 距离停止的秒数（按音轨计时且不是最后一首时未知）
+    pub remaining_secs: Option<f64>,
+    /// 剩余音轨数（含当前音轨，仅按音轨计时）
+    pub tracks_remaining: Option<u32>,
+    pub fade_out: f32,
+    pub fading: bool,
+}
+
+struct TimerState {
+    mode: SleepTimerMode,
+    deadline: Option<Instant>,
+    tracks_remaining: u32,
+    fade_out: f32,
+    /// 当前淡出增益
+    gain: f32,
+    /// 当前音轨时长缓存
+    duration: Option<(String, Option<f32>)>,
+}
+
+impl TimerState {
+    const fn counts_tracks(&self) -> bool {
+        self.deadline.is_none()
+    }
+
+    fn status(&self, remaining: Option<f64>) -> SleepTimerStatus {
+        SleepTimerStatus {
+            mode: self.mode,
+            remaining_secs: remaining,
+            tracks_remaining: self.counts_tracks().then_some(self.tracks_remaining),
+            fade_out: self.fade_out,
+            fading: self.gain < 1.0,
+        }
+    }
+}
+
+/// 睡眠定时器（由播放器状态持有）
+#[derive(Default)]
+pub struct SleepTimer {
+    state: Mutex<Option<TimerState>>,
+    /// 每次设置或取消时递增，旧的计时线程据此退出
+    generation: AtomicU64,
+    /// 最近一次上报的播放位置（秒）
+    position: AtomicU32,
+}
+
+impl SleepTimer {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 启动定时器（替换已有的定时器）
+    pub fn start(self: &Arc<Self>, app: &AppHandle, mode: SleepTimerMode, fade_out: f32) -> AppResult<SleepTimerStatus> {
+        let (deadline, tracks_remaining) = match mode {
+            SleepTimerMode::Duration { minutes } => {
+                if !minutes.is_finite() || minutes <= 0.0 {
+                    return Err(AppError::invalid_argument(format!("Invalid sleep timer duration: {minutes}")));
+                }
+                (Some(Instant::now() + Duration::from_secs_f64(minutes * 60.0)), 0)
+            }
+            SleepTimerMode::EndOfTrack => (None, 1),
+            SleepTimerMode::Tracks { count } if count > 0 => (None, count),
+            SleepTimerMode::Tracks { .. } => return Err(AppError::invalid_argument("Track count must be at least 1")),
+        };
+        let fade_out = if fade_out.is_finite() { fade_out.clamp(0.0, SLEEP_TIMER_MAX_FADE_SECS) } else { SLEEP_TIMER_DEFAULT_FADE_SECS };
+        println!("Sleep timer: {mode:?}, fade out {fade_out}s");
+
+        let previous = self.state.lock().unwrap().replace(TimerState {
+            mode,
+            deadline,
+            tracks_remaining,
+            fade_out,
+            gain: 1.0,
+            duration: None,
+        });
+        if previous.is_some_and(|p| p.gain < 1.0) {
+            restore_volume(app);
+        }
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.on_tracks_changed(app);
+
+        let timer = Arc::clone(self);
+        let app_clone = app.clone();
+        std::thread::spawn(move || timer.run(&app_clone, generation));
+        self.status().ok_or_else(|| AppError::new(ErrorCode::NotFound, "Sleep timer was cancelled"))
+    }
+
+    /// 延长定时器：按时间计时时增加分钟数，按音轨计时时增加音轨数
+    pub fn extend(&self, app: &AppHandle, minutes: Option<f64>, tracks: Option<u32>) -> AppResult<SleepTimerStatus> {
+        {
+            let mut guard = self.state.lock().unwrap();
+            let state = guard.as_mut().ok_or_else(|| AppError::new(ErrorCode::NotFound, "No sleep timer is running"))?;
+            if let Some(deadline) = state.deadline.as_mut() {
+                let minutes = minutes.filter(|m| m.is_finite() && *m > 0.0).ok_or_else(|| AppError::invalid_argument("Minutes required to extend a timed sleep timer"))?;
+                *deadline += Duration::from_secs_f64(minutes * 60.0);
+            } else {
+                let tracks = tracks.filter(|t| *t > 0).ok_or_else(|| AppError::invalid_argument("Track count required to extend a track-based sleep timer"))?;
+                state.tracks_remaining += tracks;
+                state.mode = SleepTimerMode::Tracks { count: state.tracks_remaining };
+            }
+            println!("Sleep timer extended: {:?}", state.mode);
+        }
+        // 淡出中被延长时由计时线程恢复音量；下一首可以重新预加载
+        crate::queue::sync_next_track(app);
+        self.status().ok_or_else(|| AppError::new(ErrorCode::NotFound, "Sleep timer was cancelled"))
+    }
+
+    /// 取消定时器，返回之前是否在运行
+    pub fn cancel(&self, app: &AppHandle) -> bool {
+        let Some(previous) = self.state.lock().unwrap().take() else { return false };
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if previous.gain < 1.0 {
+            restore_volume(app);
+        }
+        println!("Sleep timer cancelled");
+        let _ = app.emit("sleep-timer", None::<SleepTimerStatus>);
+        crate::queue::sync_next_track(app);
+        true
+    }
+
+    #[must_use]
+    pub fn status(&self) -> Option<SleepTimerStatus> {
+        let guard = self.state.lock().unwrap();
+        let state = guard.as_ref()?;
+        let remaining = self.remaining_secs(state, 1.0);
+        Some(state.status(remaining))
+    }
+
+    /// 当前音轨是否为停止前的最后一首（此时不预加载下一首）
+    #[must_use]
+    pub fn is_last_track(&self) -> bool {
+        self.state.lock().unwrap().as_ref().is_some_and(|s| s.counts_tracks() && s.tracks_remaining <= 1)
+    }
+
+    /// 记录播放位置（由位置事件调用）
+    pub fn set_position(&self, position: f32) {
+        self.position.store(position.to_bits(), Ordering::Relaxed);
+    }
+
+    /// 音轨结束时调用，返回 true 表示定时器到时，不再继续播放
+    pub fn on_track_ended(self: &Arc<Self>, app: &AppHandle) -> bool {
+        let expired = {
+            let Ok(mut guard) = self.state.lock() else { return false };
+            let Some(state) = guard.as_mut().filter(|s| s.counts_tracks()) else { return false };
+            state.tracks_remaining = state.tracks_remaining.saturating_sub(1);
+            state.duration = None;
+            state.tracks_remaining == 0
+        };
+        if expired {
+            // 可能位于音频线程，停止操作放到新线程
+            let timer = Arc::clone(self);
+            let app = app.clone();
+            let generation = self.generation.load(Ordering::SeqCst);
+            std::thread::spawn(move || timer.expire(&app, generation));
+        } else {
+            self.on_tracks_changed(app);
+        }
+        expired
+    }
+
+    /// 只剩最后一首时取消已排队的下一首
+    fn on_tracks_changed(&self, app: &AppHandle) {
+        if self.is_last_track()
+            && let Some(state) = app.try_state::<AppState>()
+        {
+            gapless::clear_next_track(&state.player.next_track);
+        }
+    }
+
+    /// 距离停止的秒数（播放时间）
+    fn remaining_secs(&self, state: &TimerState, rate: f32) -> Option<f64> {
+        if let Some(deadline) = state.deadline {
+            return Some(deadline.saturating_duration_since(Instant::now()).as_secs_f64());
+        }
+        if state.tracks_remaining > 1 {
+            return None;
+        }
+        let duration = state.duration.as_ref().and_then(|(_, d)| *d)?;
+        let position = f32::from_bits(self.position.load(Ordering::Relaxed));
+        Some(((duration - position).max(0.0) / rate.max(0.01)) as f64)
+    }
+
+    fn run(&self, app: &AppHandle, generation: u64) {
+        let mut last_emit: Option<Instant> = None;
+        loop {
+            std::thread::sleep(TICK);
+            if self.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            let state = app.state::<AppState>();
+            let current_path = state.player.current_path.lock().unwrap().clone();
+            let rate = state.player.stretch.rate();
+
+            // 按音轨计时的最后一首需要知道音轨时长（解码器探测放在锁外）
+            let needs_duration = {
+                let guard = self.state.lock().unwrap();
+                guard.as_ref().is_some_and(|s| {
+                    s.counts_tracks() && s.duration.as_ref().map(|(p, _)| Some(p)) != Some(current_path.as_ref())
+                })
+            };
+            if needs_duration && let Some(path) = current_path.clone() {
+                let duration = SymphoniaDecoder::new(&path).ok().and_then(|d| d.total_duration()).map(|d| d.as_secs_f32());
+                if let Some(s) = self.state.lock().unwrap().as_mut() {
+                    s.duration = Some((path, duration));
+                }
+            }
+
+            let (status, gain, changed) = {
+                let mut guard = self.state.lock().unwrap();
+                let Some(timer) = guard.as_mut() else { return };
+                let remaining = self.remaining_secs(timer, rate);
+                let gain = match remaining {
+                    Some(r) if timer.fade_out > 0.0 && r < timer.fade_out as f64 => (r / timer.fade_out as f64) as f32,
+                    _ => 1.0,
+                };
+                let changed = (gain - timer.gain).abs() > 1e-3 || (gain >= 1.0 && timer.gain < 1.0);
+                timer.gain = gain;
+                (timer.status(remaining), gain, changed)
+            };
+            if changed {
+                apply_volume(app, gain * gain);
+            }
+            if status.remaining_secs.is_some_and(|r| r <= 0.0) && status.tracks_remaining.is_none() {
+                self.expire(app, generation);
+                return;
+            }
+            if last_emit.is_none_or(|t| t.elapsed() >= Duration::from_secs(1)) {
+                last_emit = Some(Instant::now());
+                let _ = app.emit("sleep-timer", Some(status));
+            }
+        }
+    }
+
+    /// 到时：暂停播放并恢复音量
+    fn expire(&self, app: &AppHandle, generation: u64) {
+        if self.generation.compare_exchange(generation, generation + 1, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return;
+        }
+        self.state.lock().unwrap().take();
+        println!("Sleep timer expired");
+        pause_output(app);
+        restore_volume(app);
+        let _ = app.emit("sleep-timer", None::<SleepTimerStatus>);
+        let _ = app.emit("sleep-timer-expired", ());
+    }
+}
+
+/// 设置输出音量（用户音量 × 淡出增益）
+fn apply_volume(app: &AppHandle, gain: f32) {
+    let Some(state) = app.try_state::<AppState>() else { return };
+    let player = &state.player;
+    let volume = *player.target_volume.lock().unwrap() * gain;
+    if *player.exclusive_mode.lock().unwrap() {
+        #[cfg(windows)]
+        if let Some(ref wasapi) = *player.wasapi_player.lock().unwrap() {
+            let _ = wasapi.set_volume(volume);
+        }
+    } else {
+        player.sink.lock().unwrap().set_volume(volume);
+    }
+}
+
+fn restore_volume(app: &AppHandle) {
+    apply_volume(app, 1.0);
+}
+
+fn pause_output(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else { return };
+    let player = &state.player;
+    if *player.exclusive_mode.lock().unwrap() {
+        #[cfg(windows)]
+        if let Some(ref wasapi) = *player.wasapi_player.lock().unwrap() {
+            let _ = wasapi.pause();
+        }
+    } else {
+        player.sink.lock().unwrap().pause();
+    }
+    if player.player_state.status() == PlayerStatus::Playing {
+        player.player_state.transition(app, PlayerStatus::Paused, TransitionReason::SleepTimer);
+    }
+}
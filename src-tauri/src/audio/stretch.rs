@@ -0,0 +1,548 @@
+//! 变速不变调 / 变调不变速模块（WSOLA + 重采样）
+//!
+//! 以 50% 重叠的分段做波形相似叠加：每一步在名义输入位置附近搜索与上一段自然延续最相似的位置，
+//! 再与其交叉淡化输出。倍速为 1.0 时直接透传，切换时保持波形连续。
+//! 变调时先按 `倍速 / 音高比例` 伸缩时长，再以音高比例重采样，时长只由倍速决定。
+//! 音源同时记录当前输出对应的音轨位置（音轨时间），位置上报以此为准。
+
+use super::clock::TrackClock;
+use crate::config::{PitchShift, PitchShiftConfig};
+use rodio::source::SeekError;
+use rodio::Source;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// 播放倍速范围
+pub const PLAYBACK_RATE_MIN: f32 = 0.5;
+pub const PLAYBACK_RATE_MAX: f32 = 2.0;
+
+/// 相似度计算时的采样间隔（降低搜索开销）
+const CORRELATION_STRIDE: usize = 4;
+
+/// 插值所需的历史帧数
+const HISTORY_FRAMES: usize = 4;
+
+/// 变速变调参数（播放器状态持有，音频线程实时读取）
+#[derive(Clone)]
+pub struct StretchControl {
+    rate: Arc<AtomicU32>,
+    pitch: Arc<RwLock<PitchShiftConfig>>,
+}
+
+impl Default for StretchControl {
+    fn default() -> Self {
+        Self::new(PitchShiftConfig::default())
+    }
+}
+
+impl StretchControl {
+    #[must_use]
+    pub fn new(pitch: PitchShiftConfig) -> Self {
+        Self { rate: Arc::new(AtomicU32::new(1.0f32.to_bits())), pitch: Arc::new(RwLock::new(pitch)) }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn rate(&self) -> f32 {
+        f32::from_bits(self.rate.load(Ordering::Relaxed))
+    }
+
+    /// 设置倍速（自动限制在 0.5~2.0）
+    pub fn set_rate(&self, rate: f32) -> f32 {
+        let rate = rate.clamp(PLAYBACK_RATE_MIN, PLAYBACK_RATE_MAX);
+        self.rate.store(rate.to_bits(), Ordering::Relaxed);
+        rate
+    }
+
+    #[must_use]
+    pub fn pitch_settings(&self) -> PitchShiftConfig {
+        self.pitch.read().unwrap().clone()
+    }
+
+    /// 设置全局变调，返回限制后的值
+    pub fn set_global_pitch(&self, shift: PitchShift) -> PitchShift {
+        let shift = shift.clamped();
+        self.pitch.write().unwrap().global = shift;
+        shift
+    }
+
+    /// 记住（或清除）单曲变调
+    pub fn set_track_pitch(&self, path: &str, shift: Option<PitchShift>) -> Option<PitchShift> {
+        let mut pitch = self.pitch.write().unwrap();
+        let shift = shift.map(PitchShift::clamped);
+        if let Some(shift) = shift {
+            pitch.tracks.insert(path.to_string(), shift);
+        } else {
+            pitch.tracks.remove(path);
+        }
+        shift
+    }
+
+    /// 音轨当前的音高比例（音频线程调用，锁被占用时返回 None）
+    fn pitch_ratio(&self, path: &str) -> Option<f32> {
+        Some(self.pitch.try_read().ok()?.for_track(path).ratio())
+    }
+}
+
+enum Mode {
+    /// 透传，`cursor` 为下一个输出帧的输入位置
+    Bypass { cursor: u64 },
+    /// WSOLA：`tail` 为上一段的后半部分（自然延续），`nominal` 为下一段的名义起点，
+    /// `pos` 为下一个输出块对应的音轨位置
+    Stretch { tail: Vec<f32>, tail_start: u64, nominal: f64, pos: f64 },
+}
+
+/// 变速变调音源
+pub struct StretchSource<I: Source<Item = f32> + Send> {
+    input: I,
+    control: StretchControl,
+    path: String,
+    /// 当前音高比例
+    ratio: f64,
+    channels: usize,
+    sample_rate: u32,
+    /// 输出步长与搜索范围（帧）
+    hop: usize,
+    search: usize,
+    /// 淡入曲线（长度为 hop，淡出为 1 - fade）
+    fade: Vec<f32>,
+    /// 输入缓冲（交错），`base` 为 buf[0] 的帧位置
+    buf: Vec<f32>,
+    base: u64,
+    eof: bool,
+    mode: Mode,
+    /// WSOLA 输出块
+    out: Vec<f32>,
+    out_pos: usize,
+    /// 输出块第一帧对应的输入帧位置及每帧推进量
+    out_track_start: f64,
+    out_track_step: f64,
+    /// 重采样历史帧（交错）及其音轨位置，输出位于第 1、2 帧之间的 `frac` 处
+    hist: Vec<f32>,
+    hist_pos: [f64; HISTORY_FRAMES],
+    frac: f64,
+    /// 历史帧末尾的填充帧数（输入结束后）
+    pads: usize,
+    primed: bool,
+    /// 当前输出帧
+    frame: Vec<f32>,
+    frame_pos: usize,
+    frame_track: f64,
+    /// 起始播放位置（帧），用于换算音轨时间
+    start_frame: u64,
+    clock: Option<TrackClock>,
+    /// 相似度搜索的临时缓冲
+    template: Vec<f32>,
+    candidates: Vec<f32>,
+}
+
+impl<I: Source<Item = f32> + Send> StretchSource<I> {
+    pub fn new(input: I, control: StretchControl, path: &str, start_position: f32) -> Self {
+        let (channels, sample_rate) = (input.channels().max(1) as usize, input.sample_rate());
+        // 25ms 输出步长，10ms 搜索范围
+        let hop = (sample_rate as usize / 40).max(64);
+        let search = (sample_rate as usize / 100).max(16);
+        let fade = (0..hop)
+            .map(|i| {
+                let x = (i as f32 + 0.5) / hop as f32 * std::f32::consts::FRAC_PI_2;
+                x.sin() * x.sin()
+            })
+            .collect();
+        let ratio = control.pitch_ratio(path).unwrap_or(1.0) as f64;
+        Self {
+            input,
+            control,
+            path: path.to_string(),
+            ratio,
+            channels,
+            sample_rate,
+            hop,
+            search,
+            fade,
+            buf: Vec::with_capacity((hop * 3 + search * 2) * channels * 2),
+            base: 0,
+            eof: false,
+            mode: Mode::Bypass { cursor: 0 },
+            out: Vec::with_capacity(hop * channels),
+            out_pos: 0,
+            out_track_start: 0.0,
+            out_track_step: 1.0,
+            hist: vec![0.0; HISTORY_FRAMES * channels],
+            hist_pos: [0.0; HISTORY_FRAMES],
+            frac: 0.0,
+            pads: 0,
+            primed: false,
+            frame: vec![0.0; channels],
+            frame_pos: channels,
+            frame_track: 0.0,
+            start_frame: (start_position.max(0.0) as f64 * sample_rate as f64) as u64,
+            clock: None,
+            template: Vec::with_capacity(hop),
+            candidates: Vec::new(),
+        }
+    }
+
+    /// 输出时同步更新音轨时钟（音源链中没有其他时钟时使用）
+    #[must_use]
+    pub fn with_clock(mut self, clock: TrackClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// 输入定位后从新位置重新开始（当前输出帧中剩余的声道照常输出，保持帧对齐）
+    fn restart(&mut self, start_frame: u64) {
+        self.buf.clear();
+        self.base = 0;
+        self.eof = false;
+        self.mode = Mode::Bypass { cursor: 0 };
+        self.out.clear();
+        self.out_pos = 0;
+        self.hist.fill(0.0);
+        self.hist_pos = [0.0; HISTORY_FRAMES];
+        self.frac = 0.0;
+        self.pads = 0;
+        self.primed = false;
+        self.frame_track = 0.0;
+        self.start_frame = start_frame;
+        if let Some(clock) = &self.clock {
+            clock.set_samples(self.track_samples());
+        }
+    }
+
+    /// 当前输出位置对应的音轨采样数（交错，含起始位置）
+    #[must_use]
+    pub fn track_samples(&self) -> u64 {
+        (self.start_frame + self.input_frames()) * self.channels as u64
+    }
+
+    /// 当前输出位置对应的输入帧序号
+    #[must_use]
+    pub fn input_frames(&self) -> u64 {
+        self.frame_track.max(0.0) as u64
+    }
+
+    #[must_use]
+    pub const fn input(&self) -> &I {
+        &self.input
+    }
+
+    #[inline]
+    fn frames_end(&self) -> u64 {
+        self.base + (self.buf.len() / self.channels) as u64
+    }
+
+    /// 从输入读取直到缓冲覆盖到 `frame_end`（或输入结束）
+    fn ensure(&mut self, frame_end: u64) {
+        while !self.eof && self.frames_end() < frame_end {
+            for _ in 0..self.channels {
+                if let Some(s) = self.input.next() {
+                    self.buf.push(s);
+                } else {
+                    self.eof = true;
+                    break;
+                }
+            }
+        }
+        // 丢弃不完整的最后一帧
+        if self.eof {
+            let complete = self.buf.len() / self.channels * self.channels;
+            self.buf.truncate(complete);
+        }
+    }
+
+    /// 丢弃 `frame` 之前的输入
+    fn trim(&mut self, frame: u64) {
+        if frame > self.base {
+            let drop = ((frame - self.base) as usize * self.channels).min(self.buf.len());
+            self.buf.drain(..drop);
+            self.base += (drop / self.channels) as u64;
+        }
+    }
+
+    /// 帧区间在输入缓冲中的下标范围
+    #[inline]
+    fn range(&self, start: u64, frames: usize) -> std::ops::Range<usize> {
+        let from = (start - self.base) as usize * self.channels;
+        from..from + frames * self.channels
+    }
+
+    /// 生成下一个 WSOLA 输出块，输入耗尽时返回 false
+    fn refill(&mut self) -> bool {
+        self.out.clear();
+        self.out_pos = 0;
+        if let Some(ratio) = self.control.pitch_ratio(&self.path) {
+            self.ratio = ratio as f64;
+        }
+        // 变调时多伸缩出的时长由之后的重采样抵消
+        let rate = self.control.rate() as f64 / self.ratio;
+        let bypass = (rate - 1.0).abs() < 1e-3;
+
+        match std::mem::replace(&mut self.mode, Mode::Bypass { cursor: 0 }) {
+            Mode::Bypass { cursor } if bypass => self.bypass_block(cursor),
+            Mode::Bypass { cursor } => {
+                // 开始变速：以接下来的一个步长作为自然延续
+                self.ensure(cursor + self.hop as u64);
+                if self.frames_end() < cursor + self.hop as u64 {
+                    return self.bypass_block(cursor);
+                }
+                let tail = self.buf[self.range(cursor, self.hop)].to_vec();
+                let nominal = cursor as f64 - self.hop as f64 + self.hop as f64 * rate;
+                self.stretch_block(tail, cursor, nominal.max(self.base as f64), cursor as f64, rate)
+            }
+            Mode::Stretch { tail, tail_start, pos, .. } if bypass => {
+                // 恢复原速：先输出自然延续，再从其后继续透传
+                self.emit_tail(tail, tail_start, pos);
+                true
+            }
+            Mode::Stretch { tail, tail_start, nominal, pos } => self.stretch_block(tail, tail_start, nominal, pos, rate),
+        }
+    }
+
+    fn bypass_block(&mut self, cursor: u64) -> bool {
+        self.ensure(cursor + self.hop as u64);
+        let frames = (self.frames_end().saturating_sub(cursor) as usize).min(self.hop);
+        self.mode = Mode::Bypass { cursor: cursor + frames as u64 };
+        if frames == 0 {
+            return false;
+        }
+        let range = self.range(cursor, frames);
+        self.out.extend_from_slice(&self.buf[range]);
+        self.out_track_start = cursor as f64;
+        self.out_track_step = 1.0;
+        // 保留足够的历史，供切换到慢速时向前搜索
+        self.trim((cursor + frames as u64).saturating_sub((self.hop + self.search) as u64 * 2));
+        true
+    }
+
+    fn emit_tail(&mut self, tail: Vec<f32>, tail_start: u64, pos: f64) {
+        let end = (tail_start + self.hop as u64) as f64;
+        self.out = tail;
+        self.out_track_start = pos;
+        self.out_track_step = (end - pos).max(0.0) / self.hop as f64;
+        self.mode = Mode::Bypass { cursor: tail_start + self.hop as u64 };
+    }
+
+    fn stretch_block(&mut self, tail: Vec<f32>, tail_start: u64, nominal: f64, pos: f64, rate: f64) -> bool {
+        let (hop, search) = (self.hop as u64, self.search as u64);
+        let nominal_frame = nominal.round() as u64;
+        self.ensure(nominal_frame + search + 2 * hop);
+
+        let lo = nominal_frame.saturating_sub(search).max(self.base);
+        let hi = (nominal_frame + search).min(self.frames_end().saturating_sub(2 * hop));
+        if hi < lo {
+            // 输入即将结束：输出自然延续后透传剩余部分
+            self.emit_tail(tail, tail_start, pos);
+            return true;
+        }
+
+        let seg_start = self.best_offset(&tail, lo, hi);
+        let ch = self.channels;
+        let head = self.range(seg_start, self.hop);
+        for (i, (t, s)) in tail.iter().zip(&self.buf[head]).enumerate() {
+            let w = self.fade[i / ch];
+            self.out.push(t * (1.0 - w) + s * w);
+        }
+        let next_tail = self.buf[self.range(seg_start + hop, self.hop)].to_vec();
+
+        self.out_track_start = pos;
+        self.out_track_step = rate;
+        let nominal = nominal + hop as f64 * rate;
+        let pos = pos + hop as f64 * rate;
+        self.mode = Mode::Stretch { tail: next_tail, tail_start: seg_start + hop, nominal, pos };
+        self.trim((nominal as u64).saturating_sub(search).min(seg_start + hop));
+        true
+    }
+
+    /// 在 [lo, hi] 内搜索与 `tail` 归一化互相关最大的起点
+    fn best_offset(&mut self, tail: &[f32], lo: u64, hi: u64) -> u64 {
+        let ch = self.channels;
+        self.template.clear();
+        self.template.extend(tail.chunks_exact(ch).step_by(CORRELATION_STRIDE).map(|f| f.iter().sum::<f32>()));
+
+        let span = (hi - lo) as usize + self.hop;
+        let range = self.range(lo, span);
+        self.candidates.clear();
+        self.candidates.extend(self.buf[range].chunks_exact(ch).map(|f| f.iter().sum::<f32>()));
+
+        let mut best = (lo, f32::MIN);
+        for offset in 0..=(hi - lo) as usize {
+            let mut corr = 0.0;
+            let mut energy = 1e-9;
+            for (j, t) in self.template.iter().enumerate() {
+                let s = self.candidates[offset + j * CORRELATION_STRIDE];
+                corr += t * s;
+                energy += s * s;
+            }
+            let score = corr / energy.sqrt();
+            if score > best.1 {
+                best = (lo + offset as u64, score);
+            }
+        }
+        best.0
+    }
+
+    /// 把下一个 WSOLA 输出帧写入最后一个历史槽位，输入结束时复制前一帧作为填充
+    fn pull_frame(&mut self) {
+        let ch = self.channels;
+        let slot = (HISTORY_FRAMES - 1) * ch;
+        if self.pads == 0 && self.out_pos >= self.out.len() && !self.refill() {
+            self.pads = 1;
+        } else if self.pads > 0 {
+            self.pads += 1;
+        }
+        if self.pads > 0 {
+            self.hist.copy_within(slot - ch..slot, slot);
+            self.hist_pos[HISTORY_FRAMES - 1] = self.hist_pos[HISTORY_FRAMES - 2];
+            return;
+        }
+        self.hist[slot..].copy_from_slice(&self.out[self.out_pos..self.out_pos + ch]);
+        let frame = (self.out_pos / ch) as f64;
+        self.hist_pos[HISTORY_FRAMES - 1] = self.out_track_start + frame * self.out_track_step;
+        self.out_pos += ch;
+        if let Some(clock) = &self.clock
+            && self.out_pos >= self.out.len()
+        {
+            clock.set_samples(self.track_samples());
+        }
+    }
+
+    fn shift_history(&mut self) {
+        self.hist.copy_within(self.channels.., 0);
+        self.hist_pos.copy_within(1.., 0);
+        self.pull_frame();
+    }
+
+    /// 按音高比例重采样出下一帧（Catmull-Rom 插值，比例为 1 时与输入逐位相同）
+    fn next_frame(&mut self) -> bool {
+        let ch = self.channels;
+        if !self.primed {
+            self.primed = true;
+            self.pull_frame();
+            if self.pads > 0 {
+                return false;
+            }
+            // 第 0、1 帧都使用第一帧
+            for _ in 0..2 {
+                self.shift_history();
+            }
+            self.hist.copy_within(ch..2 * ch, 0);
+            self.hist_pos[0] = self.hist_pos[1];
+        }
+        // 第 1 帧已是填充帧时结束
+        if self.pads >= HISTORY_FRAMES - 1 {
+            return false;
+        }
+
+        let t = self.frac as f32;
+        for c in 0..ch {
+            let (p0, p1, p2, p3) = (self.hist[c], self.hist[ch + c], self.hist[2 * ch + c], self.hist[3 * ch + c]);
+            self.frame[c] = p1 + 0.5 * t * (p2 - p0 + t * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3 + t * (3.0 * (p1 - p2) + p3 - p0)));
+        }
+        self.frame_track = self.hist_pos[1] + (self.hist_pos[2] - self.hist_pos[1]) * self.frac;
+        self.frame_pos = 0;
+
+        self.frac += self.ratio;
+        let steps = self.frac.floor();
+        self.frac -= steps;
+        for _ in 0..steps as usize {
+            self.shift_history();
+        }
+        true
+    }
+}
+
+impl<I: Source<Item = f32> + Send> Iterator for StretchSource<I> {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.frame_pos >= self.channels && !self.next_frame() {
+            return None;
+        }
+        let s = self.frame[self.frame_pos];
+        self.frame_pos += 1;
+        Some(s)
+    }
+}
+
+impl<I: Source<Item = f32> + Send> Source for StretchSource<I> {
+    fn current_span_len(&self) -> Option<usize> { None }
+    fn channels(&self) -> u16 { self.channels as u16 }
+    fn sample_rate(&self) -> u32 { self.sample_rate }
+    fn total_duration(&self) -> Option<Duration> { self.input.total_duration() }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.restart((pos.as_secs_f64() * self.sample_rate as f64) as u64);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    const RATE: u32 = 44_100;
+    const SECS: usize = 2;
+
+    /// 立体声 440 Hz 正弦波
+    fn sine() -> SamplesBuffer {
+        let samples = (0..RATE as usize * SECS)
+            .flat_map(|i| {
+                let s = (2.0 * std::f32::consts::PI * 440.0 * i as f32 / RATE as f32).sin() * 0.5;
+                [s, s]
+            })
+            .collect::<Vec<_>>();
+        SamplesBuffer::new(2, RATE, samples)
+    }
+
+    /// 输出帧数
+    fn output_frames(control: StretchControl) -> usize {
+        StretchSource::new(sine(), control, "test.flac", 0.0).count() / 2
+    }
+
+    /// 允许结尾处相差几个 WSOLA 步长
+    fn assert_frames_near(actual: usize, expected: f64) {
+        let tolerance = f64::from(RATE) / 40.0 * 3.0;
+        assert!((actual as f64 - expected).abs() <= tolerance, "{actual} frames, expected about {expected}");
+    }
+
+    #[test]
+    fn output_length_scales_with_rate() {
+        let input = (RATE as usize * SECS) as f64;
+        for rate in [0.5, 1.0, 2.0] {
+            let control = StretchControl::default();
+            control.set_rate(rate);
+            assert_frames_near(output_frames(control), input / f64::from(rate));
+        }
+    }
+
+    #[test]
+    fn pitch_shift_keeps_frame_count() {
+        let input = (RATE as usize * SECS) as f64;
+        for semitones in [-12, -5, 7, 12] {
+            let control = StretchControl::default();
+            control.set_global_pitch(PitchShift { semitones, cents: 0 });
+            assert_frames_near(output_frames(control), input);
+        }
+    }
+
+    #[test]
+    fn pitch_shift_combines_with_rate() {
+        let input = (RATE as usize * SECS) as f64;
+        let control = StretchControl::default();
+        control.set_rate(1.5);
+        control.set_global_pitch(PitchShift { semitones: 3, cents: 50 });
+        assert_frames_near(output_frames(control), input / 1.5);
+    }
+
+    #[test]
+    fn normal_rate_passes_input_through() {
+        let output = StretchSource::new(sine(), StretchControl::default(), "test.flac", 0.0).collect::<Vec<_>>();
+        let input = sine().collect::<Vec<_>>();
+        assert_eq!(output.len(), input.len());
+        assert!(output.iter().zip(&input).all(|(a, b)| a.to_bits() == b.to_bits()));
+    }
+}
@@ -2,7 +2,9 @@
 //!
 //! 提供应用程序配置的加载、保存和管理功能。
 
+use crate::error::{AppError, AppResult, ErrorCode};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
 /// 应用程序配置数据结构
@@ -24,6 +26,12 @@ pub struct AppConfig {
     /// 歌词设置
     #[serde(default)]
     pub lyrics: LyricsConfig,
+    /// 封面设置
+    #[serde(default)]
+    pub cover: CoverConfig,
+    /// 播放历史设置
+    #[serde(default)]
+    pub history: HistoryConfig,
 }
 
 /// 子目录扫描配置
@@ -33,7 +41,59 @@ pub struct DirectoryScanConfig {
     pub enable_subdirectory_scan: bool,
     pub max_depth: u32,
     pub ignore_hidden_folders: bool,
+    /// 跳过的文件夹名（支持通配符，不区分大小写）
     pub folder_blacklist: Vec<String>,
+    /// 只扫描匹配的音频文件（glob，空表示全部）
+    #[serde(default)]
+    pub include_patterns: Vec<String>,
+    /// 跳过匹配的文件和文件夹（glob）
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    /// 跟随符号链接（循环链接会被跳过）
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// 最小文件大小（字节），更小的音频文件被忽略
+    #[serde(default)]
+    pub min_file_size: u64,
+}
+
+/// 封面来源
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CoverSource {
+    /// 内嵌的正面封面
+    EmbeddedFront,
+    /// 音频文件所在文件夹或上级文件夹中的图片（如 cover.jpg）
+    FolderFile,
+    /// 任意内嵌图片
+    EmbeddedAny,
+}
+
+/// 封面设置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CoverConfig {
+    /// 文件夹封面的文件名（glob，不区分大小写，靠前的优先）
+    pub folder_patterns: Vec<String>,
+    /// 向上查找的上级文件夹层数（0 表示只查找音频文件所在文件夹）
+    pub parent_levels: u32,
+    /// 封面来源优先级（未列出的来源不使用）
+    pub priority: Vec<CoverSource>,
+}
+
+/// 播放历史设置
+///
+/// 听过的时长达到 `completion_seconds`，或达到时长的 `completion_percent`（先到者为准）
+/// 计为完整播放，之前切换到其他音轨计为跳过。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistoryConfig {
+    /// 是否记录播放历史
+    pub enabled: bool,
+    /// 完整播放的时长比例（百分比）
+    pub completion_percent: f32,
+    /// 完整播放的时长（秒）
+    pub completion_seconds: f32,
 }
 
 /// 标题提取配置
@@ -78,8 +138,173 @@ pub struct AudioConfig {
     pub exclusive_mode: bool,
     #[serde(default = "default_volume")]
     pub volume: f32,
+    /// 交叉淡化设置
+    #[serde(default)]
+    pub crossfade: CrossfadeConfig,
+    /// 回放增益设置
+    #[serde(default)]
+    pub replay_gain: ReplayGainConfig,
+    /// 变调设置
+    #[serde(default)]
+    pub pitch_shift: PitchShiftConfig,
+    /// 标签循环设置
+    #[serde(default)]
+    pub looping: LoopConfig,
+}
+
+/// 交叉淡化曲线
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum CrossfadeCurve {
+    /// 线性
+    Linear,
+    /// 等功率（正弦/余弦）
+    #[default]
+    EqualPower,
+    /// S 曲线（smoothstep）
+    SCurve,
+}
+
+impl CrossfadeCurve {
+    /// 计算淡化进度 t（0.0~1.0）处的（淡出增益, 淡入增益）
+    #[inline]
+    #[must_use]
+    pub fn gains(self, t: f32) -> (f32, f32) {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => (1.0 - t, t),
+            Self::EqualPower => {
+                let angle = t * std::f32::consts::FRAC_PI_2;
+                (angle.cos(), angle.sin())
+            }
+            Self::SCurve => {
+                let s = t * t * (3.0 - 2.0 * t);
+                (1.0 - s, s)
+            }
+        }
+    }
 }
 
+/// 交叉淡化设置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CrossfadeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 淡化时长（秒，1~12）
+    #[serde(default = "default_crossfade_duration")]
+    pub duration: f32,
+    #[serde(default)]
+    pub curve: CrossfadeCurve,
+}
+
+/// 交叉淡化时长范围（秒）
+pub const CROSSFADE_MIN_SECS: f32 = 1.0;
+pub const CROSSFADE_MAX_SECS: f32 = 12.0;
+
+/// 回放增益模式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    /// 使用音轨增益
+    Track,
+    /// 使用专辑增益（缺失时回退到音轨增益）
+    Album,
+    /// 相邻音轨属于同一专辑时使用专辑增益，否则使用音轨增益
+    AutoByQueue,
+}
+
+/// 回放增益设置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayGainConfig {
+    #[serde(default)]
+    pub mode: ReplayGainMode,
+    /// 前置放大（dB，有增益标签的音轨）
+    #[serde(default)]
+    pub preamp: f32,
+    /// 无增益标签音轨的增益（dB）
+    #[serde(default)]
+    pub fallback_gain: f32,
+    /// 根据峰值限制增益，防止削波
+    #[serde(default = "default_true")]
+    pub prevent_clipping: bool,
+}
+
+/// 前置放大范围（dB）
+pub const REPLAY_GAIN_PREAMP_MIN_DB: f32 = -15.0;
+pub const REPLAY_GAIN_PREAMP_MAX_DB: f32 = 15.0;
+
+/// 音高偏移（半音 + 音分）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PitchShift {
+    #[serde(default)]
+    pub semitones: i32,
+    #[serde(default)]
+    pub cents: i32,
+}
+
+/// 变调范围
+pub const PITCH_SHIFT_MAX_SEMITONES: i32 = 12;
+pub const PITCH_SHIFT_MAX_CENTS: i32 = 100;
+
+impl PitchShift {
+    /// 限制在 ±12 半音、±100 音分内
+    #[must_use]
+    pub fn clamped(self) -> Self {
+        Self {
+            semitones: self.semitones.clamp(-PITCH_SHIFT_MAX_SEMITONES, PITCH_SHIFT_MAX_SEMITONES),
+            cents: self.cents.clamp(-PITCH_SHIFT_MAX_CENTS, PITCH_SHIFT_MAX_CENTS),
+        }
+    }
+
+    /// 频率比例
+    #[must_use]
+    pub fn ratio(self) -> f32 {
+        let semitones = self.semitones as f32 + self.cents as f32 / 100.0;
+        2f32.powf(semitones / 12.0)
+    }
+}
+
+/// 变调设置：全局偏移，以及按文件路径记住的单曲偏移（优先于全局）
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PitchShiftConfig {
+    #[serde(default)]
+    pub global: PitchShift,
+    #[serde(default)]
+    pub tracks: HashMap<String, PitchShift>,
+}
+
+impl PitchShiftConfig {
+    /// 音轨实际使用的偏移
+    #[must_use]
+    pub fn for_track(&self, path: &str) -> PitchShift {
+        self.tracks.get(path).copied().unwrap_or(self.global)
+    }
+}
+
+/// 标签循环设置（LOOPSTART / LOOPLENGTH / LOOPEND）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LoopConfig {
+    /// 是否按标签循环
+    #[serde(default = "default_true")]
+    pub honor_tags: bool,
+    /// 循环次数（之后淡出或播放到结尾）
+    #[serde(default = "default_loop_count")]
+    pub loop_count: u32,
+    /// 淡出时长（秒，0 表示不淡出，循环结束后播放到结尾）
+    #[serde(default = "default_loop_fade_out")]
+    pub fade_out: f32,
+}
+
+/// 淡出时长上限（秒）
+pub const LOOP_FADE_OUT_MAX_SECS: f32 = 30.0;
+
 /// 歌词设置
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -124,6 +349,18 @@ const fn default_volume() -> f32 {
     0.5
 }
 
+const fn default_crossfade_duration() -> f32 {
+    5.0
+}
+
+const fn default_loop_count() -> u32 {
+    2
+}
+
+const fn default_loop_fade_out() -> f32 {
+    10.0
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -134,6 +371,8 @@ impl Default for AppConfig {
             general: GeneralConfig::default(),
             audio: AudioConfig::default(),
             lyrics: LyricsConfig::default(),
+            cover: CoverConfig::default(),
+            history: HistoryConfig::default(),
         }
     }
 }
@@ -149,11 +388,35 @@ impl Default for DirectoryScanConfig {
                 "node_modules".to_string(),
                 "temp".to_string(),
                 "tmp".to_string(),
+                "@eaDir".to_string(),
+                "#recycle".to_string(),
+                "$RECYCLE.BIN".to_string(),
+                "System Volume Information".to_string(),
             ],
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+            follow_symlinks: false,
+            min_file_size: 0,
+        }
+    }
+}
+
+impl Default for CoverConfig {
+    fn default() -> Self {
+        Self {
+            folder_patterns: ["cover.*", "folder.*", "front.*", "album.*", "albumart*.*"].map(String::from).to_vec(),
+            parent_levels: 1,
+            priority: vec![CoverSource::EmbeddedFront, CoverSource::FolderFile, CoverSource::EmbeddedAny],
         }
     }
 }
 
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self { enabled: true, completion_percent: 50.0, completion_seconds: 240.0 }
+    }
+}
+
 impl Default for TitleExtractionConfig {
     fn default() -> Self {
         Self {
@@ -194,6 +457,41 @@ impl Default for AudioConfig {
         Self {
             exclusive_mode: false,
             volume: default_volume(),
+            crossfade: CrossfadeConfig::default(),
+            replay_gain: ReplayGainConfig::default(),
+            pitch_shift: PitchShiftConfig::default(),
+            looping: LoopConfig::default(),
+        }
+    }
+}
+
+impl Default for LoopConfig {
+    fn default() -> Self {
+        Self {
+            honor_tags: true,
+            loop_count: default_loop_count(),
+            fade_out: default_loop_fade_out(),
+        }
+    }
+}
+
+impl Default for ReplayGainConfig {
+    fn default() -> Self {
+        Self {
+            mode: ReplayGainMode::default(),
+            preamp: 0.0,
+            fallback_gain: 0.0,
+            prevent_clipping: true,
+        }
+    }
+}
+
+impl Default for CrossfadeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            duration: default_crossfade_duration(),
+            curve: CrossfadeCurve::default(),
         }
     }
 }
@@ -248,8 +546,13 @@ impl ConfigManager {
         format!("{}/user.json", self.config_dir)
     }
 
-    pub fn initialize_config_files(&self) -> Result<(), String> {
-        std::fs::create_dir_all(&self.config_dir).map_err(|e| format!("创建配置目录失败: {e}"))?;
+    pub fn initialize_config_files(&self) -> AppResult<()> {
+        std::fs::create_dir_all(&self.config_dir).map_err(|e| {
+            AppError::from(e)
+                .with_code(ErrorCode::ConfigFailed)
+                .context_message("Failed to create config directory")
+                .with_path(&self.config_dir)
+        })?;
 
         let default_config_path = self.get_default_config_path();
         let user_config_path = self.get_user_config_path();
@@ -267,7 +570,7 @@ impl ConfigManager {
         Ok(())
     }
 
-    pub fn load_config(&self) -> Result<AppConfig, String> {
+    pub fn load_config(&self) -> AppResult<AppConfig> {
         self.initialize_config_files()?;
 
         let user_config_path = self.get_user_config_path();
@@ -287,33 +590,37 @@ impl ConfigManager {
         })
     }
 
-    fn load_config_from_file(&self, file_path: &str) -> Result<AppConfig, String> {
-        let content = std::fs::read_to_string(file_path).map_err(|e| format!("Failed to read config file: {e}"))?;
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse config file: {e}"))
+    fn load_config_from_file(&self, file_path: &str) -> AppResult<AppConfig> {
+        let content = std::fs::read_to_string(file_path)
+            .map_err(|e| AppError::from(e).context_message("Failed to read config file").with_path(file_path))?;
+        serde_json::from_str(&content)
+            .map_err(|e| AppError::from(e).context_message("Failed to parse config file").with_path(file_path))
     }
 
-    pub fn save_config(&self, config: &AppConfig) -> Result<(), String> {
+    pub fn save_config(&self, config: &AppConfig) -> AppResult<()> {
         self.save_config_to_file(config, &self.get_user_config_path())
     }
 
-    pub fn save_default_config(&self, config: &AppConfig) -> Result<(), String> {
+    pub fn save_default_config(&self, config: &AppConfig) -> AppResult<()> {
         self.save_config_to_file(config, &self.get_default_config_path())
     }
 
-    fn save_config_to_file(&self, config: &AppConfig, file_path: &str) -> Result<(), String> {
-        let content = serde_json::to_string_pretty(config).map_err(|e| format!("Failed to serialize config: {e}"))?;
-        std::fs::write(file_path, content).map_err(|e| format!("Failed to write config file: {e}"))
+    fn save_config_to_file(&self, config: &AppConfig, file_path: &str) -> AppResult<()> {
+        let content = serde_json::to_string_pretty(config)
+            .map_err(|e| AppError::from(e).with_code(ErrorCode::ConfigFailed).context_message("Failed to serialize config"))?;
+        std::fs::write(file_path, content)
+            .map_err(|e| AppError::from(e).context_message("Failed to write config file").with_path(file_path))
     }
 
-    pub fn export_config(&self, config: &AppConfig, export_path: &str) -> Result<(), String> {
+    pub fn export_config(&self, config: &AppConfig, export_path: &str) -> AppResult<()> {
         self.save_config_to_file(config, export_path)
     }
 
-    pub fn import_config(&self, import_path: &str) -> Result<AppConfig, String> {
+    pub fn import_config(&self, import_path: &str) -> AppResult<AppConfig> {
         self.load_config_from_file(import_path)
     }
 
-    pub fn reset_config(&self) -> Result<AppConfig, String> {
+    pub fn reset_config(&self) -> AppResult<AppConfig> {
         let default_config = AppConfig::default();
         let user_config_path = self.get_user_config_path();
         if Path::new(&user_config_path).exists() {
@@ -7,6 +7,8 @@ pub mod manager;
 
 // 重新导出常用类型
 pub use manager::{
-    AppConfig, AudioConfig, ConfigManager, DirectoryScanConfig, GeneralConfig, PlaylistConfig,
-    TitleExtractionConfig,
+    AppConfig, AudioConfig, ConfigManager, CoverConfig, CoverSource, CrossfadeConfig, CrossfadeCurve, DirectoryScanConfig,
+    GeneralConfig, HistoryConfig, LoopConfig, PitchShift, PitchShiftConfig, PlaylistConfig, ReplayGainConfig,
+    ReplayGainMode, TitleExtractionConfig, CROSSFADE_MAX_SECS, CROSSFADE_MIN_SECS, LOOP_FADE_OUT_MAX_SECS,
+    PITCH_SHIFT_MAX_CENTS, PITCH_SHIFT_MAX_SEMITONES, REPLAY_GAIN_PREAMP_MAX_DB, REPLAY_GAIN_PREAMP_MIN_DB,
 };
@@ -1,87 +1,248 @@
 //! 错误处理模块
 //!
-//! 定义应用程序的自定义错误类型和结果类型。
+//! 定义应用程序统一的错误类型。Tauri 命令返回 [`AppResult`]，错误序列化为
+//! `{ code, message, context, causes }`：`code` 是稳定的机器可读错误码，前端据此分类，
+//! 不再匹配错误文本；`context` 记录路径、设备名等结构化信息；`causes` 是底层错误链。
 
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::collections::BTreeMap;
 use std::fmt;
 
-/// 自定义错误类型，用于音乐播放器应用
+/// 错误码（序列化为变体名，发布后只增不改）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum ErrorCode {
+    /// 文件或目录不存在
+    FileNotFound,
+    /// 路径无效（不是目录、包含非法字符、位于受保护目录等）
+    InvalidPath,
+    /// 没有访问权限
+    PermissionDenied,
+    /// 其他文件读写错误
+    Io,
+    /// 不支持的文件格式或编码
+    UnsupportedFormat,
+    /// 音频解码失败
+    DecodeFailed,
+    /// 音频输出设备不可用或被占用
+    DeviceUnavailable,
+    /// 当前没有加载音轨
+    NoTrackLoaded,
+    /// 参数无效
+    InvalidArgument,
+    /// 请求的资源不存在（插件、更新包等）
+    NotFound,
+    /// 配置读写失败
+    ConfigFailed,
+    /// 数据解析失败（JSON、接口响应、标签等）
+    ParseFailed,
+    /// 网络请求超时
+    NetworkTimeout,
+    /// 网络请求失败
+    NetworkFailed,
+    /// 当前平台不支持该功能
+    Unsupported,
+    /// 设置已保存，需要重启应用生效
+    RestartRequired,
+    /// 其他内部错误
+    Internal,
+    /// 同类操作正在进行（如媒体库扫描）
+    Busy,
+}
+
+/// 应用错误
 #[derive(Debug)]
-#[allow(dead_code)]
-pub enum AppError {
-    /// IO 相关错误
-    Io(std::io::Error),
-    /// 音频解码错误
-    AudioDecoder(String),
-    /// 文件不存在
-    FileNotFound(String),
-    /// 无效的文件路径
-    InvalidPath(String),
-    /// 配置相关错误
-    Config(String),
-    /// Tauri 相关错误
-    Tauri(tauri::Error),
-    /// JSON 序列化/反序列化错误
-    Json(serde_json::Error),
-    /// 其他通用错误
-    Other(String),
+pub struct AppError {
+    code: ErrorCode,
+    message: String,
+    context: BTreeMap<&'static str, String>,
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+impl AppError {
+    #[must_use]
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), context: BTreeMap::new(), source: None }
+    }
+
+    #[must_use]
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidArgument, message)
+    }
+
+    #[must_use]
+    pub fn device_unavailable(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::DeviceUnavailable, message)
+    }
+
+    #[must_use]
+    pub fn no_track_loaded() -> Self {
+        Self::new(ErrorCode::NoTrackLoaded, "No track currently loaded")
+    }
+
+    /// 附加上下文字段
+    #[must_use]
+    pub fn with_context(mut self, key: &'static str, value: impl fmt::Display) -> Self {
+        self.context.insert(key, value.to_string());
+        self
+    }
+
+    /// 附加文件路径
+    #[must_use]
+    pub fn with_path(self, path: impl AsRef<std::path::Path>) -> Self {
+        let path = path.as_ref().display().to_string();
+        self.with_context("path", path)
+    }
+
+    /// 附加底层错误
+    #[must_use]
+    pub fn with_source(mut self, source: impl std::error::Error + Send + Sync + 'static) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    /// 替换错误码（底层错误码不够具体时使用）
+    #[must_use]
+    pub const fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// 在错误信息前加上说明
+    #[must_use]
+    pub fn context_message(mut self, prefix: &str) -> Self {
+        self.message = format!("{prefix}: {}", self.message);
+        self
+    }
+
+    #[must_use]
+    pub const fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// 底层错误链（由近到远）
+    #[must_use]
+    pub fn causes(&self) -> Vec<String> {
+        let mut causes = Vec::new();
+        let mut next = std::error::Error::source(self);
+        while let Some(err) = next {
+            causes.push(err.to_string());
+            next = err.source();
+        }
+        causes
+    }
 }
 
 impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Io(err) => write!(f, "IO error: {err}"),
-            Self::AudioDecoder(err) => write!(f, "Audio decoder error: {err}"),
-            Self::FileNotFound(path) => write!(f, "File not found: {path}"),
-            Self::InvalidPath(path) => write!(f, "Invalid file path: {path}"),
-            Self::Config(err) => write!(f, "Configuration error: {err}"),
-            Self::Tauri(err) => write!(f, "Tauri error: {err}"),
-            Self::Json(err) => write!(f, "JSON error: {err}"),
-            Self::Other(err) => write!(f, "Error: {err}"),
-        }
+        f.write_str(&self.message)
     }
 }
 
 impl std::error::Error for AppError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Self::Io(err) => Some(err),
-            Self::Tauri(err) => Some(err),
-            Self::Json(err) => Some(err),
-            _ => None,
-        }
+        self.source.as_deref().map(|e| e as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 4)?;
+        state.serialize_field("code", &self.code)?;
+        state.serialize_field("message", &self.message)?;
+        state.serialize_field("context", &self.context)?;
+        state.serialize_field("causes", &self.causes())?;
+        state.end()
     }
 }
 
 impl From<std::io::Error> for AppError {
     fn from(err: std::io::Error) -> Self {
-        Self::Io(err)
+        use std::io::ErrorKind;
+        let code = match err.kind() {
+            ErrorKind::NotFound => ErrorCode::FileNotFound,
+            ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => ErrorCode::PermissionDenied,
+            ErrorKind::InvalidFilename | ErrorKind::NotADirectory | ErrorKind::IsADirectory => ErrorCode::InvalidPath,
+            ErrorKind::TimedOut => ErrorCode::NetworkTimeout,
+            ErrorKind::InvalidData | ErrorKind::UnexpectedEof => ErrorCode::ParseFailed,
+            _ => ErrorCode::Io,
+        };
+        Self::new(code, err.to_string()).with_source(err)
     }
 }
 
 impl From<tauri::Error> for AppError {
     fn from(err: tauri::Error) -> Self {
-        Self::Tauri(err)
+        Self::new(ErrorCode::Internal, err.to_string()).with_source(err)
     }
 }
 
 impl From<serde_json::Error> for AppError {
     fn from(err: serde_json::Error) -> Self {
-        Self::Json(err)
+        let code = if err.is_io() { ErrorCode::Io } else { ErrorCode::ParseFailed };
+        Self::new(code, err.to_string()).with_source(err)
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(err: reqwest::Error) -> Self {
+        let code = if err.is_timeout() {
+            ErrorCode::NetworkTimeout
+        } else if err.is_decode() {
+            ErrorCode::ParseFailed
+        } else {
+            ErrorCode::NetworkFailed
+        };
+        Self::new(code, err.to_string()).with_source(err)
+    }
+}
+
+impl From<lofty::error::LoftyError> for AppError {
+    fn from(err: lofty::error::LoftyError) -> Self {
+        use lofty::error::ErrorKind;
+        let code = match err.kind() {
+            ErrorKind::Io(io) => Self::from(std::io::Error::new(io.kind(), io.to_string())).code,
+            ErrorKind::UnknownFormat | ErrorKind::UnsupportedTag | ErrorKind::UnsupportedPicture | ErrorKind::NotAPicture => ErrorCode::UnsupportedFormat,
+            _ => ErrorCode::ParseFailed,
+        };
+        Self::new(code, err.to_string()).with_source(err)
+    }
+}
+
+impl From<symphonia::core::errors::Error> for AppError {
+    fn from(err: symphonia::core::errors::Error) -> Self {
+        use symphonia::core::errors::Error;
+        let code = match &err {
+            Error::IoError(io) => Self::from(std::io::Error::new(io.kind(), io.to_string())).code,
+            Error::Unsupported(_) => ErrorCode::UnsupportedFormat,
+            _ => ErrorCode::DecodeFailed,
+        };
+        Self::new(code, err.to_string()).with_source(err)
     }
 }
 
 impl From<String> for AppError {
     fn from(err: String) -> Self {
-        Self::Other(err)
+        Self::new(ErrorCode::Internal, err)
     }
 }
 
 impl From<&str> for AppError {
     fn from(err: &str) -> Self {
-        Self::Other(err.to_string())
+        Self::new(ErrorCode::Internal, err)
+    }
+}
+
+/// 仍返回 `String` 的内部函数可以直接用 `?` 传播 `AppError`
+impl From<AppError> for String {
+    fn from(err: AppError) -> Self {
+        err.message
     }
 }
 
 /// 应用结果类型
-#[allow(dead_code)]
 pub type AppResult<T> = Result<T, AppError>;
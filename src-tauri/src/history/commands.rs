@@ -0,0 +1,48 @@
+//! 播放历史相关的 Tauri 命令
+
+use super::{HistoryExportFormat, HistoryWindow, PlayHistoryPage, TopEntry, TrackPlayStats};
+use crate::error::AppResult;
+use crate::AppState;
+use std::path::Path;
+use tauri::{command, State};
+
+/// 排行默认返回的条数
+const TOP_DEFAULT_LIMIT: usize = 50;
+
+/// 分页获取播放历史（从新到旧）
+#[command]
+pub fn get_play_history(state: State<AppState>, window: Option<HistoryWindow>, offset: Option<usize>, limit: Option<usize>) -> AppResult<PlayHistoryPage> {
+    Ok(state.history.page(window.unwrap_or_default(), offset.unwrap_or(0), limit.unwrap_or(100)))
+}
+
+/// 获取音轨的播放次数、跳过次数和最近播放时间
+#[command]
+pub fn get_track_play_stats(state: State<AppState>, path: String) -> AppResult<TrackPlayStats> {
+    Ok(state.history.track_stats(&path))
+}
+
+#[command]
+pub fn get_top_tracks(state: State<AppState>, window: Option<HistoryWindow>, limit: Option<usize>) -> AppResult<Vec<TopEntry>> {
+    Ok(state.history.top_tracks(window.unwrap_or_default(), limit.unwrap_or(TOP_DEFAULT_LIMIT)))
+}
+
+#[command]
+pub fn get_top_artists(state: State<AppState>, window: Option<HistoryWindow>, limit: Option<usize>) -> AppResult<Vec<TopEntry>> {
+    Ok(state.history.top_artists(window.unwrap_or_default(), limit.unwrap_or(TOP_DEFAULT_LIMIT)))
+}
+
+#[command]
+pub fn get_top_albums(state: State<AppState>, window: Option<HistoryWindow>, limit: Option<usize>) -> AppResult<Vec<TopEntry>> {
+    Ok(state.history.top_albums(window.unwrap_or_default(), limit.unwrap_or(TOP_DEFAULT_LIMIT)))
+}
+
+/// 导出播放历史，`format` 为空时按扩展名推断（`json` 或 `csv`），返回导出的条数
+#[command]
+pub fn export_play_history(state: State<AppState>, path: String, format: Option<String>, window: Option<HistoryWindow>) -> AppResult<usize> {
+    let path = Path::new(&path);
+    let format = match format {
+        Some(format) => format.parse()?,
+        None => HistoryExportFormat::from_path(path),
+    };
+    state.history.export(path, format, window.unwrap_or_default())
+}
@@ -0,0 +1,88 @@
+//! 播放历史模块
+//!
+//! 后端在音轨开始、结束和切换时记录每次播放，并统计播放次数、跳过次数和排行。
+//! 每条记录写入后发送 `play-history-updated` 事件。
+
+pub mod commands;
+pub mod store;
+
+pub use store::{EndedPlay, HistoryExportFormat, HistoryWindow, PlayEvent, PlayHistory, PlayHistoryPage, PlayOutcome, TopEntry, TrackPlayStats};
+
+use crate::config::HistoryConfig;
+use crate::error::AppResult;
+use crate::media::artwork::CoverRules;
+use crate::media::metadata::read_track_metadata;
+use crate::media::TrackMetadata;
+use crate::AppState;
+use tauri::{AppHandle, Emitter, Manager};
+
+fn history_config(state: &AppState) -> HistoryConfig {
+    state.history_config.read().unwrap().clone()
+}
+
+fn emit_recorded(app: &AppHandle, result: AppResult<Option<PlayEvent>>) {
+    match result {
+        Ok(Some(event)) => {
+            let _ = app.emit("play-history-updated", &event);
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to record play history: {e}"),
+    }
+}
+
+/// 播放历史中使用的音轨信息：优先取媒体库索引，不在库中时只读取文字标签
+fn track_info(state: &AppState, path: &str) -> TrackMetadata {
+    state.library.track(path).unwrap_or_else(|| {
+        read_track_metadata(path, &CoverRules::disabled()).unwrap_or_else(|_| TrackMetadata { path: path.to_string(), ..Default::default() })
+    })
+}
+
+/// 音轨开始播放时由 `start_track` 调用
+///
+/// 带起始位置重新打开正在记录的音轨（如切换输出设备）时继续同一次播放。
+pub fn on_track_started(app: &AppHandle, path: &str, position: Option<f32>) {
+    let Some(state) = app.try_state::<AppState>() else { return };
+    let config = history_config(&state);
+    if !config.enabled {
+        state.history.discard();
+        return;
+    }
+    if position.is_some() && state.history.current_path().as_deref() == Some(path) {
+        return;
+    }
+    let track = track_info(&state, path);
+    emit_recorded(app, state.history.start(&track, &config));
+}
+
+/// 音轨结束时由 `emit_track_ended` 调用（位于播放事件线程或独占模式推送线程，记录放到新线程）
+///
+/// 结束的播放在当前线程取出，队列随后开始的下一首不会把它记为跳过。
+pub fn on_track_ended(app: &AppHandle, ended: Option<&str>, next: Option<&str>) {
+    let Some(state) = app.try_state::<AppState>() else { return };
+    let ended = ended.and_then(|path| state.history.end(Some(path)));
+    if ended.is_none() && next.is_none() {
+        return;
+    }
+    let (app, next) = (app.clone(), next.map(str::to_string));
+    std::thread::spawn(move || {
+        let Some(state) = app.try_state::<AppState>() else { return };
+        let config = history_config(&state);
+        if let Some(ended) = ended.filter(|_| config.enabled) {
+            emit_recorded(&app, state.history.record_ended(ended, &config).map(Some));
+        }
+        // 无缝衔接的下一首不经过 `start_track`
+        if let Some(next) = next.filter(|_| config.enabled) {
+            let track = track_info(&state, &next);
+            emit_recorded(&app, state.history.start(&track, &config));
+        }
+    });
+}
+
+/// 退出时结束当前播放
+pub fn on_exit(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else { return };
+    let config = history_config(&state);
+    if let Some(ended) = state.history.end(None).filter(|_| config.enabled) {
+        emit_recorded(app, state.history.record_ended(ended, &config).map(Some));
+    }
+}
@@ -0,0 +1,458 @@
+//! 播放历史存储
+//!
+//! 每次播放（从开始到切换、播放结束或退出）记录为一条 [`PlayEvent`]，
+//! 以 JSON Lines 格式追加到配置目录的 `play_history.jsonl`。
+//! 播放次数、跳过次数和排行都由历史记录统计得出。
+
+use crate::config::HistoryConfig;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::media::TrackMetadata;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 两次位置更新之间计入收听时长的最大间隔（秒），更大的跳变视为定位
+const MAX_POSITION_STEP: f32 = 2.0;
+/// 单页最多返回的历史记录数
+const HISTORY_PAGE_MAX: usize = 1000;
+
+/// 一次播放的结果
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PlayOutcome {
+    /// 达到完整播放阈值
+    Completed,
+    /// 未达到阈值就切换到了其他音轨
+    Skipped,
+    /// 未达到阈值就播放结束或退出（如定位到结尾）
+    Incomplete,
+}
+
+/// 一次播放记录
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayEvent {
+    pub path: String,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    /// 音轨时长（秒）
+    pub duration: Option<f64>,
+    /// 开始和结束时间（Unix 毫秒）
+    pub started_at: u64,
+    pub ended_at: u64,
+    /// 实际收听的时长（秒，不含定位跳过的部分）
+    pub listened: f64,
+    pub outcome: PlayOutcome,
+}
+
+/// 单个音轨的播放统计
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackPlayStats {
+    pub path: String,
+    /// 开始播放次数（包括正在播放的这一次）
+    pub starts: usize,
+    /// 完整播放次数
+    pub plays: usize,
+    pub skips: usize,
+    /// 最近一次播放的开始时间（Unix 毫秒）
+    pub last_played: Option<u64>,
+    /// 累计收听时长（秒）
+    pub listened: f64,
+}
+
+/// 排行条目（音轨、艺术家或专辑）
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TopEntry {
+    /// 音轨标题、艺术家名或专辑名
+    pub name: String,
+    /// 音轨的艺术家或专辑的专辑艺术家
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// 音轨路径（仅音轨排行）
+    pub path: Option<String>,
+    pub plays: usize,
+    pub starts: usize,
+    pub skips: usize,
+    pub listened: f64,
+    pub last_played: u64,
+}
+
+/// 历史记录分页（按开始时间从新到旧）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayHistoryPage {
+    pub total: usize,
+    pub offset: usize,
+    pub events: Vec<PlayEvent>,
+}
+
+/// 时间范围（Unix 毫秒，按开始时间筛选，两端为空表示不限）
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistoryWindow {
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+}
+
+impl HistoryWindow {
+    fn contains(self, event: &PlayEvent) -> bool {
+        self.since.is_none_or(|since| event.started_at >= since) && self.until.is_none_or(|until| event.started_at < until)
+    }
+}
+
+/// 导出格式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryExportFormat {
+    Json,
+    Csv,
+}
+
+/// 正在进行的播放
+#[derive(Debug)]
+struct Session {
+    event: PlayEvent,
+    last_position: Option<f32>,
+}
+
+/// 已结束但尚未记录的播放
+#[derive(Debug)]
+pub struct EndedPlay(PlayEvent);
+
+impl EndedPlay {
+    fn of(session: Session) -> Self {
+        Self(PlayEvent { ended_at: now_millis(), ..session.event })
+    }
+}
+
+/// 持久化的播放历史
+pub struct PlayHistory {
+    path: PathBuf,
+    events: RwLock<Vec<PlayEvent>>,
+    session: Mutex<Option<Session>>,
+}
+
+impl PlayHistory {
+    /// 打开配置目录中的历史记录（无法解析的行被跳过，如写入中断的最后一行）
+    #[must_use]
+    pub fn open(config_dir: &str) -> Self {
+        let path = Path::new(config_dir).join("play_history.jsonl");
+        let events: Vec<PlayEvent> = fs::read_to_string(&path)
+            .map(|content| content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+            .unwrap_or_default();
+        if !events.is_empty() {
+            println!("Loaded play history: {} plays", events.len());
+        }
+        Self { path, events: RwLock::new(events), session: Mutex::new(None) }
+    }
+
+    /// 开始新的播放，返回被结束的上一次播放（视为跳过或完整播放）
+    pub fn start(&self, track: &TrackMetadata, config: &HistoryConfig) -> AppResult<Option<PlayEvent>> {
+        let event = PlayEvent {
+            path: track.path.clone(),
+            title: track.title.clone(),
+            artists: if track.artists.is_empty() { track.artist.iter().cloned().collect() } else { track.artists.clone() },
+            album: track.album.clone(),
+            album_artist: track.album_artist.clone(),
+            duration: track.duration,
+            started_at: now_millis(),
+            ended_at: 0,
+            listened: 0.0,
+            outcome: PlayOutcome::Incomplete,
+        };
+        let previous = self.session.lock().unwrap().replace(Session { event, last_position: None });
+        previous.map(|session| self.record(EndedPlay::of(session), PlayOutcome::Skipped, config)).transpose()
+    }
+
+    /// 当前播放的音轨路径
+    #[must_use]
+    pub fn current_path(&self) -> Option<String> {
+        self.session.lock().unwrap().as_ref().map(|session| session.event.path.clone())
+    }
+
+    /// 结束当前播放（播放到结尾或退出），`path` 不为空时只结束该音轨
+    ///
+    /// 只取出播放而不写入，可以在音频线程中调用，随后用 [`Self::record_ended`] 记录。
+    pub fn end(&self, path: Option<&str>) -> Option<EndedPlay> {
+        let mut session = self.session.lock().unwrap();
+        if path.is_some_and(|path| session.as_ref().is_some_and(|s| s.event.path != path)) {
+            return None;
+        }
+        session.take().map(EndedPlay::of)
+    }
+
+    /// 记录已结束的播放
+    pub fn record_ended(&self, ended: EndedPlay, config: &HistoryConfig) -> AppResult<PlayEvent> {
+        self.record(ended, PlayOutcome::Incomplete, config)
+    }
+
+    /// 放弃当前播放（不记录）
+    pub fn discard(&self) {
+        self.session.lock().unwrap().take();
+    }
+
+    /// 记录播放位置，正常前进的部分计入收听时长（在音频线程中调用）
+    pub fn on_position(&self, position: f32) {
+        let Ok(mut session) = self.session.try_lock() else { return };
+        let Some(session) = session.as_mut() else { return };
+        if let Some(last) = session.last_position {
+            let step = position - last;
+            if step > 0.0 && step <= MAX_POSITION_STEP {
+                session.event.listened += f64::from(step);
+            }
+        }
+        session.last_position = Some(position);
+    }
+
+    /// 按阈值判定结果并追加到历史记录
+    fn record(&self, ended: EndedPlay, unfinished: PlayOutcome, config: &HistoryConfig) -> AppResult<PlayEvent> {
+        let mut event = ended.0;
+        event.outcome = if is_completed(&event, config) { PlayOutcome::Completed } else { unfinished };
+
+        let line = serde_json::to_string(&event).map_err(|e| AppError::from(e).context_message("Failed to serialize play history"))?;
+        let append = || -> std::io::Result<()> {
+            let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+            writeln!(file, "{line}")
+        };
+        append().map_err(|e| AppError::from(e).context_message("Failed to write play history").with_path(&self.path))?;
+        self.events.write().unwrap().push(event.clone());
+        Ok(event)
+    }
+
+    /// 分页获取历史记录（从新到旧）
+    #[must_use]
+    pub fn page(&self, window: HistoryWindow, offset: usize, limit: usize) -> PlayHistoryPage {
+        let events = self.events.read().unwrap();
+        let matched: Vec<&PlayEvent> = events.iter().rev().filter(|event| window.contains(event)).collect();
+        PlayHistoryPage {
+            total: matched.len(),
+            offset,
+            events: matched.into_iter().skip(offset).take(limit.clamp(1, HISTORY_PAGE_MAX)).cloned().collect(),
+        }
+    }
+
+    /// 单个音轨的播放统计
+    #[must_use]
+    pub fn track_stats(&self, path: &str) -> TrackPlayStats {
+        let mut stats = TrackPlayStats { path: path.to_string(), ..Default::default() };
+        for event in self.events.read().unwrap().iter().filter(|event| event.path == path) {
+            stats.starts += 1;
+            match event.outcome {
+                PlayOutcome::Completed => stats.plays += 1,
+                PlayOutcome::Skipped => stats.skips += 1,
+                PlayOutcome::Incomplete => {}
+            }
+            stats.listened += event.listened;
+            stats.last_played = stats.last_played.max(Some(event.started_at));
+        }
+        if let Some(session) = self.session.lock().unwrap().as_ref().filter(|s| s.event.path == path) {
+            stats.starts += 1;
+            stats.listened += session.event.listened;
+            stats.last_played = Some(session.event.started_at);
+        }
+        stats
+    }
+
+    /// 播放最多的音轨
+    #[must_use]
+    pub fn top_tracks(&self, window: HistoryWindow, limit: usize) -> Vec<TopEntry> {
+        self.top_by(window, limit, |event| {
+            let name = event.title.clone().unwrap_or_else(|| file_name(&event.path));
+            vec![(event.path.clone(), TopEntry {
+                name,
+                artist: (!event.artists.is_empty()).then(|| event.artists.join("; ")),
+                album: event.album.clone(),
+                path: Some(event.path.clone()),
+                ..Default::default()
+            })]
+        })
+    }
+
+    /// 播放最多的艺术家（多艺术家的音轨分别计入每位艺术家）
+    #[must_use]
+    pub fn top_artists(&self, window: HistoryWindow, limit: usize) -> Vec<TopEntry> {
+        self.top_by(window, limit, |event| {
+            event
+                .artists
+                .iter()
+                .map(|artist| (artist.to_lowercase(), TopEntry { name: artist.clone(), ..Default::default() }))
+                .collect()
+        })
+    }
+
+    /// 播放最多的专辑（按专辑艺术家和专辑名区分同名专辑）
+    #[must_use]
+    pub fn top_albums(&self, window: HistoryWindow, limit: usize) -> Vec<TopEntry> {
+        self.top_by(window, limit, |event| {
+            let Some(album) = event.album.clone().filter(|album| !album.is_empty()) else { return Vec::new() };
+            let artist = event.album_artist.clone().or_else(|| event.artists.first().cloned());
+            let key = format!("{}\u{0}{}", artist.as_deref().unwrap_or_default().to_lowercase(), album.to_lowercase());
+            vec![(key, TopEntry { name: album.clone(), artist, album: Some(album), ..Default::default() })]
+        })
+    }
+
+    /// 按 `keys` 分组统计，按完整播放次数、收听时长排序
+    fn top_by(&self, window: HistoryWindow, limit: usize, keys: impl Fn(&PlayEvent) -> Vec<(String, TopEntry)>) -> Vec<TopEntry> {
+        let mut groups: HashMap<String, TopEntry> = HashMap::new();
+        for event in self.events.read().unwrap().iter().filter(|event| window.contains(event)) {
+            for (key, entry) in keys(event) {
+                let group = groups.entry(key).or_insert(entry);
+                group.starts += 1;
+                match event.outcome {
+                    PlayOutcome::Completed => group.plays += 1,
+                    PlayOutcome::Skipped => group.skips += 1,
+                    PlayOutcome::Incomplete => {}
+                }
+                group.listened += event.listened;
+                group.last_played = group.last_played.max(event.started_at);
+            }
+        }
+        let mut entries: Vec<TopEntry> = groups.into_values().filter(|entry| entry.plays > 0).collect();
+        entries.sort_by(|a, b| b.plays.cmp(&a.plays).then(b.listened.total_cmp(&a.listened)).then_with(|| a.name.cmp(&b.name)));
+        entries.truncate(limit);
+        entries
+    }
+
+    /// 导出历史记录，返回导出的条数
+    pub fn export(&self, path: &Path, format: HistoryExportFormat, window: HistoryWindow) -> AppResult<usize> {
+        let events: Vec<PlayEvent> = self.events.read().unwrap().iter().filter(|event| window.contains(event)).cloned().collect();
+        let content = match format {
+            HistoryExportFormat::Json => serde_json::to_string_pretty(&events)
+                .map_err(|e| AppError::from(e).context_message("Failed to serialize play history"))?,
+            HistoryExportFormat::Csv => to_csv(&events),
+        };
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| AppError::from(e).context_message("Failed to create directory").with_path(parent))?;
+        }
+        fs::write(path, content).map_err(|e| AppError::from(e).context_message("Failed to export play history").with_path(path))?;
+        Ok(events.len())
+    }
+}
+
+impl HistoryExportFormat {
+    /// 根据文件扩展名推断格式（`.csv` 为 CSV，其余为 JSON）
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        let csv = path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        if csv { Self::Csv } else { Self::Json }
+    }
+}
+
+impl std::str::FromStr for HistoryExportFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(AppError::new(ErrorCode::InvalidArgument, "Unsupported export format").with_context("format", s)),
+        }
+    }
+}
+
+/// 收听时长达到秒数阈值或时长比例阈值（先到者为准）
+fn is_completed(event: &PlayEvent, config: &HistoryConfig) -> bool {
+    let by_seconds = config.completion_seconds > 0.0 && event.listened >= f64::from(config.completion_seconds);
+    let by_percent = event
+        .duration
+        .filter(|duration| *duration > 0.0)
+        .is_some_and(|duration| event.listened >= duration * f64::from(config.completion_percent.clamp(0.0, 100.0)) / 100.0);
+    by_seconds || by_percent
+}
+
+fn to_csv(events: &[PlayEvent]) -> String {
+    let mut csv = String::from("startedAt,endedAt,path,title,artists,album,albumArtist,duration,listened,outcome\n");
+    for event in events {
+        let outcome = match event.outcome {
+            PlayOutcome::Completed => "completed",
+            PlayOutcome::Skipped => "skipped",
+            PlayOutcome::Incomplete => "incomplete",
+        };
+        let fields = [
+            event.started_at.to_string(),
+            event.ended_at.to_string(),
+            csv_field(&event.path),
+            csv_field(event.title.as_deref().unwrap_or_default()),
+            csv_field(&event.artists.join("; ")),
+            csv_field(event.album.as_deref().unwrap_or_default()),
+            csv_field(event.album_artist.as_deref().unwrap_or_default()),
+            event.duration.map(|d| format!("{d:.3}")).unwrap_or_default(),
+            format!("{:.3}", event.listened),
+            outcome.to_string(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// CSV 字段：包含逗号、引号或换行时加引号
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) { format!("\"{}\"", value.replace('"', "\"\"")) } else { value.to_string() }
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path).file_name().map_or_else(|| path.to_string(), |name| name.to_string_lossy().to_string())
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(path: &str, artist: &str, listened: f64, outcome: PlayOutcome, started_at: u64) -> PlayEvent {
+        PlayEvent {
+            path: path.to_string(),
+            title: None,
+            artists: vec![artist.to_string()],
+            album: Some("Album".to_string()),
+            album_artist: None,
+            duration: Some(200.0),
+            started_at,
+            ended_at: started_at + 1,
+            listened,
+            outcome,
+        }
+    }
+
+    #[test]
+    fn completion_uses_first_threshold() {
+        let config = HistoryConfig::default();
+        assert!(is_completed(&event("a", "x", 100.0, PlayOutcome::Incomplete, 0), &config));
+        assert!(!is_completed(&event("a", "x", 99.0, PlayOutcome::Incomplete, 0), &config));
+        let long = PlayEvent { duration: Some(3600.0), ..event("a", "x", 240.0, PlayOutcome::Incomplete, 0) };
+        assert!(is_completed(&long, &config));
+    }
+
+    #[test]
+    fn top_entries_count_completed_plays() {
+        let history = PlayHistory {
+            path: PathBuf::new(),
+            events: RwLock::new(vec![
+                event("/a.flac", "X", 200.0, PlayOutcome::Completed, 10),
+                event("/b.flac", "y", 200.0, PlayOutcome::Completed, 20),
+                event("/b.flac", "Y", 10.0, PlayOutcome::Skipped, 30),
+                event("/b.flac", "Y", 200.0, PlayOutcome::Completed, 40),
+            ]),
+            session: Mutex::new(None),
+        };
+        let tracks = history.top_tracks(HistoryWindow::default(), 10);
+        assert_eq!(tracks[0].path.as_deref(), Some("/b.flac"));
+        assert_eq!((tracks[0].plays, tracks[0].skips, tracks[0].starts), (2, 1, 3));
+        let artists = history.top_artists(HistoryWindow { since: Some(15), until: None }, 10);
+        assert_eq!(artists.len(), 1);
+        assert_eq!(artists[0].plays, 2);
+        assert_eq!(history.page(HistoryWindow::default(), 0, 2).events[0].started_at, 40);
+    }
+}
@@ -6,8 +6,11 @@ pub mod audio;
 pub mod config;
 pub mod equalizer;
 pub mod error;
+pub mod history;
 pub mod media;
+pub mod playlists;
 pub mod plugins;
+pub mod queue;
 pub mod system;
 pub mod update;
 
@@ -19,13 +22,17 @@ use audio::SymphoniaSource;
 #[cfg(windows)]
 use audio::WasapiExclusivePlayback;
 
-use audio::DeviceMonitor;
-use config::ConfigManager;
+use audio::{ChainFormat, DeviceMonitor, ExclusiveSeek, LoopControl, LoudnessScanner, NextTrackSlot, PlaybackEvents, PlayerStateMachine, ReplayGain, SleepTimer, StretchControl};
+use config::{ConfigManager, CrossfadeConfig, HistoryConfig};
 use equalizer::{Equalizer, GlobalEqualizer};
+use history::PlayHistory;
+use media::{LibraryIndex, LibraryScanner, LibraryWatcher};
+use playlists::PlaylistStore;
+use queue::PlayQueue;
 
 use rodio::Sink;
 use std::sync::atomic::{AtomicBool, AtomicU64};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 
 /// 非 Windows 平台的占位类型
 #[cfg(not(windows))]
@@ -41,6 +48,24 @@ pub struct PlayerState {
     pub current_source: Arc<Mutex<Option<SymphoniaSource>>>,
     /// 当前播放文件路径
     pub current_path: Arc<Mutex<Option<String>>>,
+    /// 播放状态机（`player-state` 事件）
+    pub player_state: Arc<PlayerStateMachine>,
+    /// 音频线程的播放事件（由事件线程处理）
+    pub events: PlaybackEvents,
+    /// 已排队的下一首音轨（用于无缝播放）
+    pub next_track: NextTrackSlot,
+    /// 共享模式下正在播放的 Symphonia 音源链的格式
+    pub chain_format: ChainFormat,
+    /// 交叉淡化设置（音频线程实时读取）
+    pub crossfade: Arc<RwLock<CrossfadeConfig>>,
+    /// 回放增益（标签缓存与设置）
+    pub replay_gain: Arc<ReplayGain>,
+    /// 播放倍速（变速不变调）
+    pub stretch: StretchControl,
+    /// 循环设置（A–B 区间与循环标签）
+    pub looping: Arc<LoopControl>,
+    /// 睡眠定时器
+    pub sleep_timer: Arc<SleepTimer>,
     /// 目标音量
     pub target_volume: Arc<Mutex<f32>>,
     /// 当前音频设备名称
@@ -57,6 +82,8 @@ pub struct PlayerState {
     /// 非 Windows 平台的占位字段
     #[cfg(not(windows))]
     pub wasapi_player: Arc<Mutex<Option<Placeholder>>>,
+    /// 独占模式推送线程的定位请求
+    pub exclusive_seek: Arc<Mutex<ExclusiveSeek>>,
     /// 解码线程停止标志
     pub decode_thread_stop: Arc<AtomicBool>,
     /// 当前解码线程 ID（用于区分不同的播放会话）
@@ -81,6 +108,22 @@ pub struct AppState {
     pub config_manager: ConfigManager,
     /// 全局均衡器
     pub equalizer: GlobalEqualizer,
+    /// 响度扫描任务
+    pub loudness_scanner: Arc<LoudnessScanner>,
+    /// 播放队列
+    pub queue: Arc<Mutex<PlayQueue>>,
+    /// 媒体库索引
+    pub library: Arc<LibraryIndex>,
+    /// 媒体库后台扫描
+    pub library_scanner: Arc<LibraryScanner>,
+    /// 音乐目录监听器
+    pub library_watcher: LibraryWatcher,
+    /// 播放历史
+    pub history: Arc<PlayHistory>,
+    /// 播放历史设置（启动时读取，保存或重置配置时更新）
+    pub history_config: Arc<RwLock<HistoryConfig>>,
+    /// 用户播放列表
+    pub playlists: PlaylistStore,
 }
 
 // 重新导出常用类型
@@ -26,14 +26,14 @@ use mercurial_player::{
     config::ConfigManager,
     equalizer,
     equalizer::{Equalizer, GlobalEqualizer},
-    media, plugins, system, update,
+    history, media, playlists, plugins, queue, system, update,
 };
 
 #[cfg(windows)]
-use mercurial_player::audio::{WasapiExclusivePlayback, DeviceMonitor};
+use mercurial_player::audio::{WasapiExclusivePlayback, DeviceMonitor, ExclusiveSeek, LoopControl, LoudnessScanner, OutputFormat, PlaybackEvents, PlayerStateMachine, ReplayGain, SleepTimer, StretchControl};
 
 #[cfg(not(windows))]
-use mercurial_player::audio::DeviceMonitor;
+use mercurial_player::audio::{DeviceMonitor, ExclusiveSeek, LoopControl, LoudnessScanner, OutputFormat, PlaybackEvents, PlayerStateMachine, ReplayGain, SleepTimer, StretchControl};
 
 #[cfg(windows)]
 use mercurial_player::taskbar;
@@ -41,7 +41,7 @@ use mercurial_player::taskbar;
 use cpal::traits::{DeviceTrait, HostTrait};
 use rodio::{OutputStreamBuilder, Sink};
 use std::sync::atomic::{AtomicBool, AtomicU64};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 
 /// 跨平台的播放器类型别名
 #[cfg(windows)]
@@ -72,16 +72,17 @@ fn main() {
         eprintln!("Failed to initialize config files: {e}");
     }
 
-    // 从配置加载独占模式设置
-    let exclusive_mode_enabled = config_manager
+    // 从配置加载音频设置（独占模式、交叉淡化、回放增益）和播放历史设置
+    let (audio_config, history_config) = config_manager
         .load_config()
-        .map(|c| c.audio.exclusive_mode)
-        .unwrap_or(false);
+        .map(|c| (c.audio, c.history))
+        .unwrap_or_default();
+    let exclusive_mode_enabled = audio_config.exclusive_mode;
 
     println!("Loaded exclusive mode from config: {exclusive_mode_enabled}");
 
     // 根据独占模式设置创建播放器
-    let (sink, wasapi_player) = {
+    let (sink, wasapi_player, output_format) = {
         if exclusive_mode_enabled {
             create_exclusive_mode_player(&device_name)
         } else {
@@ -89,12 +90,26 @@ fn main() {
         }
     };
 
+    let config_dir = config_manager.get_config_directory().to_string();
+    media::CoverCache::init(&config_dir);
+    let player_state = PlayerStateMachine::new();
+    player_state.set_output_format(output_format);
+
     // 创建应用程序状态
     let app_state = AppState {
         player: PlayerState {
             sink: Arc::new(Mutex::new(sink)),
             current_source: Arc::new(Mutex::new(None)),
             current_path: Arc::new(Mutex::new(None)),
+            player_state: Arc::new(player_state),
+            events: PlaybackEvents::new(),
+            next_track: Arc::new(Mutex::new(None)),
+            chain_format: Arc::new(Mutex::new(None)),
+            crossfade: Arc::new(RwLock::new(audio_config.crossfade)),
+            replay_gain: Arc::new(ReplayGain::new(audio_config.replay_gain)),
+            stretch: StretchControl::new(audio_config.pitch_shift),
+            looping: Arc::new(LoopControl::new(audio_config.looping)),
+            sleep_timer: Arc::new(SleepTimer::new()),
             target_volume: Arc::new(Mutex::new(1.0)),
             current_device_name: Arc::new(Mutex::new(device_name.clone())),
             exclusive_mode: Arc::new(Mutex::new(
@@ -117,6 +132,7 @@ fn main() {
                     Arc::new(Mutex::new(None))
                 }
             },
+            exclusive_seek: Arc::new(Mutex::new(ExclusiveSeek::default())),
             decode_thread_stop: Arc::new(AtomicBool::new(false)),
             decode_thread_id: Arc::new(AtomicU64::new(0)),
             equalizer: Arc::new(Mutex::new(Equalizer::new(48000, 2))),
@@ -126,10 +142,22 @@ fn main() {
         },
         config_manager,
         equalizer: GlobalEqualizer::new(),
+        loudness_scanner: Arc::new(LoudnessScanner::new()),
+        queue: Arc::new(Mutex::new(queue::PlayQueue::new())),
+        library: Arc::new(media::LibraryIndex::open(&config_dir)),
+        library_scanner: Arc::new(media::LibraryScanner::new()),
+        library_watcher: media::LibraryWatcher::new(),
+        history: Arc::new(history::PlayHistory::open(&config_dir)),
+        history_config: Arc::new(RwLock::new(history_config)),
+        playlists: playlists::PlaylistStore::open(&config_dir),
     };
 
     tauri::Builder::default()
         .manage(app_state)
+        // 封面缓存协议，缩略图生成较慢，在固定大小的线程池中响应
+        .register_asynchronous_uri_scheme_protocol("cover", |_ctx, request, responder| {
+            media::cover_cache::spawn_request(request, move |response| responder.respond(response));
+        })
         .setup(|app| {
             use tauri::Manager;
 
@@ -147,6 +175,19 @@ fn main() {
                 println!("Device monitor started");
             }
 
+            // 启动播放事件线程（处理音频线程发出的音轨结束与切换）
+            {
+                let state: tauri::State<AppState> = app.state();
+                state.player.events.start(app.handle().clone());
+            }
+
+            // 监听音乐目录变化
+            {
+                let state: tauri::State<AppState> = app.state();
+                let music_directories = state.config_manager.load_config().map(|c| c.music_directories).unwrap_or_default();
+                state.library_watcher.start(app.handle().clone(), music_directories);
+            }
+
             // 初始化Windows任务栏缩略图工具栏
             #[cfg(windows)]
             {
@@ -199,10 +240,21 @@ fn main() {
             media::commands::write_lyrics_file,
             media::commands::get_all_audio_files,
             media::commands::check_file_exists,
+            // 媒体库索引命令
+            media::commands::rescan_library,
+            media::commands::start_library_scan,
+            media::commands::cancel_scan,
+            media::commands::get_library_playlists,
+            media::commands::get_library_tracks,
             // 元数据命令
             media::commands::get_track_metadata,
             media::commands::get_tracks_metadata_batch,
+            media::commands::get_track_pictures,
             media::commands::extract_cover,
+            media::commands::get_cover_palette,
+            media::commands::get_track_palette,
+            media::commands::write_track_tags,
+            media::commands::write_tracks_tags,
             // 网易云音乐API命令
             media::commands::netease_search_songs,
             media::commands::netease_get_lyrics,
@@ -212,7 +264,66 @@ fn main() {
             audio::commands::resume_track,
             audio::commands::set_volume,
             audio::commands::get_playback_status,
+            audio::commands::get_player_state,
+            audio::commands::get_decode_buffer_stats,
             audio::commands::seek_track,
+            audio::commands::set_next_track,
+            audio::commands::clear_next_track,
+            audio::commands::get_next_track,
+            audio::commands::get_crossfade_settings,
+            audio::commands::set_crossfade_settings,
+            audio::commands::get_replay_gain_settings,
+            audio::commands::set_replay_gain_settings,
+            audio::commands::get_playback_rate,
+            audio::commands::set_playback_rate,
+            audio::commands::get_pitch_shift_settings,
+            audio::commands::set_pitch_shift,
+            audio::commands::set_track_pitch_shift,
+            audio::commands::set_loop_region,
+            audio::commands::clear_loop_region,
+            audio::commands::get_loop_region,
+            audio::commands::get_loop_settings,
+            audio::commands::set_loop_settings,
+            // 睡眠定时器命令
+            audio::commands::set_sleep_timer,
+            audio::commands::extend_sleep_timer,
+            audio::commands::cancel_sleep_timer,
+            audio::commands::get_sleep_timer,
+            audio::commands::scan_loudness,
+            audio::commands::cancel_loudness_scan,
+            // 播放队列命令
+            queue::commands::get_play_queue,
+            queue::commands::queue_enqueue,
+            queue::commands::queue_insert,
+            queue::commands::queue_move,
+            queue::commands::queue_remove,
+            queue::commands::queue_clear,
+            queue::commands::queue_replace,
+            queue::commands::queue_play,
+            queue::commands::queue_next,
+            queue::commands::queue_previous,
+            queue::commands::queue_set_shuffle,
+            queue::commands::queue_set_repeat,
+            // 用户播放列表命令
+            playlists::commands::list_user_playlists,
+            playlists::commands::get_user_playlist,
+            playlists::commands::create_user_playlist,
+            playlists::commands::rename_user_playlist,
+            playlists::commands::duplicate_user_playlist,
+            playlists::commands::delete_user_playlist,
+            playlists::commands::reorder_user_playlists,
+            playlists::commands::add_to_user_playlist,
+            playlists::commands::remove_from_user_playlist,
+            playlists::commands::move_in_user_playlist,
+            playlists::commands::import_user_playlist,
+            playlists::commands::export_user_playlist,
+            // 播放历史命令
+            history::commands::get_play_history,
+            history::commands::get_track_play_stats,
+            history::commands::get_top_tracks,
+            history::commands::get_top_artists,
+            history::commands::get_top_albums,
+            history::commands::export_play_history,
             audio::commands::is_track_finished,
             audio::commands::get_waveform_data,
             audio::commands::get_spectrum_data,
@@ -265,26 +376,30 @@ fn main() {
             plugins::commands::open_screenshots_directory,
             // 任务栏命令（Windows Only）
             #[cfg(windows)]
-            taskbar::commands::update_taskbar_state,
-            #[cfg(windows)]
             taskbar::commands::set_taskbar_stopped,
             // 自动更新命令
             update::commands::get_app_version,
             update::commands::download_and_install_update,
             update::commands::run_installer,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                history::on_exit(app);
+            }
+        });
 }
 
 /// 创建独占模式播放器
 #[cfg(windows)]
-fn create_exclusive_mode_player(device_name: &str) -> (Sink, Option<PlatformPlayer>) {
+fn create_exclusive_mode_player(device_name: &str) -> (Sink, Option<PlatformPlayer>, OutputFormat) {
     println!("Starting in WASAPI exclusive mode");
 
     // 创建一个空的rodio sink
     let stream = OutputStreamBuilder::open_default_stream().expect("Failed to create default output stream");
     let sink = Sink::connect_new(stream.mixer());
+    let shared_format = shared_output_format(&stream);
     Box::leak(Box::new(stream));
 
     // 创建 WASAPI 独占播放器
@@ -294,24 +409,26 @@ fn create_exclusive_mode_player(device_name: &str) -> (Sink, Option<PlatformPlay
             println!(
                 "WASAPI Exclusive initialized: {actual_name} @ {sample_rate}Hz, {channels} channels"
             );
-            (sink, Some(wasapi_playback))
+            let output_format = OutputFormat { sample_rate, channels, exclusive: true };
+            (sink, Some(wasapi_playback), output_format)
         }
         Err(e) => {
             eprintln!("Failed to initialize WASAPI exclusive mode: {e}");
             eprintln!("Falling back to shared mode");
-            (sink, None)
+            (sink, None, shared_format)
         }
     }
 }
 
 /// 创建独占模式播放器（非Windows平台回退到共享模式）
 #[cfg(not(windows))]
-fn create_exclusive_mode_player(_device_name: &str) -> (Sink, Option<PlatformPlayer>) {
+fn create_exclusive_mode_player(_device_name: &str) -> (Sink, Option<PlatformPlayer>, OutputFormat) {
     println!("Exclusive mode is only supported on Windows, falling back to shared mode");
     let stream = OutputStreamBuilder::open_default_stream().expect("Failed to create default output stream");
     let sink = Sink::connect_new(stream.mixer());
+    let output_format = shared_output_format(&stream);
     Box::leak(Box::new(stream));
-    (sink, None)
+    (sink, None, output_format)
 }
 
 /// 设置任务栏按钮点击钩子
@@ -351,7 +468,9 @@ fn setup_taskbar_hook(hwnd: isize, app_handle: tauri::AppHandle) {
                         0 => {
                             // BTN_PREVIOUS
                             println!("Taskbar: Previous button clicked");
-                            let _ = app.emit("taskbar-previous", ());
+                            if !queue::skip_from_taskbar(app, false) {
+                                let _ = app.emit("taskbar-previous", ());
+                            }
                         }
                         1 => {
                             // BTN_PLAY_PAUSE
@@ -361,7 +480,9 @@ fn setup_taskbar_hook(hwnd: isize, app_handle: tauri::AppHandle) {
                         2 => {
                             // BTN_NEXT
                             println!("Taskbar: Next button clicked");
-                            let _ = app.emit("taskbar-next", ());
+                            if !queue::skip_from_taskbar(app, true) {
+                                let _ = app.emit("taskbar-next", ());
+                            }
                         }
                         _ => {}
                     }
@@ -391,7 +512,7 @@ fn setup_taskbar_hook(hwnd: isize, app_handle: tauri::AppHandle) {
 }
 
 /// 创建共享模式播放器
-fn create_shared_mode_player(device: &cpal::Device) -> (Sink, Option<PlatformPlayer>) {
+fn create_shared_mode_player(device: &cpal::Device) -> (Sink, Option<PlatformPlayer>, OutputFormat) {
     println!("Starting in shared mode");
 
     // 从选定的设备创建音频输出流
@@ -401,9 +522,16 @@ fn create_shared_mode_player(device: &cpal::Device) -> (Sink, Option<PlatformPla
         .expect("Failed to open output stream from device");
 
     let sink = Sink::connect_new(stream.mixer());
-    
+    let output_format = shared_output_format(&stream);
+
     // 保持流的存活
     Box::leak(Box::new(stream));
 
-    (sink, None)
+    (sink, None, output_format)
+}
+
+/// 共享模式输出流的格式
+fn shared_output_format(stream: &rodio::OutputStream) -> OutputFormat {
+    let config = stream.config();
+    OutputFormat { sample_rate: config.sample_rate(), channels: config.channel_count(), exclusive: false }
 }
@@ -0,0 +1,178 @@
+//! 封面查找模块
+//!
+//! 按配置的优先级为音轨选择封面：内嵌正面封面、文件夹中的封面图片（如 `cover.jpg`）、
+//! 任意内嵌图片。文件夹图片在音频文件所在文件夹及若干层上级文件夹中按文件名模式查找，
+//! 选中的图片都保存到封面缓存。
+
+use super::cover_cache::CoverCache;
+use crate::config::{CoverConfig, CoverSource};
+use glob::{MatchOptions, Pattern};
+use lofty::picture::{Picture, PictureType};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 文件夹封面支持的图片扩展名
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif", "bmp"];
+
+/// 文件名匹配选项：不区分大小写
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// 图片类型（lofty 的图片类型按用途归类）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CoverPictureType {
+    Front,
+    Back,
+    /// 艺术家、乐队、指挥、作曲等人物图片
+    Artist,
+    /// 介质（CD 盘面等）
+    Media,
+    /// 内页
+    Leaflet,
+    Other,
+}
+
+impl CoverPictureType {
+    #[must_use]
+    pub const fn of(pic_type: PictureType) -> Self {
+        match pic_type {
+            PictureType::CoverFront => Self::Front,
+            PictureType::CoverBack => Self::Back,
+            PictureType::LeadArtist
+            | PictureType::Artist
+            | PictureType::Band
+            | PictureType::Conductor
+            | PictureType::Composer
+            | PictureType::Lyricist => Self::Artist,
+            PictureType::Media => Self::Media,
+            PictureType::Leaflet => Self::Leaflet,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// 音轨中的一张内嵌图片
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackPicture {
+    pub picture_type: CoverPictureType,
+    pub mime_type: Option<String>,
+    pub description: Option<String>,
+    /// 封面缓存中的封面 ID
+    pub cover_id: String,
+}
+
+impl TrackPicture {
+    /// 保存图片到封面缓存，缓存不可用时返回 None
+    #[must_use]
+    pub fn of(picture: &Picture) -> Option<Self> {
+        Some(Self {
+            picture_type: CoverPictureType::of(picture.pic_type()),
+            mime_type: picture.mime_type().map(|mime| mime.as_str().to_string()),
+            description: picture.description().map(String::from),
+            cover_id: cache_picture(picture)?,
+        })
+    }
+}
+
+/// 由 [`CoverConfig`] 编译的封面查找规则
+#[derive(Debug)]
+pub struct CoverRules {
+    patterns: Vec<Pattern>,
+    parent_levels: usize,
+    priority: Vec<CoverSource>,
+}
+
+impl CoverRules {
+    #[must_use]
+    pub fn new(config: &CoverConfig) -> Self {
+        let patterns = config
+            .folder_patterns
+            .iter()
+            .filter_map(|pattern| {
+                Pattern::new(pattern.trim())
+                    .map_err(|e| eprintln!("Invalid cover pattern '{pattern}': {e}"))
+                    .ok()
+            })
+            .collect();
+        Self { patterns, parent_levels: config.parent_levels as usize, priority: config.priority.clone() }
+    }
+
+    /// 不查找封面的规则（只需要文字标签时使用）
+    #[must_use]
+    pub const fn disabled() -> Self {
+        Self { patterns: Vec::new(), parent_levels: 0, priority: Vec::new() }
+    }
+
+    /// 按优先级选择封面并保存到封面缓存，返回封面 ID
+    #[must_use]
+    pub fn resolve(&self, audio_path: &Path, pictures: &[Picture]) -> Option<String> {
+        self.priority.iter().find_map(|source| match source {
+            CoverSource::EmbeddedFront => pictures
+                .iter()
+                .find(|picture| picture.pic_type() == PictureType::CoverFront)
+                .and_then(cache_picture),
+            CoverSource::FolderFile => self.find_folder_cover(audio_path).and_then(|path| cache_file(&path)),
+            CoverSource::EmbeddedAny => pictures.first().and_then(cache_picture),
+        })
+    }
+
+    /// 在音频文件所在文件夹及上级文件夹中查找封面图片
+    ///
+    /// 近的文件夹优先；同一文件夹中靠前的文件名模式优先，同一模式按文件名排序。
+    #[must_use]
+    pub fn find_folder_cover(&self, audio_path: &Path) -> Option<PathBuf> {
+        if self.patterns.is_empty() {
+            return None;
+        }
+        audio_path
+            .ancestors()
+            .skip(1)
+            .take(self.parent_levels + 1)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .find_map(|dir| self.match_in_dir(dir))
+    }
+
+    fn match_in_dir(&self, dir: &Path) -> Option<PathBuf> {
+        let mut images: Vec<(String, PathBuf)> = fs::read_dir(dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| is_image_path(path) && path.is_file())
+            .filter_map(|path| Some((path.file_name()?.to_string_lossy().to_string(), path)))
+            .collect();
+        images.sort();
+        self.patterns.iter().find_map(|pattern| {
+            images
+                .iter()
+                .find(|(name, _)| pattern.matches_with(name, MATCH_OPTIONS))
+                .map(|(_, path)| path.clone())
+        })
+    }
+}
+
+fn is_image_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+/// 把内嵌图片保存到封面缓存，失败时只记录日志（不影响元数据读取）
+fn cache_picture(picture: &Picture) -> Option<String> {
+    CoverCache::global()?
+        .store(picture.data())
+        .map_err(|e| eprintln!("Failed to cache cover: {e}"))
+        .ok()
+}
+
+fn cache_file(path: &Path) -> Option<String> {
+    CoverCache::global()?
+        .store_file(path)
+        .map_err(|e| eprintln!("Failed to cache cover: {e}"))
+        .ok()
+}
@@ -6,21 +6,29 @@ use super::filesystem::{
     check_file_exists_internal, get_all_audio_files_from_dirs, get_audio_files_from_dir, read_dir,
     read_lyrics_file_internal, write_lyrics_file_internal,
 };
-use super::metadata::{Playlist, TrackMetadata, get_track_metadata_internal, extract_cover_internal};
+use super::library::{LibraryPage, LibraryPlaylist, LibraryScanSummary};
+use super::artwork::{CoverPictureType, CoverRules, TrackPicture};
+use super::cover_cache::CoverCache;
+use super::metadata::{Playlist, TrackMetadata, get_track_metadata_internal, get_track_pictures_internal, extract_cover_internal};
 use super::netease;
+use super::palette::{cover_palette, CoverPalette};
+use super::tag_editor::{write_tags, write_tags_batch, TagChanges, TagWriteOptions, TagWriteResult};
+use crate::error::{AppError, AppResult, ErrorCode};
 use crate::AppState;
-use tauri::{State, command};
+use std::sync::Arc;
+use tauri::{AppHandle, State, command};
 
 /// 读取指定目录中的子目录列表
 #[command]
-pub fn read_directory(path: String) -> Result<Vec<String>, String> {
+pub fn read_directory(path: String) -> AppResult<Vec<String>> {
     read_dir(&path)
 }
 
 /// 获取指定目录中的所有音频文件，并创建播放列表
 #[command]
-pub fn get_audio_files(path: String) -> Result<Playlist, String> {
-    get_audio_files_from_dir(&path)
+pub fn get_audio_files(state: State<AppState>, path: String) -> AppResult<Playlist> {
+    let config = state.config_manager.load_config()?;
+    get_audio_files_from_dir(&path, &config)
 }
 
 /// 获取多个目录中的所有音频文件，并创建播放列表
@@ -28,43 +36,94 @@ pub fn get_audio_files(path: String) -> Result<Playlist, String> {
 pub fn get_all_audio_files(
     state: State<AppState>,
     paths: Vec<String>,
-) -> Result<Vec<Playlist>, String> {
+) -> AppResult<Vec<Playlist>> {
     let config = state.config_manager.load_config()?;
     get_all_audio_files_from_dirs(&paths, &config)
 }
 
+/// 增量扫描音乐目录并更新媒体库索引（只解析新增或变化的文件）
+#[command]
+pub fn rescan_library(state: State<AppState>, paths: Vec<String>) -> AppResult<LibraryScanSummary> {
+    let config = state.config_manager.load_config()?;
+    state.library.rescan(&paths, &config)
+}
+
+/// 在后台增量扫描音乐目录，返回扫描 ID
+///
+/// 进度通过 `library-scan-progress` 事件发送，音轨按批通过 `library-scan-batch` 事件发送，
+/// 结束后发送 `library-scan-complete` 事件。
+#[command]
+pub fn start_library_scan(app: AppHandle, state: State<AppState>, paths: Vec<String>) -> AppResult<u64> {
+    let config = state.config_manager.load_config()?;
+    state.library_scanner.start(app, Arc::clone(&state.library), paths, config)
+}
+
+/// 取消正在运行的媒体库扫描（已解析的文件仍写入索引）
+#[command]
+pub fn cancel_scan(state: State<AppState>, scan_id: Option<u64>) -> AppResult<bool> {
+    Ok(state.library_scanner.cancel(scan_id))
+}
+
+/// 获取媒体库中的播放列表（不含音轨）
+#[command]
+pub fn get_library_playlists(state: State<AppState>, paths: Vec<String>) -> AppResult<Vec<LibraryPlaylist>> {
+    let config = state.config_manager.load_config()?;
+    Ok(state.library.playlists(&paths, &config))
+}
+
+/// 分页获取媒体库目录中的音轨
+#[command]
+pub fn get_library_tracks(
+    state: State<AppState>,
+    folder: String,
+    recursive: bool,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> AppResult<LibraryPage> {
+    Ok(state.library.tracks(&folder, recursive, offset.unwrap_or(0), limit.unwrap_or(500)))
+}
+
 /// 检查文件是否存在
 #[command]
-pub fn check_file_exists(path: String) -> Result<bool, String> {
+pub fn check_file_exists(path: String) -> AppResult<bool> {
     Ok(check_file_exists_internal(&path))
 }
 
 /// 读取歌词文件内容
 #[command]
-pub fn read_lyrics_file(path: String) -> Result<String, String> {
+pub fn read_lyrics_file(path: String) -> AppResult<String> {
     read_lyrics_file_internal(&path)
 }
 
 /// 写入歌词文件内容
 #[command]
-pub fn write_lyrics_file(path: String, content: String) -> Result<(), String> {
+pub fn write_lyrics_file(path: String, content: String) -> AppResult<()> {
     write_lyrics_file_internal(&path, &content)
 }
 
 /// 获取音轨的元数据信息
 #[command]
-pub fn get_track_metadata(path: String) -> Result<TrackMetadata, String> {
-    get_track_metadata_internal(&path)
+pub fn get_track_metadata(state: State<AppState>, path: String) -> AppResult<TrackMetadata> {
+    let config = state.config_manager.load_config()?;
+    get_track_metadata_internal(&path, &CoverRules::new(&config.cover))
 }
 
 /// 批量获取多个音轨的元数据信息
 /// 返回成功获取的元数据列表，失败的文件会被跳过
 #[command]
-pub fn get_tracks_metadata_batch(paths: Vec<String>) -> Vec<TrackMetadata> {
-    paths
+pub fn get_tracks_metadata_batch(state: State<AppState>, paths: Vec<String>) -> AppResult<Vec<TrackMetadata>> {
+    let config = state.config_manager.load_config()?;
+    let covers = CoverRules::new(&config.cover);
+    Ok(paths
         .into_iter()
-        .filter_map(|path| get_track_metadata_internal(&path).ok())
-        .collect()
+        .filter_map(|path| get_track_metadata_internal(&path, &covers).ok())
+        .collect())
+}
+
+/// 列出音轨的所有内嵌图片（正面、背面、艺术家等）
+#[command]
+pub fn get_track_pictures(path: String) -> AppResult<Vec<TrackPicture>> {
+    get_track_pictures_internal(&path)
 }
 
 /// 搜索网易云音乐歌曲
@@ -73,19 +132,54 @@ pub async fn netease_search_songs(
     keyword: String,
     limit: Option<u32>,
     offset: Option<u32>,
-) -> Result<Vec<netease::SearchSongResult>, String> {
+) -> AppResult<Vec<netease::SearchSongResult>> {
     netease::search_songs(&keyword, limit.unwrap_or(10), offset.unwrap_or(0)).await
 }
 
 /// 获取网易云音乐歌词
 #[command]
-pub async fn netease_get_lyrics(song_id: String) -> Result<netease::LyricsData, String> {
+pub async fn netease_get_lyrics(song_id: String) -> AppResult<netease::LyricsData> {
     netease::get_lyrics(&song_id).await
 }
 
 
-/// 提取音频文件的封面并保存到指定路径
+/// 提取音频文件的封面并保存到指定路径（可指定内嵌图片类型）
+#[command]
+pub fn extract_cover(
+    state: State<AppState>,
+    audio_path: String,
+    output_path: String,
+    picture_type: Option<CoverPictureType>,
+) -> AppResult<String> {
+    let config = state.config_manager.load_config()?;
+    extract_cover_internal(&audio_path, &output_path, picture_type, &CoverRules::new(&config.cover))
+}
+
+/// 获取封面的主色和对比度满足 WCAG AA 的配色组合（结果缓存在封面缓存目录中）
+#[command]
+pub async fn get_cover_palette(cover_id: String) -> AppResult<CoverPalette> {
+    let cache = CoverCache::global().ok_or_else(|| AppError::new(ErrorCode::Internal, "Cover cache is not initialized"))?;
+    cover_palette(cache, &cover_id)
+}
+
+/// 获取音轨封面的配色，没有封面时返回 None
+#[command]
+pub async fn get_track_palette(state: State<'_, AppState>, path: String) -> AppResult<Option<CoverPalette>> {
+    let config = state.config_manager.load_config()?;
+    let track = get_track_metadata_internal(&path, &CoverRules::new(&config.cover))?;
+    let Some(cover_id) = track.cover_id else { return Ok(None) };
+    let cache = CoverCache::global().ok_or_else(|| AppError::new(ErrorCode::Internal, "Cover cache is not initialized"))?;
+    cover_palette(cache, &cover_id).map(Some)
+}
+
+/// 修改单个音频文件的标签（`dryRun` 时只返回差异）
+#[command]
+pub fn write_track_tags(path: String, changes: TagChanges, options: Option<TagWriteOptions>) -> AppResult<TagWriteResult> {
+    write_tags(&path, &changes, options.unwrap_or_default())
+}
+
+/// 对多个音频文件应用相同的标签修改，每个文件单独返回结果
 #[command]
-pub fn extract_cover(audio_path: String, output_path: String) -> Result<String, String> {
-    extract_cover_internal(&audio_path, &output_path)
+pub fn write_tracks_tags(paths: Vec<String>, changes: TagChanges, options: Option<TagWriteOptions>) -> AppResult<Vec<TagWriteResult>> {
+    Ok(write_tags_batch(&paths, &changes, options.unwrap_or_default()))
 }
@@ -0,0 +1,237 @@
+//! 封面缓存模块
+//!
+//! 内嵌封面按内容的 MD5 提取到配置目录的 `covers` 目录中，同一张封面只保存一次，
+//! 元数据里只携带封面 ID。缩略图在第一次请求时生成并缓存。
+//!
+//! 前端通过 `cover` 协议读取封面：`cover://localhost/<id>/<size>`
+//! （Windows 上为 `http://cover.localhost/<id>/<size>`），`size` 为像素或 `original`。
+
+use crate::error::{AppError, AppResult, ErrorCode};
+use image::codecs::jpeg::JpegEncoder;
+use image::ImageFormat;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use tauri::http::{header, Request, Response, StatusCode};
+
+/// 预设的缩略图尺寸（最长边像素），请求的尺寸向上取最近的预设值
+pub const COVER_SIZES: [u32; 3] = [64, 256, 1024];
+/// 缩略图 JPEG 质量
+const THUMBNAIL_QUALITY: u8 = 85;
+/// 处理协议请求的线程数（滚动长列表时请求很多，多余的请求排队）
+const REQUEST_THREADS: usize = 4;
+
+static COVER_CACHE: OnceLock<CoverCache> = OnceLock::new();
+/// 协议请求线程池（与媒体库扫描使用的全局线程池分开）
+static REQUEST_POOL: OnceLock<Option<rayon::ThreadPool>> = OnceLock::new();
+/// 临时文件序号（多个线程可能同时写入同一张封面）
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 封面图片数据
+#[derive(Debug, Clone)]
+pub struct CoverImage {
+    pub data: Vec<u8>,
+    pub mime_type: &'static str,
+}
+
+/// 图片文件的大小和修改时间
+type FileStamp = (u64, Option<SystemTime>);
+
+/// 按内容寻址的封面缓存
+#[derive(Debug)]
+pub struct CoverCache {
+    dir: PathBuf,
+    /// 已保存的图片文件（文件夹封面被同一文件夹的所有音轨共用）
+    files: Mutex<HashMap<PathBuf, (FileStamp, String)>>,
+}
+
+impl CoverCache {
+    /// 初始化全局封面缓存（重复调用返回已有实例）
+    pub fn init(config_dir: &str) -> &'static Self {
+        COVER_CACHE.get_or_init(|| Self { dir: Path::new(config_dir).join("covers"), files: Mutex::new(HashMap::new()) })
+    }
+
+    /// 获取全局封面缓存，未初始化时返回 None
+    #[must_use]
+    pub fn global() -> Option<&'static Self> {
+        COVER_CACHE.get()
+    }
+
+    /// 保存封面原图，返回封面 ID（已存在时不重复写入）
+    pub fn store(&self, data: &[u8]) -> AppResult<String> {
+        let id = format!("{:x}", md5::compute(data));
+        let path = self.entry_path(&id, "");
+        if !path.exists() {
+            write_atomically(&path, data)?;
+        }
+        Ok(id)
+    }
+
+    /// 保存图片文件，文件未变化时直接返回上次的封面 ID
+    pub fn store_file(&self, path: &Path) -> AppResult<String> {
+        let metadata = fs::metadata(path).map_err(|e| AppError::from(e).with_path(path))?;
+        let stamp = (metadata.len(), metadata.modified().ok());
+        if let Some((cached, id)) = self.files.lock().unwrap().get(path) {
+            if *cached == stamp && self.entry_path(id, "").exists() {
+                return Ok(id.clone());
+            }
+        }
+        let data = fs::read(path).map_err(|e| AppError::from(e).with_path(path))?;
+        let id = self.store(&data)?;
+        self.files.lock().unwrap().insert(path.to_path_buf(), (stamp, id.clone()));
+        Ok(id)
+    }
+
+    /// 封面原图的缓存路径
+    pub fn original_path(&self, id: &str) -> AppResult<PathBuf> {
+        validate_id(id)?;
+        let path = self.entry_path(id, "");
+        if !path.is_file() {
+            return Err(AppError::new(ErrorCode::NotFound, "Cover not found in cache").with_context("id", id));
+        }
+        Ok(path)
+    }
+
+    /// 读取封面，`size` 为 None 时返回原图
+    ///
+    /// 缩略图不会放大：原图不超过目标尺寸时直接返回原图。
+    pub fn load(&self, id: &str, size: Option<u32>) -> AppResult<CoverImage> {
+        let original_path = self.original_path(id)?;
+        let original = fs::read(&original_path).map_err(|e| AppError::from(e).with_path(&original_path))?;
+        let Some(size) = size.map(thumbnail_size) else {
+            return Ok(CoverImage { mime_type: mime_type_of(&original), data: original });
+        };
+
+        let thumbnail_path = self.entry_path(id, &format!("_{size}.jpg"));
+        if let Ok(data) = fs::read(&thumbnail_path) {
+            return Ok(CoverImage { data, mime_type: "image/jpeg" });
+        }
+
+        let image = image::load_from_memory(&original).map_err(|e| {
+            AppError::new(ErrorCode::UnsupportedFormat, "Failed to decode cover image").with_source(e).with_context("id", id)
+        })?;
+        if image.width() <= size && image.height() <= size {
+            return Ok(CoverImage { mime_type: mime_type_of(&original), data: original });
+        }
+
+        let mut data = Vec::new();
+        let thumbnail = image.thumbnail(size, size).into_rgb8();
+        thumbnail
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut data, THUMBNAIL_QUALITY))
+            .map_err(|e| AppError::new(ErrorCode::Internal, "Failed to encode cover thumbnail").with_source(e).with_context("id", id))?;
+        if let Err(e) = write_atomically(&thumbnail_path, &data) {
+            eprintln!("Failed to cache cover thumbnail: {e}");
+        }
+        Ok(CoverImage { data, mime_type: "image/jpeg" })
+    }
+
+    /// 封面附属缓存文件（如配色）的路径，与封面保存在同一目录
+    pub fn sidecar_path(&self, id: &str, suffix: &str) -> AppResult<PathBuf> {
+        validate_id(id)?;
+        Ok(self.entry_path(id, suffix))
+    }
+
+    /// 缓存文件路径：`covers/<ID 前两位>/<ID><suffix>`
+    fn entry_path(&self, id: &str, suffix: &str) -> PathBuf {
+        self.dir.join(&id[..2]).join(format!("{id}{suffix}"))
+    }
+}
+
+/// 在协议请求线程池中处理请求，完成后调用 `respond`
+pub fn spawn_request(request: Request<Vec<u8>>, respond: impl FnOnce(Response<Vec<u8>>) + Send + 'static) {
+    let pool = REQUEST_POOL.get_or_init(|| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(REQUEST_THREADS)
+            .thread_name(|index| format!("cover-{index}"))
+            .build()
+            .map_err(|e| eprintln!("Failed to create cover request pool: {e}"))
+            .ok()
+    });
+    let task = move || respond(handle_request(&request));
+    match pool {
+        Some(pool) => pool.spawn(task),
+        None => rayon::spawn(task),
+    }
+}
+
+/// 处理 `cover` 协议请求，路径格式为 `/<id>/<size>`
+pub fn handle_request(request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let result = CoverCache::global()
+        .ok_or_else(|| AppError::new(ErrorCode::Internal, "Cover cache is not initialized"))
+        .and_then(|cache| {
+            let (id, size) = parse_request_path(request.uri().path())?;
+            cache.load(&id, size)
+        });
+
+    match result {
+        Ok(cover) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, cover.mime_type)
+            .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable")
+            .body(cover.data),
+        Err(e) => {
+            let status = match e.code() {
+                ErrorCode::NotFound | ErrorCode::InvalidArgument => StatusCode::NOT_FOUND,
+                _ => {
+                    eprintln!("Failed to serve cover {}: {e}", request.uri());
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            };
+            Response::builder().status(status).body(Vec::new())
+        }
+    }
+    .unwrap_or_else(|_| Response::new(Vec::new()))
+}
+
+/// 解析请求路径（前端用 `convertFileSrc` 生成地址时 `/` 会被编码为 `%2F`）
+fn parse_request_path(path: &str) -> AppResult<(String, Option<u32>)> {
+    let path = urlencoding::decode(path).map_err(|_| AppError::invalid_argument("Invalid cover path"))?;
+    let mut parts = path.trim_matches('/').split('/');
+    let id = parts.next().unwrap_or_default().to_ascii_lowercase();
+    validate_id(&id)?;
+    let size = match parts.next() {
+        None | Some("" | "original") => None,
+        Some(size) => Some(size.parse::<u32>().map_err(|_| AppError::invalid_argument("Invalid cover size").with_context("size", size))?),
+    };
+    Ok((id, size))
+}
+
+/// 封面 ID 必须是 32 位十六进制（同时防止路径穿越）
+fn validate_id(id: &str) -> AppResult<()> {
+    if id.len() == 32 && id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(AppError::invalid_argument("Invalid cover id").with_context("id", id))
+    }
+}
+
+/// 取不小于请求尺寸的最小预设尺寸
+fn thumbnail_size(requested: u32) -> u32 {
+    COVER_SIZES.into_iter().find(|&size| size >= requested).unwrap_or(COVER_SIZES[COVER_SIZES.len() - 1])
+}
+
+fn mime_type_of(data: &[u8]) -> &'static str {
+    match image::guess_format(data) {
+        Ok(ImageFormat::Png) => "image/png",
+        Ok(ImageFormat::Gif) => "image/gif",
+        Ok(ImageFormat::WebP) => "image/webp",
+        Ok(ImageFormat::Bmp) => "image/bmp",
+        _ => "image/jpeg",
+    }
+}
+
+/// 先写入同目录的临时文件再重命名，读取方不会看到写了一半的文件
+pub(crate) fn write_atomically(path: &Path, data: &[u8]) -> AppResult<()> {
+    let dir = path.parent().unwrap_or(path);
+    fs::create_dir_all(dir).map_err(|e| AppError::from(e).context_message("Failed to create directory").with_path(dir))?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!("{name}.{}.tmp", TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+    fs::write(&temp, data).map_err(|e| AppError::from(e).with_path(&temp))?;
+    fs::rename(&temp, path).map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        AppError::from(e).context_message("Failed to replace file").with_path(path)
+    })
+}
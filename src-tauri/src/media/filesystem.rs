@@ -1,52 +1,71 @@
 //! 文件系统操作模块
 //!
-//! 提供目录读取、文件检查等功能。
+//! 提供目录读取、文件检查等功能。目录扫描统一经过 [`ScanPolicy`]：
+//! 跳过隐藏和黑名单文件夹、按 glob 包含或排除、可选跟随符号链接，
+//! 并读取各级目录中的 `.mercurialignore` 覆盖全局规则。
 
+use super::artwork::CoverRules;
 use super::metadata::{get_track_metadata_internal, Playlist};
-use crate::config::AppConfig;
+use crate::config::{AppConfig, DirectoryScanConfig};
+use crate::error::{AppError, AppResult, ErrorCode};
+use glob::{MatchOptions, Pattern};
 use rayon::prelude::*;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::{DirEntry, WalkDir};
 
 /// 支持的音频文件扩展名
 pub const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "wav", "ogg", "m4a", "aac"];
 
+/// 目录内的扫描规则文件名
+pub const IGNORE_FILE_NAME: &str = ".mercurialignore";
+
+/// glob 匹配选项：不区分大小写，`*` 不跨越目录
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
 /// 读取指定目录中的子目录列表
-pub fn read_dir(path: &str) -> Result<Vec<String>, String> {
+pub fn read_dir(path: &str) -> AppResult<Vec<String>> {
     let dir = Path::new(path);
-    if !dir.is_dir() {
-        return Err("Provided path is not a directory".to_string());
-    }
+    ensure_dir(dir)?;
 
     fs::read_dir(dir)
-        .map_err(|e| e.to_string())?
+        .map_err(|e| AppError::from(e).with_path(dir))?
         .filter_map(Result::ok)
         .filter(|entry| entry.path().is_dir())
         .map(|entry| entry.path().to_str().map(String::from))
         .collect::<Option<Vec<_>>>()
-        .ok_or_else(|| "Failed to convert paths".to_string())
+        .ok_or_else(|| AppError::new(ErrorCode::InvalidPath, "Failed to convert paths").with_path(dir))
+}
+
+/// 确认路径是已存在的目录
+fn ensure_dir(dir: &Path) -> AppResult<()> {
+    if dir.is_dir() {
+        Ok(())
+    } else if dir.exists() {
+        Err(AppError::new(ErrorCode::InvalidPath, "Provided path is not a directory").with_path(dir))
+    } else {
+        Err(AppError::new(ErrorCode::FileNotFound, "Directory not found").with_path(dir))
+    }
 }
 
 /// 获取指定目录中的所有音频文件，并创建播放列表
-pub fn get_audio_files_from_dir(path: &str) -> Result<Playlist, String> {
+pub fn get_audio_files_from_dir(path: &str, config: &AppConfig) -> AppResult<Playlist> {
     let dir = Path::new(path);
-    if !dir.is_dir() {
-        return Err("Provided path is not a directory".to_string());
-    }
+    ensure_dir(dir)?;
 
-    let audio_files: Vec<_> = WalkDir::new(dir)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(is_audio_file)
-        .collect();
+    let audio_files = ScanPolicy::new(&config.directory_scan).walk(dir, dir, usize::MAX);
+    let covers = CoverRules::new(&config.cover);
 
     let tracks: Vec<_> = audio_files
         .par_iter()
         .filter_map(|entry| {
             let file_path = entry.path().to_string_lossy().to_string();
-            get_track_metadata_internal(&file_path)
+            get_track_metadata_internal(&file_path, &covers)
                 .map_err(|e| eprintln!("Failed to get metadata for file '{file_path}': {e}"))
                 .ok()
         })
@@ -63,7 +82,7 @@ pub fn get_audio_files_from_dir(path: &str) -> Result<Playlist, String> {
 }
 
 /// 获取多个目录中的所有音频文件，并创建播放列表
-pub fn get_all_audio_files_from_dirs(paths: &[String], config: &AppConfig) -> Result<Vec<Playlist>, String> {
+pub fn get_all_audio_files_from_dirs(paths: &[String], config: &AppConfig) -> AppResult<Vec<Playlist>> {
     let mut all_playlists: Vec<Playlist> = Vec::new();
 
     for path in paths {
@@ -73,10 +92,12 @@ pub fn get_all_audio_files_from_dirs(paths: &[String], config: &AppConfig) -> Re
             continue;
         }
 
+        let policy = ScanPolicy::new(&config.directory_scan);
+        let covers = CoverRules::new(&config.cover);
         if config.directory_scan.enable_subdirectory_scan && config.playlist.folder_based_playlists {
-            let playlists = scan_with_folder_playlists(dir, config.directory_scan.max_depth as usize);
+            let playlists = scan_with_folder_playlists(dir, config.directory_scan.max_depth as usize, &policy, &covers);
             all_playlists.extend(playlists);
-        } else if let Some(playlist) = scan_single_playlist(dir) {
+        } else if let Some(playlist) = scan_single_playlist(dir, &policy, &covers) {
             all_playlists.push(playlist);
         }
     }
@@ -85,13 +106,8 @@ pub fn get_all_audio_files_from_dirs(paths: &[String], config: &AppConfig) -> Re
 }
 
 /// 扫描目录并按文件夹创建播放列表
-fn scan_with_folder_playlists(dir: &Path, max_depth: usize) -> Vec<Playlist> {
-    let audio_files: Vec<_> = WalkDir::new(dir)
-        .max_depth(max_depth)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(is_audio_file)
-        .collect();
+fn scan_with_folder_playlists(dir: &Path, max_depth: usize, policy: &ScanPolicy, covers: &CoverRules) -> Vec<Playlist> {
+    let audio_files = policy.walk(dir, dir, max_depth);
 
     let tracks_with_folders: Vec<_> = audio_files
         .par_iter()
@@ -104,7 +120,7 @@ fn scan_with_folder_playlists(dir: &Path, max_depth: usize) -> Vec<Playlist> {
                 .to_string();
 
             let file_path = entry.path().to_string_lossy().to_string();
-            get_track_metadata_internal(&file_path)
+            get_track_metadata_internal(&file_path, covers)
                 .map(|metadata| (folder_name, metadata))
                 .ok()
         })
@@ -125,22 +141,18 @@ fn scan_with_folder_playlists(dir: &Path, max_depth: usize) -> Vec<Playlist> {
 }
 
 /// 扫描目录创建单个播放列表
-fn scan_single_playlist(dir: &Path) -> Option<Playlist> {
+fn scan_single_playlist(dir: &Path, policy: &ScanPolicy, covers: &CoverRules) -> Option<Playlist> {
     let playlist_name = dir
         .file_name()
         .map_or_else(|| "Unknown".to_string(), |s| s.to_string_lossy().to_string());
 
-    let audio_files: Vec<_> = WalkDir::new(dir)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(is_audio_file)
-        .collect();
+    let audio_files = policy.walk(dir, dir, usize::MAX);
 
     let tracks: Vec<_> = audio_files
         .par_iter()
         .filter_map(|entry| {
             let file_path = entry.path().to_string_lossy().to_string();
-            get_track_metadata_internal(&file_path).ok()
+            get_track_metadata_internal(&file_path, covers).ok()
         })
         .collect();
 
@@ -172,27 +184,372 @@ pub fn check_file_exists_internal(path: &str) -> bool {
 }
 
 /// 读取歌词文件内容
-pub fn read_lyrics_file_internal(path: &str) -> Result<String, String> {
-    fs::read_to_string(path).map_err(|e| e.to_string())
+pub fn read_lyrics_file_internal(path: &str) -> AppResult<String> {
+    fs::read_to_string(path).map_err(|e| AppError::from(e).with_path(path))
 }
 
 /// 写入歌词文件内容
-pub fn write_lyrics_file_internal(path: &str, content: &str) -> Result<(), String> {
+pub fn write_lyrics_file_internal(path: &str, content: &str) -> AppResult<()> {
     // 确保父目录存在
     if let Some(parent) = Path::new(path).parent()
         && !parent.exists()
     {
-        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {e}"))?;
+        fs::create_dir_all(parent).map_err(|e| AppError::from(e).context_message("Failed to create directory").with_path(parent))?;
     }
     
-    fs::write(path, content).map_err(|e| format!("Failed to write file: {e}"))
+    fs::write(path, content).map_err(|e| AppError::from(e).context_message("Failed to write file").with_path(path))
+}
+
+/// 目录扫描策略
+///
+/// 文件夹被排除的条件：隐藏（名称以 `.` 开头，Windows 上还包括隐藏属性）、
+/// 名称匹配黑名单，或匹配排除规则。音频文件还需匹配包含规则（为空时不限制）
+/// 并达到最小文件大小。各级目录中的 `.mercurialignore` 按从外到内的顺序覆盖
+/// 上述结果，`!` 开头的规则可以重新包含被全局规则排除的条目。
+///
+/// 规则语法与 `.gitignore` 相近：不含 `/` 的规则只匹配名称，含 `/` 的规则匹配
+/// 相对于音乐目录（或 `.mercurialignore` 所在目录）的路径，以 `/` 结尾的规则只匹配文件夹。
+pub struct ScanPolicy {
+    ignore_hidden: bool,
+    blacklist: Vec<Pattern>,
+    include: Vec<GlobRule>,
+    exclude: Vec<GlobRule>,
+    follow_symlinks: bool,
+    min_file_size: u64,
+}
+
+impl ScanPolicy {
+    #[must_use]
+    pub fn new(config: &DirectoryScanConfig) -> Self {
+        Self {
+            ignore_hidden: config.ignore_hidden_folders,
+            blacklist: config
+                .folder_blacklist
+                .iter()
+                .filter_map(|name| Pattern::new(name).or_else(|_| Pattern::new(&Pattern::escape(name))).ok())
+                .collect(),
+            include: config.include_patterns.iter().map(String::as_str).filter_map(GlobRule::parse).collect(),
+            exclude: config.exclude_patterns.iter().map(String::as_str).filter_map(GlobRule::parse).collect(),
+            follow_symlinks: config.follow_symlinks,
+            min_file_size: config.min_file_size,
+        }
+    }
+
+    /// 列出 `dir` 中符合策略的音频文件
+    ///
+    /// `dir` 必须是音乐目录 `root` 本身或其子目录，规则按相对 `root` 的路径匹配。
+    /// 跟随符号链接时，指向上级目录的循环链接会被跳过。
+    pub fn walk(&self, root: &Path, dir: &Path, max_depth: usize) -> Vec<DirEntry> {
+        self.walk_with(root, dir, max_depth, |_, _| true)
+    }
+
+    /// 同 [`Self::walk`]，进入每个文件夹时调用 `visit(文件夹, 已发现的文件数)`，返回 false 时停止
+    pub fn walk_with(&self, root: &Path, dir: &Path, max_depth: usize, mut visit: impl FnMut(&Path, usize) -> bool) -> Vec<DirEntry> {
+        let mut ignores = IgnoreFiles::default();
+        let walker = WalkDir::new(dir)
+            .max_depth(max_depth)
+            .follow_links(self.follow_symlinks)
+            .into_iter()
+            .filter_entry(|entry| {
+                if entry.depth() == 0 {
+                    return true;
+                }
+                if entry.path_is_symlink() && !self.follow_symlinks {
+                    return false;
+                }
+                if entry.file_type().is_dir() {
+                    !self.is_excluded(root, entry.path(), true, &mut ignores)
+                } else {
+                    self.accepts_file(root, entry.path(), &mut ignores)
+                }
+            });
+
+        let mut files = Vec::new();
+        for result in walker {
+            match result {
+                Ok(entry) if entry.file_type().is_dir() => {
+                    if !visit(entry.path(), files.len()) {
+                        break;
+                    }
+                }
+                Ok(entry) => {
+                    if entry.file_type().is_file() {
+                        files.push(entry);
+                    }
+                }
+                Err(e) => {
+                    if e.loop_ancestor().is_some() {
+                        eprintln!("Skipping symlink loop: {e}");
+                    }
+                }
+            }
+        }
+        files
+    }
+
+    /// 单个路径是否在扫描范围内（供目录监听器检查变化的文件或文件夹）
+    ///
+    /// 依次检查 `root` 与 `path` 之间的每一级文件夹，`path` 为文件时还检查文件规则。
+    /// 不检查深度限制。
+    pub fn allows(&self, root: &Path, path: &Path) -> bool {
+        let Ok(rel) = path.strip_prefix(root) else { return false };
+        let mut ignores = IgnoreFiles::default();
+        let mut current = root.to_path_buf();
+        for component in rel.components() {
+            current.push(component);
+            if !self.follow_symlinks && current.is_symlink() {
+                return false;
+            }
+            if current.as_path() != path && self.is_excluded(root, &current, true, &mut ignores) {
+                return false;
+            }
+        }
+        if path.is_dir() {
+            path == root || !self.is_excluded(root, path, true, &mut ignores)
+        } else {
+            self.accepts_file(root, path, &mut ignores)
+        }
+    }
+
+    /// 音频文件是否通过所有文件规则
+    fn accepts_file(&self, root: &Path, path: &Path, ignores: &mut IgnoreFiles) -> bool {
+        if !is_audio_path(path) {
+            return false;
+        }
+        if !self.include.is_empty() {
+            let rel = slash_path(path.strip_prefix(root).unwrap_or(path));
+            if !self.include.iter().any(|rule| rule.matches(&rel, false)) {
+                return false;
+            }
+        }
+        !self.is_excluded(root, path, false, ignores)
+            && fs::metadata(path).is_ok_and(|meta| meta.len() >= self.min_file_size)
+    }
+
+    /// 文件或文件夹是否被排除（`path` 位于 `root` 下）
+    fn is_excluded(&self, root: &Path, path: &Path, is_dir: bool, ignores: &mut IgnoreFiles) -> bool {
+        let Ok(rel) = path.strip_prefix(root) else { return false };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut excluded = is_dir
+            && ((self.ignore_hidden && is_hidden(path, &name))
+                || self.blacklist.iter().any(|pattern| pattern.matches_with(&name, MATCH_OPTIONS)));
+        excluded = apply_rules(&self.exclude, &slash_path(rel), is_dir, excluded);
+
+        // 从外到内应用各级 .mercurialignore，越近的规则优先
+        let depth = rel.components().count();
+        let dirs: Vec<&Path> = path.ancestors().skip(1).take(depth).collect();
+        for dir in dirs.into_iter().rev() {
+            let rel = slash_path(path.strip_prefix(dir).unwrap_or(path));
+            excluded = apply_rules(ignores.rules(dir), &rel, is_dir, excluded);
+        }
+        excluded
+    }
+}
+
+/// 单条 glob 规则（与 `.mercurialignore` 中的一行语法相同）
+struct GlobRule {
+    pattern: Pattern,
+    /// 含 `/` 时匹配相对路径，否则只匹配名称
+    anchored: bool,
+    /// 以 `/` 结尾时只匹配文件夹
+    dir_only: bool,
+    /// 以 `!` 开头时表示重新包含
+    negated: bool,
+}
+
+impl GlobRule {
+    /// 解析一行规则，空行和 `#` 注释返回 None
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = line.strip_prefix('!').map_or((false, line), |rest| (true, rest));
+        let (dir_only, line) = line.strip_suffix('/').map_or((false, line), |rest| (true, rest));
+        let anchored = line.contains('/');
+        let line = line.trim_start_matches('/');
+        match Pattern::new(line) {
+            Ok(pattern) => Some(Self { pattern, anchored, dir_only, negated }),
+            Err(e) => {
+                eprintln!("Invalid scan pattern '{line}': {e}");
+                None
+            }
+        }
+    }
+
+    /// `rel` 为以 `/` 分隔的相对路径
+    fn matches(&self, rel: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            self.pattern.matches_with(rel, MATCH_OPTIONS)
+        } else {
+            let name = rel.rsplit('/').next().unwrap_or(rel);
+            self.pattern.matches_with(name, MATCH_OPTIONS)
+        }
+    }
+}
+
+/// 按最后一条匹配的规则决定是否排除，没有匹配时保持原结果
+fn apply_rules(rules: &[GlobRule], rel: &str, is_dir: bool, excluded: bool) -> bool {
+    rules.iter().rev().find(|rule| rule.matches(rel, is_dir)).map_or(excluded, |rule| !rule.negated)
+}
+
+/// 按需读取并缓存各目录的 `.mercurialignore`
+#[derive(Default)]
+struct IgnoreFiles {
+    rules: HashMap<PathBuf, Vec<GlobRule>>,
+}
+
+impl IgnoreFiles {
+    fn rules(&mut self, dir: &Path) -> &[GlobRule] {
+        self.rules.entry(dir.to_path_buf()).or_insert_with(|| {
+            fs::read_to_string(dir.join(IGNORE_FILE_NAME))
+                .map(|content| content.lines().filter_map(GlobRule::parse).collect())
+                .unwrap_or_default()
+        })
+    }
 }
 
-/// 检查是否为音频文件
-fn is_audio_file(entry: &DirEntry) -> bool {
-    entry
-        .path()
-        .extension()
+/// 以 `/` 分隔的相对路径（规则在各平台上写法一致）
+fn slash_path(path: &Path) -> String {
+    path.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+#[cfg(windows)]
+fn is_hidden(path: &Path, name: &str) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    name.starts_with('.') || fs::metadata(path).is_ok_and(|meta| meta.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+}
+
+#[cfg(not(windows))]
+fn is_hidden(_path: &Path, name: &str) -> bool {
+    name.starts_with('.')
+}
+
+/// 按扩展名检查路径是否为音频文件
+fn is_audio_path(path: &Path) -> bool {
+    path.extension()
         .and_then(|ext| ext.to_str())
         .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 测试用临时目录树，离开作用域时删除
+    struct TempTree(PathBuf);
+
+    impl TempTree {
+        fn new(files: &[&str]) -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let root = std::env::temp_dir().join(format!(
+                "mercurial-scan-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::create_dir_all(&root).unwrap();
+            let tree = Self(root);
+            for file in files {
+                tree.write(file, &[0; 16]);
+            }
+            tree
+        }
+
+        fn write(&self, path: &str, content: &[u8]) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        /// 扫描结果（相对路径，已排序）
+        fn scan(&self, config: &DirectoryScanConfig) -> Vec<String> {
+            let mut files: Vec<String> = ScanPolicy::new(config)
+                .walk(&self.0, &self.0, usize::MAX)
+                .iter()
+                .map(|entry| slash_path(entry.path().strip_prefix(&self.0).unwrap()))
+                .collect();
+            files.sort();
+            files
+        }
+    }
+
+    impl Drop for TempTree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_skips_hidden_and_blacklisted_folders() {
+        let tree = TempTree::new(&[
+            "a.mp3",
+            "cover.jpg",
+            "Album/b.flac",
+            ".git/c.mp3",
+            "Album/@eaDir/d.mp3",
+            "#recycle/e.mp3",
+            "TMP/f.mp3",
+        ]);
+        let mut config = DirectoryScanConfig::default();
+        assert_eq!(tree.scan(&config), ["Album/b.flac", "a.mp3"]);
+
+        config.ignore_hidden_folders = false;
+        config.folder_blacklist.clear();
+        assert_eq!(tree.scan(&config).len(), 6);
+    }
+
+    #[test]
+    fn test_include_and_exclude_patterns() {
+        let tree = TempTree::new(&["a.mp3", "b.flac", "Live/c.flac", "Artist/Demos/d.flac", "Artist/e.flac"]);
+        let mut config = DirectoryScanConfig::default();
+        config.include_patterns = vec!["*.FLAC".to_string()];
+        config.exclude_patterns = vec!["Live/".to_string(), "Artist/Demos".to_string()];
+        assert_eq!(tree.scan(&config), ["Artist/e.flac", "b.flac"]);
+    }
+
+    #[test]
+    fn test_min_file_size() {
+        let tree = TempTree::new(&["small.mp3"]);
+        tree.write("large.mp3", &[0; 4096]);
+        let mut config = DirectoryScanConfig::default();
+        config.min_file_size = 1024;
+        assert_eq!(tree.scan(&config), ["large.mp3"]);
+    }
+
+    #[test]
+    fn test_ignore_file_overrides() {
+        let tree = TempTree::new(&["a.mp3", "Album/b.mp3", "Album/skip.mp3", "Album/Bonus/c.mp3", "Album/tmp/d.mp3"]);
+        tree.write("Album/.mercurialignore", b"# comment\nskip.mp3\nBonus/\n!tmp\n");
+        assert_eq!(tree.scan(&DirectoryScanConfig::default()), ["Album/b.mp3", "Album/tmp/d.mp3", "a.mp3"]);
+    }
+
+    #[test]
+    fn test_allows_single_paths() {
+        let tree = TempTree::new(&["Album/a.mp3", ".git/b.mp3", "Album/skip.mp3"]);
+        tree.write("Album/.mercurialignore", b"skip.mp3\n");
+        let policy = ScanPolicy::new(&DirectoryScanConfig::default());
+        assert!(policy.allows(&tree.0, &tree.0.join("Album")));
+        assert!(policy.allows(&tree.0, &tree.0.join("Album/a.mp3")));
+        assert!(!policy.allows(&tree.0, &tree.0.join(".git/b.mp3")));
+        assert!(!policy.allows(&tree.0, &tree.0.join("Album/skip.mp3")));
+        assert!(!policy.allows(&tree.0, &tree.0.join("Album/.mercurialignore")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_symlink_policy_and_loops() {
+        let tree = TempTree::new(&["Album/a.mp3"]);
+        std::os::unix::fs::symlink(tree.0.join("Album"), tree.0.join("Linked")).unwrap();
+        std::os::unix::fs::symlink(&tree.0, tree.0.join("Album/loop")).unwrap();
+        let mut config = DirectoryScanConfig::default();
+        assert_eq!(tree.scan(&config), ["Album/a.mp3"]);
+
+        config.follow_symlinks = true;
+        assert_eq!(tree.scan(&config), ["Album/a.mp3", "Linked/a.mp3"]);
+    }
+}
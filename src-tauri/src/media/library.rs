@@ -0,0 +1,491 @@
+//! 媒体库索引模块
+//!
+//! 扫描结果保存在配置目录的 `library.json` 中，以路径为键记录文件大小、修改时间和标签。
+//! 重新扫描时只解析新增或变化的文件、删除已不存在的条目；启动时直接读取索引，
+//! 音轨按播放列表分页返回，不再一次性传输整个媒体库。
+
+use super::filesystem::ScanPolicy;
+use super::artwork::CoverRules;
+use super::metadata::{read_track_metadata, track_path, TrackMetadata};
+use crate::config::AppConfig;
+use crate::error::{AppError, AppResult, ErrorCode};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::UNIX_EPOCH;
+use walkdir::DirEntry;
+
+/// 索引文件格式版本，不兼容变更时递增（旧版本索引会被丢弃并重新扫描）
+const LIBRARY_VERSION: u32 = 3;
+/// 单页最多返回的音轨数
+pub const LIBRARY_PAGE_MAX: usize = 2000;
+
+/// 索引中的一个文件
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LibraryEntry {
+    size: u64,
+    /// 修改时间（毫秒时间戳）
+    mtime: u64,
+    /// 标签与音频属性（封面只记录封面 ID）
+    track: TrackMetadata,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct LibraryData {
+    version: u32,
+    tracks: BTreeMap<String, LibraryEntry>,
+}
+
+/// 扫描结果统计
+#[derive(Debug, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryScanSummary {
+    /// 扫描后索引中的音轨数
+    pub total: usize,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+    /// 无法解析标签的文件数
+    pub failed: usize,
+}
+
+/// 媒体库播放列表（音轨通过 `get_library_tracks` 分页获取）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryPlaylist {
+    pub name: String,
+    /// 对应的目录
+    pub folder: String,
+    /// 是否包含子目录中的音轨
+    pub recursive: bool,
+    pub track_count: usize,
+}
+
+/// 一页音轨
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryPage {
+    /// 该播放列表的音轨总数
+    pub total: usize,
+    pub offset: usize,
+    pub tracks: Vec<TrackMetadata>,
+}
+
+/// 媒体库变化（`library-changed` 事件负载），路径均为音轨的 `path` 字段
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryDiff {
+    pub added: Vec<TrackMetadata>,
+    pub updated: Vec<TrackMetadata>,
+    pub removed: Vec<String>,
+    /// 移动或重命名的音轨
+    pub renamed: Vec<LibraryRename>,
+}
+
+impl LibraryDiff {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty() && self.renamed.is_empty()
+    }
+}
+
+/// 移动或重命名的音轨
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryRename {
+    /// 原路径
+    pub from: String,
+    /// 新路径下的音轨（标签沿用原条目）
+    pub track: TrackMetadata,
+}
+
+/// 扫描进度观察者（后台扫描任务用它报告进度和检查取消）
+pub trait ScanObserver: Sync {
+    /// 进入一个文件夹，`discovered` 为目前发现的音频文件数
+    fn directory(&self, _dir: &Path, _discovered: usize) {}
+    /// 发现阶段结束，`discovered` 为找到的音频文件数，`total` 为需要解析的文件数
+    fn parsing(&self, _discovered: usize, _total: usize) {}
+    /// 一个文件解析完成
+    fn parsed(&self, _path: &str, _result: &AppResult<TrackMetadata>) {}
+    /// 与单个文件无关的错误（例如音乐目录不可用）
+    fn error(&self, _path: &str, _error: &AppError) {}
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// 不报告进度、不可取消
+impl ScanObserver for () {}
+
+/// 磁盘上的文件状态
+struct FileStamp {
+    path: String,
+    size: u64,
+    mtime: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let meta = fs::metadata(path).ok()?;
+        meta.is_file().then(|| Self { path: path.to_string_lossy().to_string(), size: meta.len(), mtime: mtime_millis(&meta) })
+    }
+
+    fn of_entry(entry: &DirEntry) -> Option<Self> {
+        let meta = entry.metadata().ok()?;
+        Some(Self { path: entry.path().to_string_lossy().to_string(), size: meta.len(), mtime: mtime_millis(&meta) })
+    }
+}
+
+/// 合并解析结果的方式
+enum Merged {
+    Added(TrackMetadata),
+    Updated(TrackMetadata),
+    /// 解析失败，附带被删除的旧条目路径
+    Failed(Option<String>),
+}
+
+/// 持久化的媒体库索引
+pub struct LibraryIndex {
+    path: PathBuf,
+    data: RwLock<LibraryData>,
+}
+
+impl LibraryIndex {
+    /// 打开配置目录中的索引，不存在或无法解析时从空索引开始
+    #[must_use]
+    pub fn open(config_dir: &str) -> Self {
+        let path = Path::new(config_dir).join("library.json");
+        let data = match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<LibraryData>(&content) {
+                Ok(data) if data.version == LIBRARY_VERSION => {
+                    println!("Loaded library index: {} tracks", data.tracks.len());
+                    data
+                }
+                Ok(data) => {
+                    println!("Discarding library index version {}", data.version);
+                    LibraryData::default()
+                }
+                Err(e) => {
+                    eprintln!("Failed to parse library index, starting empty: {e}");
+                    LibraryData::default()
+                }
+            },
+            Err(_) => LibraryData::default(),
+        };
+        Self { path, data: RwLock::new(LibraryData { version: LIBRARY_VERSION, ..data }) }
+    }
+
+    /// 增量扫描音乐目录：只解析新增或大小、修改时间变化的文件，删除已不存在的条目
+    ///
+    /// 暂时无法访问的目录（例如未挂载的移动硬盘）保留原有条目。
+    pub fn rescan(&self, roots: &[String], config: &AppConfig) -> AppResult<LibraryScanSummary> {
+        self.rescan_observed(roots, config, &())
+    }
+
+    /// 同 [`Self::rescan`]，通过 `observer` 报告进度
+    ///
+    /// 取消时已解析的文件仍写入索引，但不删除任何条目（未扫描完的目录无法判断文件是否已删除）。
+    pub fn rescan_observed(&self, roots: &[String], config: &AppConfig, observer: &dyn ScanObserver) -> AppResult<LibraryScanSummary> {
+        let max_depth = scan_depth(config);
+        let policy = ScanPolicy::new(&config.directory_scan);
+        let mut found = Vec::new();
+        let mut scanned_roots = Vec::new();
+        for root in roots {
+            if observer.is_cancelled() {
+                break;
+            }
+            let dir = Path::new(root);
+            if !dir.is_dir() {
+                eprintln!("Music directory unavailable, keeping indexed tracks: {root}");
+                observer.error(root, &AppError::new(ErrorCode::FileNotFound, "Music directory unavailable").with_path(dir));
+                continue;
+            }
+            let discovered = found.len();
+            let files = policy.walk_with(dir, dir, max_depth, |path, count| {
+                observer.directory(path, discovered + count);
+                !observer.is_cancelled()
+            });
+            scanned_roots.push(dir);
+            found.extend(files.into_iter().filter_map(|entry| FileStamp::of_entry(&entry)));
+        }
+
+        let (changed, parsed) = self.parse_changed(&found, &CoverRules::new(&config.cover), observer);
+        let cancelled = observer.is_cancelled();
+        let mut summary = LibraryScanSummary { unchanged: found.len() - changed, ..Default::default() };
+        let mut data = self.data.write().unwrap();
+        for (file, result) in parsed {
+            match merge_parsed(&mut data, file, result) {
+                Merged::Added(_) => summary.added += 1,
+                Merged::Updated(_) => summary.updated += 1,
+                Merged::Failed(removed) => {
+                    summary.failed += 1;
+                    summary.removed += usize::from(removed.is_some());
+                }
+            }
+        }
+
+        if !cancelled {
+            let seen: HashSet<&str> = found.iter().map(|file| file.path.as_str()).collect();
+            let before = data.tracks.len();
+            data.tracks.retain(|key, _| {
+                let path = Path::new(key);
+                if scanned_roots.iter().any(|root| path.starts_with(root)) {
+                    seen.contains(key.as_str())
+                } else {
+                    // 已从音乐目录中移除的目录不再保留，无法访问的目录保留
+                    roots.iter().any(|root| path.starts_with(root))
+                }
+            });
+            summary.removed += before - data.tracks.len();
+        }
+        summary.total = data.tracks.len();
+
+        println!(
+            "Library scan: {} tracks, {} added, {} updated, {} removed, {} failed",
+            summary.total, summary.added, summary.updated, summary.removed, summary.failed
+        );
+        if summary.added + summary.updated + summary.removed > 0 {
+            self.save(&data)?;
+        }
+        Ok(summary)
+    }
+
+    /// 按文件系统变化增量更新索引（由目录监听器调用）
+    ///
+    /// 先处理移动和重命名（沿用已有标签，不重新解析），再检查其余变化的路径：
+    /// 新增或修改的文件、新建目录中的文件、已删除或被扫描策略排除的文件或目录。
+    pub fn apply_changes(&self, renames: &[(PathBuf, PathBuf)], paths: &BTreeSet<PathBuf>, roots: &[String], config: &AppConfig) -> AppResult<LibraryDiff> {
+        let max_depth = scan_depth(config);
+        let policy = ScanPolicy::new(&config.directory_scan);
+        let scanned = |path: &Path| root_of(path, roots).is_some_and(|(root, depth)| depth <= max_depth && policy.allows(root, path));
+        let mut diff = LibraryDiff::default();
+        {
+            let mut data = self.data.write().unwrap();
+            for (from, to) in renames {
+                for key in keys_under(&data, from) {
+                    let Some(mut entry) = data.tracks.remove(&key) else { continue };
+                    let target = match Path::new(&key).strip_prefix(from) {
+                        Ok(rest) if !rest.as_os_str().is_empty() => to.join(rest),
+                        _ => to.clone(),
+                    };
+                    if !scanned(&target) {
+                        // 移出了音乐目录或被扫描策略排除
+                        diff.removed.push(entry.track.path);
+                        continue;
+                    }
+                    let from_path = std::mem::replace(&mut entry.track.path, track_path(&target.to_string_lossy()));
+                    let name = target.file_name().unwrap_or_default().to_string_lossy().to_string();
+                    // 没有标题标签时标题取自文件名
+                    if entry.track.title.as_deref() == Some(entry.track.name.as_str()) {
+                        entry.track.title = Some(name.clone());
+                    }
+                    entry.track.name = name;
+                    diff.renamed.push(LibraryRename { from: from_path, track: entry.track.clone() });
+                    data.tracks.insert(target.to_string_lossy().to_string(), entry);
+                }
+            }
+            for path in paths.iter().filter(|path| !path.exists() || !scanned(path)) {
+                for key in keys_under(&data, path) {
+                    if let Some(entry) = data.tracks.remove(&key) {
+                        diff.removed.push(entry.track.path);
+                    }
+                }
+            }
+        }
+
+        let mut found = Vec::new();
+        for path in paths {
+            let Some((root, depth)) = root_of(path, roots).filter(|(_, depth)| *depth <= max_depth) else { continue };
+            if !policy.allows(root, path) {
+                continue;
+            }
+            if path.is_dir() {
+                found.extend(walk_audio_files(&policy, root, path, max_depth - depth));
+            } else if let Some(file) = FileStamp::of(path) {
+                found.push(file);
+            }
+        }
+        let (_, parsed) = self.parse_changed(&found, &CoverRules::new(&config.cover), &());
+        let mut data = self.data.write().unwrap();
+        for (file, result) in parsed {
+            match merge_parsed(&mut data, file, result) {
+                Merged::Added(track) => diff.added.push(track),
+                Merged::Updated(track) => diff.updated.push(track),
+                Merged::Failed(removed) => diff.removed.extend(removed),
+            }
+        }
+        if !diff.is_empty() {
+            self.save(&data)?;
+        }
+        Ok(diff)
+    }
+
+    /// 解析新增或大小、修改时间变化的文件（不持有锁），返回变化的文件数和解析结果
+    ///
+    /// 取消后剩余的文件不再解析。
+    fn parse_changed<'a>(&self, found: &'a [FileStamp], covers: &CoverRules, observer: &dyn ScanObserver) -> (usize, Vec<(&'a FileStamp, AppResult<TrackMetadata>)>) {
+        let changed: Vec<&FileStamp> = {
+            let data = self.data.read().unwrap();
+            found
+                .iter()
+                .filter(|file| match data.tracks.get(&file.path) {
+                    Some(entry) => entry.size != file.size || entry.mtime != file.mtime,
+                    None => true,
+                })
+                .collect()
+        };
+        observer.parsing(found.len(), changed.len());
+        let parsed = changed
+            .par_iter()
+            .filter_map(|file| {
+                if observer.is_cancelled() {
+                    return None;
+                }
+                let result = read_track_metadata(&file.path, covers);
+                observer.parsed(&file.path, &result);
+                Some((*file, result))
+            })
+            .collect();
+        (changed.len(), parsed)
+    }
+
+    /// 写入索引文件（先写临时文件再替换，避免中断时损坏索引）
+    fn save(&self, data: &LibraryData) -> AppResult<()> {
+        let content = serde_json::to_string(data)
+            .map_err(|e| AppError::from(e).with_code(ErrorCode::ConfigFailed).context_message("Failed to serialize library index"))?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, content)
+            .map_err(|e| AppError::from(e).context_message("Failed to write library index").with_path(&tmp_path))?;
+        fs::rename(&tmp_path, &self.path)
+            .map_err(|e| AppError::from(e).context_message("Failed to replace library index").with_path(&self.path))
+    }
+
+    /// 按配置把音乐目录中的音轨分组为播放列表
+    #[must_use]
+    pub fn playlists(&self, roots: &[String], config: &AppConfig) -> Vec<LibraryPlaylist> {
+        let data = self.data.read().unwrap();
+        if folder_based(config) {
+            // 按所在文件夹分组
+            let mut folders: BTreeMap<&Path, usize> = BTreeMap::new();
+            for path in data.tracks.keys().map(Path::new) {
+                if roots.iter().any(|root| path.starts_with(root))
+                    && let Some(parent) = path.parent()
+                {
+                    *folders.entry(parent).or_default() += 1;
+                }
+            }
+            folders
+                .into_iter()
+                .map(|(folder, track_count)| LibraryPlaylist {
+                    name: folder_name(folder),
+                    folder: folder.to_string_lossy().to_string(),
+                    recursive: false,
+                    track_count,
+                })
+                .collect()
+        } else {
+            // 每个音乐目录一个播放列表
+            roots
+                .iter()
+                .filter_map(|root| {
+                    let track_count = data.tracks.keys().filter(|path| Path::new(path).starts_with(root)).count();
+                    (track_count > 0).then(|| LibraryPlaylist {
+                        name: folder_name(Path::new(root)),
+                        folder: root.clone(),
+                        recursive: true,
+                        track_count,
+                    })
+                })
+                .collect()
+        }
+    }
+
+    /// 分页获取目录中的音轨（按路径排序）
+    #[must_use]
+    pub fn tracks(&self, folder: &str, recursive: bool, offset: usize, limit: usize) -> LibraryPage {
+        let folder = Path::new(folder);
+        let limit = limit.clamp(1, LIBRARY_PAGE_MAX);
+        let data = self.data.read().unwrap();
+        let mut total = 0;
+        let mut tracks = Vec::new();
+        for (path, entry) in &data.tracks {
+            let path = Path::new(path);
+            let included = if recursive { path.starts_with(folder) } else { path.parent() == Some(folder) };
+            if !included {
+                continue;
+            }
+            if total >= offset && tracks.len() < limit {
+                tracks.push(entry.track.clone());
+            }
+            total += 1;
+        }
+        LibraryPage { total, offset, tracks }
+    }
+
+    /// 获取索引中的单个音轨
+    #[must_use]
+    pub fn track(&self, path: &str) -> Option<TrackMetadata> {
+        self.data.read().unwrap().tracks.get(path).map(|entry| entry.track.clone())
+    }
+}
+
+/// 把解析结果写入索引
+fn merge_parsed(data: &mut LibraryData, file: &FileStamp, result: AppResult<TrackMetadata>) -> Merged {
+    match result {
+        Ok(track) => {
+            let entry = LibraryEntry { size: file.size, mtime: file.mtime, track: track.clone() };
+            if data.tracks.insert(file.path.clone(), entry).is_some() { Merged::Updated(track) } else { Merged::Added(track) }
+        }
+        Err(e) => {
+            eprintln!("Failed to get metadata for file '{}': {e}", file.path);
+            // 原来可以解析的文件现在失败时删除旧条目
+            Merged::Failed(data.tracks.remove(&file.path).map(|entry| entry.track.path))
+        }
+    }
+}
+
+/// 按扫描策略递归列出目录中的音频文件
+fn walk_audio_files(policy: &ScanPolicy, root: &Path, dir: &Path, max_depth: usize) -> impl Iterator<Item = FileStamp> {
+    policy.walk(root, dir, max_depth).into_iter().filter_map(|entry| FileStamp::of_entry(&entry))
+}
+
+/// 等于 `path` 或位于其下的索引条目
+fn keys_under(data: &LibraryData, path: &Path) -> Vec<String> {
+    data.tracks.keys().filter(|key| Path::new(key).starts_with(path)).cloned().collect()
+}
+
+/// 路径所在的音乐目录及相对深度（不在任何音乐目录中时为 None，嵌套时取最近的目录）
+fn root_of<'a>(path: &Path, roots: &'a [String]) -> Option<(&'a Path, usize)> {
+    roots
+        .iter()
+        .map(Path::new)
+        .filter_map(|root| path.strip_prefix(root).ok().map(|rest| (root, rest.components().count())))
+        .min_by_key(|(_, depth)| *depth)
+}
+
+/// 是否按文件夹生成播放列表（与目录扫描的规则一致）
+fn folder_based(config: &AppConfig) -> bool {
+    config.directory_scan.enable_subdirectory_scan && config.playlist.folder_based_playlists
+}
+
+/// 扫描深度：按文件夹生成播放列表时受 max_depth 限制
+fn scan_depth(config: &AppConfig) -> usize {
+    if folder_based(config) { config.directory_scan.max_depth as usize } else { usize::MAX }
+}
+
+fn folder_name(dir: &Path) -> String {
+    dir.file_name().map_or_else(|| "Unknown".to_string(), |s| s.to_string_lossy().to_string())
+}
+
+fn mtime_millis(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as u64)
+}
@@ -2,16 +2,20 @@
 //!
 //! 提供音轨元数据结构和处理函数。
 
-use base64::{engine::general_purpose, Engine as _};
+use super::artwork::{CoverPictureType, CoverRules, TrackPicture};
+use super::cover_cache::CoverCache;
+use crate::error::{AppError, AppResult, ErrorCode};
+use lofty::config::WriteOptions;
 use lofty::prelude::{Accessor, AudioFile, TaggedFileExt};
 use lofty::probe::Probe;
-use serde::Serialize;
+use lofty::tag::{ItemKey, Tag};
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
 /// 单个音轨的元数据
-#[derive(Debug, Serialize, Default, Clone)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
 pub struct TrackMetadata {
     pub path: String,
     pub name: String,
@@ -19,12 +23,164 @@ pub struct TrackMetadata {
     pub artist: Option<String>,
     pub album: Option<String>,
     pub duration: Option<f64>,
-    pub cover: Option<String>,
+    /// 封面缓存中的封面 ID（通过 `cover` 协议读取图片）
+    pub cover_id: Option<String>,
+    /// 是否有封面（内嵌图片或文件夹中的封面图片）
+    pub has_cover: bool,
     pub bitrate: Option<u32>,
     pub sample_rate: Option<u32>,
     pub channels: Option<u8>,
     pub bit_depth: Option<u8>,
     pub format: Option<String>,
+    pub replay_gain: Option<ReplayGainInfo>,
+    /// 所有艺术家（`artist` 为原始标签值）
+    pub artists: Vec<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u32>,
+    pub track_total: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub disc_total: Option<u32>,
+    pub genres: Vec<String>,
+    /// 日期标签原文（如 "2003" 或 "2003-05-12"）
+    pub date: Option<String>,
+    pub year: Option<u32>,
+    pub composers: Vec<String>,
+    pub lyricists: Vec<String>,
+    pub comment: Option<String>,
+    pub bpm: Option<f32>,
+    /// 合辑（各曲目艺术家不同，按专辑艺术家归类）
+    pub compilation: bool,
+    pub isrc: Option<String>,
+    pub musicbrainz: Option<MusicBrainzIds>,
+}
+
+/// MusicBrainz 标识符
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MusicBrainzIds {
+    pub recording_id: Option<String>,
+    pub track_id: Option<String>,
+    pub release_id: Option<String>,
+    pub release_group_id: Option<String>,
+    pub artist_ids: Vec<String>,
+    pub release_artist_ids: Vec<String>,
+    pub work_id: Option<String>,
+}
+
+impl MusicBrainzIds {
+    fn from_tag(tag: &Tag) -> Self {
+        let single = |key: &ItemKey| tag.get_string(key).map(str::trim).filter(|v| !v.is_empty()).map(String::from);
+        Self {
+            recording_id: single(&ItemKey::MusicBrainzRecordingId),
+            track_id: single(&ItemKey::MusicBrainzTrackId),
+            release_id: single(&ItemKey::MusicBrainzReleaseId),
+            release_group_id: single(&ItemKey::MusicBrainzReleaseGroupId),
+            artist_ids: tag_values(tag, &ItemKey::MusicBrainzArtistId),
+            release_artist_ids: tag_values(tag, &ItemKey::MusicBrainzReleaseArtistId),
+            work_id: single(&ItemKey::MusicBrainzWorkId),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 回放增益标签（REPLAYGAIN_*）
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayGainInfo {
+    /// 音轨增益（dB）
+    pub track_gain: Option<f32>,
+    /// 音轨峰值（线性，1.0 = 满刻度）
+    pub track_peak: Option<f32>,
+    /// 专辑增益（dB）
+    pub album_gain: Option<f32>,
+    /// 专辑峰值（线性）
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGainInfo {
+    /// 从标签读取回放增益（ID3v2 TXXX、Vorbis、APE、MP4 freeform 均由 lofty 映射到同一键）
+    #[must_use]
+    pub fn from_tag(tag: &Tag) -> Self {
+        let read = |key: &ItemKey| tag.get_string(key).and_then(parse_replay_gain_value);
+        Self {
+            track_gain: read(&ItemKey::ReplayGainTrackGain),
+            track_peak: read(&ItemKey::ReplayGainTrackPeak),
+            album_gain: read(&ItemKey::ReplayGainAlbumGain),
+            album_peak: read(&ItemKey::ReplayGainAlbumPeak),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.track_gain.is_none() && self.album_gain.is_none()
+    }
+}
+
+/// 解析形如 "-6.54 dB" 或 "0.988312" 的标签值
+fn parse_replay_gain_value(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let number = if value.to_ascii_lowercase().ends_with("db") { &value[..value.len() - 2] } else { value };
+    number.trim().parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+/// 读取音频文件中的回放增益（依次检查所有标签，取第一个包含增益的）
+pub fn read_replay_gain(path: &str) -> AppResult<ReplayGainInfo> {
+    let tagged_file = read_tagged_file(Path::new(path))?;
+
+    Ok(replay_gain_of(&tagged_file))
+}
+
+/// 将回放增益写入音频文件的主标签（没有标签时按格式创建）
+pub fn write_replay_gain_tags(path: &str, info: &ReplayGainInfo) -> AppResult<()> {
+    let mut tagged_file = read_tagged_file(Path::new(path))?;
+
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .ok_or_else(|| AppError::new(ErrorCode::UnsupportedFormat, "File does not support tags").with_path(path))?;
+
+    let entries = [
+        (ItemKey::ReplayGainTrackGain, info.track_gain.map(|g| format!("{g:.2} dB"))),
+        (ItemKey::ReplayGainTrackPeak, info.track_peak.map(|p| format!("{p:.6}"))),
+        (ItemKey::ReplayGainAlbumGain, info.album_gain.map(|g| format!("{g:.2} dB"))),
+        (ItemKey::ReplayGainAlbumPeak, info.album_peak.map(|p| format!("{p:.6}"))),
+    ];
+    for (key, value) in entries {
+        match value {
+            Some(value) => {
+                tag.insert_text(key, value);
+            }
+            None => tag.remove_key(&key),
+        }
+    }
+
+    tagged_file
+        .save_to_path(path, WriteOptions::default())
+        .map_err(|e| AppError::from(e).context_message("Failed to write tags").with_path(path))
+}
+
+/// 打开并解析音频文件的标签
+pub(super) fn read_tagged_file(path: &Path) -> AppResult<lofty::file::TaggedFile> {
+    Probe::open(path)
+        .and_then(Probe::read)
+        .map_err(|e| AppError::from(e).with_path(path))
+}
+
+fn replay_gain_of(tagged_file: &lofty::file::TaggedFile) -> ReplayGainInfo {
+    tagged_file
+        .primary_tag()
+        .into_iter()
+        .chain(tagged_file.tags())
+        .map(ReplayGainInfo::from_tag)
+        .find(|info| !info.is_empty())
+        .unwrap_or_default()
 }
 
 impl TrackMetadata {
@@ -52,7 +208,7 @@ impl TrackMetadata {
     
     #[must_use]
     #[allow(dead_code)]
-    pub fn with_cover(mut self, cover: Option<String>) -> Self { self.cover = cover; self }
+    pub fn with_cover_id(mut self, cover_id: Option<String>) -> Self { self.cover_id = cover_id; self }
     
     #[must_use]
     #[allow(dead_code)]
@@ -119,13 +275,15 @@ impl Playlist {
 }
 
 /// 获取音轨的元数据信息（内部函数）
-pub fn get_track_metadata_internal(path: &str) -> Result<TrackMetadata, String> {
+pub fn get_track_metadata_internal(path: &str, covers: &CoverRules) -> AppResult<TrackMetadata> {
+    read_track_metadata(path, covers)
+}
+
+/// 读取音轨元数据，按封面规则选择封面并提取到封面缓存，元数据只记录封面 ID
+pub fn read_track_metadata(path: &str, covers: &CoverRules) -> AppResult<TrackMetadata> {
     let file_path = Path::new(path);
 
-    let tagged_file = Probe::open(file_path)
-        .map_err(|e| e.to_string())?
-        .read()
-        .map_err(|e| e.to_string())?;
+    let tagged_file = read_tagged_file(file_path)?;
 
     let properties = tagged_file.properties();
     let duration = properties.duration().as_secs_f64();
@@ -137,7 +295,7 @@ pub fn get_track_metadata_internal(path: &str) -> Result<TrackMetadata, String>
         .map(str::to_uppercase);
 
     let mut metadata = TrackMetadata {
-        path: path.replace('/', "\\"),
+        path: track_path(path),
         name: file_path.file_name().unwrap_or_default().to_string_lossy().to_string(),
         duration: if duration > 0.0 { Some(duration) } else { None },
         bitrate: properties.audio_bitrate(),
@@ -148,18 +306,20 @@ pub fn get_track_metadata_internal(path: &str) -> Result<TrackMetadata, String>
         ..Default::default()
     };
 
-    if let Some(tag) = tagged_file.primary_tag() {
+    if let Some(tag) = tagged_file.primary_tag().or_else(|| tagged_file.first_tag()) {
         metadata.title = tag.title().map(|s| s.to_string());
         metadata.artist = tag.artist().map(|s| s.to_string());
         metadata.album = tag.album().map(|s| s.to_string());
-
-        if let Some(picture) = tag.pictures().first() {
-            let mime_type = picture.mime_type().map_or("image/jpeg", lofty::picture::MimeType::as_str);
-            let data = picture.data();
-            metadata.cover = Some(format!("data:{mime_type};base64,{}", general_purpose::STANDARD.encode(data)));
-        }
+        read_tag_fields(&mut metadata, tag);
     }
 
+    let pictures = embedded_pictures(&tagged_file);
+    metadata.cover_id = covers.resolve(file_path, pictures);
+    metadata.has_cover = metadata.cover_id.is_some() || !pictures.is_empty();
+
+    let replay_gain = replay_gain_of(&tagged_file);
+    metadata.replay_gain = (!replay_gain.is_empty()).then_some(replay_gain);
+
     if metadata.title.is_none() || metadata.title.as_deref() == Some("") {
         metadata.title = Some(metadata.name.clone());
     }
@@ -167,33 +327,106 @@ pub fn get_track_metadata_internal(path: &str) -> Result<TrackMetadata, String>
     Ok(metadata)
 }
 
+/// 读取扩展标签字段（编号、专辑艺术家、流派、日期、作曲等）
+fn read_tag_fields(metadata: &mut TrackMetadata, tag: &Tag) {
+    let single = |key: &ItemKey| tag.get_string(key).map(str::trim).filter(|v| !v.is_empty()).map(String::from);
+
+    metadata.artists = tag_values(tag, &ItemKey::TrackArtist);
+    metadata.album_artist = single(&ItemKey::AlbumArtist);
+    metadata.track_number = tag.track();
+    metadata.track_total = tag.track_total();
+    metadata.disc_number = tag.disk();
+    metadata.disc_total = tag.disk_total();
+    metadata.genres = tag_values(tag, &ItemKey::Genre).iter().map(|genre| resolve_genre(genre)).collect();
+    metadata.date = single(&ItemKey::RecordingDate)
+        .or_else(|| single(&ItemKey::ReleaseDate))
+        .or_else(|| single(&ItemKey::OriginalReleaseDate));
+    metadata.year = metadata.date.as_deref().and_then(parse_year);
+    metadata.composers = tag_values(tag, &ItemKey::Composer);
+    metadata.lyricists = tag_values(tag, &ItemKey::Lyricist);
+    metadata.comment = tag.comment().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    metadata.bpm = single(&ItemKey::Bpm)
+        .or_else(|| single(&ItemKey::IntegerBpm))
+        .and_then(|bpm| bpm.parse::<f32>().ok())
+        .filter(|bpm| bpm.is_finite() && *bpm > 0.0);
+    metadata.compilation = single(&ItemKey::FlagCompilation).is_some_and(|flag| flag == "1" || flag.eq_ignore_ascii_case("true"));
+    metadata.isrc = single(&ItemKey::Isrc);
+
+    let musicbrainz = MusicBrainzIds::from_tag(tag);
+    metadata.musicbrainz = (!musicbrainz.is_empty()).then_some(musicbrainz);
+}
+
+/// 读取多值字段：同一键可能有多个条目，单个条目中也可能用 `\0` 或 `;` 分隔多个值
+pub(super) fn tag_values(tag: &Tag, key: &ItemKey) -> Vec<String> {
+    let mut values: Vec<String> = Vec::new();
+    for value in tag.get_strings(key).flat_map(|v| v.split(['\0', ';'])).map(str::trim) {
+        if !value.is_empty() && !values.iter().any(|v| v == value) {
+            values.push(value.to_string());
+        }
+    }
+    values
+}
+
+/// 把 ID3v1 风格的流派编号（"17" 或 "(17)"）转换为名称
+fn resolve_genre(genre: &str) -> String {
+    genre
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| lofty::id3::v1::GENRES.get(index))
+        .map_or_else(|| genre.to_string(), |name| (*name).to_string())
+}
+
+/// 从日期标签中取年份（"2003"、"2003-05-12"、"2003/05" 等）
+fn parse_year(date: &str) -> Option<u32> {
+    let digits: String = date.trim().chars().take_while(char::is_ascii_digit).collect();
+    if digits.len() == 4 { digits.parse().ok() } else { None }
+}
+
+/// 音轨元数据中使用的路径格式
+pub(crate) fn track_path(path: &str) -> String {
+    path.replace('/', "\\")
+}
+
+/// 列出音轨的所有内嵌图片（保存到封面缓存）
+pub fn get_track_pictures_internal(path: &str) -> AppResult<Vec<TrackPicture>> {
+    let tagged_file = read_tagged_file(Path::new(path))?;
+    Ok(embedded_pictures(&tagged_file).iter().filter_map(TrackPicture::of).collect())
+}
 
 /// 提取音频文件的封面并保存到指定路径
-pub fn extract_cover_internal(audio_path: &str, output_path: &str) -> Result<String, String> {
+///
+/// `picture_type` 为 None 时按封面规则选择（可能是文件夹中的图片），否则只取该类型的内嵌图片。
+pub fn extract_cover_internal(
+    audio_path: &str,
+    output_path: &str,
+    picture_type: Option<CoverPictureType>,
+    covers: &CoverRules,
+) -> AppResult<String> {
     let file_path = Path::new(audio_path);
 
-    let tagged_file = Probe::open(file_path)
-        .map_err(|e| format!("无法打开文件: {e}"))?
-        .read()
-        .map_err(|e| format!("无法读取文件: {e}"))?;
+    let tagged_file = read_tagged_file(file_path)?;
+    let pictures = embedded_pictures(&tagged_file);
+    let cache = CoverCache::global().ok_or_else(|| AppError::new(ErrorCode::Internal, "Cover cache is not initialized"))?;
 
-    let tag = tagged_file
-        .primary_tag()
-        .ok_or_else(|| "文件没有标签信息".to_string())?;
-
-    let picture = tag
-        .pictures()
-        .first()
-        .ok_or_else(|| "文件没有封面图片".to_string())?;
+    let cover_id = match picture_type {
+        None => covers.resolve(file_path, pictures),
+        Some(picture_type) => pictures
+            .iter()
+            .find(|picture| CoverPictureType::of(picture.pic_type()) == picture_type)
+            .map(|picture| cache.store(picture.data()))
+            .transpose()?,
+    }
+    .ok_or_else(|| AppError::new(ErrorCode::NotFound, "File has no cover art").with_path(file_path))?;
+    let cover = cache.load(&cover_id, None)?;
 
-    let data = picture.data();
-    
     // 根据 MIME 类型确定文件扩展名
-    let extension = match picture.mime_type().map(lofty::picture::MimeType::as_str) {
-        Some("image/png") => "png",
-        Some("image/gif") => "gif",
-        Some("image/webp") => "webp",
-        Some("image/bmp") => "bmp",
+    let extension = match cover.mime_type {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
         _ => "jpg", // 默认为 jpg
     };
 
@@ -209,12 +442,21 @@ pub fn extract_cover_internal(audio_path: &str, output_path: &str) -> Result<Str
     // 确保父目录存在
     if let Some(parent) = final_path.parent() {
         fs::create_dir_all(parent)
-            .map_err(|e| format!("无法创建目录: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to create directory").with_path(parent))?;
     }
 
     // 写入文件
-    fs::write(&final_path, data)
-        .map_err(|e| format!("无法写入文件: {e}"))?;
+    fs::write(&final_path, cover.data)
+        .map_err(|e| AppError::from(e).context_message("Failed to write file").with_path(&final_path))?;
 
     Ok(final_path.to_string_lossy().to_string())
 }
+
+/// 音轨的内嵌图片（与标签字段读取同一个标签）
+fn embedded_pictures(tagged_file: &lofty::file::TaggedFile) -> &[lofty::picture::Picture] {
+    tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())
+        .map(Tag::pictures)
+        .unwrap_or_default()
+}
@@ -2,12 +2,23 @@
 //!
 //! 提供文件系统操作和音频元数据处理功能。
 
+pub mod artwork;
 pub mod commands;
+pub mod cover_cache;
 pub mod filesystem;
 pub mod http_client;
+pub mod library;
 pub mod metadata;
 pub mod netease;
+pub mod palette;
+pub mod scanner;
+pub mod tag_editor;
+pub mod watcher;
 
 // 重新导出常用类型
+pub use cover_cache::CoverCache;
 pub use filesystem::{get_audio_files_from_dir, read_dir, AUDIO_EXTENSIONS};
-pub use metadata::{Playlist, TrackMetadata};
+pub use library::LibraryIndex;
+pub use scanner::LibraryScanner;
+pub use watcher::LibraryWatcher;
+pub use metadata::{MusicBrainzIds, Playlist, ReplayGainInfo, TrackMetadata};
@@ -4,6 +4,7 @@
 
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, CONTENT_TYPE, REFERER, USER_AGENT};
 use serde::{Deserialize, Serialize};
+use crate::error::{AppError, AppResult, ErrorCode};
 use crate::media::http_client::get_client;
 
 /// 搜索结果中的歌曲信息
@@ -100,8 +101,37 @@ fn build_headers() -> HeaderMap {
     headers
 }
 
+/// 读取响应内容，HTTP 状态码不是 2xx 时返回错误
+async fn read_response(response: reqwest::Response) -> AppResult<String> {
+    let status = response.status();
+    let response_text = response.text().await.map_err(|e| AppError::from(e).context_message("Read response failed"))?;
+    if !status.is_success() {
+        return Err(AppError::new(ErrorCode::NetworkFailed, format!("HTTP error: {status} - {}", safe_truncate(&response_text, 200)))
+            .with_context("status", status.as_u16()));
+    }
+    Ok(response_text)
+}
+
+/// 解析 JSON 响应
+fn parse_response<T: serde::de::DeserializeOwned>(response_text: &str) -> AppResult<T> {
+    serde_json::from_str(response_text).map_err(|e| {
+        AppError::from(e)
+            .context_message("Parse response failed")
+            .with_context("response", safe_truncate(response_text, 200))
+    })
+}
+
+/// 检查接口返回码
+fn check_api_code(code: i32) -> AppResult<()> {
+    if code == 200 {
+        Ok(())
+    } else {
+        Err(AppError::new(ErrorCode::NetworkFailed, format!("API error: code {code}")).with_context("apiCode", code))
+    }
+}
+
 /// 搜索歌曲 - 使用 Web API
-pub async fn search_songs(keyword: &str, limit: u32, offset: u32) -> Result<Vec<SearchSongResult>, String> {
+pub async fn search_songs(keyword: &str, limit: u32, offset: u32) -> AppResult<Vec<SearchSongResult>> {
     let client = get_client();
     
     // 使用 cloudsearch API（更稳定）
@@ -120,21 +150,12 @@ pub async fn search_songs(keyword: &str, limit: u32, offset: u32) -> Result<Vec<
         .form(&params)
         .send()
         .await
-        .map_err(|e| format!("Request failed: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Request failed"))?;
 
-    let status = response.status();
-    let response_text = response.text().await.map_err(|e| format!("Read response failed: {e}"))?;
-    
-    if !status.is_success() {
-        return Err(format!("HTTP error: {status} - {response_text}"));
-    }
+    let response_text = read_response(response).await?;
 
-    let data: CloudSearchResponse = serde_json::from_str(&response_text)
-        .map_err(|e| format!("Parse response failed: {e} - Response: {}", safe_truncate(&response_text, 200)))?;
-
-    if data.code != 200 {
-        return Err(format!("API error: code {}", data.code));
-    }
+    let data: CloudSearchResponse = parse_response(&response_text)?;
+    check_api_code(data.code)?;
 
     let songs: Vec<SearchSongResult> = data.result
         .and_then(|r| r.songs)
@@ -153,7 +174,7 @@ pub async fn search_songs(keyword: &str, limit: u32, offset: u32) -> Result<Vec<
 }
 
 /// 获取歌词 - 使用 Web API
-pub async fn get_lyrics(song_id: &str) -> Result<LyricsData, String> {
+pub async fn get_lyrics(song_id: &str) -> AppResult<LyricsData> {
     let client = get_client();
     
     let url = format!("https://music.163.com/api/song/lyric?id={song_id}&lv=-1&tv=-1&rv=-1&kv=-1");
@@ -163,21 +184,12 @@ pub async fn get_lyrics(song_id: &str) -> Result<LyricsData, String> {
         .headers(build_headers())
         .send()
         .await
-        .map_err(|e| format!("Request failed: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Request failed"))?;
 
-    let status = response.status();
-    let response_text = response.text().await.map_err(|e| format!("Read response failed: {e}"))?;
-    
-    if !status.is_success() {
-        return Err(format!("HTTP error: {status} - {response_text}"));
-    }
+    let response_text = read_response(response).await?;
 
-    let data: LyricResponse = serde_json::from_str(&response_text)
-        .map_err(|e| format!("Parse response failed: {e} - Response: {}", safe_truncate(&response_text, 200)))?;
-
-    if data.code != 200 {
-        return Err(format!("API error: code {}", data.code));
-    }
+    let data: LyricResponse = parse_response(&response_text)?;
+    check_api_code(data.code)?;
 
     Ok(LyricsData {
         lrc: data.lrc.and_then(|l| l.lyric).unwrap_or_default(),
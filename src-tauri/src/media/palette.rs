@@ -0,0 +1,289 @@
+//! 封面配色模块
+//!
+//! 用中位切分（median cut）把封面缩略图量化为少量主色，并为每个主色生成满足
+//! WCAG AA 的前景色/背景色组合。亮度和对比度的计算与前端 `colorContrast.ts` 一致。
+//! 结果以 `<封面 ID>.palette.json` 缓存在封面缓存目录中。
+
+use super::cover_cache::{write_atomically, CoverCache};
+use crate::error::{AppError, AppResult, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// 缓存格式版本，算法变化时递增（旧缓存会被重新计算）
+const PALETTE_VERSION: u32 = 1;
+/// 主色数量上限
+const PALETTE_SIZE: usize = 6;
+/// 用于量化的缩略图尺寸
+const SAMPLE_SIZE: u32 = 64;
+/// 颜色距离（RGB 欧氏距离）小于该值的主色合并
+const MERGE_DISTANCE: u32 = 24;
+/// WCAG AA 普通文本对比度
+const AA_NORMAL: f64 = 4.5;
+/// WCAG AAA 普通文本对比度
+const AAA_NORMAL: f64 = 7.0;
+/// 调整前景色亮度的步长和最大次数（与 `adjustColorForContrast` 相同）
+const ADJUST_STEP: f64 = 0.05;
+const ADJUST_MAX_ATTEMPTS: usize = 100;
+
+type Rgb = [u8; 3];
+
+/// 一个主色
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PaletteSwatch {
+    /// 十六进制颜色（`#rrggbb`）
+    pub color: String,
+    /// 占比（0.0~1.0）
+    pub population: f32,
+    /// WCAG 相对亮度
+    pub luminance: f32,
+}
+
+/// 前景色/背景色组合（对比度至少满足 WCAG AA 普通文本）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PaletteColorPair {
+    pub name: String,
+    pub foreground: String,
+    pub background: String,
+    /// 对比度（保留两位小数）
+    pub ratio: f32,
+    /// 达到的 WCAG 等级（"AA" 或 "AAA"）
+    pub level: String,
+}
+
+/// 封面配色
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverPalette {
+    #[serde(default)]
+    version: u32,
+    pub cover_id: String,
+    /// 按占比从高到低排列的主色
+    pub swatches: Vec<PaletteSwatch>,
+    /// 每个主色作为背景时的前景色，顺序与 `swatches` 相同
+    pub pairs: Vec<PaletteColorPair>,
+    /// 占比最高的主色是否为深色（白色文字对比度更高）
+    pub dark: bool,
+}
+
+/// 获取封面配色，优先读取缓存
+pub fn cover_palette(cache: &CoverCache, cover_id: &str) -> AppResult<CoverPalette> {
+    let cache_path = cache.sidecar_path(cover_id, ".palette.json")?;
+    if let Some(palette) = fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<CoverPalette>(&content).ok())
+        .filter(|palette| palette.version == PALETTE_VERSION)
+    {
+        return Ok(palette);
+    }
+
+    let cover = cache.load(cover_id, Some(SAMPLE_SIZE))?;
+    let image = image::load_from_memory(&cover.data).map_err(|e| {
+        AppError::new(ErrorCode::UnsupportedFormat, "Failed to decode cover image").with_source(e).with_context("id", cover_id)
+    })?;
+    let pixels: Vec<Rgb> = image.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).into_rgb8().pixels().map(|p| p.0).collect();
+    let palette = build_palette(cover_id, &pixels);
+
+    match serde_json::to_vec(&palette) {
+        Ok(content) => {
+            if let Err(e) = write_atomically(&cache_path, &content) {
+                eprintln!("Failed to cache cover palette: {e}");
+            }
+        }
+        Err(e) => eprintln!("Failed to serialize cover palette: {e}"),
+    }
+    Ok(palette)
+}
+
+fn build_palette(cover_id: &str, pixels: &[Rgb]) -> CoverPalette {
+    let total = pixels.len().max(1) as f32;
+    let swatches = merge_similar(
+        median_cut(pixels.to_vec(), PALETTE_SIZE)
+            .into_iter()
+            .map(|bucket| (average(&bucket), bucket.len() as f32 / total))
+            .collect(),
+    );
+
+    let pairs = swatches
+        .iter()
+        .enumerate()
+        .map(|(index, &(background, _))| {
+            let name = if index == 0 { "dominant".to_string() } else { format!("swatch{index}") };
+            contrast_pair(name, background, &swatches)
+        })
+        .collect();
+    let dark = swatches.first().is_some_and(|&(color, _)| contrast_ratio([255; 3], color) >= contrast_ratio([0; 3], color));
+
+    CoverPalette {
+        version: PALETTE_VERSION,
+        cover_id: cover_id.to_string(),
+        swatches: swatches
+            .iter()
+            .map(|&(color, population)| PaletteSwatch {
+                color: to_hex(color),
+                population,
+                luminance: relative_luminance(color) as f32,
+            })
+            .collect(),
+        pairs,
+        dark,
+    }
+}
+
+/// 中位切分：反复把范围最大的颜色桶沿最长的通道从中位数处分开，结果按像素数从多到少排列
+fn median_cut(pixels: Vec<Rgb>, count: usize) -> Vec<Vec<Rgb>> {
+    let mut buckets = vec![pixels];
+    buckets.retain(|bucket| !bucket.is_empty());
+    while buckets.len() < count {
+        let Some((index, channel)) = buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, bucket)| bucket.len() > 1)
+            .map(|(index, bucket)| {
+                let (channel, range) = widest_channel(bucket);
+                (index, channel, u64::from(range) * bucket.len() as u64)
+            })
+            .filter(|&(_, _, score)| score > 0)
+            .max_by_key(|&(_, _, score)| score)
+            .map(|(index, channel, _)| (index, channel))
+        else {
+            break;
+        };
+        let mut bucket = buckets.swap_remove(index);
+        bucket.sort_unstable_by_key(|pixel| pixel[channel]);
+        let upper = bucket.split_off(bucket.len() / 2);
+        buckets.push(bucket);
+        buckets.push(upper);
+    }
+    buckets.sort_by_key(|bucket| std::cmp::Reverse(bucket.len()));
+    buckets
+}
+
+/// 合并相近的主色（按中位数切分时同一颜色可能落入多个桶），合并到占比更高的颜色上
+fn merge_similar(swatches: Vec<(Rgb, f32)>) -> Vec<(Rgb, f32)> {
+    let mut merged: Vec<(Rgb, f32)> = Vec::with_capacity(swatches.len());
+    for (color, population) in swatches {
+        match merged.iter_mut().find(|(kept, _)| distance_squared(*kept, color) < MERGE_DISTANCE * MERGE_DISTANCE) {
+            Some((_, kept_population)) => *kept_population += population,
+            None => merged.push((color, population)),
+        }
+    }
+    merged.sort_by(|a, b| b.1.total_cmp(&a.1));
+    merged
+}
+
+fn distance_squared(a: Rgb, b: Rgb) -> u32 {
+    a.iter().zip(b).map(|(x, y)| u32::from(x.abs_diff(y)).pow(2)).sum()
+}
+
+/// 取值范围最大的通道及其范围
+fn widest_channel(pixels: &[Rgb]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let (min, max) = pixels
+                .iter()
+                .fold((u8::MAX, u8::MIN), |(min, max), pixel| (min.min(pixel[channel]), max.max(pixel[channel])));
+            (channel, max.saturating_sub(min))
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+fn average(pixels: &[Rgb]) -> Rgb {
+    let len = pixels.len().max(1) as u64;
+    let mut sum = [0u64; 3];
+    for pixel in pixels {
+        for (total, value) in sum.iter_mut().zip(pixel) {
+            *total += u64::from(*value);
+        }
+    }
+    sum.map(|total| u8::try_from((total + len / 2) / len).unwrap_or(u8::MAX))
+}
+
+/// 为背景色选择对比度最高的主色作前景，不满足 AA 时像 `adjustColorForContrast` 一样逐步调整亮度
+fn contrast_pair(name: String, background: Rgb, swatches: &[(Rgb, f32)]) -> PaletteColorPair {
+    let candidate = swatches
+        .iter()
+        .map(|&(color, _)| color)
+        .max_by(|a, b| contrast_ratio(*a, background).total_cmp(&contrast_ratio(*b, background)))
+        .unwrap_or(background);
+    let mut foreground = adjust_for_contrast(candidate, background);
+    if contrast_ratio(foreground, background) < AA_NORMAL {
+        // 调整方向上达不到要求时退回黑色或白色
+        foreground = if contrast_ratio([255; 3], background) >= contrast_ratio([0; 3], background) { [255; 3] } else { [0; 3] };
+    }
+
+    let ratio = contrast_ratio(foreground, background);
+    PaletteColorPair {
+        name,
+        foreground: to_hex(foreground),
+        background: to_hex(background),
+        ratio: ((ratio * 100.0).round() / 100.0) as f32,
+        level: if ratio >= AAA_NORMAL { "AAA" } else { "AA" }.to_string(),
+    }
+}
+
+/// 远离背景色的方向（更亮或更暗）逐步调整，直到满足 AA 或达到次数上限
+fn adjust_for_contrast(color: Rgb, background: Rgb) -> Rgb {
+    let lighten = relative_luminance(color) > relative_luminance(background);
+    let mut current = color;
+    for _ in 0..ADJUST_MAX_ATTEMPTS {
+        if contrast_ratio(current, background) >= AA_NORMAL {
+            break;
+        }
+        current = current.map(|value| {
+            let value = f64::from(value);
+            let adjusted = if lighten { value + (255.0 - value) * ADJUST_STEP } else { value * (1.0 - ADJUST_STEP) };
+            adjusted.round().clamp(0.0, 255.0) as u8
+        });
+    }
+    current
+}
+
+/// WCAG 相对亮度（与 `colorContrast.ts` 的 `getRelativeLuminance` 相同）
+fn relative_luminance(color: Rgb) -> f64 {
+    let [r, g, b] = color.map(|value| {
+        let value = f64::from(value) / 255.0;
+        if value <= 0.039_28 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+fn to_hex(color: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contrast_matches_wcag() {
+        assert!((contrast_ratio([255; 3], [0; 3]) - 21.0).abs() < 1e-9);
+        assert!((contrast_ratio([0x77; 3], [255; 3]) - 4.48).abs() < 0.01);
+    }
+
+    #[test]
+    fn median_cut_separates_colors() {
+        let mut pixels = vec![[200, 20, 20]; 300];
+        pixels.extend(vec![[20, 20, 200]; 100]);
+        let palette = build_palette("id", &pixels);
+        assert_eq!(palette.swatches.len(), 2);
+        assert_eq!(palette.swatches[0].color, "#c81414");
+        assert!((palette.swatches[0].population - 0.75).abs() < 1e-6);
+        assert!(palette.pairs.iter().all(|pair| pair.ratio >= 4.5));
+    }
+
+    #[test]
+    fn adjusts_low_contrast_foreground() {
+        let pair = contrast_pair("gray".to_string(), [0x80; 3], &[([0x80; 3], 1.0)]);
+        assert!(pair.ratio >= 4.5);
+        assert_eq!(pair.background, "#808080");
+    }
+}
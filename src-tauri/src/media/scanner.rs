@@ -0,0 +1,269 @@
+//! 媒体库后台扫描模块
+//!
+//! 扫描在后台线程中运行并返回扫描 ID。进度通过 `library-scan-progress` 事件发送，
+//! 解析出的音轨按批通过 `library-scan-batch` 事件发送，结束（包括取消）后发送
+//! `library-scan-complete` 事件。
+
+use super::library::{LibraryIndex, LibraryScanSummary, ScanObserver};
+use super::metadata::TrackMetadata;
+use crate::config::AppConfig;
+use crate::error::{AppError, AppResult, ErrorCode};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// 每批发送的音轨数
+const BATCH_SIZE: usize = 200;
+/// 进度事件的最小间隔（出错时立即发送）
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 扫描阶段
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ScanPhase {
+    /// 遍历目录，查找音频文件
+    Discovering,
+    /// 解析新增或变化的文件
+    Parsing,
+}
+
+/// 扫描中的错误
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryScanError {
+    pub path: String,
+    pub message: String,
+}
+
+/// 扫描进度事件
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryScanProgress {
+    pub scan_id: u64,
+    pub phase: ScanPhase,
+    /// 已发现的音频文件数
+    pub discovered: usize,
+    /// 已解析的文件数
+    pub parsed: usize,
+    /// 需要解析的文件数（发现阶段为 0）
+    pub total: usize,
+    pub current_directory: Option<String>,
+    pub error: Option<LibraryScanError>,
+}
+
+/// 一批解析出的音轨
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryScanBatch {
+    pub scan_id: u64,
+    pub tracks: Vec<TrackMetadata>,
+}
+
+/// 扫描完成事件
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryScanComplete {
+    pub scan_id: u64,
+    pub cancelled: bool,
+    /// 索引写入失败时为 None
+    pub summary: Option<LibraryScanSummary>,
+    pub error: Option<String>,
+}
+
+/// 媒体库扫描任务管理（同一时间只运行一个扫描）
+#[derive(Default)]
+pub struct LibraryScanner {
+    next_id: AtomicU64,
+    active: Mutex<Option<(u64, Arc<AtomicBool>)>>,
+}
+
+/// 扫描线程结束（包括 panic）时清除正在运行的扫描
+struct ActiveScan {
+    scanner: Arc<LibraryScanner>,
+    scan_id: u64,
+}
+
+impl Drop for ActiveScan {
+    fn drop(&mut self) {
+        if let Ok(mut active) = self.scanner.active.lock()
+            && active.as_ref().is_some_and(|(id, _)| *id == self.scan_id)
+        {
+            *active = None;
+        }
+    }
+}
+
+impl LibraryScanner {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在后台启动增量扫描，返回扫描 ID
+    pub fn start(self: &Arc<Self>, app: AppHandle, library: Arc<LibraryIndex>, roots: Vec<String>, config: AppConfig) -> AppResult<u64> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let scan_id = {
+            let mut active = self.active.lock().unwrap();
+            if active.is_some() {
+                return Err(AppError::new(ErrorCode::Busy, "A library scan is already running"));
+            }
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+            *active = Some((id, Arc::clone(&cancel)));
+            id
+        };
+
+        let scanner = Arc::clone(self);
+        std::thread::spawn(move || {
+            let active = ActiveScan { scanner, scan_id };
+            let reporter = ScanReporter::new(&app, scan_id, &cancel);
+            let result = library.rescan_observed(&roots, &config, &reporter);
+            reporter.finish();
+            drop(active);
+
+            let cancelled = cancel.load(Ordering::SeqCst);
+            println!("Library scan {scan_id} finished, cancelled: {cancelled}");
+            let complete = match result {
+                Ok(summary) => LibraryScanComplete { scan_id, cancelled, summary: Some(summary), error: None },
+                Err(e) => {
+                    eprintln!("Library scan {scan_id} failed: {e}");
+                    LibraryScanComplete { scan_id, cancelled, summary: None, error: Some(e.to_string()) }
+                }
+            };
+            let _ = app.emit("library-scan-complete", complete);
+        });
+        Ok(scan_id)
+    }
+
+    /// 取消正在运行的扫描（指定 ID 时只取消该扫描）
+    pub fn cancel(&self, scan_id: Option<u64>) -> bool {
+        let active = self.active.lock().unwrap();
+        active.as_ref().is_some_and(|(id, cancel)| {
+            if scan_id.is_some_and(|requested| requested != *id) {
+                return false;
+            }
+            cancel.store(true, Ordering::SeqCst);
+            true
+        })
+    }
+}
+
+/// 把扫描进度转换为事件（在 rayon 线程中调用）
+struct ScanReporter<'a> {
+    app: &'a AppHandle,
+    scan_id: u64,
+    cancel: &'a AtomicBool,
+    state: Mutex<ReporterState>,
+}
+
+struct ReporterState {
+    phase: ScanPhase,
+    discovered: usize,
+    parsed: usize,
+    total: usize,
+    current_directory: Option<String>,
+    last_progress: Option<Instant>,
+    batch: Vec<TrackMetadata>,
+}
+
+impl<'a> ScanReporter<'a> {
+    fn new(app: &'a AppHandle, scan_id: u64, cancel: &'a AtomicBool) -> Self {
+        Self {
+            app,
+            scan_id,
+            cancel,
+            state: Mutex::new(ReporterState {
+                phase: ScanPhase::Discovering,
+                discovered: 0,
+                parsed: 0,
+                total: 0,
+                current_directory: None,
+                last_progress: None,
+                batch: Vec::new(),
+            }),
+        }
+    }
+
+    /// 发送进度事件，`force` 为 false 时按间隔节流
+    fn emit_progress(&self, state: &mut ReporterState, error: Option<LibraryScanError>, force: bool) {
+        if !force && state.last_progress.is_some_and(|t| t.elapsed() < PROGRESS_INTERVAL) {
+            return;
+        }
+        state.last_progress = Some(Instant::now());
+        let _ = self.app.emit(
+            "library-scan-progress",
+            LibraryScanProgress {
+                scan_id: self.scan_id,
+                phase: state.phase,
+                discovered: state.discovered,
+                parsed: state.parsed,
+                total: state.total,
+                current_directory: state.current_directory.clone(),
+                error,
+            },
+        );
+    }
+
+    fn emit_batch(&self, state: &mut ReporterState) {
+        if state.batch.is_empty() {
+            return;
+        }
+        let tracks = std::mem::take(&mut state.batch);
+        let _ = self.app.emit("library-scan-batch", LibraryScanBatch { scan_id: self.scan_id, tracks });
+    }
+
+    /// 发送剩余的音轨和最终进度
+    fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.emit_batch(&mut state);
+        self.emit_progress(&mut state, None, true);
+    }
+}
+
+impl ScanObserver for ScanReporter<'_> {
+    fn directory(&self, dir: &Path, discovered: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.discovered = discovered;
+        state.current_directory = Some(dir.to_string_lossy().to_string());
+        self.emit_progress(&mut state, None, false);
+    }
+
+    fn parsing(&self, discovered: usize, total: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.phase = ScanPhase::Parsing;
+        state.discovered = discovered;
+        state.total = total;
+        self.emit_progress(&mut state, None, true);
+    }
+
+    fn parsed(&self, path: &str, result: &AppResult<TrackMetadata>) {
+        let mut state = self.state.lock().unwrap();
+        state.parsed += 1;
+        state.current_directory = Path::new(path).parent().map(|dir| dir.to_string_lossy().to_string());
+        match result {
+            Ok(track) => {
+                state.batch.push(track.clone());
+                if state.batch.len() >= BATCH_SIZE {
+                    self.emit_batch(&mut state);
+                }
+                self.emit_progress(&mut state, None, false);
+            }
+            Err(e) => {
+                let error = LibraryScanError { path: path.to_string(), message: e.to_string() };
+                self.emit_progress(&mut state, Some(error), true);
+            }
+        }
+    }
+
+    fn error(&self, path: &str, error: &AppError) {
+        let mut state = self.state.lock().unwrap();
+        let error = LibraryScanError { path: path.to_string(), message: error.to_string() };
+        self.emit_progress(&mut state, Some(error), true);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
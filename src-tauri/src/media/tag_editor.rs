@@ -0,0 +1,282 @@
+//! 标签编辑模块
+//!
+//! 通过 lofty 的通用标签写入各格式（ID3v2、Vorbis Comment、MP4、APE），字段映射由 lofty 完成。
+//! 写入时先复制到同目录的临时文件并在副本上修改，成功后再原子替换原文件，
+//! 任何一步失败都不会改动原文件。
+
+use super::metadata::{read_tagged_file, tag_values};
+use crate::error::{AppError, AppResult, ErrorCode};
+use lofty::config::WriteOptions;
+use lofty::picture::{Picture, PictureType};
+use lofty::prelude::{Accessor, AudioFile, TaggedFileExt};
+use lofty::tag::{ItemKey, ItemValue, Tag, TagItem};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 要修改的标签字段
+///
+/// 字段为 None 时不修改；空字符串、空列表或 0 表示删除该字段。
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TagChanges {
+    pub title: Option<String>,
+    pub artists: Option<Vec<String>>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u32>,
+    pub track_total: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub disc_total: Option<u32>,
+    pub genres: Option<Vec<String>>,
+    /// 日期（如 "2003" 或 "2003-05-12"）
+    pub date: Option<String>,
+    pub composers: Option<Vec<String>>,
+    pub lyricists: Option<Vec<String>>,
+    pub comment: Option<String>,
+    pub bpm: Option<u32>,
+    pub compilation: Option<bool>,
+    pub isrc: Option<String>,
+    pub cover: Option<CoverChange>,
+}
+
+/// 封面修改
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum CoverChange {
+    /// 从图片文件嵌入封面（替换原有的正面封面）
+    Set { path: String },
+    /// 删除所有内嵌图片
+    Remove,
+}
+
+/// ID3v2 版本（只影响使用 ID3v2 的格式）
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Id3v2Version {
+    #[serde(rename = "2.3")]
+    V3,
+    #[default]
+    #[serde(rename = "2.4")]
+    V4,
+}
+
+/// 写入选项
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TagWriteOptions {
+    pub id3v2_version: Id3v2Version,
+    /// 只计算差异，不写入文件
+    pub dry_run: bool,
+}
+
+/// 单个字段的变化
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TagFieldDiff {
+    pub field: &'static str,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// 单个文件的写入结果
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TagWriteResult {
+    pub path: String,
+    pub changes: Vec<TagFieldDiff>,
+    /// 是否已写入（试运行或没有变化时为 false）
+    pub written: bool,
+    pub error: Option<String>,
+}
+
+/// 修改单个文件的标签
+pub fn write_tags(path: &str, changes: &TagChanges, options: TagWriteOptions) -> AppResult<TagWriteResult> {
+    let file_path = Path::new(path);
+    // 封面图片先读取，避免文件不存在时只写入了部分字段
+    let cover = match &changes.cover {
+        Some(CoverChange::Set { path }) => Some(read_cover_picture(Path::new(path))?),
+        _ => None,
+    };
+
+    let mut tagged_file = read_tagged_file(file_path)?;
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .ok_or_else(|| AppError::new(ErrorCode::UnsupportedFormat, "File does not support tags").with_path(file_path))?;
+
+    let before = snapshot(tag);
+    apply_changes(tag, changes, cover);
+    let after = snapshot(tag);
+    let diff = diff_snapshots(&before, &after);
+
+    let written = !options.dry_run && !diff.is_empty();
+    if written {
+        let write_options = WriteOptions::default().use_id3v23(options.id3v2_version == Id3v2Version::V3);
+        save_atomically(file_path, |temp| {
+            tagged_file
+                .save_to_path(temp, write_options)
+                .map_err(|e| AppError::from(e).context_message("Failed to write tags").with_path(file_path))
+        })?;
+        println!("Wrote {} tag fields to {path}", diff.len());
+    }
+
+    Ok(TagWriteResult { path: path.to_string(), changes: diff, written, error: None })
+}
+
+/// 对多个文件应用相同的修改，单个文件失败不影响其他文件
+pub fn write_tags_batch(paths: &[String], changes: &TagChanges, options: TagWriteOptions) -> Vec<TagWriteResult> {
+    paths
+        .iter()
+        .map(|path| {
+            write_tags(path, changes, options).unwrap_or_else(|e| {
+                eprintln!("Failed to write tags to {path}: {e}");
+                TagWriteResult { path: path.clone(), changes: Vec::new(), written: false, error: Some(e.to_string()) }
+            })
+        })
+        .collect()
+}
+
+fn apply_changes(tag: &mut Tag, changes: &TagChanges, cover: Option<Picture>) {
+    set_text(tag, ItemKey::TrackTitle, changes.title.as_deref());
+    set_values(tag, ItemKey::TrackArtist, changes.artists.as_deref());
+    set_text(tag, ItemKey::AlbumTitle, changes.album.as_deref());
+    set_text(tag, ItemKey::AlbumArtist, changes.album_artist.as_deref());
+    set_values(tag, ItemKey::Genre, changes.genres.as_deref());
+    set_text(tag, ItemKey::RecordingDate, changes.date.as_deref());
+    set_values(tag, ItemKey::Composer, changes.composers.as_deref());
+    set_values(tag, ItemKey::Lyricist, changes.lyricists.as_deref());
+    set_text(tag, ItemKey::Comment, changes.comment.as_deref());
+    set_text(tag, ItemKey::Isrc, changes.isrc.as_deref());
+
+    set_number(tag, changes.track_number, Tag::set_track, Tag::remove_track);
+    set_number(tag, changes.track_total, Tag::set_track_total, Tag::remove_track_total);
+    set_number(tag, changes.disc_number, Tag::set_disk, Tag::remove_disk);
+    set_number(tag, changes.disc_total, Tag::set_disk_total, Tag::remove_disk_total);
+
+    if let Some(bpm) = changes.bpm {
+        // 读取时优先使用 Bpm，写入整数 BPM 时一并删除，避免旧值覆盖新值
+        tag.remove_key(&ItemKey::Bpm);
+        set_text(tag, ItemKey::IntegerBpm, Some(&if bpm == 0 { String::new() } else { bpm.to_string() }));
+    }
+    if let Some(compilation) = changes.compilation {
+        set_text(tag, ItemKey::FlagCompilation, Some(if compilation { "1" } else { "" }));
+    }
+
+    match (&changes.cover, cover) {
+        (Some(CoverChange::Set { .. }), Some(picture)) => {
+            tag.remove_picture_type(PictureType::CoverFront);
+            tag.push_picture(picture);
+        }
+        (Some(CoverChange::Remove), _) => {
+            let types: Vec<PictureType> = tag.pictures().iter().map(Picture::pic_type).collect();
+            for pic_type in types {
+                tag.remove_picture_type(pic_type);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn set_text(tag: &mut Tag, key: ItemKey, value: Option<&str>) {
+    match value.map(str::trim) {
+        Some("") => tag.remove_key(&key),
+        Some(value) => {
+            tag.insert_text(key, value.to_string());
+        }
+        None => {}
+    }
+}
+
+/// 写入多值字段（每个值一个条目，由 lofty 按格式合并或拆分）
+fn set_values(tag: &mut Tag, key: ItemKey, values: Option<&[String]>) {
+    let Some(values) = values else { return };
+    tag.remove_key(&key);
+    for value in values.iter().map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        tag.push(TagItem::new(key.clone(), ItemValue::Text(value.to_string())));
+    }
+}
+
+fn set_number(tag: &mut Tag, value: Option<u32>, set: fn(&mut Tag, u32), remove: fn(&mut Tag)) {
+    match value {
+        Some(0) => remove(tag),
+        Some(value) => set(tag, value),
+        None => {}
+    }
+}
+
+/// 读取图片文件作为正面封面
+fn read_cover_picture(path: &Path) -> AppResult<Picture> {
+    let mut file = fs::File::open(path).map_err(|e| AppError::from(e).with_path(path))?;
+    let mut picture = Picture::from_reader(&mut file)
+        .map_err(|e| AppError::new(ErrorCode::UnsupportedFormat, "Unsupported cover image").with_source(e).with_path(path))?;
+    picture.set_pic_type(PictureType::CoverFront);
+    Ok(picture)
+}
+
+/// 可编辑字段的当前值（用于计算差异）
+fn snapshot(tag: &Tag) -> BTreeMap<&'static str, String> {
+    let number = |value: Option<u32>| value.map(|v| v.to_string());
+    let list = |key: &ItemKey| Some(tag_values(tag, key).join("; "));
+    let text = |key: &ItemKey| tag.get_string(key).map(String::from);
+    let cover = tag.pictures().iter().map(|picture| {
+        let mime = picture.mime_type().map_or("image", lofty::picture::MimeType::as_str);
+        format!("{:?} ({mime}, {} bytes)", picture.pic_type(), picture.data().len())
+    });
+
+    [
+        ("title", text(&ItemKey::TrackTitle)),
+        ("artists", list(&ItemKey::TrackArtist)),
+        ("album", text(&ItemKey::AlbumTitle)),
+        ("albumArtist", text(&ItemKey::AlbumArtist)),
+        ("trackNumber", number(tag.track())),
+        ("trackTotal", number(tag.track_total())),
+        ("discNumber", number(tag.disk())),
+        ("discTotal", number(tag.disk_total())),
+        ("genres", list(&ItemKey::Genre)),
+        ("date", text(&ItemKey::RecordingDate)),
+        ("composers", list(&ItemKey::Composer)),
+        ("lyricists", list(&ItemKey::Lyricist)),
+        ("comment", text(&ItemKey::Comment)),
+        ("bpm", text(&ItemKey::Bpm).or_else(|| text(&ItemKey::IntegerBpm))),
+        ("compilation", text(&ItemKey::FlagCompilation)),
+        ("isrc", text(&ItemKey::Isrc)),
+        ("cover", Some(cover.collect::<Vec<_>>().join("; "))),
+    ]
+    .into_iter()
+    .filter_map(|(field, value)| value.filter(|v| !v.is_empty()).map(|v| (field, v)))
+    .collect()
+}
+
+fn diff_snapshots(before: &BTreeMap<&'static str, String>, after: &BTreeMap<&'static str, String>) -> Vec<TagFieldDiff> {
+    let fields: BTreeSet<&'static str> = before.keys().chain(after.keys()).copied().collect();
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let (old, new) = (before.get(field), after.get(field));
+            (old != new).then(|| TagFieldDiff { field, old: old.cloned(), new: new.cloned() })
+        })
+        .collect()
+}
+
+/// 在同目录的临时副本上执行 `write`，成功后替换原文件；失败时删除副本，原文件保持不变
+fn save_atomically(path: &Path, write: impl FnOnce(&Path) -> AppResult<()>) -> AppResult<()> {
+    let temp = temp_path(path);
+    fs::copy(path, &temp).map_err(|e| AppError::from(e).context_message("Failed to create temporary file").with_path(&temp))?;
+    let result = write(&temp).and_then(|()| {
+        fs::rename(&temp, path).map_err(|e| AppError::from(e).context_message("Failed to replace file").with_path(path))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// 临时文件名：隐藏且不带音频扩展名，扫描和目录监听都会忽略
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.tagedit.tmp"))
+}
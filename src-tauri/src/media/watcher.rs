@@ -0,0 +1,178 @@
+//! 音乐目录监听模块
+//!
+//! 监听所有音乐目录（Linux 上为 inotify，由 notify 封装各平台实现）。一段时间内连续的
+//! 文件变化合并后增量更新媒体库索引，移动按重命名处理，差异通过 `library-changed`
+//! 事件发送给前端和插件。
+
+use crate::AppState;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// 最后一次变化后等待的静默时间
+const DEBOUNCE: Duration = Duration::from_millis(750);
+/// 持续变化时最长等待时间（例如大批量复制）
+const MAX_BATCH_WAIT: Duration = Duration::from_secs(5);
+
+/// 音乐目录监听器
+pub struct LibraryWatcher {
+    inner: Mutex<WatcherInner>,
+}
+
+#[derive(Default)]
+struct WatcherInner {
+    app: Option<AppHandle>,
+    /// 丢弃时事件通道关闭，处理线程随之结束
+    watcher: Option<RecommendedWatcher>,
+}
+
+impl Default for LibraryWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LibraryWatcher {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { inner: Mutex::new(WatcherInner::default()) }
+    }
+
+    /// 启动监听（应用启动时调用）
+    pub fn start(&self, app: AppHandle, roots: Vec<String>) {
+        self.inner.lock().unwrap().app = Some(app);
+        self.set_roots(roots);
+    }
+
+    /// 音乐目录变化后重新监听
+    pub fn set_roots(&self, roots: Vec<String>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.watcher = None;
+        let Some(app) = inner.app.clone() else { return };
+        if roots.is_empty() {
+            return;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("Failed to create library watcher: {e}");
+                return;
+            }
+        };
+        for root in &roots {
+            match watcher.watch(Path::new(root), RecursiveMode::Recursive) {
+                Ok(()) => println!("Watching music directory: {root}"),
+                Err(e) => eprintln!("Failed to watch music directory {root}: {e}"),
+            }
+        }
+        inner.watcher = Some(watcher);
+        thread::spawn(move || run(&app, &rx, &roots));
+    }
+}
+
+/// 一批合并后的变化
+#[derive(Default)]
+struct ChangeBatch {
+    renames: Vec<(PathBuf, PathBuf)>,
+    paths: BTreeSet<PathBuf>,
+    /// 尚未配对的重命名源路径（部分平台分别发送 From 和 To）
+    pending_from: Option<PathBuf>,
+}
+
+impl ChangeBatch {
+    fn add(&mut self, event: Event) {
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                self.pending_from = None;
+                self.renames.push((event.paths[0].clone(), event.paths[1].clone()));
+                // 目标可能被替换为新内容（例如先写临时文件再重命名覆盖）
+                self.paths.insert(event.paths[1].clone());
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                self.pending_from = event.paths.first().cloned();
+                self.paths.extend(event.paths);
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                if let (Some(from), Some(to)) = (self.pending_from.take(), event.paths.first()) {
+                    self.renames.push((from, to.clone()));
+                }
+                self.paths.extend(event.paths);
+            }
+            EventKind::Access(_) | EventKind::Other => {}
+            _ => self.paths.extend(event.paths),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.renames.is_empty() && self.paths.is_empty()
+    }
+}
+
+/// 处理线程：收集一批变化后更新索引
+fn run(app: &AppHandle, rx: &Receiver<notify::Result<Event>>, roots: &[String]) {
+    loop {
+        let mut batch = ChangeBatch::default();
+        // 等待第一个事件，通道关闭（监听器被替换或丢弃）时退出
+        match rx.recv() {
+            Ok(Ok(event)) => batch.add(event),
+            Ok(Err(e)) => {
+                eprintln!("Library watcher error: {e}");
+                continue;
+            }
+            Err(_) => break,
+        }
+        let started = Instant::now();
+        let mut closed = false;
+        while started.elapsed() < MAX_BATCH_WAIT {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(Ok(event)) => batch.add(event),
+                Ok(Err(e)) => eprintln!("Library watcher error: {e}"),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    closed = true;
+                    break;
+                }
+            }
+        }
+        if !batch.is_empty() {
+            apply_batch(app, &batch, roots);
+        }
+        if closed {
+            break;
+        }
+    }
+    println!("Library watcher stopped");
+}
+
+fn apply_batch(app: &AppHandle, batch: &ChangeBatch, roots: &[String]) {
+    let state = app.state::<AppState>();
+    let config = match state.config_manager.load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to load config for library update: {e}");
+            return;
+        }
+    };
+    match state.library.apply_changes(&batch.renames, &batch.paths, roots, &config) {
+        Ok(diff) if !diff.is_empty() => {
+            println!(
+                "Library changed: {} added, {} updated, {} removed, {} renamed",
+                diff.added.len(),
+                diff.updated.len(),
+                diff.removed.len(),
+                diff.renamed.len()
+            );
+            let _ = app.emit("library-changed", &diff);
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to update library index: {e}"),
+    }
+}
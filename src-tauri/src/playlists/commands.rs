@@ -0,0 +1,107 @@
+//! 用户播放列表相关的 Tauri 命令
+
+use super::{emit_playlists_changed, entry_for, export_file, import_file, PlaylistFormat, PlaylistImport, UserPlaylist, UserPlaylistSummary};
+use crate::error::AppResult;
+use crate::AppState;
+use std::path::Path;
+use tauri::{command, AppHandle, State};
+
+/// 修改播放列表中的音轨并发送 `user-playlists-changed` 事件
+fn update_entries<T>(
+    app: &AppHandle,
+    state: &State<AppState>,
+    id: &str,
+    f: impl FnOnce(&mut UserPlaylist) -> AppResult<T>,
+) -> AppResult<UserPlaylist> {
+    let (_, playlist) = state.playlists.update(id, f)?;
+    emit_playlists_changed(app, &state.playlists);
+    Ok(playlist)
+}
+
+#[command]
+pub fn list_user_playlists(state: State<AppState>) -> AppResult<Vec<UserPlaylistSummary>> {
+    Ok(state.playlists.summaries())
+}
+
+#[command]
+pub fn get_user_playlist(state: State<AppState>, id: String) -> AppResult<UserPlaylist> {
+    state.playlists.get(&id)
+}
+
+#[command]
+pub fn create_user_playlist(app: AppHandle, state: State<AppState>, name: String, paths: Option<Vec<String>>) -> AppResult<UserPlaylist> {
+    let entries = paths.unwrap_or_default().iter().map(|path| entry_for(&state.library, path)).collect();
+    let playlist = state.playlists.create(&name, entries)?;
+    emit_playlists_changed(&app, &state.playlists);
+    Ok(playlist)
+}
+
+#[command]
+pub fn rename_user_playlist(app: AppHandle, state: State<AppState>, id: String, name: String) -> AppResult<UserPlaylist> {
+    let playlist = state.playlists.rename(&id, &name)?;
+    emit_playlists_changed(&app, &state.playlists);
+    Ok(playlist)
+}
+
+/// 复制播放列表，名称为空时自动生成
+#[command]
+pub fn duplicate_user_playlist(app: AppHandle, state: State<AppState>, id: String, name: Option<String>) -> AppResult<UserPlaylist> {
+    let playlist = state.playlists.duplicate(&id, name.as_deref())?;
+    emit_playlists_changed(&app, &state.playlists);
+    Ok(playlist)
+}
+
+#[command]
+pub fn delete_user_playlist(app: AppHandle, state: State<AppState>, id: String) -> AppResult<()> {
+    state.playlists.delete(&id)?;
+    emit_playlists_changed(&app, &state.playlists);
+    Ok(())
+}
+
+/// 调整播放列表的显示顺序
+#[command]
+pub fn reorder_user_playlists(app: AppHandle, state: State<AppState>, ids: Vec<String>) -> AppResult<Vec<UserPlaylistSummary>> {
+    state.playlists.reorder(&ids)?;
+    emit_playlists_changed(&app, &state.playlists);
+    Ok(state.playlists.summaries())
+}
+
+/// 添加音轨，`index` 为空时添加到末尾
+#[command]
+pub fn add_to_user_playlist(app: AppHandle, state: State<AppState>, id: String, paths: Vec<String>, index: Option<usize>) -> AppResult<UserPlaylist> {
+    let entries = paths.iter().map(|path| entry_for(&state.library, path)).collect();
+    update_entries(&app, &state, &id, |playlist| {
+        playlist.insert(index, entries);
+        Ok(())
+    })
+}
+
+#[command]
+pub fn remove_from_user_playlist(app: AppHandle, state: State<AppState>, id: String, indices: Vec<usize>) -> AppResult<UserPlaylist> {
+    update_entries(&app, &state, &id, |playlist| Ok(playlist.remove(&indices)))
+}
+
+#[command]
+pub fn move_in_user_playlist(app: AppHandle, state: State<AppState>, id: String, from: usize, to: usize) -> AppResult<UserPlaylist> {
+    update_entries(&app, &state, &id, |playlist| playlist.move_entry(from, to))
+}
+
+/// 导入 M3U/M3U8、PLS 或 XSPF 文件为新的播放列表，返回找不到的条目
+#[command]
+pub fn import_user_playlist(app: AppHandle, state: State<AppState>, path: String, name: Option<String>) -> AppResult<PlaylistImport> {
+    let import = import_file(&state.playlists, &state.library, Path::new(&path), name.as_deref())?;
+    emit_playlists_changed(&app, &state.playlists);
+    Ok(import)
+}
+
+/// 导出播放列表，`format` 为空时按扩展名判断，返回导出的音轨数
+#[command]
+pub fn export_user_playlist(state: State<AppState>, id: String, path: String, format: Option<String>, relative: Option<bool>) -> AppResult<usize> {
+    let path = Path::new(&path);
+    let format = match format {
+        Some(format) => format.parse()?,
+        None => PlaylistFormat::from_path(path)?,
+    };
+    let playlist = state.playlists.get(&id)?;
+    export_file(&playlist, &state.library, path, format, relative.unwrap_or(false))
+}
@@ -0,0 +1,441 @@
+//! 播放列表文件格式
+//!
+//! 读写 M3U/M3U8（含 `#EXTINF`）、PLS 和 XSPF。条目位置可以是绝对路径、相对于
+//! 播放列表文件所在目录的路径或 `file://` 地址；网络地址不支持，导入时报告为缺失。
+
+use crate::error::{AppError, AppResult, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Component, Path, PathBuf, MAIN_SEPARATOR_STR};
+
+/// 播放列表文件格式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PlaylistFormat {
+    M3u,
+    M3u8,
+    Pls,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    /// 根据文件扩展名判断格式
+    pub fn from_path(path: &Path) -> AppResult<Self> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+        extension.parse().map_err(|_| {
+            AppError::new(ErrorCode::UnsupportedFormat, "Unsupported playlist format").with_path(path)
+        })
+    }
+}
+
+impl std::str::FromStr for PlaylistFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "m3u" => Ok(Self::M3u),
+            "m3u8" => Ok(Self::M3u8),
+            "pls" => Ok(Self::Pls),
+            "xspf" => Ok(Self::Xspf),
+            _ => Err(AppError::new(ErrorCode::UnsupportedFormat, "Unsupported playlist format").with_context("format", s)),
+        }
+    }
+}
+
+/// 播放列表文件中的一个条目（读取时位置尚未解析，写入时位置已转换好）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileEntry {
+    pub location: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// 秒
+    pub duration: Option<f64>,
+}
+
+/// 读取的播放列表文件
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistFile {
+    /// 文件中记录的标题（`#PLAYLIST:` 或 XSPF 的 `<title>`）
+    pub title: Option<String>,
+    pub entries: Vec<FileEntry>,
+}
+
+/// 条目缺失的原因
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MissingReason {
+    /// 文件不存在
+    NotFound,
+    /// 网络地址（不支持）
+    Remote,
+    /// 无法解析的位置
+    Invalid,
+}
+
+/// 导入时无法找到的条目
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingEntry {
+    /// 在播放列表文件中的序号（从 0 开始）
+    pub index: usize,
+    /// 文件中记录的原始位置
+    pub location: String,
+    pub reason: MissingReason,
+}
+
+/// 解析播放列表文件内容
+pub fn parse(content: &str, format: PlaylistFormat) -> AppResult<PlaylistFile> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    match format {
+        PlaylistFormat::M3u | PlaylistFormat::M3u8 => Ok(parse_m3u(content)),
+        PlaylistFormat::Pls => Ok(parse_pls(content)),
+        PlaylistFormat::Xspf => parse_xspf(content),
+    }
+}
+
+/// 生成播放列表文件内容（M3U 同样以 UTF-8 写入）
+#[must_use]
+pub fn write(title: &str, entries: &[FileEntry], format: PlaylistFormat) -> String {
+    match format {
+        PlaylistFormat::M3u | PlaylistFormat::M3u8 => write_m3u(title, entries),
+        PlaylistFormat::Pls => write_pls(entries),
+        PlaylistFormat::Xspf => write_xspf(title, entries),
+    }
+}
+
+fn parse_m3u(content: &str) -> PlaylistFile {
+    let mut playlist = PlaylistFile::default();
+    let mut pending: Option<FileEntry> = None;
+    for line in content.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            pending = Some(parse_extinf(info));
+        } else if let Some(title) = line.strip_prefix("#PLAYLIST:") {
+            playlist.title = non_empty(title);
+        } else if !line.starts_with('#') {
+            let entry = pending.take().unwrap_or_default();
+            playlist.entries.push(FileEntry { location: line.to_string(), ..entry });
+        }
+    }
+    playlist
+}
+
+/// `#EXTINF:<秒>[ 属性],<艺术家> - <标题>`
+fn parse_extinf(info: &str) -> FileEntry {
+    let (head, display) = info.split_once(',').unwrap_or((info, ""));
+    let duration = head
+        .split_whitespace()
+        .next()
+        .and_then(|secs| secs.parse::<f64>().ok())
+        .filter(|secs| *secs > 0.0);
+    let (artist, title) = split_display_name(display);
+    FileEntry { location: String::new(), title, artist, duration }
+}
+
+/// 拆分 `艺术家 - 标题`
+fn split_display_name(display: &str) -> (Option<String>, Option<String>) {
+    match display.split_once(" - ") {
+        Some((artist, title)) => (non_empty(artist), non_empty(title)),
+        None => (None, non_empty(display)),
+    }
+}
+
+fn parse_pls(content: &str) -> PlaylistFile {
+    let mut entries: BTreeMap<usize, FileEntry> = BTreeMap::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else { continue };
+        let key = key.trim().to_ascii_lowercase();
+        let Some((field, index)) = ["file", "title", "length"]
+            .into_iter()
+            .find_map(|field| key.strip_prefix(field).and_then(|n| n.parse::<usize>().ok()).map(|n| (field, n)))
+        else {
+            continue;
+        };
+        let entry = entries.entry(index).or_default();
+        match field {
+            "file" => entry.location = value.trim().to_string(),
+            "title" => (entry.artist, entry.title) = split_display_name(value),
+            _ => entry.duration = value.trim().parse::<f64>().ok().filter(|secs| *secs > 0.0),
+        }
+    }
+    PlaylistFile { title: None, entries: entries.into_values().filter(|entry| !entry.location.is_empty()).collect() }
+}
+
+fn parse_xspf(content: &str) -> AppResult<PlaylistFile> {
+    let document = roxmltree::Document::parse(content)
+        .map_err(|e| AppError::new(ErrorCode::ParseFailed, "Failed to parse XSPF playlist").with_source(e))?;
+    let root = document.root_element();
+    let child_text = |node: roxmltree::Node, name: &str| {
+        node.children()
+            .find(|child| child.is_element() && child.tag_name().name() == name)
+            .and_then(|child| child.text())
+            .and_then(non_empty)
+    };
+    let entries = root
+        .descendants()
+        .filter(|node| node.is_element() && node.tag_name().name() == "track")
+        .filter_map(|track| {
+            Some(FileEntry {
+                location: child_text(track, "location")?,
+                title: child_text(track, "title"),
+                artist: child_text(track, "creator"),
+                // XSPF 的时长单位为毫秒
+                duration: child_text(track, "duration")
+                    .and_then(|ms| ms.parse::<f64>().ok())
+                    .filter(|ms| *ms > 0.0)
+                    .map(|ms| ms / 1000.0),
+            })
+        })
+        .collect();
+    Ok(PlaylistFile { title: child_text(root, "title"), entries })
+}
+
+fn write_m3u(title: &str, entries: &[FileEntry]) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    let _ = writeln!(out, "#PLAYLIST:{}", single_line(title));
+    for entry in entries {
+        let _ = writeln!(out, "#EXTINF:{},{}", length_secs(entry.duration), display_name(entry));
+        let _ = writeln!(out, "{}", entry.location);
+    }
+    out
+}
+
+fn write_pls(entries: &[FileEntry]) -> String {
+    let mut out = String::from("[playlist]\n");
+    for (index, entry) in entries.iter().enumerate() {
+        let n = index + 1;
+        let _ = writeln!(out, "File{n}={}", entry.location);
+        let _ = writeln!(out, "Title{n}={}", display_name(entry));
+        let _ = writeln!(out, "Length{n}={}", length_secs(entry.duration));
+    }
+    let _ = writeln!(out, "NumberOfEntries={}", entries.len());
+    out.push_str("Version=2\n");
+    out
+}
+
+fn write_xspf(title: &str, entries: &[FileEntry]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+    let _ = writeln!(out, "  <title>{}</title>", escape_xml(title));
+    out.push_str("  <trackList>\n");
+    for entry in entries {
+        out.push_str("    <track>\n");
+        let _ = writeln!(out, "      <location>{}</location>", escape_xml(&entry.location));
+        if let Some(title) = &entry.title {
+            let _ = writeln!(out, "      <title>{}</title>", escape_xml(title));
+        }
+        if let Some(artist) = &entry.artist {
+            let _ = writeln!(out, "      <creator>{}</creator>", escape_xml(artist));
+        }
+        if let Some(duration) = entry.duration {
+            let _ = writeln!(out, "      <duration>{}</duration>", (duration * 1000.0).round() as u64);
+        }
+        out.push_str("    </track>\n");
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+    out
+}
+
+/// 解析条目位置：相对路径相对于播放列表文件所在目录，`uri` 为 true 时相对路径经过百分号编码（XSPF）
+pub fn resolve_location(location: &str, base_dir: &Path, uri: bool) -> Result<PathBuf, MissingReason> {
+    let location = location.trim();
+    let path = if let Some(path) = file_uri_path(location) {
+        path
+    } else if is_remote(location) {
+        return Err(MissingReason::Remote);
+    } else if uri {
+        urlencoding::decode(location).map_err(|_| MissingReason::Invalid)?.into_owned()
+    } else {
+        location.to_string()
+    };
+    if path.is_empty() {
+        return Err(MissingReason::Invalid);
+    }
+    // 其他平台上打开 Windows 生成的播放列表
+    let path = if cfg!(windows) { path } else { path.replace('\\', "/") };
+    let path = normalize(&base_dir.join(path));
+    if path.is_file() { Ok(path) } else { Err(MissingReason::NotFound) }
+}
+
+/// 导出时的条目位置：`base_dir` 不为空时尽量写成相对路径，`uri` 为 true 时写成 URI（XSPF）
+#[must_use]
+pub fn export_location(path: &str, base_dir: Option<&Path>, uri: bool) -> String {
+    let path = Path::new(path);
+    if let Some(relative) = base_dir.and_then(|base| relative_to(path, base)) {
+        let parts: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+        return if uri {
+            parts.iter().map(|part| urlencoding::encode(part).into_owned()).collect::<Vec<_>>().join("/")
+        } else {
+            parts.join(MAIN_SEPARATOR_STR)
+        };
+    }
+    if !uri {
+        return path.to_string_lossy().to_string();
+    }
+
+    // file:///home/user/a.flac 或 file:///C:/Music/a.flac
+    let normalized = path.to_string_lossy().replace('\\', "/");
+    let encoded: Vec<String> = normalized
+        .split('/')
+        .map(|part| if is_drive(part) { part.to_string() } else { urlencoding::encode(part).into_owned() })
+        .collect();
+    let encoded = encoded.join("/");
+    if let Some(unc) = encoded.strip_prefix("//") {
+        format!("file://{unc}")
+    } else if encoded.starts_with('/') {
+        format!("file://{encoded}")
+    } else {
+        format!("file:///{encoded}")
+    }
+}
+
+/// `file:` 地址对应的本地路径
+fn file_uri_path(location: &str) -> Option<String> {
+    let rest = location.get(..5).filter(|scheme| scheme.eq_ignore_ascii_case("file:")).map(|_| &location[5..])?;
+    let path = match rest.strip_prefix("//") {
+        Some(rest) => {
+            let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            if host.is_empty() || host.eq_ignore_ascii_case("localhost") { path.to_string() } else { format!("//{host}{path}") }
+        }
+        None => rest.to_string(),
+    };
+    let path = urlencoding::decode(&path).ok()?.into_owned();
+    // `/C:/Music` → `C:/Music`
+    match path.strip_prefix('/') {
+        Some(rest) if rest.split('/').next().is_some_and(is_drive) => Some(rest.to_string()),
+        _ => Some(path),
+    }
+}
+
+/// 带协议的地址（`http://` 等），不包括 Windows 盘符路径
+fn is_remote(location: &str) -> bool {
+    location.split_once("://").is_some_and(|(scheme, _)| {
+        scheme.len() > 1 && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+fn is_drive(part: &str) -> bool {
+    let bytes = part.as_bytes();
+    bytes.len() == 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+/// 去掉路径中的 `.` 和 `..`（不访问文件系统）
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// `path` 相对于 `base` 的路径，不在同一个根（如不同盘符）时返回 None
+fn relative_to(path: &Path, base: &Path) -> Option<PathBuf> {
+    let (path, base) = (normalize(path), normalize(base));
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    if !path.first().is_some_and(|root| matches!(root, Component::Prefix(_) | Component::RootDir)) || path.first() != base.first() {
+        return None;
+    }
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &path[common..] {
+        relative.push(component);
+    }
+    Some(relative)
+}
+
+/// `#EXTINF` 和 PLS 中显示的名称：`艺术家 - 标题`，没有标题时用文件名
+fn display_name(entry: &FileEntry) -> String {
+    let title = entry.title.clone().unwrap_or_else(|| {
+        let name = entry.location.rsplit(['/', '\\']).next().unwrap_or(&entry.location);
+        name.rsplit_once('.').map_or(name, |(stem, _)| stem).to_string()
+    });
+    let name = match &entry.artist {
+        Some(artist) => format!("{artist} - {title}"),
+        None => title,
+    };
+    single_line(&name)
+}
+
+/// 整数秒，未知时为 -1
+fn length_secs(duration: Option<f64>) -> i64 {
+    duration.map_or(-1, |secs| secs.round() as i64)
+}
+
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_extinf() {
+        let playlist = parse("#EXTM3U\n#PLAYLIST:Mix\n#EXTINF:215,Artist - Song\nmusic/a.flac\n\nb.mp3\n", PlaylistFormat::M3u8).unwrap();
+        assert_eq!(playlist.title.as_deref(), Some("Mix"));
+        assert_eq!(playlist.entries.len(), 2);
+        assert_eq!(playlist.entries[0].artist.as_deref(), Some("Artist"));
+        assert_eq!(playlist.entries[0].title.as_deref(), Some("Song"));
+        assert_eq!(playlist.entries[0].duration, Some(215.0));
+        assert_eq!(playlist.entries[1], FileEntry { location: "b.mp3".to_string(), ..Default::default() });
+    }
+
+    #[test]
+    fn round_trips_formats() {
+        let entries = vec![FileEntry {
+            location: "file:///music/a%20%26%20b.flac".to_string(),
+            title: Some("A & B".to_string()),
+            artist: Some("X".to_string()),
+            duration: Some(61.0),
+        }];
+        for format in [PlaylistFormat::M3u8, PlaylistFormat::Pls, PlaylistFormat::Xspf] {
+            let parsed = parse(&write("List", &entries, format), format).unwrap();
+            assert_eq!(parsed.entries.len(), 1, "{format:?}");
+            assert_eq!(parsed.entries[0].location, entries[0].location, "{format:?}");
+            assert_eq!(parsed.entries[0].title.as_deref(), Some("A & B"), "{format:?}");
+            assert_eq!(parsed.entries[0].duration, Some(61.0), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn converts_locations() {
+        assert_eq!(file_uri_path("file:///music/a%20b.flac").as_deref(), Some("/music/a b.flac"));
+        assert_eq!(file_uri_path("file:///C:/Music/a.flac").as_deref(), Some("C:/Music/a.flac"));
+        assert!(is_remote("https://example.com/a.mp3"));
+        assert!(!is_remote("C:\\Music\\a.mp3"));
+        assert_eq!(relative_to(Path::new("/music/x/a.flac"), Path::new("/music/lists")), Some(PathBuf::from("../x/a.flac")));
+        assert_eq!(export_location("/music/a b.flac", Some(Path::new("/music")), true), "a%20b.flac");
+        assert_eq!(export_location("/music/a b.flac", None, true), "file:///music/a%20b.flac");
+    }
+}
@@ -0,0 +1,98 @@
+//! 用户播放列表模块
+//!
+//! 与按文件夹生成的 [`crate::media::Playlist`] 不同，用户播放列表由后端保存在配置目录中，
+//! 支持导入和导出 M3U/M3U8、PLS、XSPF。列表变化通过 `user-playlists-changed` 事件通知前端。
+
+pub mod commands;
+pub mod formats;
+pub mod store;
+
+pub use formats::{MissingEntry, MissingReason, PlaylistFormat};
+pub use store::{PlaylistEntry, PlaylistStore, UserPlaylist, UserPlaylistSummary};
+
+use crate::error::{AppError, AppResult};
+use crate::media::LibraryIndex;
+use formats::FileEntry;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+
+/// 导入结果
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistImport {
+    pub playlist: UserPlaylist,
+    /// 找不到的条目（不会加入播放列表）
+    pub missing: Vec<MissingEntry>,
+}
+
+pub fn emit_playlists_changed(app: &AppHandle, store: &PlaylistStore) {
+    let _ = app.emit("user-playlists-changed", store.summaries());
+}
+
+/// 播放列表条目，标签优先取媒体库索引
+#[must_use]
+pub fn entry_for(library: &LibraryIndex, path: &str) -> PlaylistEntry {
+    library.track(path).map_or_else(
+        || PlaylistEntry { path: path.to_string(), ..Default::default() },
+        |track| PlaylistEntry { path: path.to_string(), title: track.title, artist: track.artist, duration: track.duration },
+    )
+}
+
+/// 导入播放列表文件，名称为空时使用文件中的标题或文件名
+pub fn import_file(store: &PlaylistStore, library: &LibraryIndex, path: &Path, name: Option<&str>) -> AppResult<PlaylistImport> {
+    let format = PlaylistFormat::from_path(path)?;
+    let content = fs::read(path).map_err(|e| AppError::from(e).context_message("Failed to read playlist").with_path(path))?;
+    // 旧的 .m3u 可能不是 UTF-8，无法解码的字符被替换
+    let file = formats::parse(&String::from_utf8_lossy(&content), format).map_err(|e| e.with_path(path))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut entries = Vec::with_capacity(file.entries.len());
+    let mut missing = Vec::new();
+    for (index, raw) in file.entries.into_iter().enumerate() {
+        match formats::resolve_location(&raw.location, base_dir, format == PlaylistFormat::Xspf) {
+            Ok(resolved) => {
+                let mut entry = entry_for(library, &resolved.to_string_lossy());
+                entry.title = entry.title.or(raw.title);
+                entry.artist = entry.artist.or(raw.artist);
+                entry.duration = entry.duration.or(raw.duration);
+                entries.push(entry);
+            }
+            Err(reason) => missing.push(MissingEntry { index, location: raw.location, reason }),
+        }
+    }
+
+    let name = name
+        .map(str::to_string)
+        .or(file.title)
+        .unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().to_string());
+    let playlist = store.create(&name, entries)?;
+    println!("Imported playlist '{}': {} tracks, {} missing", playlist.name, playlist.entries.len(), missing.len());
+    Ok(PlaylistImport { playlist, missing })
+}
+
+/// 导出播放列表，`relative` 为 true 时尽量使用相对于导出文件的路径，返回导出的音轨数
+pub fn export_file(playlist: &UserPlaylist, library: &LibraryIndex, path: &Path, format: PlaylistFormat, relative: bool) -> AppResult<usize> {
+    let base_dir = path.parent().filter(|_| relative);
+    let entries: Vec<FileEntry> = playlist
+        .entries
+        .iter()
+        .map(|entry| {
+            let track = (entry.title.is_none() || entry.duration.is_none()).then(|| library.track(&entry.path)).flatten();
+            FileEntry {
+                location: formats::export_location(&entry.path, base_dir, format == PlaylistFormat::Xspf),
+                title: entry.title.clone().or_else(|| track.as_ref().and_then(|t| t.title.clone())),
+                artist: entry.artist.clone().or_else(|| track.as_ref().and_then(|t| t.artist.clone())),
+                duration: entry.duration.or_else(|| track.as_ref().and_then(|t| t.duration)),
+            }
+        })
+        .collect();
+    let content = formats::write(&playlist.name, &entries, format);
+
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| AppError::from(e).context_message("Failed to create directory").with_path(parent))?;
+    }
+    fs::write(path, content).map_err(|e| AppError::from(e).context_message("Failed to export playlist").with_path(path))?;
+    Ok(entries.len())
+}
@@ -0,0 +1,281 @@
+//! 用户播放列表存储
+//!
+//! 每个播放列表保存为配置目录 `playlists/<ID>.json`，列表顺序保存在 `playlists/order.json`。
+
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::media::cover_cache::write_atomically;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ORDER_FILE: &str = "order.json";
+
+/// 生成播放列表 ID 的序号（同一毫秒内创建多个播放列表）
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 播放列表中的一个音轨
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlaylistEntry {
+    pub path: String,
+    /// 添加时已知的标签（来自媒体库或导入的播放列表文件），用于导出和显示缺失的音轨
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// 秒
+    pub duration: Option<f64>,
+}
+
+/// 用户播放列表
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserPlaylist {
+    pub id: String,
+    pub name: String,
+    /// 创建和修改时间（Unix 毫秒）
+    pub created_at: u64,
+    pub updated_at: u64,
+    #[serde(default)]
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// 播放列表概要（列表视图使用，不含音轨）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserPlaylistSummary {
+    pub id: String,
+    pub name: String,
+    pub track_count: usize,
+    /// 已知时长之和（秒）
+    pub duration: f64,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl UserPlaylist {
+    #[must_use]
+    pub fn summary(&self) -> UserPlaylistSummary {
+        UserPlaylistSummary {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            track_count: self.entries.len(),
+            duration: self.entries.iter().filter_map(|entry| entry.duration).fold(0.0, |total, secs| total + secs),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    /// 插入音轨，`index` 为空或超出范围时添加到末尾
+    pub fn insert(&mut self, index: Option<usize>, entries: Vec<PlaylistEntry>) {
+        let index = index.unwrap_or(self.entries.len()).min(self.entries.len());
+        self.entries.splice(index..index, entries);
+    }
+
+    /// 删除多个位置的音轨（重复或超出范围的位置被忽略），返回删除的数量
+    pub fn remove(&mut self, indices: &[usize]) -> usize {
+        let before = self.entries.len();
+        let mut index = 0;
+        self.entries.retain(|_| {
+            let keep = !indices.contains(&index);
+            index += 1;
+            keep
+        });
+        before - self.entries.len()
+    }
+
+    pub fn move_entry(&mut self, from: usize, to: usize) -> AppResult<()> {
+        if from >= self.entries.len() || to >= self.entries.len() {
+            return Err(AppError::invalid_argument("Playlist index out of range")
+                .with_context("from", from)
+                .with_context("to", to));
+        }
+        let entry = self.entries.remove(from);
+        self.entries.insert(to, entry);
+        Ok(())
+    }
+}
+
+/// 用户播放列表管理
+pub struct PlaylistStore {
+    dir: PathBuf,
+    /// 按显示顺序排列
+    playlists: Mutex<Vec<UserPlaylist>>,
+}
+
+impl PlaylistStore {
+    /// 读取配置目录中的播放列表，无法解析的文件被跳过
+    #[must_use]
+    pub fn open(config_dir: &str) -> Self {
+        let dir = Path::new(config_dir).join("playlists");
+        let mut playlists: Vec<UserPlaylist> = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "json") && path.file_name().is_some_and(|name| name != ORDER_FILE))
+                    .filter_map(|path| {
+                        let playlist = fs::read_to_string(&path)
+                            .map_err(AppError::from)
+                            .and_then(|content| serde_json::from_str::<UserPlaylist>(&content).map_err(AppError::from));
+                        match playlist {
+                            Ok(playlist) if validate_id(&playlist.id).is_ok() => Some(playlist),
+                            Ok(_) => None,
+                            Err(e) => {
+                                eprintln!("Failed to load playlist {}: {e}", path.display());
+                                None
+                            }
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // 不在顺序文件中的播放列表按创建时间排在最后
+        let order: Vec<String> = fs::read_to_string(dir.join(ORDER_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let position: HashMap<&str, usize> = order.iter().enumerate().map(|(index, id)| (id.as_str(), index)).collect();
+        playlists.sort_by_key(|playlist| (position.get(playlist.id.as_str()).copied().unwrap_or(usize::MAX), playlist.created_at));
+        if !playlists.is_empty() {
+            println!("Loaded {} user playlists", playlists.len());
+        }
+        Self { dir, playlists: Mutex::new(playlists) }
+    }
+
+    #[must_use]
+    pub fn summaries(&self) -> Vec<UserPlaylistSummary> {
+        self.playlists.lock().unwrap().iter().map(UserPlaylist::summary).collect()
+    }
+
+    pub fn get(&self, id: &str) -> AppResult<UserPlaylist> {
+        self.playlists.lock().unwrap().iter().find(|playlist| playlist.id == id).cloned().ok_or_else(|| not_found(id))
+    }
+
+    /// 新建播放列表（添加到最后）
+    pub fn create(&self, name: &str, entries: Vec<PlaylistEntry>) -> AppResult<UserPlaylist> {
+        let name = validate_name(name)?;
+        let mut playlists = self.playlists.lock().unwrap();
+        let now = now_millis();
+        let playlist = UserPlaylist { id: new_id(now), name, created_at: now, updated_at: now, entries };
+        self.save(&playlist)?;
+        playlists.push(playlist.clone());
+        self.save_order(&playlists)?;
+        Ok(playlist)
+    }
+
+    /// 修改播放列表并保存
+    pub fn update<T>(&self, id: &str, f: impl FnOnce(&mut UserPlaylist) -> AppResult<T>) -> AppResult<(T, UserPlaylist)> {
+        let mut playlists = self.playlists.lock().unwrap();
+        let playlist = playlists.iter_mut().find(|playlist| playlist.id == id).ok_or_else(|| not_found(id))?;
+        let mut updated = playlist.clone();
+        let value = f(&mut updated)?;
+        updated.updated_at = now_millis();
+        self.save(&updated)?;
+        *playlist = updated.clone();
+        Ok((value, updated))
+    }
+
+    pub fn rename(&self, id: &str, name: &str) -> AppResult<UserPlaylist> {
+        let name = validate_name(name)?;
+        self.update(id, |playlist| {
+            playlist.name = name;
+            Ok(())
+        })
+        .map(|((), playlist)| playlist)
+    }
+
+    /// 复制播放列表，插入到原播放列表之后，名称为空时使用 `<原名称> (2)` 等不重复的名称
+    pub fn duplicate(&self, id: &str, name: Option<&str>) -> AppResult<UserPlaylist> {
+        let mut playlists = self.playlists.lock().unwrap();
+        let index = playlists.iter().position(|playlist| playlist.id == id).ok_or_else(|| not_found(id))?;
+        let source = &playlists[index];
+        let name = match name {
+            Some(name) => validate_name(name)?,
+            // 最多有 len 个名称被占用，范围内一定有可用的名称
+            None => (2..=playlists.len() + 2)
+                .map(|n| format!("{} ({n})", source.name))
+                .find(|candidate| !playlists.iter().any(|playlist| playlist.name == *candidate))
+                .unwrap_or_default(),
+        };
+        let now = now_millis();
+        let copy = UserPlaylist { id: new_id(now), name, created_at: now, updated_at: now, entries: source.entries.clone() };
+        self.save(&copy)?;
+        playlists.insert(index + 1, copy.clone());
+        self.save_order(&playlists)?;
+        Ok(copy)
+    }
+
+    pub fn delete(&self, id: &str) -> AppResult<()> {
+        let mut playlists = self.playlists.lock().unwrap();
+        let index = playlists.iter().position(|playlist| playlist.id == id).ok_or_else(|| not_found(id))?;
+        let path = self.playlist_path(id)?;
+        fs::remove_file(&path)
+            .or_else(|e| if e.kind() == std::io::ErrorKind::NotFound { Ok(()) } else { Err(e) })
+            .map_err(|e| AppError::from(e).context_message("Failed to delete playlist").with_path(&path))?;
+        playlists.remove(index);
+        self.save_order(&playlists)
+    }
+
+    /// 调整播放列表顺序，`ids` 中未列出的播放列表保持原有相对顺序排在最后
+    pub fn reorder(&self, ids: &[String]) -> AppResult<()> {
+        let mut playlists = self.playlists.lock().unwrap();
+        if let Some(unknown) = ids.iter().find(|id| !playlists.iter().any(|playlist| playlist.id == **id)) {
+            return Err(not_found(unknown));
+        }
+        let position: HashMap<&str, usize> = ids.iter().enumerate().map(|(index, id)| (id.as_str(), index)).collect();
+        let mut reordered = playlists.clone();
+        reordered.sort_by_key(|playlist| position.get(playlist.id.as_str()).copied().unwrap_or(usize::MAX));
+        self.save_order(&reordered)?;
+        *playlists = reordered;
+        Ok(())
+    }
+
+    fn save(&self, playlist: &UserPlaylist) -> AppResult<()> {
+        let content = serde_json::to_vec_pretty(playlist).map_err(|e| AppError::from(e).context_message("Failed to serialize playlist"))?;
+        write_atomically(&self.playlist_path(&playlist.id)?, &content)
+    }
+
+    fn save_order(&self, playlists: &[UserPlaylist]) -> AppResult<()> {
+        let order: Vec<&str> = playlists.iter().map(|playlist| playlist.id.as_str()).collect();
+        let content = serde_json::to_vec_pretty(&order).map_err(|e| AppError::from(e).context_message("Failed to serialize playlist order"))?;
+        write_atomically(&self.dir.join(ORDER_FILE), &content)
+    }
+
+    fn playlist_path(&self, id: &str) -> AppResult<PathBuf> {
+        validate_id(id)?;
+        Ok(self.dir.join(format!("{id}.json")))
+    }
+}
+
+fn not_found(id: &str) -> AppError {
+    AppError::new(ErrorCode::NotFound, "Playlist not found").with_context("id", id)
+}
+
+fn validate_name(name: &str) -> AppResult<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::invalid_argument("Playlist name cannot be empty"));
+    }
+    Ok(name.to_string())
+}
+
+/// 播放列表 ID 为十六进制（同时防止路径穿越）
+fn validate_id(id: &str) -> AppResult<()> {
+    if !id.is_empty() && id.len() <= 32 && id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(AppError::invalid_argument("Invalid playlist id").with_context("id", id))
+    }
+}
+
+fn new_id(now: u64) -> String {
+    format!("{now:012x}{:04x}", ID_COUNTER.fetch_add(1, Ordering::Relaxed) & 0xffff)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
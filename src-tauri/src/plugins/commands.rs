@@ -3,32 +3,33 @@
 use super::manager::{
     self, PluginManifest,
 };
-use serde::{Deserialize, Serialize};
+use crate::error::{AppError, AppResult, ErrorCode};
+use serde::Serialize;
 use tauri::command;
 
 /// 安装结果
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
 pub struct InstallResult {
     pub success: bool,
     pub path: Option<String>,
-    pub error: Option<String>,
+    pub error: Option<AppError>,
 }
 
 /// 列出所有插件
 #[command]
-pub fn list_plugins() -> Result<Vec<String>, String> {
+pub fn list_plugins() -> AppResult<Vec<String>> {
     manager::list_plugin_dirs()
 }
 
 /// 读取插件清单
 #[command]
-pub fn read_plugin_manifest(path: &str) -> Result<PluginManifest, String> {
+pub fn read_plugin_manifest(path: &str) -> AppResult<PluginManifest> {
     manager::read_manifest(path)
 }
 
 /// 读取插件主文件
 #[command]
-pub fn read_plugin_main(path: &str, main: &str) -> Result<String, String> {
+pub fn read_plugin_main(path: &str, main: &str) -> AppResult<String> {
     let main_file = if main.is_empty() { "index.js" } else { main };
     manager::read_main_file(path, main_file)
 }
@@ -53,20 +54,20 @@ pub fn install_plugin(source: &str) -> InstallResult {
 
 /// 卸载插件
 #[command]
-pub fn uninstall_plugin(plugin_id: &str) -> Result<(), String> {
+pub fn uninstall_plugin(plugin_id: &str) -> AppResult<()> {
     manager::uninstall_plugin(plugin_id)
 }
 
 /// 获取插件目录路径
 #[command]
-pub fn get_plugins_directory() -> Result<String, String> {
+pub fn get_plugins_directory() -> AppResult<String> {
     manager::get_plugins_dir()
         .map(|p| p.to_string_lossy().to_string())
 }
 
 /// 在文件管理器中打开插件目录
 #[command]
-pub fn open_plugins_directory() -> Result<(), String> {
+pub fn open_plugins_directory() -> AppResult<()> {
     let plugins_dir = manager::get_plugins_dir()?;
     
     #[cfg(target_os = "windows")]
@@ -74,7 +75,7 @@ pub fn open_plugins_directory() -> Result<(), String> {
         std::process::Command::new("explorer")
             .arg(&plugins_dir)
             .spawn()
-            .map_err(|e| format!("无法打开目录: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to open directory"))?;
     }
     
     #[cfg(target_os = "macos")]
@@ -82,7 +83,7 @@ pub fn open_plugins_directory() -> Result<(), String> {
         std::process::Command::new("open")
             .arg(&plugins_dir)
             .spawn()
-            .map_err(|e| format!("无法打开目录: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to open directory"))?;
     }
     
     #[cfg(target_os = "linux")]
@@ -90,7 +91,7 @@ pub fn open_plugins_directory() -> Result<(), String> {
         std::process::Command::new("xdg-open")
             .arg(&plugins_dir)
             .spawn()
-            .map_err(|e| format!("无法打开目录: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to open directory"))?;
     }
     
     Ok(())
@@ -98,42 +99,42 @@ pub fn open_plugins_directory() -> Result<(), String> {
 
 /// 保存截图到程序目录下的 screenshots 文件夹
 #[command]
-pub fn save_screenshot(filename: &str, data: Vec<u8>) -> Result<String, String> {
+pub fn save_screenshot(filename: &str, data: Vec<u8>) -> AppResult<String> {
     let exe_path = std::env::current_exe()
-        .map_err(|e| format!("无法获取可执行文件路径: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Failed to locate executable"))?;
     let exe_dir = exe_path.parent()
-        .ok_or("无法获取可执行文件目录")?;
+        .ok_or_else(|| AppError::new(ErrorCode::Io, "Failed to locate executable directory"))?;
     
     let screenshots_dir = exe_dir.join("screenshots");
     
     // 创建目录（如果不存在）
     if !screenshots_dir.exists() {
         std::fs::create_dir_all(&screenshots_dir)
-            .map_err(|e| format!("无法创建截图目录: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to create screenshots directory"))?;
     }
     
     let file_path = screenshots_dir.join(filename);
     
     std::fs::write(&file_path, &data)
-        .map_err(|e| format!("无法保存截图: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Failed to save screenshot"))?;
     
     Ok(file_path.to_string_lossy().to_string())
 }
 
 /// 打开截图目录
 #[command]
-pub fn open_screenshots_directory() -> Result<(), String> {
+pub fn open_screenshots_directory() -> AppResult<()> {
     let exe_path = std::env::current_exe()
-        .map_err(|e| format!("无法获取可执行文件路径: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Failed to locate executable"))?;
     let exe_dir = exe_path.parent()
-        .ok_or("无法获取可执行文件目录")?;
+        .ok_or_else(|| AppError::new(ErrorCode::Io, "Failed to locate executable directory"))?;
     
     let screenshots_dir = exe_dir.join("screenshots");
     
     // 创建目录（如果不存在）
     if !screenshots_dir.exists() {
         std::fs::create_dir_all(&screenshots_dir)
-            .map_err(|e| format!("无法创建截图目录: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to create screenshots directory"))?;
     }
     
     #[cfg(target_os = "windows")]
@@ -141,7 +142,7 @@ pub fn open_screenshots_directory() -> Result<(), String> {
         std::process::Command::new("explorer")
             .arg(&screenshots_dir)
             .spawn()
-            .map_err(|e| format!("无法打开目录: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to open directory"))?;
     }
     
     #[cfg(target_os = "macos")]
@@ -149,7 +150,7 @@ pub fn open_screenshots_directory() -> Result<(), String> {
         std::process::Command::new("open")
             .arg(&screenshots_dir)
             .spawn()
-            .map_err(|e| format!("无法打开目录: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to open directory"))?;
     }
     
     #[cfg(target_os = "linux")]
@@ -157,7 +158,7 @@ pub fn open_screenshots_directory() -> Result<(), String> {
         std::process::Command::new("xdg-open")
             .arg(&screenshots_dir)
             .spawn()
-            .map_err(|e| format!("无法打开目录: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to open directory"))?;
     }
     
     Ok(())
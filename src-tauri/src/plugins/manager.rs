@@ -4,6 +4,7 @@
 use std::fs;
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
+use crate::error::{AppError, AppResult, ErrorCode};
 
 /// 插件清单
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,26 +29,26 @@ const fn default_auto_activate() -> bool {
 }
 
 /// 获取插件目录路径（与可执行文件同级）
-pub fn get_plugins_dir() -> Result<PathBuf, String> {
+pub fn get_plugins_dir() -> AppResult<PathBuf> {
     let exe_path = std::env::current_exe()
-        .map_err(|e| format!("无法获取可执行文件路径: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Failed to locate executable"))?;
     
     let exe_dir = exe_path.parent()
-        .ok_or("无法获取可执行文件目录")?;
+        .ok_or_else(|| AppError::new(ErrorCode::Io, "Failed to locate executable directory"))?;
     
     let plugins_dir = exe_dir.join("plugins");
     
     // 确保目录存在
     if !plugins_dir.exists() {
         fs::create_dir_all(&plugins_dir)
-            .map_err(|e| format!("无法创建插件目录: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to create plugins directory"))?;
     }
     
     Ok(plugins_dir)
 }
 
 /// 列出所有插件目录
-pub fn list_plugin_dirs() -> Result<Vec<String>, String> {
+pub fn list_plugin_dirs() -> AppResult<Vec<String>> {
     let plugins_dir = get_plugins_dir()?;
     
     let mut plugin_dirs = Vec::new();
@@ -69,47 +70,47 @@ pub fn list_plugin_dirs() -> Result<Vec<String>, String> {
 }
 
 /// 读取插件清单
-pub fn read_manifest(plugin_name: &str) -> Result<PluginManifest, String> {
+pub fn read_manifest(plugin_name: &str) -> AppResult<PluginManifest> {
     let plugins_dir = get_plugins_dir()?;
     let manifest_path = plugins_dir.join(plugin_name).join("manifest.json");
     
     let content = fs::read_to_string(&manifest_path)
-        .map_err(|e| format!("无法读取插件清单: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Failed to read plugin manifest").with_path(&manifest_path))?;
     
     let manifest: PluginManifest = serde_json::from_str(&content)
-        .map_err(|e| format!("无法解析插件清单: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Failed to parse plugin manifest").with_path(&manifest_path))?;
     
     Ok(manifest)
 }
 
 /// 读取插件主文件
-pub fn read_main_file(plugin_name: &str, main_file: &str) -> Result<String, String> {
+pub fn read_main_file(plugin_name: &str, main_file: &str) -> AppResult<String> {
     let plugins_dir = get_plugins_dir()?;
     let main_path = plugins_dir.join(plugin_name).join(main_file);
     
     fs::read_to_string(&main_path)
-        .map_err(|e| format!("无法读取插件主文件: {e}"))
+        .map_err(|e| AppError::from(e).context_message("Failed to read plugin main file").with_path(&main_path))
 }
 
 /// 安装插件
-pub fn install_plugin_from_path(source_path: &str) -> Result<String, String> {
+pub fn install_plugin_from_path(source_path: &str) -> AppResult<String> {
     let source = PathBuf::from(source_path);
     
     if !source.exists() {
-        return Err("源路径不存在".to_string());
+        return Err(AppError::new(ErrorCode::FileNotFound, "Plugin source not found").with_path(&source));
     }
     
     // 读取源目录的 manifest
     let manifest_path = source.join("manifest.json");
     if !manifest_path.exists() {
-        return Err("插件缺少 manifest.json".to_string());
+        return Err(AppError::new(ErrorCode::NotFound, "Plugin is missing manifest.json").with_path(&source));
     }
     
     let manifest_content = fs::read_to_string(&manifest_path)
-        .map_err(|e| format!("无法读取清单: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Failed to read plugin manifest"))?;
     
     let manifest: PluginManifest = serde_json::from_str(&manifest_content)
-        .map_err(|e| format!("无法解析清单: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Failed to parse plugin manifest"))?;
     
     // 复制到插件目录
     let plugins_dir = get_plugins_dir()?;
@@ -117,7 +118,7 @@ pub fn install_plugin_from_path(source_path: &str) -> Result<String, String> {
     
     if target_dir.exists() {
         fs::remove_dir_all(&target_dir)
-            .map_err(|e| format!("无法删除旧版本: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to remove previous version"))?;
     }
     
     copy_dir_recursive(&source, &target_dir)?;
@@ -126,25 +127,25 @@ pub fn install_plugin_from_path(source_path: &str) -> Result<String, String> {
 }
 
 /// 卸载插件
-pub fn uninstall_plugin(plugin_id: &str) -> Result<(), String> {
+pub fn uninstall_plugin(plugin_id: &str) -> AppResult<()> {
     let plugins_dir = get_plugins_dir()?;
     let plugin_dir = plugins_dir.join(plugin_id);
     
     if plugin_dir.exists() {
         fs::remove_dir_all(&plugin_dir)
-            .map_err(|e| format!("无法删除插件: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to remove plugin"))?;
     }
     
     Ok(())
 }
 
 /// 递归复制目录
-fn copy_dir_recursive(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
+fn copy_dir_recursive(src: &PathBuf, dst: &PathBuf) -> AppResult<()> {
     fs::create_dir_all(dst)
-        .map_err(|e| format!("无法创建目录: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Failed to create directory"))?;
     
-    for entry in fs::read_dir(src).map_err(|e| format!("无法读取目录: {e}"))? {
-        let entry = entry.map_err(|e| format!("无法读取条目: {e}"))?;
+    for entry in fs::read_dir(src).map_err(|e| AppError::from(e).context_message("Failed to read directory"))? {
+        let entry = entry.map_err(|e| AppError::from(e).context_message("Failed to read directory entry"))?;
         let src_path = entry.path();
         let dst_path = dst.join(entry.file_name());
         
@@ -152,7 +153,7 @@ fn copy_dir_recursive(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
             copy_dir_recursive(&src_path, &dst_path)?;
         } else {
             fs::copy(&src_path, &dst_path)
-                .map_err(|e| format!("无法复制文件: {e}"))?;
+                .map_err(|e| AppError::from(e).context_message("Failed to copy file"))?;
         }
     }
     
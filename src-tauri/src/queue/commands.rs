@@ -0,0 +1,127 @@
+//! 播放队列相关的 Tauri 命令
+
+use super::{emit_queue_changed, play_from_queue, sync_next_track, PlayQueue, QueueSnapshot, RepeatMode};
+use crate::error::AppResult;
+use crate::AppState;
+use tauri::{command, AppHandle, State};
+
+/// 修改队列，发送 `queue-changed` 事件并同步预加载的下一首
+fn update_queue<T>(
+    app: &AppHandle,
+    state: &State<AppState>,
+    f: impl FnOnce(&mut PlayQueue) -> AppResult<T>,
+) -> AppResult<(T, QueueSnapshot)> {
+    let (value, snapshot) = {
+        let mut queue = state.queue.lock().unwrap();
+        let value = f(&mut queue)?;
+        (value, queue.snapshot())
+    };
+    emit_queue_changed(app, &snapshot);
+    Ok((value, snapshot))
+}
+
+#[command]
+pub fn get_play_queue(state: State<AppState>) -> AppResult<QueueSnapshot> {
+    Ok(state.queue.lock().unwrap().snapshot())
+}
+
+/// 添加到队列末尾
+#[command]
+pub fn queue_enqueue(app: AppHandle, state: State<AppState>, paths: Vec<String>) -> AppResult<QueueSnapshot> {
+    let ((), snapshot) = update_queue(&app, &state, |q| {
+        q.enqueue(paths);
+        Ok(())
+    })?;
+    sync_next_track(&app);
+    Ok(snapshot)
+}
+
+/// 插入到指定位置
+#[command]
+pub fn queue_insert(app: AppHandle, state: State<AppState>, index: usize, paths: Vec<String>) -> AppResult<QueueSnapshot> {
+    let ((), snapshot) = update_queue(&app, &state, |q| {
+        q.insert(index, paths);
+        Ok(())
+    })?;
+    sync_next_track(&app);
+    Ok(snapshot)
+}
+
+#[command]
+pub fn queue_move(app: AppHandle, state: State<AppState>, from: usize, to: usize) -> AppResult<QueueSnapshot> {
+    let ((), snapshot) = update_queue(&app, &state, |q| q.move_item(from, to))?;
+    sync_next_track(&app);
+    Ok(snapshot)
+}
+
+#[command]
+pub fn queue_remove(app: AppHandle, state: State<AppState>, index: usize) -> AppResult<QueueSnapshot> {
+    let (removed, snapshot) = update_queue(&app, &state, |q| q.remove(index))?;
+    println!("Removed from queue: {}", removed.path);
+    sync_next_track(&app);
+    Ok(snapshot)
+}
+
+#[command]
+pub fn queue_clear(app: AppHandle, state: State<AppState>) -> AppResult<QueueSnapshot> {
+    let ((), snapshot) = update_queue(&app, &state, |q| {
+        q.clear();
+        Ok(())
+    })?;
+    Ok(snapshot)
+}
+
+/// 用前端播放列表替换队列，`current` 为正在播放的条目（不会重新播放）
+#[command]
+pub fn queue_replace(app: AppHandle, state: State<AppState>, paths: Vec<String>, current: Option<usize>) -> AppResult<QueueSnapshot> {
+    let ((), snapshot) = update_queue(&app, &state, |q| q.replace(paths, current))?;
+    sync_next_track(&app);
+    Ok(snapshot)
+}
+
+/// 播放队列中的指定条目
+#[command]
+pub fn queue_play(app: AppHandle, state: State<AppState>, index: usize) -> AppResult<QueueSnapshot> {
+    let (path, snapshot) = update_queue(&app, &state, |q| q.jump(index))?;
+    play_from_queue(&app, &path)?;
+    Ok(snapshot)
+}
+
+/// 手动切换到下一首（单曲循环时也会前进）
+#[command]
+pub fn queue_next(app: AppHandle, state: State<AppState>) -> AppResult<QueueSnapshot> {
+    let (path, snapshot) = update_queue(&app, &state, |q| Ok(q.advance(false)))?;
+    if let Some(path) = path {
+        play_from_queue(&app, &path)?;
+    }
+    Ok(snapshot)
+}
+
+#[command]
+pub fn queue_previous(app: AppHandle, state: State<AppState>) -> AppResult<QueueSnapshot> {
+    let (path, snapshot) = update_queue(&app, &state, |q| Ok(q.previous()))?;
+    if let Some(path) = path {
+        play_from_queue(&app, &path)?;
+    }
+    Ok(snapshot)
+}
+
+#[command]
+pub fn queue_set_shuffle(app: AppHandle, state: State<AppState>, enabled: bool) -> AppResult<QueueSnapshot> {
+    let ((), snapshot) = update_queue(&app, &state, |q| {
+        q.set_shuffle(enabled);
+        Ok(())
+    })?;
+    sync_next_track(&app);
+    Ok(snapshot)
+}
+
+#[command]
+pub fn queue_set_repeat(app: AppHandle, state: State<AppState>, mode: RepeatMode) -> AppResult<QueueSnapshot> {
+    let ((), snapshot) = update_queue(&app, &state, |q| {
+        q.set_repeat(mode);
+        Ok(())
+    })?;
+    sync_next_track(&app);
+    Ok(snapshot)
+}
@@ -0,0 +1,116 @@
+//! 播放队列模块
+//!
+//! 后端持有的播放队列：音轨结束时自动前进，并预加载下一首以实现无缝播放，
+//! 前端隐藏或繁忙时也能继续播放。队列变化通过 `queue-changed` 事件通知前端、插件和任务栏。
+
+pub mod commands;
+pub mod play_queue;
+
+pub use play_queue::{PlayQueue, QueueItem, QueueSnapshot, RepeatMode};
+
+use crate::audio::{gapless, playback};
+use crate::error::AppResult;
+use crate::AppState;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub fn emit_queue_changed(app: &AppHandle, snapshot: &QueueSnapshot) {
+    let _ = app.emit("queue-changed", snapshot);
+}
+
+/// 播放队列中的音轨，并预加载下一首
+pub fn play_from_queue(app: &AppHandle, path: &str) -> AppResult<()> {
+    let state = app.state::<AppState>();
+    playback::start_track(app, &state, path, None)?;
+    sync_next_track(app);
+    Ok(())
+}
+
+/// 使无缝播放的下一首与队列保持一致（仅在队列驱动当前播放时生效）
+pub fn sync_next_track(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let playing = state.player.current_path.lock().unwrap().clone();
+    let next = {
+        let queue = state.queue.lock().unwrap();
+        if playing.is_none() || queue.current_path() != playing {
+            return;
+        }
+        // 睡眠定时器将在当前音轨结束后停止
+        if state.player.sleep_timer.is_last_track() {
+            None
+        } else {
+            queue.peek_next()
+        }
+    };
+    if next == gapless::peek_next_path(&state.player.next_track) {
+        return;
+    }
+    match next {
+        Some(path) => {
+            if let Err(e) = playback::queue_next_track(app, &state, &path) {
+                eprintln!("Failed to preload next queue track {path}: {e}");
+            }
+        }
+        None => gapless::clear_next_track(&state.player.next_track),
+    }
+}
+
+/// 音轨结束时由 `emit_track_ended` 调用（位于播放事件线程或独占模式推送线程，耗时操作放到新线程）
+///
+/// 返回队列将要加载播放的下一首（已无缝衔接或队列结束时为空）。
+pub fn on_track_ended(app: &AppHandle, ended: Option<&str>, next: Option<&str>) -> Option<String> {
+    let state = app.try_state::<AppState>()?;
+    let (advanced, snapshot) = {
+        let mut queue = state.queue.lock().unwrap();
+        if ended.is_none() || queue.current_path().as_deref() != ended {
+            return None;
+        }
+        let mut advanced = queue.advance(true);
+        // 已衔接的音轨与队列不一致（期间队列被修改），以实际播放的为准
+        if let Some(next) = next
+            && advanced.as_deref() != Some(next)
+            && let Some(index) = queue.position_of_path(next)
+        {
+            advanced = queue.jump(index).ok();
+        }
+        (advanced, queue.snapshot())
+    };
+    emit_queue_changed(app, &snapshot);
+
+    let gapless_started = next.is_some();
+    let queued = advanced.clone().filter(|_| !gapless_started);
+    let app = app.clone();
+    std::thread::spawn(move || match advanced {
+        // 下一首已无缝开始，只需预加载再下一首
+        Some(_) if gapless_started => sync_next_track(&app),
+        Some(path) => {
+            if let Err(e) = play_from_queue(&app, &path) {
+                eprintln!("Failed to play next queue track {path}: {e}");
+            }
+        }
+        None => println!("Play queue finished"),
+    });
+    queued
+}
+
+/// 任务栏上一首/下一首按钮：队列非空时由后端直接切换，返回 false 表示交给前端处理
+pub fn skip_from_taskbar(app: &AppHandle, forward: bool) -> bool {
+    let Some(state) = app.try_state::<AppState>() else { return false };
+    let (path, snapshot) = {
+        let mut queue = state.queue.lock().unwrap();
+        if queue.is_empty() {
+            return false;
+        }
+        let path = if forward { queue.advance(false) } else { queue.previous() };
+        (path, queue.snapshot())
+    };
+    emit_queue_changed(app, &snapshot);
+    if let Some(path) = path {
+        let app = app.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = play_from_queue(&app, &path) {
+                eprintln!("Failed to play queue track {path}: {e}");
+            }
+        });
+    }
+    true
+}
@@ -0,0 +1,516 @@
+//! 播放队列数据结构
+//!
+//! 队列条目带有稳定 ID，插入、移动、删除不会影响当前音轨的定位。
+//! 随机播放维护一个独立的播放顺序（一轮内不重复），列表循环时重新洗牌。
+//! 下一轮的顺序由预先生成的种子决定，预览下一首不会修改队列。
+
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+
+/// 循环模式（序列化名称与前端 `RepeatMode` 一致）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepeatMode {
+    #[default]
+    #[serde(rename = "none")]
+    Off,
+    /// 单曲循环
+    #[serde(rename = "track")]
+    One,
+    /// 列表循环
+    #[serde(rename = "list")]
+    All,
+}
+
+/// 队列条目
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueItem {
+    pub id: u64,
+    pub path: String,
+}
+
+/// 队列快照（用于 `queue-changed` 事件和命令返回值）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSnapshot {
+    pub items: Vec<QueueItem>,
+    pub current_index: Option<usize>,
+    pub next_index: Option<usize>,
+    pub shuffle: bool,
+    pub repeat: RepeatMode,
+}
+
+fn out_of_range(index: usize) -> AppError {
+    AppError::invalid_argument("Queue index out of range").with_context("index", index)
+}
+
+/// 简单的 SplitMix64 随机数生成器（洗牌用）
+struct Rng(u64);
+
+impl Rng {
+    fn seeded() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        Self(nanos ^ 0x9E37_79B9_7F4A_7C15)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// 返回 [0, n) 内的随机数
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// 播放队列
+pub struct PlayQueue {
+    items: Vec<QueueItem>,
+    next_id: u64,
+    current: Option<u64>,
+    shuffle: bool,
+    repeat: RepeatMode,
+    /// 随机播放顺序（条目 ID）
+    order: Vec<u64>,
+    /// 当前音轨在随机顺序中的位置（None 表示尚未开始本轮）
+    order_pos: Option<usize>,
+    /// 下一轮随机顺序的种子（预览与前进得到相同的顺序）
+    round_seed: u64,
+    rng: Rng,
+}
+
+impl Default for PlayQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlayQueue {
+    #[must_use]
+    pub fn new() -> Self {
+        let mut rng = Rng::seeded();
+        Self {
+            items: Vec::new(),
+            next_id: 0,
+            current: None,
+            shuffle: false,
+            repeat: RepeatMode::Off,
+            order: Vec::new(),
+            order_pos: None,
+            round_seed: rng.next_u64(),
+            rng,
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[must_use]
+    pub const fn shuffle(&self) -> bool {
+        self.shuffle
+    }
+
+    #[must_use]
+    pub const fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    fn index_of(&self, id: u64) -> Option<usize> {
+        self.items.iter().position(|item| item.id == id)
+    }
+
+    /// 按路径查找条目位置
+    #[must_use]
+    pub fn position_of_path(&self, path: &str) -> Option<usize> {
+        self.items.iter().position(|item| item.path == path)
+    }
+
+    fn path_of(&self, id: u64) -> Option<String> {
+        self.items.iter().find(|item| item.id == id).map(|item| item.path.clone())
+    }
+
+    #[must_use]
+    pub fn current_index(&self) -> Option<usize> {
+        self.current.and_then(|id| self.index_of(id))
+    }
+
+    #[must_use]
+    pub fn current_path(&self) -> Option<String> {
+        self.current.and_then(|id| self.path_of(id))
+    }
+
+    fn new_items(&mut self, paths: Vec<String>) -> Vec<QueueItem> {
+        paths
+            .into_iter()
+            .map(|path| {
+                self.next_id += 1;
+                QueueItem { id: self.next_id, path }
+            })
+            .collect()
+    }
+
+    /// 随机模式下把新条目插入到本轮尚未播放的部分
+    fn add_to_order(&mut self, ids: impl Iterator<Item = u64>) {
+        if !self.shuffle {
+            return;
+        }
+        let start = self.order_pos.map_or(0, |p| p + 1);
+        for id in ids {
+            let at = start + self.rng.below(self.order.len() - start + 1);
+            self.order.insert(at, id);
+        }
+    }
+
+    /// 添加到队列末尾
+    pub fn enqueue(&mut self, paths: Vec<String>) {
+        let first_id = self.next_id + 1;
+        let items = self.new_items(paths);
+        self.items.extend(items);
+        self.add_to_order(first_id..=self.next_id);
+    }
+
+    /// 插入到指定位置（超出范围时追加到末尾）
+    pub fn insert(&mut self, index: usize, paths: Vec<String>) {
+        let index = index.min(self.items.len());
+        let first_id = self.next_id + 1;
+        let items = self.new_items(paths);
+        self.items.splice(index..index, items);
+        self.add_to_order(first_id..=self.next_id);
+    }
+
+    /// 移动条目（只影响列表顺序，不影响随机顺序）
+    pub fn move_item(&mut self, from: usize, to: usize) -> AppResult<()> {
+        if from >= self.items.len() || to >= self.items.len() {
+            return Err(AppError::invalid_argument("Queue index out of range")
+                .with_context("from", from)
+                .with_context("to", to));
+        }
+        let item = self.items.remove(from);
+        self.items.insert(to, item);
+        Ok(())
+    }
+
+    /// 删除条目；删除当前音轨时，当前位置退到前一首，使下一首仍是原本紧随其后的音轨
+    pub fn remove(&mut self, index: usize) -> AppResult<QueueItem> {
+        if index >= self.items.len() {
+            return Err(out_of_range(index));
+        }
+        let removed = self.items.remove(index);
+        let was_current = self.current == Some(removed.id);
+
+        if self.shuffle {
+            if let Some(pos) = self.order.iter().position(|id| *id == removed.id) {
+                self.order.remove(pos);
+                match self.order_pos {
+                    Some(p) if pos < p => self.order_pos = Some(p - 1),
+                    Some(p) if pos == p => self.order_pos = p.checked_sub(1),
+                    _ => {}
+                }
+            }
+            if was_current {
+                self.current = self.order_pos.map(|p| self.order[p]);
+            }
+        } else if was_current {
+            self.current = index.checked_sub(1).map(|i| self.items[i].id);
+        }
+        Ok(removed)
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.order.clear();
+        self.order_pos = None;
+        self.current = None;
+    }
+
+    /// 替换全部条目，`current` 为正在播放的条目（只标记当前位置）
+    pub fn replace(&mut self, paths: Vec<String>, current: Option<usize>) -> AppResult<()> {
+        if let Some(index) = current.filter(|index| *index >= paths.len()) {
+            return Err(out_of_range(index));
+        }
+        self.clear();
+        self.enqueue(paths);
+        if let Some(index) = current {
+            self.jump(index)?;
+        }
+        Ok(())
+    }
+
+    /// 跳转到指定条目
+    pub fn jump(&mut self, index: usize) -> AppResult<String> {
+        let item = self.items.get(index).cloned().ok_or_else(|| out_of_range(index))?;
+        if self.shuffle {
+            // 把目标移到本轮当前位置之后，避免本轮内重复
+            let mut pos = self.order_pos;
+            if let Some(target) = self.order.iter().position(|id| *id == item.id) {
+                self.order.remove(target);
+                pos = match pos {
+                    Some(p) if target < p => Some(p - 1),
+                    Some(p) if target == p => p.checked_sub(1),
+                    other => other,
+                };
+            }
+            let at = pos.map_or(0, |p| p + 1).min(self.order.len());
+            self.order.insert(at, item.id);
+            self.order_pos = Some(at);
+        }
+        self.current = Some(item.id);
+        Ok(item.path)
+    }
+
+    /// 下一轮随机顺序（由种子决定，尽量避免第一首与刚播放的相同）
+    fn next_round(&self) -> Vec<u64> {
+        let mut order: Vec<u64> = self.items.iter().map(|item| item.id).collect();
+        Rng(self.round_seed).shuffle(&mut order);
+        if order.len() > 1 && order.first() == self.current.as_ref() {
+            let last = order.len() - 1;
+            order.swap(0, last);
+        }
+        order
+    }
+
+    /// 计算下一首的条目 ID（本轮结束且列表循环时取下一轮的第一首）
+    fn next_id(&self) -> Option<u64> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let wrap = self.repeat == RepeatMode::All;
+        if self.shuffle {
+            let next = self.order_pos.map_or(0, |p| p + 1);
+            if next < self.order.len() {
+                return Some(self.order[next]);
+            }
+            if !wrap {
+                return None;
+            }
+            self.next_round().first().copied()
+        } else {
+            let next = self.current_index().map_or(0, |i| i + 1);
+            if next < self.items.len() {
+                Some(self.items[next].id)
+            } else if wrap {
+                Some(self.items[0].id)
+            } else {
+                None
+            }
+        }
+    }
+
+    fn set_current(&mut self, id: u64) {
+        self.current = Some(id);
+        if self.shuffle {
+            self.order_pos = self.order.iter().position(|o| *o == id);
+        }
+    }
+
+    /// 自动播放时的下一首条目 ID（单曲循环时为当前音轨）
+    fn auto_next_id(&self) -> Option<u64> {
+        if self.repeat == RepeatMode::One && self.current.is_some() {
+            return self.current;
+        }
+        self.next_id()
+    }
+
+    /// 自动播放时的下一首（不修改队列，用于预加载）
+    #[must_use]
+    pub fn peek_next(&self) -> Option<String> {
+        self.auto_next_id().and_then(|id| self.path_of(id))
+    }
+
+    /// 前进到下一首；`auto` 为音轨自然结束（单曲循环时重复当前音轨）
+    pub fn advance(&mut self, auto: bool) -> Option<String> {
+        if auto && self.repeat == RepeatMode::One && self.current.is_some() {
+            return self.current_path();
+        }
+        let id = self.next_id()?;
+        if self.shuffle && self.order_pos.map_or(0, |p| p + 1) >= self.order.len() {
+            // 本轮已播完，采用预览时给出的下一轮顺序
+            self.order = self.next_round();
+            self.order_pos = None;
+            self.round_seed = self.rng.next_u64();
+        }
+        self.set_current(id);
+        self.path_of(id)
+    }
+
+    /// 回到上一首；已在开头且未开启列表循环时重新播放当前音轨
+    pub fn previous(&mut self) -> Option<String> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let wrap = self.repeat == RepeatMode::All;
+        let id = if self.shuffle {
+            match self.order_pos {
+                Some(p) if p > 0 => Some(self.order[p - 1]),
+                _ if wrap => self.order.last().copied(),
+                _ => self.current,
+            }
+        } else {
+            match self.current_index() {
+                Some(i) if i > 0 => Some(self.items[i - 1].id),
+                _ if wrap => self.items.last().map(|item| item.id),
+                _ => self.current,
+            }
+        }?;
+        self.set_current(id);
+        self.path_of(id)
+    }
+
+    /// 开关随机播放；开启时当前音轨排在新顺序的最前面
+    pub fn set_shuffle(&mut self, enabled: bool) {
+        if self.shuffle == enabled {
+            return;
+        }
+        self.shuffle = enabled;
+        if enabled {
+            let mut rest: Vec<u64> = self.items.iter().map(|item| item.id).filter(|id| Some(*id) != self.current).collect();
+            self.rng.shuffle(&mut rest);
+            self.order = self.current.into_iter().chain(rest).collect();
+            self.order_pos = self.current.map(|_| 0);
+        } else {
+            self.order.clear();
+            self.order_pos = None;
+        }
+    }
+
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.repeat = repeat;
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> QueueSnapshot {
+        QueueSnapshot {
+            items: self.items.clone(),
+            current_index: self.current_index(),
+            next_index: self.auto_next_id().and_then(|id| self.index_of(id)),
+            shuffle: self.shuffle,
+            repeat: self.repeat,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue_of(count: usize) -> PlayQueue {
+        let mut queue = PlayQueue::new();
+        queue.enqueue((0..count).map(|i| format!("{i}.flac")).collect());
+        queue
+    }
+
+    /// 按自动播放依次前进，返回播放过的路径
+    fn play_through(queue: &mut PlayQueue, steps: usize) -> Vec<String> {
+        (0..steps).map_while(|_| queue.advance(true)).collect()
+    }
+
+    #[test]
+    fn peek_matches_advance_across_shuffle_rounds() {
+        let mut queue = queue_of(5);
+        queue.set_shuffle(true);
+        queue.set_repeat(RepeatMode::All);
+        for _ in 0..12 {
+            let peeked = queue.peek_next();
+            let next_index = queue.snapshot().next_index;
+            // 预览不修改队列
+            assert_eq!(queue.peek_next(), peeked);
+            let advanced = queue.advance(true);
+            assert_eq!(advanced, peeked);
+            assert_eq!(queue.current_index(), next_index);
+        }
+    }
+
+    #[test]
+    fn shuffle_round_plays_every_item_once() {
+        let mut queue = queue_of(6);
+        queue.set_shuffle(true);
+        let mut played = play_through(&mut queue, 10);
+        assert_eq!(played.len(), 6);
+        played.sort();
+        played.dedup();
+        assert_eq!(played.len(), 6);
+        assert_eq!(queue.peek_next(), None);
+        assert_eq!(queue.snapshot().next_index, None);
+    }
+
+    #[test]
+    fn repeat_modes() {
+        let mut queue = queue_of(3);
+        assert_eq!(play_through(&mut queue, 5), ["0.flac", "1.flac", "2.flac"]);
+
+        queue.set_repeat(RepeatMode::All);
+        assert_eq!(queue.peek_next().as_deref(), Some("0.flac"));
+        assert_eq!(queue.snapshot().next_index, Some(0));
+
+        queue.set_repeat(RepeatMode::One);
+        assert_eq!(queue.peek_next().as_deref(), Some("2.flac"));
+        assert_eq!(queue.advance(true).as_deref(), Some("2.flac"));
+        // 手动下一首不受单曲循环影响
+        assert_eq!(queue.advance(false), None);
+    }
+
+    #[test]
+    fn removing_current_keeps_following_track_next() {
+        let mut queue = queue_of(4);
+        queue.jump(1).unwrap();
+        queue.remove(1).unwrap();
+        assert_eq!(queue.current_index(), Some(0));
+        assert_eq!(queue.peek_next().as_deref(), Some("2.flac"));
+
+        let mut queue = queue_of(4);
+        queue.set_shuffle(true);
+        queue.advance(false);
+        let expected = queue.snapshot().next_index.map(|i| queue.snapshot().items[i].path.clone());
+        let current = queue.current_index().unwrap();
+        queue.remove(current).unwrap();
+        assert_eq!(queue.peek_next(), expected);
+    }
+
+    #[test]
+    fn replace_keeps_playing_item_current() {
+        let mut queue = queue_of(3);
+        queue.jump(2).unwrap();
+        queue.replace(vec!["a.flac".to_string(), "2.flac".to_string(), "b.flac".to_string()], Some(1)).unwrap();
+        assert_eq!(queue.current_path().as_deref(), Some("2.flac"));
+        assert_eq!(queue.peek_next().as_deref(), Some("b.flac"));
+        assert!(queue.replace(Vec::new(), Some(0)).is_err());
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn inserted_items_play_in_the_current_round() {
+        let mut queue = queue_of(3);
+        queue.jump(0).unwrap();
+        queue.insert(1, vec!["new.flac".to_string()]);
+        assert_eq!(queue.current_index(), Some(0));
+        assert_eq!(queue.peek_next().as_deref(), Some("new.flac"));
+
+        let mut queue = queue_of(3);
+        queue.set_shuffle(true);
+        queue.advance(false);
+        queue.insert(0, vec!["new.flac".to_string()]);
+        let played = play_through(&mut queue, 10);
+        assert_eq!(played.len(), 3);
+        assert!(played.iter().any(|path| path == "new.flac"));
+    }
+}
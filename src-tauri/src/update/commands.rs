@@ -1,5 +1,6 @@
 //! 自动更新命令模块
 
+use crate::error::{AppError, AppResult, ErrorCode};
 use tauri::Emitter;
 
 /// 获取应用版本信息
@@ -13,7 +14,7 @@ pub fn get_app_version(app: tauri::AppHandle) -> String {
 pub async fn download_and_install_update(
     app: tauri::AppHandle,
     download_url: String,
-) -> Result<(), String> {
+) -> AppResult<()> {
     tokio::spawn(async move {
         if let Err(e) = download_update(&app, &download_url).await {
             let _ = app.emit("update-error", &e);
@@ -27,13 +28,13 @@ pub async fn download_and_install_update(
 }
 
 /// 下载更新文件的实际实现
-async fn download_update(app: &tauri::AppHandle, download_url: &str) -> Result<(), String> {
+async fn download_update(app: &tauri::AppHandle, download_url: &str) -> AppResult<()> {
     use reqwest::Client;
     use std::fs;
     use std::io::Write;
 
     // 首先尝试使用可执行文件同级的 updates 目录（便于程序唤起安装）；若不可写则回退到系统临时目录
-    let cache_dir = (|| -> Result<std::path::PathBuf, String> {
+    let cache_dir = (|| -> AppResult<std::path::PathBuf> {
         // 获取 exe 所在目录
         if let Ok(exe_path) = std::env::current_exe() {
             if let Some(exe_dir) = exe_path.parent() {
@@ -51,7 +52,7 @@ async fn download_update(app: &tauri::AppHandle, download_url: &str) -> Result<(
         }
         // 回退到 Temp 目录
         let tmp = std::env::temp_dir().join("Mercurial Player").join("updates");
-        fs::create_dir_all(&tmp).map_err(|e| format!("Failed to create updates dir: {e}"))?;
+        fs::create_dir_all(&tmp).map_err(|e| AppError::from(e).context_message("Failed to create updates dir").with_path(&tmp))?;
         Ok(tmp)
     })()?;
 
@@ -66,11 +67,11 @@ async fn download_update(app: &tauri::AppHandle, download_url: &str) -> Result<(
         .get(download_url)
         .send()
         .await
-        .map_err(|e| format!("Download failed: {e}"))?;
+        .map_err(|e| AppError::from(e).context_message("Download failed").with_context("url", download_url))?;
 
     let total_size = response
         .content_length()
-        .ok_or("Failed to get content length")?;
+        .ok_or_else(|| AppError::new(ErrorCode::NetworkFailed, "Failed to get content length").with_context("url", download_url))?;
 
     // 记录并发出开始下载的日志
     let start_msg = format!("Starting download from: {download_url}");
@@ -94,29 +95,22 @@ async fn download_update(app: &tauri::AppHandle, download_url: &str) -> Result<(
     let _ = app.emit("update-log", &save_msg);
 
     // 下载并保存文件
+    // 出错时同时发出日志
+    let log_error = |e: AppError| {
+        let _ = app.emit("update-log", e.message());
+        e
+    };
     let mut file = fs::File::create(&installer_path)
-        .map_err(|e| {
-            let msg = format!("Failed to create file: {e}");
-            let _ = app.emit("update-log", &msg);
-            msg
-        })?;
+        .map_err(|e| log_error(AppError::from(e).context_message("Failed to create file").with_path(&installer_path)))?;
 
     let mut downloaded = 0u64;
     let mut stream = response.bytes_stream();
     use futures::stream::StreamExt;
 
     while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| {
-            let msg = format!("Download error: {e}");
-            let _ = app.emit("update-log", &msg);
-            msg
-        })?;
+        let chunk = chunk.map_err(|e| log_error(AppError::from(e).context_message("Download error")))?;
         file.write_all(&chunk)
-            .map_err(|e| {
-                let msg = format!("Write error: {e}");
-                let _ = app.emit("update-log", &msg);
-                msg
-            })?;
+            .map_err(|e| log_error(AppError::from(e).context_message("Write error").with_path(&installer_path)))?;
 
         downloaded += chunk.len() as u64;
         let progress = ((downloaded as f64 / total_size as f64) * 100.0) as u32;
@@ -138,24 +132,24 @@ async fn download_update(app: &tauri::AppHandle, download_url: &str) -> Result<(
 
     #[cfg(not(windows))]
     {
-        Err("Auto-update is Windows-only".to_string())
+        Err(AppError::new(ErrorCode::Unsupported, "Auto-update is Windows-only"))
     }
 }
 
 /// 执行安装程序（由前端在用户确认后调用）
 #[tauri::command]
-pub fn run_installer(app: tauri::AppHandle, installer_path: String) -> Result<(), String> {
+pub fn run_installer(app: tauri::AppHandle, installer_path: String) -> AppResult<()> {
     #[cfg(windows)]
     {
         use std::process::Command;
         // 在开发模式下禁止直接执行安装，避免误操作
         if cfg!(debug_assertions) {
-            return Err("Installer execution is disabled in dev builds. Build a release to run installer.".to_string());
+            return Err(AppError::new(ErrorCode::Unsupported, "Installer execution is disabled in dev builds. Build a release to run installer."));
         }
 
         Command::new(&installer_path)
             .spawn()
-            .map_err(|e| format!("Failed to execute installer: {e}"))?;
+            .map_err(|e| AppError::from(e).context_message("Failed to execute installer").with_path(&installer_path))?;
 
         // 通知前端安装已启动
         let _ = app.emit("installer-started", &installer_path);
@@ -173,6 +167,6 @@ pub fn run_installer(app: tauri::AppHandle, installer_path: String) -> Result<()
 
     #[cfg(not(windows))]
     {
-        Err("Installer execution is Windows-only".to_string())
+        Err(AppError::new(ErrorCode::Unsupported, "Installer execution is Windows-only"))
     }
 }
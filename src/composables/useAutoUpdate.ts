@@ -2,6 +2,8 @@ import { ref, computed } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
 import { listen, UnlistenFn } from '@tauri-apps/api/event'
 import logger from '@/utils/logger'
+import { backendErrorMessage } from '@/utils/audioErrorClassifier'
+import type { BackendError } from '@/types'
 
 /** GitHub Release 资源类型 */
 interface GitHubAsset {
@@ -65,9 +67,9 @@ async function startListeners() {
   unlistenFns.push(un1)
 
   // 错误事件
-  const un2 = await listen<string>('update-error', event => {
+  const un2 = await listen<BackendError>('update-error', event => {
     logger.warn('[auto-update] error', event.payload)
-    error.value = event.payload as string
+    error.value = event.payload.message
     isDownloading.value = false
   })
   unlistenFns.push(un2)
@@ -314,7 +316,7 @@ const downloadAndInstall = async () => {
       downloadUrl: downloadUrl.value,
     })
   } catch (err) {
-    error.value = err instanceof Error ? err.message : 'Download failed'
+    error.value = backendErrorMessage(err) || 'Download failed'
     logger.error('Download failed:', err)
     isDownloading.value = false
   }
@@ -331,7 +333,7 @@ const runInstaller = async () => {
   try {
     await invoke('run_installer', { installerPath: installerPath.value })
   } catch (err) {
-    error.value = err instanceof Error ? err.message : String(err)
+    error.value = backendErrorMessage(err)
   }
 }
 
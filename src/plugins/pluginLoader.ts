@@ -7,6 +7,7 @@ import { invoke } from '@tauri-apps/api/core'
 import logger from '../utils/logger'
 import pluginManager, { type PluginAPI, type PluginPermissionType, PluginPermission } from './pluginManager'
 import { validatePluginCode } from './pluginSandbox'
+import type { BackendError } from '@/types'
 
 // 插件清单类型
 interface PluginManifest {
@@ -24,7 +25,7 @@ interface PluginManifest {
 interface InstallResult {
   success: boolean
   path?: string
-  error?: string
+  error?: BackendError
 }
 
 /**
@@ -289,7 +290,7 @@ export async function installPlugin(source: string): Promise<InstallResult> {
       await loadPlugin(result.path)
       return result
     }
-    throw new Error(result.error || '安装失败')
+    throw new Error(result.error?.message || '安装失败')
   } catch (error) {
     logger.error('安装插件失败:', error)
     throw error
@@ -4,6 +4,7 @@
  */
 
 import { reactive, markRaw, watch, type WatchStopHandle } from 'vue'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
 import logger from '../utils/logger'
 import { createPluginAPI } from './pluginAPI'
 import { createPluginSandbox, type PluginSandbox } from './pluginSandbox'
@@ -337,6 +338,8 @@ class PluginManager {
   private storage: Map<string, Record<string, unknown>>
   // 播放器状态监听器
   private _playerWatcherStop: WatchStopHandle | null
+  // 后端 player-state 事件监听器
+  private _playerStateUnlisten: UnlistenFn | null
 
   constructor() {
     this.plugins = reactive(new Map()) as Map<string, Plugin>
@@ -355,6 +358,7 @@ class PluginManager {
     this.eventListeners = new Map()
     this.storage = new Map()
     this._playerWatcherStop = null
+    this._playerStateUnlisten = null
   }
 
   /**
@@ -388,6 +392,15 @@ class PluginManager {
       },
       { immediate: false }
     )
+
+    // 转发后端状态机事件（包含加载、缓冲、结束、错误等细分状态）
+    try {
+      this._playerStateUnlisten = await listen('player-state', (event) => {
+        this.emit('player:state', event.payload)
+      })
+    } catch (e) {
+      logger.warn('监听 player-state 事件失败:', e)
+    }
     
     logger.info('插件管理器已初始化')
   }
@@ -400,6 +413,10 @@ class PluginManager {
       this._playerWatcherStop()
       this._playerWatcherStop = null
     }
+    if (this._playerStateUnlisten) {
+      this._playerStateUnlisten()
+      this._playerStateUnlisten = null
+    }
     
     // 强制保存所有插件存储
     for (const [pluginId, storage] of this.storage) {
@@ -10,6 +10,8 @@ import type {
   PlaylistConfig,
   GeneralConfig,
   LyricsConfig,
+  CoverConfig,
+  HistoryConfig,
   UIConfig,
   AudioConfig,
   VisualizerConfig,
@@ -58,6 +60,8 @@ interface ConfigState {
   playlist: PlaylistConfig
   general: GeneralConfig
   lyrics: LyricsConfig
+  cover: CoverConfig
+  history: HistoryConfig
   ui: UIConfig
   audio: AudioConfig
   visualizer: VisualizerConfig
@@ -80,7 +84,11 @@ export const useConfigStore = defineStore('config', {
       enableSubdirectoryScan: true,
       maxDepth: 3,
       ignoreHiddenFolders: true,
-      folderBlacklist: ['.git', 'node_modules', 'temp', 'tmp']
+      folderBlacklist: ['.git', 'node_modules', 'temp', 'tmp', '@eaDir', '#recycle', '$RECYCLE.BIN', 'System Volume Information'],
+      includePatterns: [],
+      excludePatterns: [],
+      followSymlinks: false,
+      minFileSize: 0
     },
 
     // 标题提取配置
@@ -121,6 +129,20 @@ export const useConfigStore = defineStore('config', {
       lyricsStyle: 'modern',
     },
 
+    // 封面设置
+    cover: {
+      folderPatterns: ['cover.*', 'folder.*', 'front.*', 'album.*', 'albumart*.*'],
+      parentLevels: 1,
+      priority: ['embeddedFront', 'folderFile', 'embeddedAny']
+    },
+
+    // 播放历史设置
+    history: {
+      enabled: true,
+      completionPercent: 50,
+      completionSeconds: 240
+    },
+
     // UI设置
     ui: {
       showSettings: false,
@@ -131,7 +153,27 @@ export const useConfigStore = defineStore('config', {
     // 音频设置
     audio: {
       exclusiveMode: false,
-      volume: 0.5
+      volume: 0.5,
+      crossfade: {
+        enabled: false,
+        duration: 5,
+        curve: 'equal-power'
+      },
+      replayGain: {
+        mode: 'off',
+        preamp: 0,
+        fallbackGain: 0,
+        preventClipping: true
+      },
+      pitchShift: {
+        global: { semitones: 0, cents: 0 },
+        tracks: {}
+      },
+      looping: {
+        honorTags: true,
+        loopCount: 2,
+        fadeOut: 10
+      }
     },
 
     // 可视化设置
@@ -442,7 +484,23 @@ export const useConfigStore = defineStore('config', {
       }
     },
 
-    addCustomSeparator(separator: string): void {
+    setCoverConfig(config: Partial<CoverConfig>): void {
+      this.cover = { ...this.cover, ...config }
+      this._markDirty()
+      if (this.general.autoSaveConfig && !this._isInitializing) {
+        this.saveConfig()
+      }
+    },
+
+    setHistoryConfig(config: Partial<HistoryConfig>): void {
+      this.history = { ...this.history, ...config }
+      this._markDirty()
+      if (this.general.autoSaveConfig && !this._isInitializing) {
+        this.saveConfig()
+      }
+    },
+
+        addCustomSeparator(separator: string): void {
       if (separator && !this.titleExtraction.customSeparators.includes(separator)) {
         this.titleExtraction.customSeparators.push(separator)
         this._markDirty()
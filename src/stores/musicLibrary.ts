@@ -1,8 +1,13 @@
 import { defineStore } from 'pinia'
 import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
 import { useConfigStore } from './config'
 import logger from '../utils/logger'
-import type { Track, Playlist, LibraryStats } from '@/types'
+import { backendErrorMessage } from '../utils/audioErrorClassifier'
+import type { Track, Playlist, LibraryStats, LibraryPage, LibraryPlaylist, LibraryDiff, LibraryScanProgress, LibraryScanBatch, LibraryScanComplete } from '@/types'
+
+/** 每次从媒体库索引获取的音轨数 */
+const LIBRARY_PAGE_SIZE = 500
 
 interface PlayHistoryItem extends Track {
   timestamp: string
@@ -25,6 +30,11 @@ interface MusicLibraryState {
   error: string | null
   directoryTree: unknown | null
   stats: LibraryStats
+  scanId: number | null
+  scanProgress: LibraryScanProgress | null
+  _libraryChangedUnlisten: UnlistenFn | null
+  _scanUnlisteners: UnlistenFn[]
+  _scanResolve: ((complete: LibraryScanComplete) => void) | null
 }
 
 export const useMusicLibraryStore = defineStore('musicLibrary', {
@@ -59,7 +69,15 @@ export const useMusicLibraryStore = defineStore('musicLibrary', {
       totalAudioFiles: 0,
       totalPlaylists: 0,
       maxDepth: 0
-    }
+    },
+
+    // 后台扫描
+    scanId: null,
+    scanProgress: null,
+
+    _libraryChangedUnlisten: null,
+    _scanUnlisteners: [],
+    _scanResolve: null
   }),
 
   getters: {
@@ -151,7 +169,7 @@ export const useMusicLibraryStore = defineStore('musicLibrary', {
         return { success: true, message: 'Music directories loaded successfully' }
       } catch (error) {
         logger.error('Error loading music directories:', error)
-        return { success: false, message: String(error) }
+        return { success: false, message: backendErrorMessage(error) }
       }
     },
 
@@ -168,7 +186,7 @@ export const useMusicLibraryStore = defineStore('musicLibrary', {
         return { success: true, message: 'Folder added successfully' }
       } catch (error) {
         logger.error('Error adding music folder:', error)
-        return { success: false, message: String(error) }
+        return { success: false, message: backendErrorMessage(error) }
       }
     },
 
@@ -191,7 +209,7 @@ export const useMusicLibraryStore = defineStore('musicLibrary', {
         return { success: true, message: 'Folder removed successfully' }
       } catch (error) {
         logger.error('Error removing music folder:', error)
-        return { success: false, message: String(error) }
+        return { success: false, message: backendErrorMessage(error) }
       }
     },
 
@@ -208,16 +226,121 @@ export const useMusicLibraryStore = defineStore('musicLibrary', {
         return { success: true, message: 'Music directories updated successfully' }
       } catch (error) {
         logger.error('Error setting music directories:', error)
-        return { success: false, message: String(error) }
+        return { success: false, message: backendErrorMessage(error) }
       }
     },
 
     /**
-     * 刷新音乐文件夹
+     * 刷新音乐文件夹（后台增量扫描，完成后从媒体库索引重新加载）
+     *
+     * 扫描中解析出的音轨按批合并到播放列表中。
      */
     async refreshMusicFolders(): Promise<{ success: boolean; message: string }> {
       try {
-        this.playlists = await invoke<Playlist[]>('get_all_audio_files', { paths: this.musicFolders })
+        await this.listenLibraryScan()
+        const finished = new Promise<LibraryScanComplete>(resolve => {
+          this._scanResolve = resolve
+        })
+        this.scanId = await invoke<number>('start_library_scan', { paths: this.musicFolders })
+        const complete = await finished
+        logger.info('Library scan:', complete)
+        if (complete.error) {
+          return { success: false, message: complete.error }
+        }
+        return await this.loadLibrary()
+      } catch (error) {
+        this._scanResolve = null
+        logger.error('Error refreshing music folders:', error)
+        return { success: false, message: backendErrorMessage(error) }
+      }
+    },
+
+    /**
+     * 取消正在运行的扫描（已解析的音轨仍会保存）
+     */
+    async cancelScan(): Promise<void> {
+      if (this.scanId === null) return
+      try {
+        await invoke<boolean>('cancel_scan', { scanId: this.scanId })
+      } catch (error) {
+        logger.error('Error cancelling library scan:', error)
+      }
+    },
+
+    /**
+     * 监听后台扫描的进度、分批结果和完成事件
+     */
+    async listenLibraryScan(): Promise<void> {
+      if (this._scanUnlisteners.length > 0) return
+      try {
+        this._scanUnlisteners = await Promise.all([
+          listen<LibraryScanProgress>('library-scan-progress', (event) => {
+            if (event.payload.scanId !== this.scanId) return
+            this.scanProgress = event.payload
+            if (event.payload.error) {
+              logger.warn(`Library scan error: ${event.payload.error.path}: ${event.payload.error.message}`)
+            }
+          }),
+          listen<LibraryScanBatch>('library-scan-batch', (event) => {
+            if (event.payload.scanId !== this.scanId) return
+            this._mergeScanBatch(event.payload.tracks)
+          }),
+          listen<LibraryScanComplete>('library-scan-complete', (event) => {
+            if (event.payload.scanId !== this.scanId) return
+            this.scanId = null
+            this.scanProgress = null
+            this._scanResolve?.(event.payload)
+            this._scanResolve = null
+          }),
+        ])
+      } catch (error) {
+        logger.error('Failed to setup library scan listeners:', error)
+      }
+    },
+
+    /**
+     * 把扫描中解析出的音轨按所在文件夹合并到播放列表
+     */
+    _mergeScanBatch(tracks: Track[]): void {
+      for (const track of tracks) {
+        const parts = track.path.split(/[\\/]/)
+        const folderName = parts.length > 1 ? parts[parts.length - 2] : 'Unknown'
+        let playlist = this.playlists.find(p => p.name === folderName)
+        if (!playlist) {
+          playlist = { name: folderName, files: [] }
+          this.playlists.push(playlist)
+        }
+        const index = playlist.files.findIndex(file => file.path === track.path)
+        if (index >= 0) {
+          playlist.files[index] = track
+        } else {
+          playlist.files.push(track)
+        }
+      }
+    },
+
+    /**
+     * 从媒体库索引分页加载播放列表（不扫描磁盘）
+     */
+    async loadLibrary(): Promise<{ success: boolean; message: string }> {
+      try {
+        const infos = await invoke<LibraryPlaylist[]>('get_library_playlists', { paths: this.musicFolders })
+        const playlists: Playlist[] = []
+        for (const info of infos) {
+          const files: Track[] = []
+          while (files.length < info.trackCount) {
+            const page = await invoke<LibraryPage>('get_library_tracks', {
+              folder: info.folder,
+              recursive: info.recursive,
+              offset: files.length,
+              limit: LIBRARY_PAGE_SIZE,
+            })
+            if (page.tracks.length === 0) break
+            files.push(...page.tracks)
+          }
+          playlists.push({ name: info.name, files })
+        }
+        this.playlists = playlists
         
         // 获取配置中的排序顺序
         const configStore = useConfigStore()
@@ -243,10 +366,26 @@ export const useMusicLibraryStore = defineStore('musicLibrary', {
           }
         })
         
-        return { success: true, message: 'Library refreshed successfully' }
+        return { success: true, message: 'Library loaded successfully' }
       } catch (error) {
-        logger.error('Error refreshing music folders:', error)
-        return { success: false, message: String(error) }
+        logger.error('Error loading library:', error)
+        return { success: false, message: backendErrorMessage(error) }
+      }
+    },
+
+    /**
+     * 监听后端目录监听器发出的媒体库变化，收到后从索引重新加载
+     */
+    async listenLibraryChanges(): Promise<void> {
+      if (this._libraryChangedUnlisten) return
+      try {
+        this._libraryChangedUnlisten = await listen<LibraryDiff>('library-changed', async (event) => {
+          const { added, updated, removed, renamed } = event.payload
+          logger.info(`Library changed: ${added.length} added, ${updated.length} updated, ${removed.length} removed, ${renamed.length} renamed`)
+          await this.loadLibrary()
+        })
+      } catch (error) {
+        logger.error('Failed to setup library-changed listener:', error)
       }
     },
 
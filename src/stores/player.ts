@@ -3,11 +3,12 @@ import { invoke } from '@tauri-apps/api/core'
 import { listen, type UnlistenFn } from '@tauri-apps/api/event'
 import FileUtils from '../utils/fileUtils'
 import LyricsParser from '../utils/lyricsParser'
+import { coverUrl } from '../utils/coverUrl'
 import logger from '../utils/logger'
 import errorHandler, { ErrorType, ErrorSeverity } from '../utils/errorHandler'
-import { classifyAudioInvokeError } from '../utils/audioErrorClassifier'
+import { backendErrorMessage, classifyAudioInvokeError } from '../utils/audioErrorClassifier'
 import { useConfigStore } from './config'
-import type { Track, AudioInfo, LyricLine, RepeatMode, CacheItem } from '@/types'
+import type { Track, AudioInfo, LyricLine, RepeatMode, CacheItem, PlayerStateEvent, TrackEndedEvent, QueueSnapshot } from '@/types'
 
 /**
  * 简单的LRU缓存实现
@@ -105,6 +106,10 @@ interface PlayerState {
   currentLyricIndex: number
   lyricsOffset: number
   audioInfo: AudioInfo
+  /** 后端状态机的最近一次状态 */
+  playerState: PlayerStateEvent | null
+  /** 后端播放队列与 playlist 一致（队列负责自动前进） */
+  _queueSynced: boolean
   _isLoading: boolean
   _statusPollId: ReturnType<typeof setTimeout> | null
   lastTrackIndex: number
@@ -114,6 +119,8 @@ interface PlayerState {
   _isDestroyed: boolean
   _trackEndedUnlisten: UnlistenFn | null
   _positionUnlisten: UnlistenFn | null
+  _playerStateUnlisten: UnlistenFn | null
+  _queueChangedUnlisten: UnlistenFn | null
   _taskbarPreviousUnlisten: UnlistenFn | null
   _taskbarPlayPauseUnlisten: UnlistenFn | null
   _taskbarNextUnlisten: UnlistenFn | null
@@ -152,6 +159,8 @@ export const usePlayerStore = defineStore('player', {
       bitDepth: null,
       format: null
     },
+    playerState: null,
+    _queueSynced: false,
 
     // 加载状态
     _isLoading: false,
@@ -171,6 +180,8 @@ export const usePlayerStore = defineStore('player', {
     // 事件监听器
     _trackEndedUnlisten: null,
     _positionUnlisten: null,
+    _playerStateUnlisten: null,
+    _queueChangedUnlisten: null,
     _taskbarPreviousUnlisten: null,
     _taskbarPlayPauseUnlisten: null,
     _taskbarNextUnlisten: null,
@@ -274,6 +285,9 @@ export const usePlayerStore = defineStore('player', {
 
       this._setupTrackEndedListener()
       this._setupPositionListener()
+      this._setupPlayerStateListener()
+      this._setupQueueListener()
+      this._syncQueueModes()
       this._setupTaskbarListeners()
       this._setupDeviceListeners()
       this._startCleanupTask()
@@ -283,10 +297,10 @@ export const usePlayerStore = defineStore('player', {
 
     async _setupTrackEndedListener(): Promise<void> {
       try {
-        this._trackEndedUnlisten = await listen('track-ended', () => {
+        this._trackEndedUnlisten = await listen<TrackEndedEvent>('track-ended', (event) => {
           if (this._isDestroyed) return
-          logger.debug('Received track-ended event')
-          this._onEnded()
+          logger.debug('Received track-ended event', event.payload)
+          this._onEnded(event.payload)
         })
       } catch (err) {
         logger.error('Failed to setup track-ended listener:', err)
@@ -307,6 +321,73 @@ export const usePlayerStore = defineStore('player', {
       }
     },
 
+    /**
+     * 根据封面 ID 生成当前音轨的封面地址，没有封面 ID 时读取元数据
+     */
+    async _loadCover(path: string): Promise<void> {
+      if (!this.currentTrack || this.currentTrack.cover || !this.currentTrack.hasCover) return
+      const coverId = this.currentTrack.coverId
+        ?? (await invoke<Track>('get_track_metadata', { path })).coverId
+      if (this.currentTrack?.path === path && coverId) {
+        this.currentTrack = { ...this.currentTrack, coverId, cover: coverUrl(coverId, 1024) }
+      }
+    },
+
+    async _setupPlayerStateListener(): Promise<void> {
+      try {
+        this._playerStateUnlisten = await listen<PlayerStateEvent>('player-state', (event) => {
+          if (this._isDestroyed) return
+          const state = event.payload
+          // 丢弃乱序到达的旧事件
+          if (this.playerState && state.seq <= this.playerState.seq) return
+          this.playerState = state
+          // 后端主动暂停（如睡眠定时器）时同步播放状态
+          if (state.status === 'paused' && this.isPlaying) {
+            this.isPlaying = false
+            this.stopStatusPolling()
+          }
+        })
+      } catch (err) {
+        logger.error('Failed to setup player-state listener:', err)
+      }
+    },
+
+    async _setupQueueListener(): Promise<void> {
+      try {
+        this._queueChangedUnlisten = await listen<QueueSnapshot>('queue-changed', (event) => {
+          if (this._isDestroyed || this._isLoading) return
+          // 后端队列切换了音轨（自动前进、任务栏按钮），界面跟随
+          const { items, currentIndex } = event.payload
+          const current = currentIndex !== null ? items[currentIndex] : undefined
+          if (current && current.path !== this.currentTrack?.path) {
+            this._followTrack(current.path)
+          }
+        })
+      } catch (err) {
+        logger.error('Failed to setup queue-changed listener:', err)
+      }
+    },
+
+    /**
+     * 用 playlist 替换后端播放队列，`playingPath` 为正在播放的音轨（只标记，不重新播放）
+     */
+    async _syncQueue(playingPath: string | undefined = this.currentTrack?.path): Promise<void> {
+      const paths = this.playlist.map(t => t.path)
+      const index = playingPath ? paths.indexOf(playingPath) : -1
+      try {
+        await invoke('queue_replace', { paths, current: index >= 0 ? index : null })
+        this._queueSynced = true
+      } catch (err) {
+        this._queueSynced = false
+        logger.error('Failed to sync play queue:', err)
+      }
+    },
+
+    _syncQueueModes(): void {
+      invoke('queue_set_repeat', { mode: this.repeatMode }).catch(err => logger.error('Failed to set queue repeat:', err))
+      invoke('queue_set_shuffle', { enabled: this.isShuffle }).catch(err => logger.error('Failed to set queue shuffle:', err))
+    },
+
     async _setupTaskbarListeners(): Promise<void> {
       try {
         // 监听任务栏上一首按钮
@@ -386,7 +467,7 @@ export const usePlayerStore = defineStore('player', {
             )
           } catch (err) {
             errorHandler.handle(
-              err instanceof Error ? err : new Error(String(err)),
+              err instanceof Error ? err : new Error(backendErrorMessage(err)),
               {
                 type: ErrorType.AUDIO_DEVICE_ERROR,
                 severity: ErrorSeverity.HIGH,
@@ -459,7 +540,7 @@ export const usePlayerStore = defineStore('player', {
             )
           } catch (err) {
             errorHandler.handle(
-              err instanceof Error ? err : new Error(String(err)),
+              err instanceof Error ? err : new Error(backendErrorMessage(err)),
               {
                 type: ErrorType.AUDIO_DEVICE_ERROR,
                 severity: ErrorSeverity.MEDIUM,
@@ -477,13 +558,6 @@ export const usePlayerStore = defineStore('player', {
       }
     },
 
-    _updateTaskbarState(): void {
-      // 更新 Windows 任务栏按钮状态
-      invoke('update_taskbar_state', { isPlaying: this.isPlaying }).catch(() => {
-        // 忽略非 Windows 平台的错误
-      })
-    },
-
     // --- 核心行为 ---
 
     play(): void {
@@ -520,7 +594,56 @@ export const usePlayerStore = defineStore('player', {
 
       this._isLoading = true
       this.stopStatusPolling()
+      this._setCurrentTrack(track)
+
+      invoke('pause_track').catch(err => logger.debug("pause before play:", err))
+
+      try {
+        logger.info('Playing track:', track.path)
 
+        // 播放列表中的音轨交给后端队列播放，之后由队列自动前进
+        const index = this.playlist.findIndex(t => t.path === track.path)
+        const playPromise = index >= 0
+          ? (this._queueSynced ? Promise.resolve() : this._syncQueue()).then(() =>
+              this._queueSynced ? invoke('queue_play', { index }) : invoke('play_track', { path: track.path }))
+          : invoke('play_track', { path: track.path })
+        const timeoutPromise = new Promise((_, reject) => {
+          setTimeout(() => reject(new Error('播放超时')), 5000)
+        })
+
+        await Promise.race([playPromise, timeoutPromise])
+
+        this.isPlaying = true
+        this.startStatusPolling()
+        this._loadTrackExtras(track.path)
+      } catch (err) {
+        const type = classifyAudioInvokeError(err)
+        const handled = errorHandler.handle(
+          err instanceof Error ? err : new Error(backendErrorMessage(err)),
+          {
+            type,
+            severity: ErrorSeverity.HIGH,
+            context: { trackPath: track.path, trackName: track.name },
+            showToUser: true,
+          }
+        )
+
+        logger.error('Failed to play track:', handled)
+        this.isPlaying = false
+
+        const currentIdx = this.playlist.findIndex(t => t.path === track.path)
+        if (this.playlist.length > 1 && currentIdx < this.playlist.length - 1) {
+          setTimeout(() => this.nextTrack(), 100)
+        }
+      } finally {
+        this._isLoading = false
+      }
+    },
+
+    /**
+     * 更新当前音轨及其元数据（不涉及后端播放）
+     */
+    _setCurrentTrack(track: Track): void {
       // 获取元数据
       const metadataCache = this._getMetadataCache()
       let metadata = metadataCache.get(track.path)
@@ -557,48 +680,31 @@ export const usePlayerStore = defineStore('player', {
         bitDepth: metadata.bitDepth || null,
         format: metadata.format || null,
       }
+    },
 
-      invoke('pause_track').catch(err => logger.debug("pause before play:", err))
-
-      try {
-        logger.info('Playing track:', track.path)
-
-        const playPromise = invoke('play_track', { path: track.path })
-        const timeoutPromise = new Promise((_, reject) => {
-          setTimeout(() => reject(new Error('播放超时')), 5000)
-        })
-
-        await Promise.race([playPromise, timeoutPromise])
+    _loadTrackExtras(path: string): void {
+      this.loadLyrics(path).catch(err => {
+        logger.debug('Lyrics load error:', err)
+      })
+      this._loadCover(path).catch(err => {
+        logger.debug('Cover load error:', err)
+      })
+    },
 
+    /**
+     * 后端已经开始播放的音轨（无缝衔接、交叉淡化、队列前进）：只同步界面，不重新播放
+     */
+    _followTrack(path: string): void {
+      if (this.currentTrack?.path === path) {
+        // 单曲循环：同一音轨从头开始
+        this.currentTime = 0
         this.isPlaying = true
-        this.startStatusPolling()
-        this._updateTaskbarState()
-
-        this.loadLyrics(track.path).catch(err => {
-          logger.debug('Lyrics load error:', err)
-        })
-      } catch (err) {
-        const type = classifyAudioInvokeError(err)
-        const handled = errorHandler.handle(
-          err instanceof Error ? err : new Error(String(err)),
-          {
-            type,
-            severity: ErrorSeverity.HIGH,
-            context: { trackPath: track.path, trackName: track.name },
-            showToUser: true,
-          }
-        )
-
-        logger.error('Failed to play track:', handled)
-        this.isPlaying = false
-
-        const currentIdx = this.playlist.findIndex(t => t.path === track.path)
-        if (this.playlist.length > 1 && currentIdx < this.playlist.length - 1) {
-          setTimeout(() => this.nextTrack(), 100)
-        }
-      } finally {
-        this._isLoading = false
+        return
       }
+      const track = this.playlist.find(t => t.path === path) ?? { path }
+      this._setCurrentTrack(track)
+      this.isPlaying = true
+      this._loadTrackExtras(path)
     },
 
     pause(): void {
@@ -607,7 +713,6 @@ export const usePlayerStore = defineStore('player', {
         .then(() => {
           this.isPlaying = false
           this.stopStatusPolling()
-          this._updateTaskbarState()
         })
         .catch(err => logger.error("Failed to pause:", err))
     },
@@ -618,7 +723,6 @@ export const usePlayerStore = defineStore('player', {
         .then(() => {
           this.isPlaying = true
           this.startStatusPolling()
-          this._updateTaskbarState()
         })
         .catch(err => logger.error("Failed to resume:", err))
     },
@@ -653,25 +757,34 @@ export const usePlayerStore = defineStore('player', {
 
     // --- 播放结束 ---
 
-    async _onEnded(): Promise<void> {
+    async _onEnded(event?: TrackEndedEvent): Promise<void> {
       if (this._isDestroyed) return
 
-      invoke('pause_track').catch(err => logger.debug("pause on ended:", err))
-
-      if (this.repeatMode === 'track') {
-        await this.playTrack(this.currentTrack!)
-      } else if (this.repeatMode === 'list') {
-        const nextIndex = (this.currentTrackIndex + 1) % this.playlist.length
-        await this.playTrack(this.playlist[nextIndex])
-      } else if (this.currentTrackIndex < this.playlist.length - 1) {
-        const nextIndex = this.currentTrackIndex + 1
-        await this.playTrack(this.playlist[nextIndex])
-      } else {
+      // 睡眠定时器已停止播放：不再前进（已衔接的下一首由后端暂停在开头）
+      if (event?.stopped) {
+        if (event.nextPath) this._followTrack(event.nextPath)
+        else this.currentTime = this.duration
         this.isPlaying = false
         this.stopStatusPolling()
-        this.currentTime = this.duration
-        invoke('pause_track').catch(err => logger.debug("pause after playlist ended:", err))
+        return
       }
+
+      // 后端已无缝衔接下一首：暂停或重新播放会重建输出并清掉预加载，产生间隙
+      if (event?.nextPath) {
+        this._followTrack(event.nextPath)
+        return
+      }
+
+      // 后端队列已前进并开始加载下一首（重复模式、随机播放由队列处理）
+      if (event?.queuedPath) {
+        this._followTrack(event.queuedPath)
+        return
+      }
+
+      this.isPlaying = false
+      this.stopStatusPolling()
+      this.currentTime = this.duration
+      invoke('pause_track').catch(err => logger.debug("pause after playlist ended:", err))
     },
 
     // --- 文件检查 ---
@@ -704,6 +817,7 @@ export const usePlayerStore = defineStore('player', {
 
       this.currentTrack = null
       this.playlist = []
+      this._queueSynced = false
       this.currentTime = 0
       this.duration = 0
       this.lyrics = null
@@ -725,43 +839,45 @@ export const usePlayerStore = defineStore('player', {
 
     async nextTrack(): Promise<void> {
       if (!this.currentTrack || this._isLoading) return
-
-      let nextIndex: number
-      if (this.isShuffle) {
-        if (this.playlist.length <= 1) {
-          nextIndex = 0
-        } else {
-          do {
-            nextIndex = Math.floor(Math.random() * this.playlist.length)
-          } while (nextIndex === this.currentTrackIndex)
-        }
-      } else {
-        nextIndex = (this.currentTrackIndex + 1) % this.playlist.length
-      }
-
-      await this.playTrack(this.playlist[nextIndex])
+      await this._skip('queue_next')
     },
 
     async previousTrack(): Promise<void> {
       if (!this.currentTrack || this._isLoading) return
+      await this._skip('queue_previous')
+    },
 
-      let prevIndex: number
-      if (this.isShuffle) {
-        if (this.playlist.length <= 1) {
-          prevIndex = 0
-        } else {
-          do {
-            prevIndex = Math.floor(Math.random() * this.playlist.length)
-          } while (prevIndex === this.currentTrackIndex)
-        }
-      } else {
-        prevIndex = this.currentTrackIndex - 1
-        if (prevIndex < 0) {
-          prevIndex = this.playlist.length - 1
-        }
+    /**
+     * 由后端队列切换上一首/下一首（随机播放、重复模式由队列处理），界面跟随队列的当前音轨
+     */
+    async _skip(command: 'queue_next' | 'queue_previous'): Promise<void> {
+      if (this.playlist.length === 0) return
+      if (!this._queueSynced || this.currentTrackIndex < 0) {
+        await this._syncQueue()
       }
 
-      await this.playTrack(this.playlist[prevIndex])
+      this._isLoading = true
+      try {
+        const { items, currentIndex } = await invoke<QueueSnapshot>(command)
+        if (currentIndex !== null) {
+          this._followTrack(items[currentIndex].path)
+          this.startStatusPolling()
+        }
+      } catch (err) {
+        const handled = errorHandler.handle(
+          err instanceof Error ? err : new Error(backendErrorMessage(err)),
+          {
+            type: classifyAudioInvokeError(err),
+            severity: ErrorSeverity.HIGH,
+            context: { command },
+            showToUser: true,
+          }
+        )
+        logger.error('Failed to skip track:', handled)
+        this.isPlaying = false
+      } finally {
+        this._isLoading = false
+      }
     },
 
     // --- 播放控制 ---
@@ -835,6 +951,7 @@ export const usePlayerStore = defineStore('player', {
       } else {
         this.repeatMode = 'none'
       }
+      this._syncQueueModes()
     },
 
     toggleShuffle(): void {
@@ -842,6 +959,7 @@ export const usePlayerStore = defineStore('player', {
       if (this.isShuffle) {
         this.repeatMode = 'none'
       }
+      this._syncQueueModes()
     },
 
     // --- 歌词偏移 ---
@@ -872,6 +990,7 @@ export const usePlayerStore = defineStore('player', {
       if (currentIndex === -1 || this.playlist.length === 0) {
         this.playlist.unshift(track)
         logger.info('Added track to beginning of playlist:', track.path)
+        this._syncQueue()
         return
       }
 
@@ -891,6 +1010,7 @@ export const usePlayerStore = defineStore('player', {
         this.playlist.splice(currentIndex + 1, 0, track)
         logger.info('Added new track to next position:', track.path)
       }
+      this._syncQueue()
     },
 
     /**
@@ -905,6 +1025,7 @@ export const usePlayerStore = defineStore('player', {
       if (currentIndex === -1 || this.playlist.length === 0) {
         this.playlist.unshift(...tracks)
         logger.info(`Added ${tracks.length} tracks to beginning of playlist`)
+        this._syncQueue()
         return
       }
 
@@ -915,12 +1036,16 @@ export const usePlayerStore = defineStore('player', {
       // 插入到当前曲目后面
       this.playlist.splice(currentIndex + 1, 0, ...newTracks)
       logger.info(`Added ${newTracks.length} new tracks to next position`)
+      this._syncQueue()
     },
 
     // --- 数据加载 ---
 
     loadPlaylist(playlist: Track[]): void {
+      // 正在播放的音轨若在新列表中，队列从它继续前进
+      const playingPath = this.isPlaying ? this.currentTrack?.path : undefined
       this.playlist = playlist
+      this._syncQueue(playingPath)
       if (playlist && playlist.length > 0) {
         const firstTrack = playlist[0]
         this.currentTrack = firstTrack
@@ -1009,6 +1134,16 @@ export const usePlayerStore = defineStore('player', {
         this._positionUnlisten = null
       }
 
+      if (this._playerStateUnlisten) {
+        this._playerStateUnlisten()
+        this._playerStateUnlisten = null
+      }
+
+      if (this._queueChangedUnlisten) {
+        this._queueChangedUnlisten()
+        this._queueChangedUnlisten = null
+      }
+
       // 清理任务栏事件监听
       if (this._taskbarPreviousUnlisten) {
         this._taskbarPreviousUnlisten()
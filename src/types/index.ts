@@ -8,13 +8,183 @@ export interface Track {
   artist?: string
   displayArtist?: string
   album?: string
+  /** 封面缓存中的封面 ID */
+  coverId?: string | null
+  /** 当前音轨的大尺寸封面地址（由 coverId 生成，仅前端使用） */
   cover?: string
+  /** 是否有内嵌封面 */
+  hasCover?: boolean
   duration?: number
   bitrate?: number | null
   sampleRate?: number | null
   channels?: number | null
   bitDepth?: number | null
   format?: string | null
+  replayGain?: ReplayGainInfo | null
+  artists?: string[]
+  albumArtist?: string | null
+  trackNumber?: number | null
+  trackTotal?: number | null
+  discNumber?: number | null
+  discTotal?: number | null
+  genres?: string[]
+  /** 日期标签原文（如 "2003" 或 "2003-05-12"） */
+  date?: string | null
+  year?: number | null
+  composers?: string[]
+  lyricists?: string[]
+  comment?: string | null
+  bpm?: number | null
+  compilation?: boolean
+  isrc?: string | null
+  musicbrainz?: MusicBrainzIds | null
+}
+
+export type CoverPictureType = 'front' | 'back' | 'artist' | 'media' | 'leaflet' | 'other'
+
+/** 音轨中的一张内嵌图片 */
+export interface TrackPicture {
+  pictureType: CoverPictureType
+  mimeType: string | null
+  description: string | null
+  coverId: string
+}
+
+/** 封面主色 */
+export interface PaletteSwatch {
+  color: string
+  /** 占比（0~1） */
+  population: number
+  /** WCAG 相对亮度 */
+  luminance: number
+}
+
+/** 前景色/背景色组合，对比度至少满足 WCAG AA（可直接交给 colorContrast.ts 检查） */
+export interface PaletteColorPair {
+  name: string
+  foreground: string
+  background: string
+  ratio: number
+  level: 'AA' | 'AAA'
+}
+
+/** 封面配色（get_cover_palette / get_track_palette） */
+export interface CoverPalette {
+  coverId: string
+  /** 按占比从高到低排列 */
+  swatches: PaletteSwatch[]
+  /** 每个主色作为背景时的组合，顺序与 swatches 相同 */
+  pairs: PaletteColorPair[]
+  /** 主色是否为深色 */
+  dark: boolean
+}
+
+/** 一次播放的结果：完整播放、中途切换、未达到阈值就结束 */
+export type PlayOutcome = 'completed' | 'skipped' | 'incomplete'
+
+/** 播放历史记录（`play-history-updated` 事件） */
+export interface PlayEvent {
+  path: string
+  title: string | null
+  artists: string[]
+  album: string | null
+  albumArtist: string | null
+  /** 秒 */
+  duration: number | null
+  /** Unix 毫秒 */
+  startedAt: number
+  endedAt: number
+  /** 实际收听的秒数 */
+  listened: number
+  outcome: PlayOutcome
+}
+
+/** 按开始时间筛选（Unix 毫秒） */
+export interface HistoryWindow {
+  since?: number | null
+  until?: number | null
+}
+
+export interface PlayHistoryPage {
+  total: number
+  offset: number
+  /** 从新到旧 */
+  events: PlayEvent[]
+}
+
+export interface TrackPlayStats {
+  path: string
+  starts: number
+  plays: number
+  skips: number
+  lastPlayed: number | null
+  listened: number
+}
+
+/** 播放排行条目（音轨、艺术家或专辑） */
+export interface TopEntry {
+  name: string
+  artist: string | null
+  album: string | null
+  path: string | null
+  plays: number
+  starts: number
+  skips: number
+  listened: number
+  lastPlayed: number
+}
+
+export interface MusicBrainzIds {
+  recordingId: string | null
+  trackId: string | null
+  releaseId: string | null
+  releaseGroupId: string | null
+  artistIds: string[]
+  releaseArtistIds: string[]
+  workId: string | null
+}
+
+export interface ReplayGainInfo {
+  /** dB */
+  trackGain: number | null
+  trackPeak: number | null
+  /** dB */
+  albumGain: number | null
+  albumPeak: number | null
+}
+
+/** 标签修改（未提供的字段不修改，空字符串、空列表或 0 表示删除） */
+export interface TagChanges {
+  title?: string
+  artists?: string[]
+  album?: string
+  albumArtist?: string
+  trackNumber?: number
+  trackTotal?: number
+  discNumber?: number
+  discTotal?: number
+  genres?: string[]
+  date?: string
+  composers?: string[]
+  lyricists?: string[]
+  comment?: string
+  bpm?: number
+  compilation?: boolean
+  isrc?: string
+  cover?: { action: 'set'; path: string } | { action: 'remove' }
+}
+
+export interface TagWriteOptions {
+  id3v2Version?: '2.3' | '2.4'
+  /** 只返回差异，不写入文件 */
+  dryRun?: boolean
+}
+
+export interface TagWriteResult {
+  path: string
+  changes: Array<{ field: string; old: string | null; new: string | null }>
+  written: boolean
+  error: string | null
 }
 
 export interface AudioInfo {
@@ -56,9 +226,149 @@ export interface Playlist {
   totalFiles?: number
 }
 
+/** 媒体库播放列表（音轨通过 get_library_tracks 分页获取） */
+export interface LibraryPlaylist {
+  name: string
+  folder: string
+  recursive: boolean
+  trackCount: number
+}
+
+/** 用户播放列表中的音轨（标签为添加时已知的信息） */
+export interface PlaylistEntry {
+  path: string
+  title: string | null
+  artist: string | null
+  /** 秒 */
+  duration: number | null
+}
+
+/** 后端保存的用户播放列表 */
+export interface UserPlaylist {
+  id: string
+  name: string
+  /** Unix 毫秒 */
+  createdAt: number
+  updatedAt: number
+  entries: PlaylistEntry[]
+}
+
+/** 用户播放列表概要（`user-playlists-changed` 事件） */
+export interface UserPlaylistSummary {
+  id: string
+  name: string
+  trackCount: number
+  /** 已知时长之和（秒） */
+  duration: number
+  createdAt: number
+  updatedAt: number
+}
+
+export type PlaylistFormat = 'm3u' | 'm3u8' | 'pls' | 'xspf'
+
+/** 导入时找不到的条目：文件不存在、网络地址或无法解析的位置 */
+export interface MissingEntry {
+  index: number
+  location: string
+  reason: 'notFound' | 'remote' | 'invalid'
+}
+
+export interface PlaylistImport {
+  playlist: UserPlaylist
+  missing: MissingEntry[]
+}
+
+export interface LibraryPage {
+  total: number
+  offset: number
+  tracks: Track[]
+}
+
+export interface LibraryScanSummary {
+  total: number
+  added: number
+  updated: number
+  removed: number
+  unchanged: number
+  failed: number
+}
+
+/** 媒体库扫描进度（library-scan-progress 事件） */
+export interface LibraryScanProgress {
+  scanId: number
+  phase: 'discovering' | 'parsing'
+  discovered: number
+  parsed: number
+  total: number
+  currentDirectory: string | null
+  error: { path: string; message: string } | null
+}
+
+/** 扫描中解析出的一批音轨（library-scan-batch 事件） */
+export interface LibraryScanBatch {
+  scanId: number
+  tracks: Track[]
+}
+
+/** 媒体库扫描完成（library-scan-complete 事件） */
+export interface LibraryScanComplete {
+  scanId: number
+  cancelled: boolean
+  summary: LibraryScanSummary | null
+  error: string | null
+}
+
+/** 媒体库变化（library-changed 事件） */
+export interface LibraryDiff {
+  added: Track[]
+  updated: Track[]
+  removed: string[]
+  renamed: Array<{ from: string; track: Track }>
+}
+
 export type RepeatMode = 'none' | 'track' | 'list'
 export type SortOrder = 'asc' | 'desc'
 
+export interface QueueItem {
+  id: number
+  path: string
+}
+
+/** 音轨结束（`track-ended` 事件） */
+export interface TrackEndedEvent {
+  path: string | null
+  /** 已无缝衔接的下一首，为空表示播放停止 */
+  nextPath: string | null
+  /** 睡眠定时器到时停止了播放（已衔接的下一首被暂停在开头） */
+  stopped: boolean
+  /** 队列前进后由后端加载播放的下一首（非无缝衔接），与 nextPath 都为空表示播放结束 */
+  queuedPath: string | null
+}
+
+/** 后端播放队列快照（`queue-changed` 事件） */
+export interface QueueSnapshot {
+  items: QueueItem[]
+  currentIndex: number | null
+  nextIndex: number | null
+  shuffle: boolean
+  repeat: RepeatMode
+}
+
+export type SleepTimerMode =
+  | { kind: 'duration'; minutes: number }
+  | { kind: 'end-of-track' }
+  | { kind: 'tracks'; count: number }
+
+export interface SleepTimerStatus {
+  mode: SleepTimerMode
+  /** 距离停止的秒数（按音轨计时且不是最后一首时为 null） */
+  remainingSecs: number | null
+  /** 剩余音轨数（含当前音轨，仅按音轨计时） */
+  tracksRemaining: number | null
+  fadeOut: number
+  fading: boolean
+}
+
 // ============ 配置类型 ============
 
 export interface DirectoryScanConfig {
@@ -66,6 +376,31 @@ export interface DirectoryScanConfig {
   maxDepth: number
   ignoreHiddenFolders: boolean
   folderBlacklist: string[]
+  includePatterns: string[]
+  excludePatterns: string[]
+  followSymlinks: boolean
+  minFileSize: number
+}
+
+/** 封面来源：内嵌正面封面、文件夹中的封面图片、任意内嵌图片 */
+export type CoverSource = 'embeddedFront' | 'folderFile' | 'embeddedAny'
+
+export interface CoverConfig {
+  /** 文件夹封面的文件名（glob，不区分大小写，靠前的优先） */
+  folderPatterns: string[]
+  /** 向上查找的上级文件夹层数 */
+  parentLevels: number
+  /** 封面来源优先级 */
+  priority: CoverSource[]
+}
+
+/** 播放历史设置：收听时长达到秒数或时长比例（先到者为准）计为完整播放 */
+export interface HistoryConfig {
+  enabled: boolean
+  /** 百分比 */
+  completionPercent: number
+  /** 秒 */
+  completionSeconds: number
 }
 
 export interface TitleExtractionConfig {
@@ -109,9 +444,99 @@ export interface UIConfig {
   miniMode: boolean
 }
 
+export type CrossfadeCurve = 'linear' | 'equal-power' | 's-curve'
+
+export interface CrossfadeConfig {
+  enabled: boolean
+  /** 淡化时长（秒，1–12） */
+  duration: number
+  curve: CrossfadeCurve
+}
+
+export type ReplayGainMode = 'off' | 'track' | 'album' | 'auto-by-queue'
+
+export interface ReplayGainConfig {
+  mode: ReplayGainMode
+  /** 前置放大（dB） */
+  preamp: number
+  /** 无增益标签音轨的增益（dB） */
+  fallbackGain: number
+  preventClipping: boolean
+}
+
+export interface PitchShift {
+  /** 半音（±12） */
+  semitones: number
+  /** 音分（±100） */
+  cents: number
+}
+
+export interface PitchShiftConfig {
+  global: PitchShift
+  /** 按文件路径记住的单曲设置 */
+  tracks: Record<string, PitchShift>
+}
+
+export interface LoopConfig {
+  /** 按 LOOPSTART / LOOPLENGTH / LOOPEND 标签循环 */
+  honorTags: boolean
+  loopCount: number
+  /** 循环结束后的淡出时长（秒，0 表示播放到结尾） */
+  fadeOut: number
+}
+
+export interface AbLoop {
+  path: string
+  start: number
+  end: number
+}
+
+export type PlayerStatus = 'stopped' | 'loading' | 'buffering' | 'playing' | 'paused' | 'ended' | 'error'
+
+export type TransitionReason =
+  | 'init' | 'play' | 'ready' | 'pause' | 'resume' | 'seek'
+  | 'track-changed' | 'track-ended' | 'sleep-timer' | 'error'
+
+export interface OutputFormat {
+  sampleRate: number
+  channels: number
+  exclusive: boolean
+}
+
+/** 后端 `player-state` 事件 */
+export interface PlayerStateEvent {
+  version: number
+  /** 转换序号，单调递增 */
+  seq: number
+  status: PlayerStatus
+  path: string | null
+  duration: number | null
+  /** 音轨采样率（未知时为 null） */
+  sampleRate: number | null
+  position: number
+  outputFormat: OutputFormat | null
+  reason: TransitionReason
+  error: string | null
+}
+
+/** 后端 `get_decode_buffer_stats` 返回的解码缓冲区统计（采样数） */
+export interface DecodeBufferStats {
+  path: string
+  capacity: number
+  fill: number
+  minFill: number
+  underruns: number
+  producerWaits: number
+  written: number
+}
+
 export interface AudioConfig {
   exclusiveMode: boolean
   volume: number
+  crossfade: CrossfadeConfig
+  replayGain: ReplayGainConfig
+  pitchShift: PitchShiftConfig
+  looping: LoopConfig
 }
 
 export interface VisualizerConfig {
@@ -127,6 +552,8 @@ export interface AppConfig {
   playlist: PlaylistConfig
   general: GeneralConfig
   lyrics: LyricsConfig
+  cover: CoverConfig
+  history: HistoryConfig
   ui: UIConfig
   audio: AudioConfig
   visualizer: VisualizerConfig
@@ -188,6 +615,21 @@ export interface AppError extends Error {
   timestamp: string
 }
 
+/** 后端错误码（与 Rust `ErrorCode` 一致） */
+export type BackendErrorCode =
+  | 'FileNotFound' | 'InvalidPath' | 'PermissionDenied' | 'Io'
+  | 'UnsupportedFormat' | 'DecodeFailed' | 'DeviceUnavailable' | 'NoTrackLoaded'
+  | 'InvalidArgument' | 'NotFound' | 'ConfigFailed' | 'ParseFailed'
+  | 'NetworkTimeout' | 'NetworkFailed' | 'Unsupported' | 'RestartRequired' | 'Internal' | 'Busy'
+
+/** Tauri 命令返回的错误 */
+export interface BackendError {
+  code: BackendErrorCode
+  message: string
+  context: Record<string, string>
+  causes: string[]
+}
+
 // ============ 日志类型 ============
 
 export enum LogLevel {
@@ -1,62 +1,48 @@
 import { ErrorType } from './errorHandler'
+import type { BackendError, BackendErrorCode } from '@/types'
 
-function toMessage(err: unknown): string {
+/**
+ * 判断是否为 Tauri 命令返回的结构化错误
+ */
+export function isBackendError(err: unknown): err is BackendError {
+  if (!err || typeof err !== 'object') return false
+  const record = err as Record<string, unknown>
+  return typeof record.code === 'string' && typeof record.message === 'string'
+}
+
+/**
+ * 提取错误信息文本
+ */
+export function backendErrorMessage(err: unknown): string {
   if (!err) return ''
   if (typeof err === 'string') return err
-  if (err instanceof Error) return err.message || String(err)
-  try {
-    // tauri invoke errors sometimes come as objects with message/error fields
-    const record = err as Record<string, unknown>
-    if (typeof record.message === 'string') return record.message
-    if (typeof record.error === 'string') return record.error
-    if (typeof record.toString === 'function') return record.toString()
-    return JSON.stringify(err)
-  } catch {
-    return String(err)
-  }
+  if (err instanceof Error || isBackendError(err)) return err.message
+  return String(err)
+}
+
+const CODE_TO_TYPE: Partial<Record<BackendErrorCode, ErrorType>> = {
+  UnsupportedFormat: ErrorType.AUDIO_DECODE_ERROR,
+  DecodeFailed: ErrorType.AUDIO_DECODE_ERROR,
+  DeviceUnavailable: ErrorType.AUDIO_DEVICE_ERROR,
+  FileNotFound: ErrorType.FILE_NOT_FOUND,
+  InvalidPath: ErrorType.FILE_NOT_FOUND,
+  PermissionDenied: ErrorType.FILE_PERMISSION_DENIED,
+  Io: ErrorType.FILE_READ_ERROR,
+  InvalidArgument: ErrorType.DATA_VALIDATION_ERROR,
+  ParseFailed: ErrorType.DATA_PARSE_ERROR,
+  ConfigFailed: ErrorType.CONFIG_SAVE_ERROR,
+  NetworkTimeout: ErrorType.NETWORK_TIMEOUT,
+  NetworkFailed: ErrorType.NETWORK,
 }
 
 /**
- * 将后端（Tauri/Rust）返回的错误信息，映射到更精确的前端 ErrorType。
+ * 将后端（Tauri/Rust）返回的错误码映射到前端 ErrorType。
  *
- * 目标：把“格式探测/解码失败”与“音频设备/输出流失败”区分开，避免误提示用户检查音频设备。
+ * 区分“格式探测/解码失败”与“音频设备/输出流失败”，避免误提示用户检查音频设备。
  */
 export function classifyAudioInvokeError(err: unknown): ErrorType {
-  const msg = toMessage(err)
-  const m = msg.toLowerCase()
-
-  // --- 解码/格式探测类（你提到的场景） ---
-  const decodePatterns = [
-    'unrecognized format',
-    'failed to probe format',
-    'no audio track found',
-    'failed to create decoder',
-    'decode error',
-    'decoder failed',
-    'end of stream',
-    'unexpected eof',
-    'unsupported', // e.g. unsupported audio buffer format / codec
-  ]
-  if (decodePatterns.some(p => m.includes(p))) {
-    return ErrorType.AUDIO_DECODE_ERROR
+  if (isBackendError(err)) {
+    return CODE_TO_TYPE[err.code] ?? ErrorType.AUDIO_PLAYBACK_ERROR
   }
-
-  // --- 设备/输出流类 ---
-  const devicePatterns = [
-    'audio device not found',
-    'failed to get output devices',
-    'failed to open output stream',
-    'failed to create output stream',
-    'failed to create output stream builder',
-    'failed to initialize wasapi',
-    'wasapi',
-    'device may be in use',
-    'no default output device',
-    'not initialized',
-  ]
-  if (devicePatterns.some(p => m.includes(p))) {
-    return ErrorType.AUDIO_DEVICE_ERROR
-  }
-
   return ErrorType.AUDIO_PLAYBACK_ERROR
 }
@@ -0,0 +1,18 @@
+/**
+ * 封面地址工具
+ * 封面由后端提取到封面缓存，通过 `cover` 协议按尺寸读取
+ */
+import { convertFileSrc } from '@tauri-apps/api/core'
+
+/** 后端预设的缩略图尺寸，其他尺寸会向上取最近的预设值 */
+export type CoverSize = 64 | 256 | 1024 | 'original'
+
+/**
+ * 根据封面 ID 生成图片地址，没有封面时返回 undefined
+ */
+export function coverUrl(coverId: string | null | undefined, size: CoverSize = 256): string | undefined {
+  if (!coverId) return undefined
+  return convertFileSrc(`${coverId}/${size}`, 'cover')
+}
+
+export default coverUrl
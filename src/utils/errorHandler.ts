@@ -166,8 +166,12 @@ class ErrorHandler {
         context
       )
     } else {
+      // Tauri 命令返回的错误是 { code, message, context, causes } 对象
+      const message = typeof (error as { message?: unknown })?.message === 'string'
+        ? (error as { message: string }).message
+        : String(error)
       appError = new AppError(
-        String(error) || '未知错误',
+        message || '未知错误',
         type,
         severity,
         error,